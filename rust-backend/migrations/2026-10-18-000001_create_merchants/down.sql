@@ -0,0 +1,4 @@
+ALTER TABLE transactions DROP COLUMN merchant_id;
+
+DROP TABLE merchant_aliases;
+DROP TABLE merchants;
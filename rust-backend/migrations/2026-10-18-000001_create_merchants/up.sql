@@ -0,0 +1,27 @@
+CREATE TABLE merchants (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR NOT NULL,
+    normalized_name VARCHAR NOT NULL,
+    tax_id VARCHAR,
+    default_category_id UUID REFERENCES categories(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (user_id, normalized_name)
+);
+
+CREATE INDEX merchants_user_tax_id_idx ON merchants (user_id, tax_id);
+
+CREATE TABLE merchant_aliases (
+    id UUID PRIMARY KEY,
+    merchant_id UUID NOT NULL REFERENCES merchants(id) ON DELETE CASCADE,
+    alias VARCHAR NOT NULL,
+    normalized_alias VARCHAR NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (merchant_id, normalized_alias)
+);
+
+CREATE INDEX merchant_aliases_normalized_alias_idx ON merchant_aliases (normalized_alias);
+
+ALTER TABLE transactions
+    ADD COLUMN merchant_id UUID REFERENCES merchants(id) ON DELETE SET NULL;
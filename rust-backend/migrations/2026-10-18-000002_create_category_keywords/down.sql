@@ -0,0 +1,3 @@
+DROP INDEX transactions_user_merchant_idx;
+
+DROP TABLE category_keywords;
@@ -0,0 +1,6 @@
+DROP TABLE rules;
+
+ALTER TABLE transactions
+    DROP COLUMN source,
+    DROP COLUMN excluded,
+    DROP COLUMN tags;
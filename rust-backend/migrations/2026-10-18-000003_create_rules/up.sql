@@ -0,0 +1,20 @@
+ALTER TABLE transactions
+    ADD COLUMN tags TEXT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN excluded BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN source VARCHAR NOT NULL DEFAULT 'manual';
+
+CREATE TABLE rules (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR NOT NULL,
+    position INTEGER NOT NULL DEFAULT 0,
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    match_mode VARCHAR NOT NULL DEFAULT 'all',
+    stop_processing BOOLEAN NOT NULL DEFAULT FALSE,
+    conditions JSONB NOT NULL DEFAULT '[]',
+    actions JSONB NOT NULL DEFAULT '[]',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX rules_user_position_idx ON rules (user_id, position);
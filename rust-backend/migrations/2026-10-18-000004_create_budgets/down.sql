@@ -0,0 +1,3 @@
+DROP INDEX transactions_user_category_date_idx;
+
+DROP TABLE budgets;
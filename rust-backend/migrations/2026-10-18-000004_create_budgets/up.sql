@@ -0,0 +1,17 @@
+CREATE TABLE budgets (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    category_id UUID NOT NULL REFERENCES categories(id) ON DELETE CASCADE,
+    amount NUMERIC(14, 2) NOT NULL CHECK (amount >= 0),
+    period VARCHAR NOT NULL DEFAULT 'monthly',
+    start_date DATE NOT NULL,
+    end_date DATE,
+    rollover BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (period IN ('weekly', 'monthly', 'yearly', 'custom')),
+    CHECK (period <> 'custom' OR end_date IS NOT NULL)
+);
+
+CREATE INDEX budgets_user_idx ON budgets (user_id);
+CREATE INDEX transactions_user_category_date_idx ON transactions (user_id, category_id, date);
@@ -0,0 +1,6 @@
+DROP INDEX transactions_recurring_occurrence_idx;
+
+ALTER TABLE transactions
+    DROP COLUMN recurring_id;
+
+DROP TABLE recurring_transactions;
@@ -0,0 +1,32 @@
+CREATE TABLE recurring_transactions (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR NOT NULL,
+    merchant VARCHAR NOT NULL,
+    merchant_id UUID REFERENCES merchants(id) ON DELETE SET NULL,
+    amount NUMERIC(14, 2) NOT NULL,
+    category_id UUID REFERENCES categories(id) ON DELETE SET NULL,
+    notes TEXT,
+    tags TEXT[] NOT NULL DEFAULT '{}',
+    frequency VARCHAR NOT NULL DEFAULT 'monthly',
+    interval_count INTEGER NOT NULL DEFAULT 1 CHECK (interval_count > 0),
+    day_of_month INTEGER CHECK (day_of_month BETWEEN 1 AND 31),
+    start_date DATE NOT NULL,
+    end_date DATE,
+    next_run DATE NOT NULL,
+    last_run DATE,
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (frequency IN ('daily', 'weekly', 'monthly', 'yearly'))
+);
+
+CREATE INDEX recurring_transactions_due_idx ON recurring_transactions (next_run) WHERE active;
+CREATE INDEX recurring_transactions_user_idx ON recurring_transactions (user_id);
+
+ALTER TABLE transactions
+    ADD COLUMN recurring_id UUID REFERENCES recurring_transactions(id) ON DELETE SET NULL;
+
+-- A template never produces two transactions for the same occurrence, even if two workers race
+CREATE UNIQUE INDEX transactions_recurring_occurrence_idx ON transactions (recurring_id, date)
+    WHERE recurring_id IS NOT NULL;
@@ -0,0 +1,19 @@
+CREATE TABLE bills (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    file_path VARCHAR NOT NULL,
+    file_name VARCHAR NOT NULL,
+    file_size BIGINT NOT NULL,
+    file_type VARCHAR NOT NULL,
+    ocr_text TEXT,
+    ocr_confidence REAL,
+    extracted_data JSONB,
+    transaction_id UUID REFERENCES transactions(id) ON DELETE SET NULL,
+    status VARCHAR NOT NULL DEFAULT 'pending_review',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (status IN ('pending_review', 'reviewed'))
+);
+
+CREATE INDEX bills_user_status_idx ON bills (user_id, status);
+CREATE INDEX transactions_user_date_idx ON transactions (user_id, date);
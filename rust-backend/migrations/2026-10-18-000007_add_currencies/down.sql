@@ -0,0 +1,12 @@
+DROP FUNCTION convert_amount(UUID, NUMERIC, VARCHAR, VARCHAR, TIMESTAMPTZ, TEXT);
+
+DROP TABLE exchange_rates;
+
+ALTER TABLE recurring_transactions
+    DROP COLUMN currency;
+
+ALTER TABLE transactions
+    DROP COLUMN currency;
+
+ALTER TABLE users
+    DROP COLUMN base_currency;
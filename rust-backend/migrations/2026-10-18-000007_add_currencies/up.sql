@@ -0,0 +1,52 @@
+ALTER TABLE users
+    ADD COLUMN base_currency VARCHAR(3) NOT NULL DEFAULT 'THB';
+
+ALTER TABLE transactions
+    ADD COLUMN currency VARCHAR(3) NOT NULL DEFAULT 'THB';
+
+ALTER TABLE recurring_transactions
+    ADD COLUMN currency VARCHAR(3) NOT NULL DEFAULT 'THB';
+
+-- One unit of from_currency is worth `rate` units of to_currency on rate_date
+CREATE TABLE exchange_rates (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    from_currency VARCHAR(3) NOT NULL,
+    to_currency VARCHAR(3) NOT NULL,
+    rate NUMERIC(20, 10) NOT NULL CHECK (rate > 0),
+    rate_date DATE NOT NULL,
+    source VARCHAR NOT NULL DEFAULT 'manual',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (from_currency <> to_currency),
+    CHECK (source IN ('manual', 'csv')),
+    UNIQUE (user_id, from_currency, to_currency, rate_date)
+);
+
+-- Convert an amount using the user's rate closest to the local date of `at`: the latest rate on or
+-- before that date, else the earliest one after it. Inverse pairs are used as 1 / rate.
+-- Returns NULL when the user has no rate at all for the pair.
+CREATE FUNCTION convert_amount(
+    p_user_id UUID,
+    p_amount NUMERIC,
+    p_from VARCHAR,
+    p_to VARCHAR,
+    p_at TIMESTAMPTZ,
+    p_tz TEXT
+) RETURNS NUMERIC
+LANGUAGE sql STABLE AS $$
+    SELECT CASE
+        WHEN p_from = p_to THEN p_amount
+        ELSE p_amount * (
+            SELECT CASE WHEN er.from_currency = p_from THEN er.rate ELSE 1 / er.rate END
+            FROM exchange_rates er
+            WHERE er.user_id = p_user_id
+              AND ((er.from_currency = p_from AND er.to_currency = p_to)
+                OR (er.from_currency = p_to AND er.to_currency = p_from))
+            ORDER BY er.rate_date > CAST(p_at AT TIME ZONE p_tz AS date),
+                     abs(er.rate_date - CAST(p_at AT TIME ZONE p_tz AS date)),
+                     er.from_currency = p_from DESC
+            LIMIT 1
+        )
+    END
+$$;
@@ -0,0 +1,10 @@
+DROP INDEX transactions_transfer_idx;
+DROP INDEX transactions_account_date_idx;
+
+ALTER TABLE transactions
+    DROP CONSTRAINT transactions_direction_check,
+    DROP COLUMN transfer_id,
+    DROP COLUMN direction,
+    DROP COLUMN account_id;
+
+DROP TABLE accounts;
@@ -0,0 +1,25 @@
+CREATE TABLE accounts (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR NOT NULL,
+    account_type VARCHAR NOT NULL,
+    currency VARCHAR(3) NOT NULL DEFAULT 'THB',
+    opening_balance NUMERIC(14, 2) NOT NULL DEFAULT 0,
+    institution VARCHAR,
+    archived BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (account_type IN ('cash', 'bank', 'credit_card', 'e_wallet')),
+    UNIQUE (user_id, name)
+);
+
+-- Amounts stay positive; direction says whether money left or entered the account.
+-- Both legs of a transfer share a transfer_id and are left out of spending.
+ALTER TABLE transactions
+    ADD COLUMN account_id UUID REFERENCES accounts(id),
+    ADD COLUMN direction VARCHAR NOT NULL DEFAULT 'expense',
+    ADD COLUMN transfer_id UUID,
+    ADD CONSTRAINT transactions_direction_check CHECK (direction IN ('expense', 'income'));
+
+CREATE INDEX transactions_account_date_idx ON transactions (account_id, date);
+CREATE INDEX transactions_transfer_idx ON transactions (transfer_id);
@@ -0,0 +1,12 @@
+DROP FUNCTION spending_amount(VARCHAR, NUMERIC);
+
+ALTER TABLE categories
+    DROP CONSTRAINT categories_kind_check,
+    DROP COLUMN kind;
+
+DROP INDEX transactions_refund_of_idx;
+
+ALTER TABLE transactions
+    DROP CONSTRAINT transactions_type_check,
+    DROP COLUMN refund_of,
+    DROP COLUMN transaction_type;
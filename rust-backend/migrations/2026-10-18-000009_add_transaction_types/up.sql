@@ -0,0 +1,32 @@
+-- What a transaction means, on top of which way the money moved: refunds bring money in but
+-- reduce spending, transfers move money between the user's own accounts
+ALTER TABLE transactions
+    ADD COLUMN transaction_type VARCHAR NOT NULL DEFAULT 'expense',
+    ADD COLUMN refund_of UUID REFERENCES transactions(id) ON DELETE SET NULL,
+    ADD CONSTRAINT transactions_type_check CHECK (transaction_type IN ('expense', 'income', 'refund', 'transfer'));
+
+UPDATE transactions
+SET transaction_type = CASE
+    WHEN transfer_id IS NOT NULL THEN 'transfer'
+    WHEN direction = 'income' THEN 'income'
+    ELSE 'expense'
+END;
+
+CREATE INDEX transactions_refund_of_idx ON transactions (refund_of);
+
+ALTER TABLE categories
+    ADD COLUMN kind VARCHAR NOT NULL DEFAULT 'expense',
+    ADD CONSTRAINT categories_kind_check CHECK (kind IN ('expense', 'income'));
+
+-- An amount as it counts towards spending: refunds count against it, income and transfers not at all
+CREATE FUNCTION spending_amount(p_type VARCHAR, p_amount NUMERIC)
+RETURNS NUMERIC
+LANGUAGE SQL
+IMMUTABLE
+AS $$
+    SELECT CASE p_type
+        WHEN 'expense' THEN p_amount
+        WHEN 'refund' THEN -p_amount
+        ELSE 0
+    END
+$$;
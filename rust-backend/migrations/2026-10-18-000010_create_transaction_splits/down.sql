@@ -0,0 +1,2 @@
+DROP VIEW transaction_allocations;
+DROP TABLE transaction_splits;
@@ -0,0 +1,46 @@
+-- A transaction split into allocations, each with its own category. Allocations either name
+-- items of the transaction (by position in its items list) or just carry an amount; together
+-- they add up to the transaction amount.
+CREATE TABLE transaction_splits (
+    id UUID PRIMARY KEY,
+    transaction_id UUID NOT NULL REFERENCES transactions(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    category_id UUID REFERENCES categories(id) ON DELETE SET NULL,
+    amount NUMERIC(14, 2) NOT NULL CHECK (amount > 0),
+    item_indexes INTEGER[] NOT NULL DEFAULT '{}',
+    notes TEXT,
+    tags TEXT[] NOT NULL DEFAULT '{}',
+    position INTEGER NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (transaction_id, position)
+);
+
+CREATE INDEX transaction_splits_user_category_idx ON transaction_splits (user_id, category_id);
+
+-- What category reports and budgets count: the splits of split transactions and every other
+-- transaction as a whole
+CREATE VIEW transaction_allocations AS
+SELECT s.id,
+       t.id AS transaction_id,
+       t.user_id,
+       s.category_id,
+       s.amount,
+       t.currency,
+       t.date,
+       t.transaction_type,
+       t.excluded
+FROM transaction_splits s
+JOIN transactions t ON t.id = s.transaction_id
+UNION ALL
+SELECT t.id,
+       t.id AS transaction_id,
+       t.user_id,
+       t.category_id,
+       t.amount,
+       t.currency,
+       t.date,
+       t.transaction_type,
+       t.excluded
+FROM transactions t
+WHERE NOT EXISTS (SELECT 1 FROM transaction_splits s WHERE s.transaction_id = t.id);
@@ -0,0 +1,15 @@
+ALTER TABLE transactions
+    ADD COLUMN tags TEXT[] NOT NULL DEFAULT '{}';
+
+UPDATE transactions t
+SET tags = tagged.names
+FROM (
+    SELECT tt.transaction_id, ARRAY_AGG(g.name ORDER BY g.name) AS names
+    FROM transaction_tags tt
+    JOIN tags g ON g.id = tt.tag_id
+    GROUP BY tt.transaction_id
+) AS tagged
+WHERE tagged.transaction_id = t.id;
+
+DROP TABLE transaction_tags;
+DROP TABLE tags;
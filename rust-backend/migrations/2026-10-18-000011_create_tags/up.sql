@@ -0,0 +1,39 @@
+-- Tags become shared records instead of free text on each transaction, so they can be renamed
+-- and merged in one place. Names are unique per user, ignoring case.
+CREATE TABLE tags (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR NOT NULL,
+    color VARCHAR,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (BTRIM(name) <> '')
+);
+
+CREATE UNIQUE INDEX tags_user_name_idx ON tags (user_id, LOWER(name));
+
+CREATE TABLE transaction_tags (
+    transaction_id UUID NOT NULL REFERENCES transactions(id) ON DELETE CASCADE,
+    tag_id UUID NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+    PRIMARY KEY (transaction_id, tag_id)
+);
+
+CREATE INDEX transaction_tags_tag_idx ON transaction_tags (tag_id);
+
+-- Carry the existing tag arrays over; spellings that differ only in case become one tag
+INSERT INTO tags (id, user_id, name)
+SELECT gen_random_uuid(), user_id, MIN(tag)
+FROM (
+    SELECT user_id, BTRIM(UNNEST(tags)) AS tag
+    FROM transactions
+) AS existing
+WHERE tag <> ''
+GROUP BY user_id, LOWER(tag);
+
+INSERT INTO transaction_tags (transaction_id, tag_id)
+SELECT DISTINCT t.id, g.id
+FROM transactions t
+CROSS JOIN LATERAL UNNEST(t.tags) AS tag
+JOIN tags g ON g.user_id = t.user_id AND LOWER(g.name) = LOWER(BTRIM(tag));
+
+ALTER TABLE transactions DROP COLUMN tags;
@@ -0,0 +1,9 @@
+DROP TRIGGER bills_search_refresh ON bills;
+DROP FUNCTION bills_search_refresh();
+DROP TRIGGER transactions_search_refresh ON transactions;
+DROP FUNCTION transactions_search_refresh();
+DROP FUNCTION transaction_item_names(JSONB);
+
+ALTER TABLE transactions
+    DROP COLUMN search_vector,
+    DROP COLUMN search_text;
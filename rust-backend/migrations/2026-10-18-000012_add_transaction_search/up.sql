@@ -0,0 +1,70 @@
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+-- Searchable text of each transaction: merchant, notes, item names and the OCR text of its bills.
+-- search_vector holds the words for full-text search, weighted in that order; search_text holds
+-- the same text whole for substring and trigram matching, which Thai (no spaces) needs.
+ALTER TABLE transactions
+    ADD COLUMN search_text TEXT NOT NULL DEFAULT '',
+    ADD COLUMN search_vector TSVECTOR NOT NULL DEFAULT ''::tsvector;
+
+CREATE FUNCTION transaction_item_names(p_items JSONB)
+RETURNS TEXT
+LANGUAGE SQL
+IMMUTABLE
+AS $$
+    SELECT COALESCE(STRING_AGG(item->>'name', ' '), '')
+    FROM JSONB_ARRAY_ELEMENTS(
+        CASE WHEN JSONB_TYPEOF(p_items) = 'array' THEN p_items ELSE '[]'::jsonb END
+    ) AS item
+$$;
+
+CREATE FUNCTION transactions_search_refresh()
+RETURNS TRIGGER
+LANGUAGE plpgsql
+AS $$
+DECLARE
+    v_items TEXT := transaction_item_names(NEW.items);
+    v_ocr TEXT;
+BEGIN
+    SELECT COALESCE(STRING_AGG(ocr_text, ' '), '') INTO v_ocr
+    FROM bills
+    WHERE transaction_id = NEW.id;
+
+    NEW.search_text := CONCAT_WS(' ', NEW.merchant, NEW.notes, v_items, v_ocr);
+    NEW.search_vector :=
+        SETWEIGHT(TO_TSVECTOR('simple', COALESCE(NEW.merchant, '')), 'A') ||
+        SETWEIGHT(TO_TSVECTOR('simple', v_items), 'B') ||
+        SETWEIGHT(TO_TSVECTOR('simple', COALESCE(NEW.notes, '')), 'C') ||
+        SETWEIGHT(TO_TSVECTOR('simple', v_ocr), 'D');
+    RETURN NEW;
+END
+$$;
+
+CREATE TRIGGER transactions_search_refresh
+BEFORE INSERT OR UPDATE ON transactions
+FOR EACH ROW EXECUTE FUNCTION transactions_search_refresh();
+
+-- A bill linked to, moved off or re-read for a transaction changes what the transaction matches
+CREATE FUNCTION bills_search_refresh()
+RETURNS TRIGGER
+LANGUAGE plpgsql
+AS $$
+BEGIN
+    UPDATE transactions
+    SET search_text = search_text
+    WHERE id IN (
+        CASE WHEN TG_OP <> 'INSERT' THEN OLD.transaction_id END,
+        CASE WHEN TG_OP <> 'DELETE' THEN NEW.transaction_id END
+    );
+    RETURN NULL;
+END
+$$;
+
+CREATE TRIGGER bills_search_refresh
+AFTER INSERT OR DELETE OR UPDATE OF transaction_id, ocr_text ON bills
+FOR EACH ROW EXECUTE FUNCTION bills_search_refresh();
+
+UPDATE transactions SET search_text = search_text;
+
+CREATE INDEX transactions_search_vector_idx ON transactions USING GIN (search_vector);
+CREATE INDEX transactions_search_text_trgm_idx ON transactions USING GIN (search_text gin_trgm_ops);
@@ -0,0 +1,14 @@
+-- Bulk edits keep a snapshot of what they changed so they can be undone. The snapshot holds the
+-- affected transaction rows with their splits and tag links, plus the bill and refund links that
+-- a delete clears.
+CREATE TABLE bulk_operations (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    operation JSONB NOT NULL,
+    transaction_ids UUID[] NOT NULL,
+    snapshot JSONB NOT NULL,
+    applied_at TIMESTAMPTZ NOT NULL,
+    undone_at TIMESTAMPTZ
+);
+
+CREATE INDEX bulk_operations_user_applied_idx ON bulk_operations (user_id, applied_at);
@@ -0,0 +1,3 @@
+ALTER TABLE transactions DROP COLUMN import_batch_id;
+DROP TABLE import_batches;
+DROP TABLE import_profiles;
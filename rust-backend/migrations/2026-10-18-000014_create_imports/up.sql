@@ -0,0 +1,35 @@
+-- Saved column mappings for a bank's CSV export, so each month's statement imports the same way
+CREATE TABLE import_profiles (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR NOT NULL,
+    account_id UUID REFERENCES accounts(id) ON DELETE SET NULL,
+    mapping JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX import_profiles_user_name_idx ON import_profiles (user_id, LOWER(name));
+
+-- One uploaded statement; rolling it back deletes the transactions it created
+CREATE TABLE import_batches (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    profile_id UUID REFERENCES import_profiles(id) ON DELETE SET NULL,
+    account_id UUID REFERENCES accounts(id) ON DELETE SET NULL,
+    format VARCHAR NOT NULL,
+    file_name VARCHAR NOT NULL,
+    row_count INTEGER NOT NULL,
+    imported_count INTEGER NOT NULL,
+    duplicate_count INTEGER NOT NULL,
+    error_count INTEGER NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    rolled_back_at TIMESTAMPTZ
+);
+
+CREATE INDEX import_batches_user_created_idx ON import_batches (user_id, created_at);
+
+ALTER TABLE transactions
+    ADD COLUMN import_batch_id UUID REFERENCES import_batches(id) ON DELETE SET NULL;
+
+CREATE INDEX transactions_import_batch_idx ON transactions (import_batch_id);
@@ -0,0 +1,2 @@
+ALTER TABLE import_batches DROP COLUMN statement_account;
+ALTER TABLE transactions DROP COLUMN external_id;
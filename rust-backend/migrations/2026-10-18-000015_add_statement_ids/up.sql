@@ -0,0 +1,8 @@
+-- The bank's own ID for a statement line (the OFX FITID), so importing the same statement twice
+-- doesn't record its transactions twice
+ALTER TABLE transactions ADD COLUMN external_id VARCHAR;
+
+CREATE INDEX transactions_external_id_idx ON transactions (user_id, external_id) WHERE external_id IS NOT NULL;
+
+-- The account number a statement file names, used to pick the account for later statements
+ALTER TABLE import_batches ADD COLUMN statement_account VARCHAR;
@@ -0,0 +1,2 @@
+ALTER TABLE transactions DROP COLUMN bank_reference;
+ALTER TABLE transactions DROP COLUMN value_date;
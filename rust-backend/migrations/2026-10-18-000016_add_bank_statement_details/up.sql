@@ -0,0 +1,4 @@
+-- Bank statements separate the day a line was booked (the transaction date) from the day it
+-- took value, and carry the payer's own reference, e.g. an invoice number
+ALTER TABLE transactions ADD COLUMN value_date DATE;
+ALTER TABLE transactions ADD COLUMN bank_reference VARCHAR;
@@ -0,0 +1,16 @@
+-- The plain-text accounting account a category or account is exported as, when the user has
+-- chosen one instead of the default (Expenses:Groceries, Assets:Bank:KBank, ...)
+CREATE TABLE ledger_accounts (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    category_id UUID REFERENCES categories(id) ON DELETE CASCADE,
+    account_id UUID REFERENCES accounts(id) ON DELETE CASCADE,
+    name VARCHAR NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK ((category_id IS NULL) <> (account_id IS NULL))
+);
+
+CREATE UNIQUE INDEX ledger_accounts_category_idx ON ledger_accounts (category_id) WHERE category_id IS NOT NULL;
+CREATE UNIQUE INDEX ledger_accounts_account_idx ON ledger_accounts (account_id) WHERE account_id IS NOT NULL;
+CREATE INDEX ledger_accounts_user_idx ON ledger_accounts (user_id);
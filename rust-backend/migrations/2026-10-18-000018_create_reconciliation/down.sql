@@ -0,0 +1,2 @@
+DROP TABLE reconciliation_matches;
+ALTER TABLE transactions DROP COLUMN reconciled;
@@ -0,0 +1,23 @@
+-- A scanned receipt and the bank line it was paid with describe the same spend. Confirming a
+-- proposed pair marks both transactions reconciled
+ALTER TABLE transactions ADD COLUMN reconciled BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Pairs proposed by the reconciliation engine, kept once confirmed or rejected so a rejected
+-- pair isn't proposed again
+CREATE TABLE reconciliation_matches (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    receipt_transaction_id UUID NOT NULL REFERENCES transactions(id) ON DELETE CASCADE,
+    bank_transaction_id UUID NOT NULL REFERENCES transactions(id) ON DELETE CASCADE,
+    score REAL NOT NULL,
+    amount_difference NUMERIC NOT NULL,
+    days_apart INTEGER NOT NULL,
+    merchant_similarity REAL NOT NULL,
+    status VARCHAR NOT NULL DEFAULT 'proposed',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (receipt_transaction_id, bank_transaction_id)
+);
+
+CREATE INDEX reconciliation_matches_user_status_idx ON reconciliation_matches (user_id, status);
+CREATE INDEX reconciliation_matches_bank_idx ON reconciliation_matches (bank_transaction_id);
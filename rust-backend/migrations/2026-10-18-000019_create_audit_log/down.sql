@@ -0,0 +1,7 @@
+DROP TRIGGER users_audit ON users;
+DROP TRIGGER bills_audit ON bills;
+DROP TRIGGER categories_audit ON categories;
+DROP TRIGGER transactions_audit ON transactions;
+DROP FUNCTION audit_row_change();
+DROP TABLE audit_log;
+DROP FUNCTION audit_log_append_only();
@@ -0,0 +1,104 @@
+-- Append-only history of every change to transactions, categories, bills and users. Rows are
+-- written by triggers so no code path can skip them; the user and request behind a change come
+-- from the audit.actor_id and audit.request settings the app sets on its connection
+CREATE TABLE audit_log (
+    id BIGSERIAL PRIMARY KEY,
+    occurred_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    actor_id UUID,
+    -- The user whose data changed; no foreign keys, so history outlives what it describes
+    owner_id UUID,
+    action VARCHAR NOT NULL CHECK (action IN ('create', 'update', 'delete')),
+    entity_type VARCHAR NOT NULL,
+    entity_id UUID NOT NULL,
+    -- The whole row for creates and deletes, only the changed columns for updates
+    old_values JSONB,
+    new_values JSONB,
+    request JSONB
+);
+
+CREATE INDEX audit_log_entity_idx ON audit_log (entity_type, entity_id, id);
+CREATE INDEX audit_log_owner_id_occurred_at_idx ON audit_log (owner_id, occurred_at);
+
+CREATE FUNCTION audit_log_append_only()
+RETURNS TRIGGER
+LANGUAGE plpgsql
+AS $$
+BEGIN
+    RAISE EXCEPTION 'audit_log is append-only';
+END
+$$;
+
+CREATE TRIGGER audit_log_append_only
+BEFORE UPDATE OR DELETE OR TRUNCATE ON audit_log
+FOR EACH STATEMENT EXECUTE FUNCTION audit_log_append_only();
+
+-- Search columns are derived and updated_at changes with everything, so neither is recorded;
+-- password hashes are recorded only as having changed
+CREATE FUNCTION audit_row_change()
+RETURNS TRIGGER
+LANGUAGE plpgsql
+AS $$
+DECLARE
+    v_old JSONB;
+    v_new JSONB;
+    v_row JSONB;
+    v_key TEXT;
+BEGIN
+    IF TG_OP <> 'INSERT' THEN
+        v_old := TO_JSONB(OLD) - 'search_text' - 'search_vector';
+    END IF;
+    IF TG_OP <> 'DELETE' THEN
+        v_new := TO_JSONB(NEW) - 'search_text' - 'search_vector';
+    END IF;
+    v_row := COALESCE(v_new, v_old);
+
+    IF TG_OP = 'UPDATE' THEN
+        FOR v_key IN SELECT JSONB_OBJECT_KEYS(v_new) LOOP
+            IF v_key <> 'updated_at' AND v_new -> v_key IS DISTINCT FROM v_old -> v_key THEN
+                CONTINUE;
+            END IF;
+            v_old := v_old - v_key;
+            v_new := v_new - v_key;
+        END LOOP;
+        IF v_new = '{}'::jsonb THEN
+            RETURN NULL;
+        END IF;
+    END IF;
+
+    IF v_old ? 'password_hash' THEN
+        v_old := JSONB_SET(v_old, '{password_hash}', '"[redacted]"');
+    END IF;
+    IF v_new ? 'password_hash' THEN
+        v_new := JSONB_SET(v_new, '{password_hash}', '"[redacted]"');
+    END IF;
+
+    INSERT INTO audit_log (actor_id, owner_id, action, entity_type, entity_id, old_values, new_values, request)
+    VALUES (
+        CAST(NULLIF(CURRENT_SETTING('audit.actor_id', TRUE), '') AS UUID),
+        CAST(CASE WHEN TG_TABLE_NAME = 'users' THEN v_row ->> 'id' ELSE v_row ->> 'user_id' END AS UUID),
+        CASE TG_OP WHEN 'INSERT' THEN 'create' WHEN 'UPDATE' THEN 'update' ELSE 'delete' END,
+        TG_TABLE_NAME,
+        CAST(v_row ->> 'id' AS UUID),
+        v_old,
+        v_new,
+        CAST(NULLIF(CURRENT_SETTING('audit.request', TRUE), '') AS JSONB)
+    );
+    RETURN NULL;
+END
+$$;
+
+CREATE TRIGGER transactions_audit
+AFTER INSERT OR UPDATE OR DELETE ON transactions
+FOR EACH ROW EXECUTE FUNCTION audit_row_change();
+
+CREATE TRIGGER categories_audit
+AFTER INSERT OR UPDATE OR DELETE ON categories
+FOR EACH ROW EXECUTE FUNCTION audit_row_change();
+
+CREATE TRIGGER bills_audit
+AFTER INSERT OR UPDATE OR DELETE ON bills
+FOR EACH ROW EXECUTE FUNCTION audit_row_change();
+
+CREATE TRIGGER users_audit
+AFTER INSERT OR UPDATE OR DELETE ON users
+FOR EACH ROW EXECUTE FUNCTION audit_row_change();
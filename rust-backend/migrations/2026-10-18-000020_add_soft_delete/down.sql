@@ -0,0 +1,55 @@
+DROP TRIGGER bills_search_refresh ON bills;
+CREATE TRIGGER bills_search_refresh
+AFTER INSERT OR DELETE OR UPDATE OF transaction_id, ocr_text ON bills
+FOR EACH ROW EXECUTE FUNCTION bills_search_refresh();
+
+CREATE OR REPLACE FUNCTION transactions_search_refresh()
+RETURNS TRIGGER
+LANGUAGE plpgsql
+AS $$
+DECLARE
+    v_items TEXT := transaction_item_names(NEW.items);
+    v_ocr TEXT;
+BEGIN
+    SELECT COALESCE(STRING_AGG(ocr_text, ' '), '') INTO v_ocr
+    FROM bills
+    WHERE transaction_id = NEW.id;
+
+    NEW.search_text := CONCAT_WS(' ', NEW.merchant, NEW.notes, v_items, v_ocr);
+    NEW.search_vector :=
+        SETWEIGHT(TO_TSVECTOR('simple', COALESCE(NEW.merchant, '')), 'A') ||
+        SETWEIGHT(TO_TSVECTOR('simple', v_items), 'B') ||
+        SETWEIGHT(TO_TSVECTOR('simple', COALESCE(NEW.notes, '')), 'C') ||
+        SETWEIGHT(TO_TSVECTOR('simple', v_ocr), 'D');
+    RETURN NEW;
+END
+$$;
+
+CREATE OR REPLACE VIEW transaction_allocations AS
+SELECT s.id,
+       t.id AS transaction_id,
+       t.user_id,
+       s.category_id,
+       s.amount,
+       t.currency,
+       t.date,
+       t.transaction_type,
+       t.excluded
+FROM transaction_splits s
+JOIN transactions t ON t.id = s.transaction_id
+UNION ALL
+SELECT t.id,
+       t.id AS transaction_id,
+       t.user_id,
+       t.category_id,
+       t.amount,
+       t.currency,
+       t.date,
+       t.transaction_type,
+       t.excluded
+FROM transactions t
+WHERE NOT EXISTS (SELECT 1 FROM transaction_splits s WHERE s.transaction_id = t.id);
+
+ALTER TABLE categories DROP COLUMN deleted_at;
+ALTER TABLE bills DROP COLUMN deleted_at;
+ALTER TABLE transactions DROP COLUMN deleted_at;
@@ -0,0 +1,66 @@
+-- Deleted transactions, bills and categories go to the trash first: deleted_at is set and they
+-- are left out everywhere until restored, or purged for good once the retention period is over
+ALTER TABLE transactions ADD COLUMN deleted_at TIMESTAMPTZ;
+ALTER TABLE bills ADD COLUMN deleted_at TIMESTAMPTZ;
+ALTER TABLE categories ADD COLUMN deleted_at TIMESTAMPTZ;
+
+CREATE INDEX transactions_deleted_at_idx ON transactions (deleted_at) WHERE deleted_at IS NOT NULL;
+CREATE INDEX bills_deleted_at_idx ON bills (deleted_at) WHERE deleted_at IS NOT NULL;
+CREATE INDEX categories_deleted_at_idx ON categories (deleted_at) WHERE deleted_at IS NOT NULL;
+
+-- Trashed transactions count towards no report or budget
+CREATE OR REPLACE VIEW transaction_allocations AS
+SELECT s.id,
+       t.id AS transaction_id,
+       t.user_id,
+       s.category_id,
+       s.amount,
+       t.currency,
+       t.date,
+       t.transaction_type,
+       t.excluded
+FROM transaction_splits s
+JOIN transactions t ON t.id = s.transaction_id
+WHERE t.deleted_at IS NULL
+UNION ALL
+SELECT t.id,
+       t.id AS transaction_id,
+       t.user_id,
+       t.category_id,
+       t.amount,
+       t.currency,
+       t.date,
+       t.transaction_type,
+       t.excluded
+FROM transactions t
+WHERE t.deleted_at IS NULL
+  AND NOT EXISTS (SELECT 1 FROM transaction_splits s WHERE s.transaction_id = t.id);
+
+-- A trashed bill's OCR text no longer makes its transaction searchable
+CREATE OR REPLACE FUNCTION transactions_search_refresh()
+RETURNS TRIGGER
+LANGUAGE plpgsql
+AS $$
+DECLARE
+    v_items TEXT := transaction_item_names(NEW.items);
+    v_ocr TEXT;
+BEGIN
+    SELECT COALESCE(STRING_AGG(ocr_text, ' '), '') INTO v_ocr
+    FROM bills
+    WHERE transaction_id = NEW.id
+      AND deleted_at IS NULL;
+
+    NEW.search_text := CONCAT_WS(' ', NEW.merchant, NEW.notes, v_items, v_ocr);
+    NEW.search_vector :=
+        SETWEIGHT(TO_TSVECTOR('simple', COALESCE(NEW.merchant, '')), 'A') ||
+        SETWEIGHT(TO_TSVECTOR('simple', v_items), 'B') ||
+        SETWEIGHT(TO_TSVECTOR('simple', COALESCE(NEW.notes, '')), 'C') ||
+        SETWEIGHT(TO_TSVECTOR('simple', v_ocr), 'D');
+    RETURN NEW;
+END
+$$;
+
+DROP TRIGGER bills_search_refresh ON bills;
+CREATE TRIGGER bills_search_refresh
+AFTER INSERT OR DELETE OR UPDATE OF transaction_id, ocr_text, deleted_at ON bills
+FOR EACH ROW EXECUTE FUNCTION bills_search_refresh();
@@ -0,0 +1,33 @@
+DROP TRIGGER users_create_personal_ledger ON users;
+DROP FUNCTION users_create_personal_ledger();
+DROP FUNCTION invitation_token_hash(TEXT);
+DROP TABLE ledger_invitations;
+DROP TABLE ledger_members;
+
+-- Shared ledgers have no user to go back to, so their data goes with them
+DELETE FROM ledgers WHERE id NOT IN (SELECT id FROM users);
+
+DO $$
+DECLARE
+    v_fk RECORD;
+BEGIN
+    FOR v_fk IN
+        SELECT c.conname, CAST(c.conrelid AS REGCLASS) AS table_name
+        FROM pg_constraint c
+        JOIN pg_attribute a ON a.attrelid = c.conrelid AND a.attnum = c.conkey[1]
+        WHERE c.contype = 'f'
+          AND c.confrelid = CAST('ledgers' AS REGCLASS)
+          AND CARDINALITY(c.conkey) = 1
+          AND a.attname = 'user_id'
+    LOOP
+        EXECUTE FORMAT('ALTER TABLE %s DROP CONSTRAINT %I', v_fk.table_name, v_fk.conname);
+        EXECUTE FORMAT(
+            'ALTER TABLE %s ADD CONSTRAINT %I FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE',
+            v_fk.table_name,
+            v_fk.conname
+        );
+    END LOOP;
+END
+$$;
+
+DROP TABLE ledgers;
@@ -0,0 +1,99 @@
+-- A ledger owns the data that used to belong to a single user, so a household or a team can
+-- share it. Every user keeps a personal ledger with the same id as the user, which is what
+-- the existing user_id columns already hold; they now reference ledgers instead of users
+CREATE TABLE ledgers (
+    id UUID PRIMARY KEY,
+    name VARCHAR NOT NULL,
+    base_currency VARCHAR(3) NOT NULL DEFAULT 'THB',
+    -- Deleting a user deletes the ledgers they created, their personal one included
+    created_by UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX ledgers_created_by_idx ON ledgers (created_by);
+
+INSERT INTO ledgers (id, name, base_currency, created_by, created_at, updated_at)
+SELECT id, name, base_currency, id, created_at, NOW()
+FROM users;
+
+-- Re-point every user_id foreign key at ledgers, keeping the constraint names
+DO $$
+DECLARE
+    v_fk RECORD;
+BEGIN
+    FOR v_fk IN
+        SELECT c.conname, CAST(c.conrelid AS REGCLASS) AS table_name
+        FROM pg_constraint c
+        JOIN pg_attribute a ON a.attrelid = c.conrelid AND a.attnum = c.conkey[1]
+        WHERE c.contype = 'f'
+          AND c.confrelid = CAST('users' AS REGCLASS)
+          AND CARDINALITY(c.conkey) = 1
+          AND a.attname = 'user_id'
+    LOOP
+        EXECUTE FORMAT('ALTER TABLE %s DROP CONSTRAINT %I', v_fk.table_name, v_fk.conname);
+        EXECUTE FORMAT(
+            'ALTER TABLE %s ADD CONSTRAINT %I FOREIGN KEY (user_id) REFERENCES ledgers(id) ON DELETE CASCADE',
+            v_fk.table_name,
+            v_fk.conname
+        );
+    END LOOP;
+END
+$$;
+
+-- Owners manage the ledger and its members, editors change its data, viewers only read it
+CREATE TABLE ledger_members (
+    ledger_id UUID NOT NULL REFERENCES ledgers(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    role VARCHAR NOT NULL CHECK (role IN ('owner', 'editor', 'viewer')),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (ledger_id, user_id)
+);
+
+CREATE INDEX ledger_members_user_id_idx ON ledger_members (user_id);
+
+INSERT INTO ledger_members (ledger_id, user_id, role)
+SELECT id, id, 'owner'
+FROM users;
+
+-- Only a hash of the token is kept; the token itself is shown once, to whoever invited
+CREATE TABLE ledger_invitations (
+    id UUID PRIMARY KEY,
+    ledger_id UUID NOT NULL REFERENCES ledgers(id) ON DELETE CASCADE,
+    email VARCHAR NOT NULL,
+    role VARCHAR NOT NULL CHECK (role IN ('owner', 'editor', 'viewer')),
+    token_hash VARCHAR NOT NULL UNIQUE,
+    invited_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL,
+    accepted_at TIMESTAMPTZ,
+    accepted_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    revoked_at TIMESTAMPTZ
+);
+
+CREATE INDEX ledger_invitations_ledger_id_idx ON ledger_invitations (ledger_id);
+
+CREATE FUNCTION invitation_token_hash(p_token TEXT)
+RETURNS TEXT
+LANGUAGE sql IMMUTABLE AS $$
+    SELECT ENCODE(SHA256(CONVERT_TO(p_token, 'UTF8')), 'hex')
+$$;
+
+-- New users get their personal ledger as they are created
+CREATE FUNCTION users_create_personal_ledger()
+RETURNS TRIGGER
+LANGUAGE plpgsql
+AS $$
+BEGIN
+    INSERT INTO ledgers (id, name, base_currency, created_by, created_at, updated_at)
+    VALUES (NEW.id, NEW.name, NEW.base_currency, NEW.id, NEW.created_at, NEW.created_at);
+    INSERT INTO ledger_members (ledger_id, user_id, role, created_at, updated_at)
+    VALUES (NEW.id, NEW.id, 'owner', NEW.created_at, NEW.created_at);
+    RETURN NULL;
+END
+$$;
+
+CREATE TRIGGER users_create_personal_ledger
+AFTER INSERT ON users
+FOR EACH ROW EXECUTE FUNCTION users_create_personal_ledger();
@@ -0,0 +1,3 @@
+DROP INDEX transactions_external_id_idx;
+
+CREATE INDEX transactions_external_id_idx ON transactions (user_id, external_id) WHERE external_id IS NOT NULL;
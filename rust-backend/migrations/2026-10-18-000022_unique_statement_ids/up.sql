@@ -0,0 +1,17 @@
+-- A statement line is recorded once per account, even while it sits in the trash. Lines imported
+-- twice before this keep the bank ID on their newest live copy only
+UPDATE transactions t
+SET external_id = NULL
+WHERE t.external_id IS NOT NULL
+  AND EXISTS (
+    SELECT 1 FROM transactions o
+    WHERE o.user_id = t.user_id
+      AND o.account_id IS NOT DISTINCT FROM t.account_id
+      AND o.external_id = t.external_id
+      AND (o.deleted_at IS NULL, o.created_at, o.id) > (t.deleted_at IS NULL, t.created_at, t.id)
+  );
+
+DROP INDEX transactions_external_id_idx;
+
+CREATE UNIQUE INDEX transactions_external_id_idx ON transactions (user_id, account_id, external_id) NULLS NOT DISTINCT
+    WHERE external_id IS NOT NULL;
@@ -0,0 +1,14 @@
+ALTER TABLE transaction_splits
+    ADD COLUMN tags TEXT[] NOT NULL DEFAULT '{}';
+
+UPDATE transaction_splits s
+SET tags = tagged.names
+FROM (
+    SELECT st.split_id, ARRAY_AGG(g.name ORDER BY g.name) AS names
+    FROM split_tags st
+    JOIN tags g ON g.id = st.tag_id
+    GROUP BY st.split_id
+) AS tagged
+WHERE tagged.split_id = s.id;
+
+DROP TABLE split_tags;
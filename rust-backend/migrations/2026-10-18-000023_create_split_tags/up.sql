@@ -0,0 +1,28 @@
+-- Split tags move onto the shared tags too, so renames, merges, tag filters and the spending by
+-- tag report cover them
+CREATE TABLE split_tags (
+    split_id UUID NOT NULL REFERENCES transaction_splits(id) ON DELETE CASCADE,
+    tag_id UUID NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+    PRIMARY KEY (split_id, tag_id)
+);
+
+CREATE INDEX split_tags_tag_idx ON split_tags (tag_id);
+
+-- Names no transaction carried yet become tags; spellings that differ only in case become one
+INSERT INTO tags (id, user_id, name)
+SELECT gen_random_uuid(), user_id, MIN(tag)
+FROM (
+    SELECT user_id, BTRIM(UNNEST(tags)) AS tag
+    FROM transaction_splits
+) AS existing
+WHERE tag <> ''
+GROUP BY user_id, LOWER(tag)
+ON CONFLICT DO NOTHING;
+
+INSERT INTO split_tags (split_id, tag_id)
+SELECT DISTINCT s.id, g.id
+FROM transaction_splits s
+CROSS JOIN LATERAL UNNEST(s.tags) AS tag
+JOIN tags g ON g.user_id = s.user_id AND LOWER(g.name) = LOWER(BTRIM(tag));
+
+ALTER TABLE transaction_splits DROP COLUMN tags;
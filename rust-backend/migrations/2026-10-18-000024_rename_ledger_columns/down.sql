@@ -0,0 +1,119 @@
+CREATE OR REPLACE FUNCTION audit_row_change()
+RETURNS TRIGGER
+LANGUAGE plpgsql
+AS $$
+DECLARE
+    v_old JSONB;
+    v_new JSONB;
+    v_row JSONB;
+    v_key TEXT;
+BEGIN
+    IF TG_OP <> 'INSERT' THEN
+        v_old := TO_JSONB(OLD) - 'search_text' - 'search_vector';
+    END IF;
+    IF TG_OP <> 'DELETE' THEN
+        v_new := TO_JSONB(NEW) - 'search_text' - 'search_vector';
+    END IF;
+    v_row := COALESCE(v_new, v_old);
+
+    IF TG_OP = 'UPDATE' THEN
+        FOR v_key IN SELECT JSONB_OBJECT_KEYS(v_new) LOOP
+            IF v_key <> 'updated_at' AND v_new -> v_key IS DISTINCT FROM v_old -> v_key THEN
+                CONTINUE;
+            END IF;
+            v_old := v_old - v_key;
+            v_new := v_new - v_key;
+        END LOOP;
+        IF v_new = '{}'::jsonb THEN
+            RETURN NULL;
+        END IF;
+    END IF;
+
+    IF v_old ? 'password_hash' THEN
+        v_old := JSONB_SET(v_old, '{password_hash}', '"[redacted]"');
+    END IF;
+    IF v_new ? 'password_hash' THEN
+        v_new := JSONB_SET(v_new, '{password_hash}', '"[redacted]"');
+    END IF;
+
+    INSERT INTO audit_log (actor_id, owner_id, action, entity_type, entity_id, old_values, new_values, request)
+    VALUES (
+        CAST(NULLIF(CURRENT_SETTING('audit.actor_id', TRUE), '') AS UUID),
+        CAST(CASE WHEN TG_TABLE_NAME = 'users' THEN v_row ->> 'id' ELSE v_row ->> 'user_id' END AS UUID),
+        CASE TG_OP WHEN 'INSERT' THEN 'create' WHEN 'UPDATE' THEN 'update' ELSE 'delete' END,
+        TG_TABLE_NAME,
+        CAST(v_row ->> 'id' AS UUID),
+        v_old,
+        v_new,
+        CAST(NULLIF(CURRENT_SETTING('audit.request', TRUE), '') AS JSONB)
+    );
+    RETURN NULL;
+END
+$$;
+
+DROP FUNCTION convert_amount(UUID, NUMERIC, VARCHAR, VARCHAR, TIMESTAMPTZ, TEXT);
+
+CREATE FUNCTION convert_amount(
+    p_user_id UUID,
+    p_amount NUMERIC,
+    p_from VARCHAR,
+    p_to VARCHAR,
+    p_at TIMESTAMPTZ,
+    p_tz TEXT
+) RETURNS NUMERIC
+LANGUAGE sql STABLE AS $$
+    SELECT CASE
+        WHEN p_from = p_to THEN p_amount
+        ELSE p_amount * (
+            SELECT CASE WHEN er.from_currency = p_from THEN er.rate ELSE 1 / er.rate END
+            FROM exchange_rates er
+            WHERE er.user_id = p_user_id
+              AND ((er.from_currency = p_from AND er.to_currency = p_to)
+                OR (er.from_currency = p_to AND er.to_currency = p_from))
+            ORDER BY er.rate_date > CAST(p_at AT TIME ZONE p_tz AS date),
+                     abs(er.rate_date - CAST(p_at AT TIME ZONE p_tz AS date)),
+                     er.from_currency = p_from DESC
+            LIMIT 1
+        )
+    END
+$$;
+
+UPDATE bulk_operations
+SET snapshot = snapshot || JSONB_BUILD_OBJECT(
+    'transactions', (
+        SELECT COALESCE(JSONB_AGG(r - 'ledger_id' || JSONB_BUILD_OBJECT('user_id', r -> 'ledger_id')), '[]'::jsonb)
+        FROM JSONB_ARRAY_ELEMENTS(snapshot -> 'transactions') AS r
+    ),
+    'splits', (
+        SELECT COALESCE(JSONB_AGG(r - 'ledger_id' || JSONB_BUILD_OBJECT('user_id', r -> 'ledger_id')), '[]'::jsonb)
+        FROM JSONB_ARRAY_ELEMENTS(snapshot -> 'splits') AS r
+    )
+)
+WHERE undone_at IS NULL;
+
+ALTER VIEW transaction_allocations RENAME COLUMN ledger_id TO user_id;
+
+DO $$
+DECLARE
+    v_fk RECORD;
+BEGIN
+    FOR v_fk IN
+        SELECT c.conname, CAST(c.conrelid AS REGCLASS) AS table_name
+        FROM pg_constraint c
+        JOIN pg_attribute a ON a.attrelid = c.conrelid AND a.attnum = c.conkey[1]
+        WHERE c.contype = 'f'
+          AND c.confrelid = CAST('ledgers' AS REGCLASS)
+          AND CARDINALITY(c.conkey) = 1
+          AND a.attname = 'ledger_id'
+          AND c.conrelid NOT IN (CAST('ledger_members' AS REGCLASS), CAST('ledger_invitations' AS REGCLASS))
+    LOOP
+        EXECUTE FORMAT('ALTER TABLE %s RENAME COLUMN ledger_id TO user_id', v_fk.table_name);
+        EXECUTE FORMAT(
+            'ALTER TABLE %s RENAME CONSTRAINT %I TO %I',
+            v_fk.table_name,
+            v_fk.conname,
+            REPLACE(v_fk.conname, 'ledger_id', 'user_id')
+        );
+    END LOOP;
+END
+$$;
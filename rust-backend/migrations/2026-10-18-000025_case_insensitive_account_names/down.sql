@@ -0,0 +1,3 @@
+DROP INDEX accounts_ledger_name_idx;
+
+ALTER TABLE accounts ADD CONSTRAINT accounts_user_id_name_key UNIQUE (ledger_id, name);
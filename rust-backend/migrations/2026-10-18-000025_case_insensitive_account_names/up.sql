@@ -0,0 +1,5 @@
+-- Account names were only unique as typed, while the app treats them as unique ignoring case;
+-- the index makes two requests racing on "Cash" and "cash" fail the same way
+ALTER TABLE accounts DROP CONSTRAINT accounts_user_id_name_key;
+
+CREATE UNIQUE INDEX accounts_ledger_name_idx ON accounts (ledger_id, LOWER(name));
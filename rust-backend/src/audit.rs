@@ -0,0 +1,64 @@
+use std::cell::RefCell;
+use std::future::Future;
+
+use actix_web::http::header::USER_AGENT;
+use actix_web::HttpRequest;
+use diesel::prelude::*;
+use diesel::sql_query;
+use diesel::sql_types::Text;
+use uuid::Uuid;
+
+use crate::models::audit::AuditContext;
+
+// The request being served, for the audit triggers to record against each change it makes
+tokio::task_local! {
+    static CONTEXT: RefCell<AuditContext>;
+}
+
+impl AuditContext {
+    pub fn from_request(req: &HttpRequest) -> Self {
+        AuditContext {
+            actor_id: None,
+            request_id: Uuid::new_v4(),
+            method: req.method().to_string(),
+            path: req.path().to_string(),
+            ip: req.connection_info().realip_remote_addr().map(str::to_string),
+            user_agent: req
+                .headers()
+                .get(USER_AGENT)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string),
+        }
+    }
+}
+
+// Run a request's handling with its audit context in scope
+pub fn scope<F: Future>(context: AuditContext, f: F) -> impl Future<Output = F::Output> {
+    CONTEXT.scope(RefCell::new(context), f)
+}
+
+// Attribute the rest of the request's changes to the authenticated user
+pub fn set_actor(user_id: Uuid) {
+    let _ = CONTEXT.try_with(|context| context.borrow_mut().actor_id = Some(user_id));
+}
+
+pub fn current() -> Option<AuditContext> {
+    CONTEXT.try_with(|context| context.borrow().clone()).ok()
+}
+
+// Pass the context on to the audit triggers for the connection's next queries; None clears it
+// so a pooled connection doesn't credit the last request's user with someone else's changes
+pub fn apply(conn: &mut PgConnection, context: Option<&AuditContext>) -> QueryResult<()> {
+    let actor_id = context
+        .and_then(|context| context.actor_id)
+        .map(|id| id.to_string())
+        .unwrap_or_default();
+    let request = context
+        .and_then(|context| serde_json::to_string(context).ok())
+        .unwrap_or_default();
+    sql_query("SELECT set_config('audit.actor_id', $1, false), set_config('audit.request', $2, false)")
+        .bind::<Text, _>(actor_id)
+        .bind::<Text, _>(request)
+        .execute(conn)?;
+    Ok(())
+}
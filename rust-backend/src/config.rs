@@ -9,25 +9,14 @@ use serde::{Deserialize, Serialize};
 #[derive(Debug, Clone)]
 pub struct Config {
     pub server: ServerConfig,
-    pub database: DatabaseConfig,
     pub jwt: JwtConfig,
 }
 
 #[derive(Debug, Clone)]
 pub struct ServerConfig {
-    pub host: String,
-    pub port: u16,
-    pub cors_origin: String,
     pub upload_dir: String,
 }
 
-#[derive(Debug, Clone)]
-pub struct DatabaseConfig {
-    pub url: String,
-    pub max_connections: u32,
-    pub timeout: Duration,
-}
-
 #[derive(Debug, Clone)]
 pub struct JwtConfig {
     pub secret: String,
@@ -104,29 +93,8 @@ impl Config {
     pub fn from_env() -> Self {
         Config {
             server: ServerConfig {
-                host: env::var("HOST").unwrap_or_else(|_| "127.0.0.1".to_string()),
-                port: env::var("PORT")
-                    .unwrap_or_else(|_| "8080".to_string())
-                    .parse()
-                    .unwrap_or(8080),
-                cors_origin: env::var("CORS_ORIGIN")
-                    .unwrap_or_else(|_| "http://localhost:3000".to_string()),
                 upload_dir: env::var("UPLOAD_DIR").unwrap_or_else(|_| "./uploads".to_string()),
             },
-            database: DatabaseConfig {
-                url: env::var("DATABASE_URL")
-                    .expect("DATABASE_URL environment variable must be set"),
-                max_connections: env::var("DB_MAX_CONNECTIONS")
-                    .unwrap_or_else(|_| "5".to_string())
-                    .parse()
-                    .unwrap_or(5),
-                timeout: Duration::from_secs(
-                    env::var("DB_TIMEOUT")
-                        .unwrap_or_else(|_| "30".to_string())
-                        .parse()
-                        .unwrap_or(30),
-                ),
-            },
             jwt: JwtConfig {
                 secret: env::var("JWT_SECRET")
                     .expect("JWT_SECRET environment variable must be set"),
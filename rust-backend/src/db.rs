@@ -1,9 +1,12 @@
+use actix_web::web;
 use diesel::pg::PgConnection;
 use diesel::r2d2::{self, ConnectionManager};
 use std::env;
 
+use crate::audit;
+use crate::error::AppError;
+
 pub type DbPool = r2d2::Pool<ConnectionManager<PgConnection>>;
-pub type DbConnection = r2d2::PooledConnection<ConnectionManager<PgConnection>>;
 
 pub fn establish_connection() -> DbPool {
     let database_url = env::var("DATABASE_URL")
@@ -13,4 +16,25 @@ pub fn establish_connection() -> DbPool {
     r2d2::Pool::builder()
         .build(manager)
         .expect("Failed to create database connection pool")
-} 
\ No newline at end of file
+}
+
+// Run a blocking Diesel query on the actix blocking thread pool. Changes it makes are audited
+// against the current request
+pub async fn run<F, T>(pool: &web::Data<DbPool>, f: F) -> Result<T, AppError>
+where
+    F: FnOnce(&mut PgConnection) -> Result<T, AppError> + Send + 'static,
+    T: Send + 'static,
+{
+    let pool = pool.clone();
+    let context = audit::current();
+    web::block(move || {
+        let mut conn = pool.get()
+            .map_err(|e| AppError::InternalServerError(format!("Failed to get database connection: {}", e)))?;
+        audit::apply(&mut conn, context.as_ref())?;
+        let result = f(&mut conn);
+        audit::apply(&mut conn, None)?;
+        result
+    })
+    .await
+    .map_err(|e| AppError::InternalServerError(format!("Blocking task failed: {}", e)))?
+}
@@ -15,15 +15,15 @@ pub enum AppError {
     #[error("JSON error: {0}")]
     JsonError(#[from] serde_json::Error),
     
-    #[error("Image processing error: {0}")]
-    ImageError(String),
-    
     #[error("OCR processing error: {0}")]
     OcrError(String),
     
     #[error("Authentication error: {0}")]
     AuthError(String),
     
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+    
     #[error("Not found: {0}")]
     NotFound(String),
     
@@ -38,9 +38,6 @@ pub enum AppError {
     
     #[error("External API error: {0}")]
     ExternalApiError(String),
-    
-    #[error("Parse error: {0}")]
-    ParseError(String),
 }
 
 impl ResponseError for AppError {
@@ -50,15 +47,14 @@ impl ResponseError for AppError {
             AppError::EnvError(_) => HttpResponse::InternalServerError().json(ErrorResponse::new(self)),
             AppError::IoError(_) => HttpResponse::InternalServerError().json(ErrorResponse::new(self)),
             AppError::JsonError(_) => HttpResponse::BadRequest().json(ErrorResponse::new(self)),
-            AppError::ImageError(_) => HttpResponse::BadRequest().json(ErrorResponse::new(self)),
             AppError::OcrError(_) => HttpResponse::BadRequest().json(ErrorResponse::new(self)),
             AppError::AuthError(_) => HttpResponse::Unauthorized().json(ErrorResponse::new(self)),
+            AppError::Forbidden(_) => HttpResponse::Forbidden().json(ErrorResponse::new(self)),
             AppError::NotFound(_) => HttpResponse::NotFound().json(ErrorResponse::new(self)),
             AppError::BadRequest(_) => HttpResponse::BadRequest().json(ErrorResponse::new(self)),
             AppError::InternalServerError(_) => HttpResponse::InternalServerError().json(ErrorResponse::new(self)),
             AppError::ConfigError(_) => HttpResponse::InternalServerError().json(ErrorResponse::new(self)),
             AppError::ExternalApiError(_) => HttpResponse::InternalServerError().json(ErrorResponse::new(self)),
-            AppError::ParseError(_) => HttpResponse::BadRequest().json(ErrorResponse::new(self)),
         }
     }
 }
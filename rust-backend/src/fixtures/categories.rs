@@ -1,6 +1,6 @@
 use diesel::PgConnection;
 use uuid::Uuid;
-use crate::models::category::{Category, NewCategory};
+use crate::models::category::{CategoryKind, NewCategory};
 use diesel::prelude::*;
 use chrono::Utc;
 
@@ -23,9 +23,10 @@ pub fn load(connection: &mut PgConnection) -> Result<(), Box<dyn std::error::Err
             description: Some("Food and household items".to_string()),
             color: Some("#4CAF50".to_string()),
             icon: Some("shopping_cart".to_string()),
-            user_id: system_user_id,
+            ledger_id: system_user_id,
             created_at: Utc::now(),
             updated_at: Utc::now(),
+            kind: CategoryKind::Expense.as_str().to_string(),
         },
         NewCategory {
             id: Uuid::new_v4(),
@@ -33,9 +34,10 @@ pub fn load(connection: &mut PgConnection) -> Result<(), Box<dyn std::error::Err
             description: Some("Restaurants and take-out".to_string()),
             color: Some("#FF9800".to_string()),
             icon: Some("restaurant".to_string()),
-            user_id: system_user_id,
+            ledger_id: system_user_id,
             created_at: Utc::now(),
             updated_at: Utc::now(),
+            kind: CategoryKind::Expense.as_str().to_string(),
         },
         NewCategory {
             id: Uuid::new_v4(),
@@ -43,9 +45,10 @@ pub fn load(connection: &mut PgConnection) -> Result<(), Box<dyn std::error::Err
             description: Some("Public transport, gas, etc.".to_string()),
             color: Some("#2196F3".to_string()),
             icon: Some("directions_car".to_string()),
-            user_id: system_user_id,
+            ledger_id: system_user_id,
             created_at: Utc::now(),
             updated_at: Utc::now(),
+            kind: CategoryKind::Expense.as_str().to_string(),
         },
         NewCategory {
             id: Uuid::new_v4(),
@@ -53,9 +56,10 @@ pub fn load(connection: &mut PgConnection) -> Result<(), Box<dyn std::error::Err
             description: Some("Movies, concerts, events".to_string()),
             color: Some("#9C27B0".to_string()),
             icon: Some("local_movies".to_string()),
-            user_id: system_user_id,
+            ledger_id: system_user_id,
             created_at: Utc::now(),
             updated_at: Utc::now(),
+            kind: CategoryKind::Expense.as_str().to_string(),
         },
         NewCategory {
             id: Uuid::new_v4(),
@@ -63,9 +67,10 @@ pub fn load(connection: &mut PgConnection) -> Result<(), Box<dyn std::error::Err
             description: Some("Water, electricity, internet".to_string()),
             color: Some("#607D8B".to_string()),
             icon: Some("power".to_string()),
-            user_id: system_user_id,
+            ledger_id: system_user_id,
             created_at: Utc::now(),
             updated_at: Utc::now(),
+            kind: CategoryKind::Expense.as_str().to_string(),
         },
         NewCategory {
             id: Uuid::new_v4(),
@@ -73,9 +78,10 @@ pub fn load(connection: &mut PgConnection) -> Result<(), Box<dyn std::error::Err
             description: Some("Medical expenses".to_string()),
             color: Some("#F44336".to_string()),
             icon: Some("local_hospital".to_string()),
-            user_id: system_user_id,
+            ledger_id: system_user_id,
             created_at: Utc::now(),
             updated_at: Utc::now(),
+            kind: CategoryKind::Expense.as_str().to_string(),
         },
         NewCategory {
             id: Uuid::new_v4(),
@@ -83,9 +89,10 @@ pub fn load(connection: &mut PgConnection) -> Result<(), Box<dyn std::error::Err
             description: Some("Clothing, electronics, etc.".to_string()),
             color: Some("#E91E63".to_string()),
             icon: Some("shopping_bag".to_string()),
-            user_id: system_user_id,
+            ledger_id: system_user_id,
             created_at: Utc::now(),
             updated_at: Utc::now(),
+            kind: CategoryKind::Expense.as_str().to_string(),
         },
         NewCategory {
             id: Uuid::new_v4(),
@@ -93,9 +100,43 @@ pub fn load(connection: &mut PgConnection) -> Result<(), Box<dyn std::error::Err
             description: Some("Miscellaneous expenses".to_string()),
             color: Some("#9E9E9E".to_string()),
             icon: Some("more_horiz".to_string()),
-            user_id: system_user_id,
+            ledger_id: system_user_id,
             created_at: Utc::now(),
             updated_at: Utc::now(),
+            kind: CategoryKind::Expense.as_str().to_string(),
+        },
+        NewCategory {
+            id: Uuid::new_v4(),
+            name: "Salary".to_string(),
+            description: Some("Wages and bonuses".to_string()),
+            color: Some("#2E7D32".to_string()),
+            icon: Some("work".to_string()),
+            ledger_id: system_user_id,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            kind: CategoryKind::Income.as_str().to_string(),
+        },
+        NewCategory {
+            id: Uuid::new_v4(),
+            name: "Freelance".to_string(),
+            description: Some("Client and side-project income".to_string()),
+            color: Some("#00897B".to_string()),
+            icon: Some("laptop".to_string()),
+            ledger_id: system_user_id,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            kind: CategoryKind::Income.as_str().to_string(),
+        },
+        NewCategory {
+            id: Uuid::new_v4(),
+            name: "Interest".to_string(),
+            description: Some("Bank interest and dividends".to_string()),
+            color: Some("#558B2F".to_string()),
+            icon: Some("savings".to_string()),
+            ledger_id: system_user_id,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            kind: CategoryKind::Income.as_str().to_string(),
         },
     ];
     
@@ -1,11 +1,9 @@
 pub mod users;
 pub mod categories;
-// Commenting out transactions until we resolve Diesel type issues
-// pub mod transactions;
+pub mod transactions;
 
 use diesel::PgConnection;
 use diesel::prelude::*;
-use diesel::r2d2::{ConnectionManager, Pool};
 use crate::db::establish_connection;
 
 pub fn load_all_fixtures() -> Result<(), Box<dyn std::error::Error>> {
@@ -20,8 +18,7 @@ pub fn load_all_fixtures() -> Result<(), Box<dyn std::error::Error>> {
         // Load fixtures in order
         users::load(conn)?;
         categories::load(conn)?;
-        // Temporarily skip transactions until we fix type issues
-        // transactions::load(conn)?;
+        transactions::load(conn)?;
         
         Ok(())
     })?;
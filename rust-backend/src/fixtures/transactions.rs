@@ -1,11 +1,13 @@
 use diesel::PgConnection;
 use uuid::Uuid;
-use crate::models::transaction::{NewTransaction, TransactionItem};
-use crate::models::category::DbCategory;
+use crate::models::transaction::{Direction, NewTransaction, TransactionItem, TransactionType, SOURCE_MANUAL};
+use crate::models::category::{CategoryKind, DbCategory};
 use diesel::prelude::*;
 use chrono::{Utc, Duration};
 use serde_json::json;
 use rand::Rng;
+use bigdecimal::BigDecimal;
+use crate::money::{Currency, Money};
 
 pub fn load(connection: &mut PgConnection) -> Result<(), Box<dyn std::error::Error>> {
     // Get users
@@ -16,7 +18,9 @@ pub fn load(connection: &mut PgConnection) -> Result<(), Box<dyn std::error::Err
     
     // Get categories
     use crate::schema::categories;
-    let all_categories: Vec<DbCategory> = categories::table.load(connection)?;
+    let all_categories: Vec<DbCategory> = categories::table
+        .filter(categories::kind.eq(CategoryKind::Expense.as_str()))
+        .load(connection)?;
     
     let mut transactions = Vec::new();
     let mut rng = rand::thread_rng();
@@ -37,15 +41,16 @@ pub fn load(connection: &mut PgConnection) -> Result<(), Box<dyn std::error::Err
             let category = &all_categories[category_index];
             
             // Generate a random transaction amount between $5 and $200
-            let amount = (rng.gen_range(500..20000) as f64) / 100.0;
-            let amount_string = amount.to_string(); // Convert to string
+            let amount_cents: i64 = rng.gen_range(500..20000);
+            let amount_decimal = BigDecimal::new(amount_cents.into(), 2);
             
             // Generate 1-4 items for the transaction
             let num_items = rng.gen_range(1..=4);
             let mut items = Vec::new();
             
             for _j in 0..num_items {
-                let item_price = amount / (num_items as f64) * (0.8 + rng.gen_range(0.0..0.4));
+                let item_cents = amount_cents / num_items * rng.gen_range(80..120) / 100;
+                let item_price = Money::new(BigDecimal::new(item_cents.into(), 2), Currency::THB);
                 let item_quantity = rng.gen_range(1..=3);
                 
                 items.push(TransactionItem {
@@ -56,7 +61,7 @@ pub fn load(connection: &mut PgConnection) -> Result<(), Box<dyn std::error::Err
             }
             
             // List of merchants
-            let merchants = vec![
+            let merchants = [
                 "Grocery Store", "Coffee Shop", "Restaurant", "Gas Station", 
                 "Department Store", "Online Shop", "Pharmacy", "Hardware Store"
             ];
@@ -65,16 +70,30 @@ pub fn load(connection: &mut PgConnection) -> Result<(), Box<dyn std::error::Err
             // Create transaction
             let transaction = NewTransaction {
                 id: Uuid::new_v4(),
-                amount: amount_string,
+                amount: amount_decimal,
                 date: transaction_date,
                 merchant: merchants[merchant_index].to_string(),
                 category_id: Some(category.id),
                 notes: Some(format!("Sample transaction {}", _i + 1)),
                 items: Some(json!(items)),
                 image_path: None,
-                user_id: user.0,
+                ledger_id: user.0,
                 created_at: Utc::now(),
                 updated_at: Utc::now(),
+                merchant_id: None,
+                excluded: false,
+                source: SOURCE_MANUAL.to_string(),
+                recurring_id: None,
+                currency: Currency::THB.to_string(),
+                account_id: None,
+                direction: Direction::Expense.as_str().to_string(),
+                transfer_id: None,
+                transaction_type: TransactionType::Expense.as_str().to_string(),
+                refund_of: None,
+                import_batch_id: None,
+                external_id: None,
+                value_date: None,
+                bank_reference: None,
             };
             
             transactions.push(transaction);
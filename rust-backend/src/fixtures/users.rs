@@ -1,7 +1,7 @@
 use diesel::PgConnection;
 use uuid::Uuid;
 use bcrypt::{hash, DEFAULT_COST};
-use crate::models::user::{User, NewUser};
+use crate::models::user::NewUser;
 use diesel::prelude::*;
 use chrono::Utc;
 
@@ -0,0 +1,209 @@
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::handlers::auth::{LedgerEditor, LedgerViewer};
+use crate::models::account::{
+    AccountChanges, AccountFilters, BalanceQuery, CreateAccountDto, CreateTransferDto, DbAccount, NewAccount,
+    UpdateAccountDto,
+};
+use crate::money::{Currency, Money};
+use crate::schema::{accounts, transactions};
+use crate::services::accounts as account_service;
+use crate::services::exchange_rates::base_currency;
+use actix_web::{web, HttpResponse};
+use chrono::Utc;
+use diesel::prelude::*;
+use uuid::Uuid;
+
+fn parse_account_id(id: &str) -> Result<Uuid, AppError> {
+    Uuid::parse_str(id).map_err(|_| AppError::BadRequest("Invalid account ID".to_string()))
+}
+
+// List the user's accounts with their current balances; archived ones only on request
+pub async fn get_accounts(
+    pool: web::Data<DbPool>,
+    access: LedgerViewer,
+    filters: web::Query<AccountFilters>,
+) -> Result<HttpResponse, AppError> {
+    let include_archived = filters.include_archived.unwrap_or(false);
+
+    let response = db::run(&pool, move |conn| {
+        let mut query = accounts::table
+            .filter(accounts::ledger_id.eq(access.ledger_id))
+            .into_boxed();
+        if !include_archived {
+            query = query.filter(accounts::archived.eq(false));
+        }
+        let all = query.order(accounts::name.asc()).load::<DbAccount>(conn)?;
+        account_service::to_responses_with_balances(conn, access.ledger_id, all)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+// Get a single account with its current balance
+pub async fn get_account(
+    pool: web::Data<DbPool>,
+    access: LedgerViewer,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let account_id = parse_account_id(&path.into_inner())?;
+
+    let response = db::run(&pool, move |conn| {
+        let account = account_service::find_account(conn, access.ledger_id, account_id)?;
+        Ok(account_service::to_responses_with_balances(conn, access.ledger_id, vec![account])?.remove(0))
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+// Create an account; the currency defaults to the user's base currency
+pub async fn create_account(
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
+    account_data: web::Json<CreateAccountDto>,
+) -> Result<HttpResponse, AppError> {
+    let data = account_data.into_inner();
+    let name = data.name.trim().to_string();
+    if name.is_empty() {
+        return Err(AppError::BadRequest("Account name is required".to_string()));
+    }
+
+    let response = db::run(&pool, move |conn| {
+        account_service::ensure_name_available(conn, access.ledger_id, &name, None)?;
+        let currency = match data.currency {
+            Some(currency) => currency,
+            None => base_currency(conn, access.ledger_id)?,
+        };
+        let opening_balance = Money::new(data.opening_balance.unwrap_or_default(), currency);
+
+        let new_account = NewAccount {
+            id: Uuid::new_v4(),
+            ledger_id: access.ledger_id,
+            name,
+            account_type: data.account_type.as_str().to_string(),
+            currency: currency.to_string(),
+            opening_balance: opening_balance.into_amount(),
+            institution: data.institution.map(|i| i.trim().to_string()).filter(|i| !i.is_empty()),
+            archived: false,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+
+        let created = diesel::insert_into(accounts::table)
+            .values(&new_account)
+            .get_result::<DbAccount>(conn)
+            .map_err(|e| account_service::name_error(e, &new_account.name))?;
+        Ok(account_service::to_responses_with_balances(conn, access.ledger_id, vec![created])?.remove(0))
+    })
+    .await?;
+
+    Ok(HttpResponse::Created().json(response))
+}
+
+// Update an account; archiving hides it and stops new transactions from using it
+pub async fn update_account(
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
+    path: web::Path<String>,
+    account_data: web::Json<UpdateAccountDto>,
+) -> Result<HttpResponse, AppError> {
+    let account_id = parse_account_id(&path.into_inner())?;
+    let data = account_data.into_inner();
+
+    let response = db::run(&pool, move |conn| {
+        let existing = account_service::find_account(conn, access.ledger_id, account_id)?;
+
+        let name = data.name.map(|n| n.trim().to_string());
+        if let Some(name) = &name {
+            if name.is_empty() {
+                return Err(AppError::BadRequest("Account name is required".to_string()));
+            }
+            account_service::ensure_name_available(conn, access.ledger_id, name, Some(existing.id))?;
+        }
+
+        let changes = AccountChanges {
+            name,
+            account_type: data.account_type.map(|t| t.as_str().to_string()),
+            opening_balance: data
+                .opening_balance
+                .map(|balance| Currency::from_code(&existing.currency).round(&balance)),
+            institution: data.institution.map(|i| i.trim().to_string()),
+            archived: data.archived,
+            updated_at: Utc::now(),
+        };
+
+        let updated = diesel::update(accounts::table.find(existing.id))
+            .set(&changes)
+            .get_result::<DbAccount>(conn)
+            .map_err(|e| account_service::name_error(e, changes.name.as_deref().unwrap_or(&existing.name)))?;
+        Ok(account_service::to_responses_with_balances(conn, access.ledger_id, vec![updated])?.remove(0))
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+// Delete an account that has no transactions; accounts with history should be archived instead
+pub async fn delete_account(
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let account_id = parse_account_id(&path.into_inner())?;
+
+    db::run(&pool, move |conn| {
+        let account = account_service::find_account(conn, access.ledger_id, account_id)?;
+        let used = transactions::table
+            .filter(transactions::account_id.eq(account.id))
+            .count()
+            .get_result::<i64>(conn)?;
+        if used > 0 {
+            return Err(AppError::BadRequest(format!(
+                "Account {} has {} transactions; archive it instead",
+                account.name, used
+            )));
+        }
+        diesel::delete(accounts::table.find(account.id)).execute(conn)?;
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+// Transactions on an account with the running balance after each one
+pub async fn get_account_balance(
+    pool: web::Data<DbPool>,
+    access: LedgerViewer,
+    path: web::Path<String>,
+    query: web::Query<BalanceQuery>,
+) -> Result<HttpResponse, AppError> {
+    let account_id = parse_account_id(&path.into_inner())?;
+    let query = query.into_inner();
+
+    let response = db::run(&pool, move |conn| {
+        let account = account_service::find_account(conn, access.ledger_id, account_id)?;
+        account_service::account_balance(conn, account, query.start_date, query.end_date)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+// Move money between two accounts; both legs are left out of spending
+pub async fn create_transfer(
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
+    transfer_data: web::Json<CreateTransferDto>,
+) -> Result<HttpResponse, AppError> {
+    let transfer = transfer_data.into_inner();
+
+    let response = db::run(&pool, move |conn| {
+        conn.transaction(|conn| account_service::create_transfer(conn, access.ledger_id, &transfer))
+    })
+    .await?;
+
+    Ok(HttpResponse::Created().json(response))
+}
@@ -0,0 +1,41 @@
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::handlers::auth::{LedgerEditor, LedgerViewer};
+use crate::models::audit::{AuditEntity, HistoryQuery};
+use crate::services::audit as audit_service;
+use actix_web::{web, HttpResponse};
+use uuid::Uuid;
+
+// Who changed one of your transactions, categories, bills or your profile, when, from where, and
+// what the values were before and after
+pub async fn get_history(
+    pool: web::Data<DbPool>,
+    access: LedgerViewer,
+    path: web::Path<(String, String)>,
+    query: web::Query<HistoryQuery>,
+) -> Result<HttpResponse, AppError> {
+    let (entity_type, entity_id) = path.into_inner();
+    let entity = AuditEntity::parse(&entity_type)
+        .ok_or_else(|| AppError::BadRequest(format!("No history is kept for {}", entity_type)))?;
+    let entity_id = Uuid::parse_str(&entity_id).map_err(|_| AppError::BadRequest("Invalid ID".to_string()))?;
+    let limit = query.into_inner().limit;
+    // A profile belongs to its user rather than to whichever ledger is selected
+    let owner_id = if entity == AuditEntity::Users { access.user_id } else { access.ledger_id };
+
+    let entries = db::run(&pool, move |conn| audit_service::history(conn, owner_id, entity, entity_id, limit)).await?;
+    Ok(HttpResponse::Ok().json(entries))
+}
+
+// Put a row back the way the audit entry left it; a profile only by its own user
+pub async fn restore_version(
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let entry_id = path
+        .into_inner()
+        .parse::<i64>()
+        .map_err(|_| AppError::BadRequest("Invalid audit entry ID".to_string()))?;
+    let response = db::run(&pool, move |conn| audit_service::restore_version(conn, access.ledger_id, access.user_id, entry_id)).await?;
+    Ok(HttpResponse::Ok().json(response))
+}
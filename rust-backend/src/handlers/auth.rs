@@ -1,12 +1,119 @@
+use crate::audit;
 use crate::config::{Claims, Config};
-use crate::db::DbPool;
+use crate::db::{self, DbPool};
 use crate::error::AppError;
+use crate::models::shared_ledger::LedgerRole;
 use crate::models::user::{AuthResponse, CreateUserDto, LoginDto, User, UserResponse};
-use actix_web::{web, HttpResponse};
+use crate::money::Currency;
+use crate::services::ledgers as ledger_service;
+use actix_web::dev::Payload;
+use actix_web::http::header::AUTHORIZATION;
+use actix_web::{web, FromRequest, HttpRequest, HttpResponse};
 use chrono::{Duration, Utc};
-use jsonwebtoken::{encode, EncodingKey, Header};
+use futures::future::{ready, LocalBoxFuture, Ready};
+use std::ops::Deref;
+use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
 use uuid::Uuid;
 
+// Authenticated caller, extracted from the `Authorization: Bearer <jwt>` header
+#[derive(Debug, Clone, Copy)]
+pub struct AuthUser {
+    pub user_id: Uuid,
+}
+
+impl FromRequest for AuthUser {
+    type Error = AppError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(extract_auth_user(req))
+    }
+}
+
+fn extract_auth_user(req: &HttpRequest) -> Result<AuthUser, AppError> {
+    let header = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .ok_or_else(|| AppError::AuthError("Missing authorization header".to_string()))?;
+    
+    let token = header
+        .strip_prefix("Bearer ")
+        .ok_or_else(|| AppError::AuthError("Invalid authorization header".to_string()))?;
+    
+    let claims = decode_token(token)?;
+    let user_id = Uuid::parse_str(&claims.sub)
+        .map_err(|_| AppError::AuthError("Invalid token subject".to_string()))?;
+    audit::set_actor(user_id);
+    
+    Ok(AuthUser { user_id })
+}
+
+// Names the ledger a request works on; without it, the caller's personal ledger
+pub const LEDGER_HEADER: &str = "X-Ledger-Id";
+
+// The ledger a request works on and who is asking; the extractor has already checked their role
+#[derive(Debug, Clone, Copy)]
+pub struct LedgerAccess {
+    pub user_id: Uuid,
+    pub ledger_id: Uuid,
+}
+
+async fn extract_ledger_access(req: HttpRequest, required: LedgerRole) -> Result<LedgerAccess, AppError> {
+    let user = extract_auth_user(&req)?;
+    let ledger_id = match req.headers().get(LEDGER_HEADER) {
+        Some(value) => value
+            .to_str()
+            .ok()
+            .and_then(|value| Uuid::parse_str(value.trim()).ok())
+            .ok_or_else(|| AppError::BadRequest(format!("Invalid {} header", LEDGER_HEADER)))?,
+        None => user.user_id,
+    };
+    let pool = req
+        .app_data::<web::Data<DbPool>>()
+        .cloned()
+        .ok_or_else(|| AppError::InternalServerError("Database pool is not configured".to_string()))?;
+
+    db::run(&pool, move |conn| {
+        ledger_service::require_role(conn, ledger_id, user.user_id, required)
+    })
+    .await?;
+    Ok(LedgerAccess { user_id: user.user_id, ledger_id })
+}
+
+// An extractor that admits members of the selected ledger with at least the given role
+macro_rules! ledger_extractor {
+    ($name:ident, $role:expr) => {
+        #[derive(Debug, Clone, Copy)]
+        pub struct $name(pub LedgerAccess);
+
+        impl Deref for $name {
+            type Target = LedgerAccess;
+
+            fn deref(&self) -> &LedgerAccess {
+                &self.0
+            }
+        }
+
+        impl FromRequest for $name {
+            type Error = AppError;
+            type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+            fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+                let req = req.clone();
+                Box::pin(async move { extract_ledger_access(req, $role).await.map($name) })
+            }
+        }
+    };
+}
+
+// Reads the selected ledger
+ledger_extractor!(LedgerViewer, LedgerRole::Viewer);
+// Changes the selected ledger's data
+ledger_extractor!(LedgerEditor, LedgerRole::Editor);
+// Manages the selected ledger as a whole, e.g. backing it up or restoring into it
+ledger_extractor!(LedgerOwner, LedgerRole::Owner);
+
 pub async fn register(
     _pool: web::Data<DbPool>,
     user_data: web::Json<CreateUserDto>,
@@ -35,6 +142,7 @@ pub async fn register(
             id: user.id,
             email: user.email,
             name: user.name,
+            base_currency: Currency::default(),
             created_at: user.created_at,
         },
     };
@@ -70,6 +178,7 @@ pub async fn login(
             id: user.id,
             email: user.email,
             name: user.name,
+            base_currency: Currency::default(),
             created_at: user.created_at,
         },
     };
@@ -101,4 +210,17 @@ fn generate_token(user_id: &Uuid) -> Result<String, AppError> {
         &EncodingKey::from_secret(jwt_secret.as_bytes()),
     )
     .map_err(|e| AppError::AuthError(format!("Failed to generate token: {}", e)))
-} 
\ No newline at end of file
+}
+
+// JWT token validation
+fn decode_token(token: &str) -> Result<Claims, AppError> {
+    let config = Config::from_env();
+    
+    decode::<Claims>(
+        token,
+        &DecodingKey::from_secret(config.jwt.secret.as_bytes()),
+        &Validation::default(),
+    )
+    .map(|data| data.claims)
+    .map_err(|e| AppError::AuthError(format!("Invalid token: {}", e)))
+}
@@ -0,0 +1,57 @@
+use crate::config::Config;
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::handlers::auth::LedgerOwner;
+use crate::handlers::imports::read_upload;
+use crate::models::backup::RestoreQuery;
+use crate::services::backup as backup_service;
+use actix_multipart::Multipart;
+use actix_web::http::header;
+use actix_web::{web, HttpResponse};
+use chrono::Utc;
+use std::path::PathBuf;
+
+// Backups carry every receipt image, so they are allowed to be much bigger than a statement
+const MAX_BACKUP_SIZE: usize = 500 * 1024 * 1024;
+
+// Everything in the selected ledger, with its bill and receipt images, as a zip archive another
+// instance can restore; only owners can take one
+pub async fn download_backup(pool: web::Data<DbPool>, access: LedgerOwner) -> Result<HttpResponse, AppError> {
+    let ledger_id = access.ledger_id;
+    let archive = db::run(&pool, move |conn| {
+        let upload_dir = PathBuf::from(Config::from_env().server.upload_dir);
+        let manifest = backup_service::create_manifest(conn, ledger_id, &upload_dir)?;
+        backup_service::write_archive(&manifest)
+    })
+    .await?;
+
+    let disposition = format!(
+        "attachment; filename=\"tenny-ledger-backup-{}.zip\"",
+        Utc::now().format("%Y-%m-%d")
+    );
+    Ok(HttpResponse::Ok()
+        .content_type("application/zip")
+        .insert_header((header::CONTENT_DISPOSITION, disposition))
+        .body(archive))
+}
+
+// Restore an uploaded backup into the selected ledger; `?dry_run=true` reports what would be
+// created, merged and skipped without changing anything
+pub async fn restore_backup(
+    pool: web::Data<DbPool>,
+    access: LedgerOwner,
+    query: web::Query<RestoreQuery>,
+    payload: Multipart,
+) -> Result<HttpResponse, AppError> {
+    let upload = read_upload(payload, MAX_BACKUP_SIZE).await?;
+    let ledger_id = access.ledger_id;
+    let dry_run = query.dry_run;
+
+    let report = db::run(&pool, move |conn| {
+        let (manifest, archive) = backup_service::read_archive(upload.bytes)?;
+        let upload_dir = PathBuf::from(Config::from_env().server.upload_dir);
+        backup_service::restore(conn, ledger_id, manifest, archive, &upload_dir, dry_run)
+    })
+    .await?;
+    Ok(HttpResponse::Ok().json(report))
+}
@@ -0,0 +1,63 @@
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::handlers::auth::{LedgerEditor, LedgerViewer};
+use crate::models::bill::{BillFilters, BillResponse, DbBill};
+use crate::schema::bills;
+use crate::services::bills as bill_service;
+use actix_web::{web, HttpResponse};
+use chrono::Utc;
+use diesel::prelude::*;
+use uuid::Uuid;
+
+fn parse_bill_id(id: &str) -> Result<Uuid, AppError> {
+    Uuid::parse_str(id).map_err(|_| AppError::BadRequest("Invalid bill ID".to_string()))
+}
+
+// List the user's uploaded bills, newest first, optionally by status
+pub async fn get_bills(
+    pool: web::Data<DbPool>,
+    access: LedgerViewer,
+    filters: web::Query<BillFilters>,
+) -> Result<HttpResponse, AppError> {
+    let status = filters.into_inner().status;
+
+    let response = db::run(&pool, move |conn| {
+        let mut query = bills::table
+            .filter(bills::ledger_id.eq(access.ledger_id))
+            .filter(bills::deleted_at.is_null())
+            .into_boxed();
+        if let Some(status) = status {
+            query = query.filter(bills::status.eq(status));
+        }
+
+        Ok(query
+            .order(bills::created_at.desc())
+            .load::<DbBill>(conn)?
+            .into_iter()
+            .map(BillResponse::from)
+            .collect::<Vec<_>>())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+// Move a bill to the trash; its image is removed when the trash is purged
+pub async fn delete_bill(
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let bill_id = parse_bill_id(&path.into_inner())?;
+
+    db::run(&pool, move |conn| {
+        let bill = bill_service::find_bill(conn, access.ledger_id, bill_id)?;
+        diesel::update(bills::table.find(bill.id))
+            .set(bills::deleted_at.eq(Some(Utc::now())))
+            .execute(conn)?;
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
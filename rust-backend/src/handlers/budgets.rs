@@ -0,0 +1,231 @@
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::handlers::auth::{LedgerEditor, LedgerViewer};
+use crate::models::budget::{
+    BudgetChanges, BudgetPeriod, BudgetResponse, BudgetStatusQuery, CreateBudgetDto, DbBudget,
+    NewBudget, UpdateBudgetDto,
+};
+use crate::schema::budgets;
+use crate::services::budgets as budget_service;
+use crate::services::exchange_rates::base_currency;
+use crate::services::transactions::category_names;
+use actix_web::{web, HttpResponse};
+use bigdecimal::{BigDecimal, Signed};
+use chrono::{NaiveDate, Utc};
+use diesel::prelude::*;
+use uuid::Uuid;
+
+fn parse_budget_id(id: &str) -> Result<Uuid, AppError> {
+    Uuid::parse_str(id).map_err(|_| AppError::BadRequest("Invalid budget ID".to_string()))
+}
+
+fn validate_budget(
+    amount: &BigDecimal,
+    period: BudgetPeriod,
+    start_date: NaiveDate,
+    end_date: Option<NaiveDate>,
+) -> Result<(), AppError> {
+    if amount.is_negative() {
+        return Err(AppError::BadRequest("Budget amount must be zero or more".to_string()));
+    }
+    if period == BudgetPeriod::Custom {
+        match end_date {
+            None => return Err(AppError::BadRequest("A custom budget needs an end date".to_string())),
+            Some(end_date) if end_date < start_date => {
+                return Err(AppError::BadRequest("End date must not be before the start date".to_string()))
+            }
+            Some(_) => {}
+        }
+    }
+    Ok(())
+}
+
+// List the user's budgets
+pub async fn get_budgets(
+    pool: web::Data<DbPool>,
+    access: LedgerViewer,
+) -> Result<HttpResponse, AppError> {
+    let response = db::run(&pool, move |conn| {
+        let all = budgets::table
+            .filter(budgets::ledger_id.eq(access.ledger_id))
+            .order(budgets::created_at.asc())
+            .load::<DbBudget>(conn)?;
+
+        let category_ids: Vec<Uuid> = all.iter().map(|b| b.category_id).collect();
+        let names = category_names(conn, &category_ids)?;
+        let currency = base_currency(conn, access.ledger_id)?;
+
+        Ok(all
+            .into_iter()
+            .map(|budget| {
+                let category = names.get(&budget.category_id).cloned().unwrap_or_default();
+                BudgetResponse::new(budget, category, currency)
+            })
+            .collect::<Vec<_>>())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+// Get a single budget by ID
+pub async fn get_budget(
+    pool: web::Data<DbPool>,
+    access: LedgerViewer,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let budget_id = parse_budget_id(&path.into_inner())?;
+
+    let response = db::run(&pool, move |conn| {
+        let budget = budget_service::find_budget(conn, access.ledger_id, budget_id)?;
+        let category = budget_service::owned_category_name(conn, access.ledger_id, budget.category_id)?;
+        let currency = base_currency(conn, access.ledger_id)?;
+        Ok(BudgetResponse::new(budget, category, currency))
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+// Create a budget for one of the user's categories
+pub async fn create_budget(
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
+    budget_data: web::Json<CreateBudgetDto>,
+) -> Result<HttpResponse, AppError> {
+    let budget_data = budget_data.into_inner();
+    let start_date = budget_data.start_date.unwrap_or_else(|| Utc::now().date_naive());
+    validate_budget(&budget_data.amount, budget_data.period, start_date, budget_data.end_date)?;
+
+    let response = db::run(&pool, move |conn| {
+        let currency = base_currency(conn, access.ledger_id)?;
+        let amount =
+            budget_service::budget_amount(conn, access.ledger_id, &budget_data.amount, budget_data.currency)?;
+        let category = budget_service::owned_category_name(conn, access.ledger_id, budget_data.category_id)?;
+
+        let new_budget = NewBudget {
+            id: Uuid::new_v4(),
+            ledger_id: access.ledger_id,
+            category_id: budget_data.category_id,
+            amount,
+            period: budget_data.period.as_str().to_string(),
+            start_date,
+            end_date: budget_data.end_date,
+            rollover: budget_data.rollover.unwrap_or(false),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+
+        let created = diesel::insert_into(budgets::table)
+            .values(&new_budget)
+            .get_result::<DbBudget>(conn)?;
+        Ok(BudgetResponse::new(created, category, currency))
+    })
+    .await?;
+
+    Ok(HttpResponse::Created().json(response))
+}
+
+// Update a budget
+pub async fn update_budget(
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
+    path: web::Path<String>,
+    budget_data: web::Json<UpdateBudgetDto>,
+) -> Result<HttpResponse, AppError> {
+    let budget_id = parse_budget_id(&path.into_inner())?;
+    let budget_data = budget_data.into_inner();
+
+    let response = db::run(&pool, move |conn| {
+        let currency = base_currency(conn, access.ledger_id)?;
+        let amount = budget_data
+            .amount
+            .as_ref()
+            .map(|amount| budget_service::budget_amount(conn, access.ledger_id, amount, budget_data.currency))
+            .transpose()?;
+        let existing = budget_service::find_budget(conn, access.ledger_id, budget_id)?;
+        let category_id = budget_data.category_id.unwrap_or(existing.category_id);
+        let category = budget_service::owned_category_name(conn, access.ledger_id, category_id)?;
+
+        let period = budget_data
+            .period
+            .or_else(|| BudgetPeriod::parse(&existing.period))
+            .unwrap_or(BudgetPeriod::Monthly);
+        validate_budget(
+            amount.as_ref().unwrap_or(&existing.amount),
+            period,
+            budget_data.start_date.unwrap_or(existing.start_date),
+            budget_data.end_date.or(existing.end_date),
+        )?;
+
+        let changes = BudgetChanges {
+            category_id: budget_data.category_id,
+            amount,
+            period: budget_data.period.map(|p| p.as_str().to_string()),
+            start_date: budget_data.start_date,
+            end_date: budget_data.end_date,
+            rollover: budget_data.rollover,
+            updated_at: Utc::now(),
+        };
+
+        let updated = diesel::update(budgets::table.find(existing.id))
+            .set(&changes)
+            .get_result::<DbBudget>(conn)?;
+        Ok(BudgetResponse::new(updated, category, currency))
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+// Delete a budget
+pub async fn delete_budget(
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let budget_id = parse_budget_id(&path.into_inner())?;
+
+    db::run(&pool, move |conn| {
+        let budget = budget_service::find_budget(conn, access.ledger_id, budget_id)?;
+        diesel::delete(budgets::table.find(budget.id)).execute(conn)?;
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+// Spent vs budget vs remaining per category for the period containing `date` (default today)
+pub async fn budget_status(
+    pool: web::Data<DbPool>,
+    access: LedgerViewer,
+    query: web::Query<BudgetStatusQuery>,
+) -> Result<HttpResponse, AppError> {
+    let query = query.into_inner();
+    let today = Utc::now().date_naive();
+    let date = query.date.unwrap_or(today);
+
+    let statuses = db::run(&pool, move |conn| {
+        let mut budget_query = budgets::table
+            .filter(budgets::ledger_id.eq(access.ledger_id))
+            .into_boxed();
+        if let Some(category_id) = query.category_id {
+            budget_query = budget_query.filter(budgets::category_id.eq(category_id));
+        }
+        let all = budget_query.order(budgets::created_at.asc()).load::<DbBudget>(conn)?;
+
+        let category_ids: Vec<Uuid> = all.iter().map(|b| b.category_id).collect();
+        let names = category_names(conn, &category_ids)?;
+
+        all.iter()
+            .map(|budget| {
+                let category = names.get(&budget.category_id).cloned().unwrap_or_default();
+                budget_service::budget_status(conn, budget, category, date, today)
+            })
+            .collect::<Result<Vec<_>, AppError>>()
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(statuses))
+}
@@ -0,0 +1,91 @@
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::handlers::auth::{LedgerEditor, LedgerViewer};
+use crate::models::category::{Category, CategoryFeedbackDto, DbCategory, SuggestCategoriesDto};
+use crate::schema::categories;
+use crate::services::categorizer;
+use actix_web::{web, HttpResponse};
+use chrono::Utc;
+use diesel::prelude::*;
+use uuid::Uuid;
+
+// The ledger's categories, leaving out any in the trash
+pub async fn get_categories(pool: web::Data<DbPool>, access: LedgerViewer) -> Result<HttpResponse, AppError> {
+    let categories = db::run(&pool, move |conn| {
+        Ok(categories::table
+            .filter(categories::ledger_id.eq(access.ledger_id))
+            .filter(categories::deleted_at.is_null())
+            .order((categories::kind.asc(), categories::name.asc()))
+            .load::<DbCategory>(conn)?
+            .into_iter()
+            .map(Category::from)
+            .collect::<Vec<_>>())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(categories))
+}
+
+// Suggest categories for a merchant and its item names
+pub async fn suggest_categories(
+    pool: web::Data<DbPool>,
+    access: LedgerViewer,
+    request: web::Json<SuggestCategoriesDto>,
+) -> Result<HttpResponse, AppError> {
+    let request = request.into_inner();
+    let limit = request.limit.unwrap_or(categorizer::DEFAULT_SUGGESTION_LIMIT).clamp(1, 20);
+
+    let suggestions = db::run(&pool, move |conn| {
+        categorizer::suggest_categories(
+            conn,
+            access.ledger_id,
+            request.merchant.as_deref(),
+            &request.items.unwrap_or_default(),
+            limit,
+        )
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(suggestions))
+}
+
+// Record which category the user actually chose so future suggestions improve
+pub async fn category_feedback(
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
+    feedback: web::Json<CategoryFeedbackDto>,
+) -> Result<HttpResponse, AppError> {
+    let feedback = feedback.into_inner();
+
+    db::run(&pool, move |conn| categorizer::record_feedback(conn, access.ledger_id, &feedback)).await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+// Move a category to the trash. What was filed under it shows as uncategorized until it's restored
+pub async fn delete_category(
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let category_id =
+        Uuid::parse_str(&path.into_inner()).map_err(|_| AppError::BadRequest("Invalid category ID".to_string()))?;
+
+    db::run(&pool, move |conn| {
+        let trashed = diesel::update(
+            categories::table
+                .filter(categories::id.eq(category_id))
+                .filter(categories::ledger_id.eq(access.ledger_id))
+                .filter(categories::deleted_at.is_null()),
+        )
+        .set(categories::deleted_at.eq(Some(Utc::now())))
+        .execute(conn)?;
+        if trashed == 0 {
+            return Err(AppError::NotFound(format!("Category {} not found", category_id)));
+        }
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,19 @@
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::handlers::auth::LedgerViewer;
+use crate::models::dashboard::DashboardQuery;
+use crate::services::dashboard as dashboard_service;
+use crate::services::reports::timezone_or_default;
+use actix_web::{web, HttpResponse};
+
+// This month's spending against last month, with top lists and review counts
+pub async fn get_dashboard(
+    pool: web::Data<DbPool>,
+    access: LedgerViewer,
+    query: web::Query<DashboardQuery>,
+) -> Result<HttpResponse, AppError> {
+    let tz = timezone_or_default(query.into_inner().tz.as_deref());
+
+    let summary = db::run(&pool, move |conn| dashboard_service::summary(conn, access.ledger_id, &tz)).await?;
+    Ok(HttpResponse::Ok().json(summary))
+}
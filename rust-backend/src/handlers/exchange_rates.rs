@@ -0,0 +1,117 @@
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::handlers::auth::{LedgerEditor, LedgerViewer};
+use crate::models::exchange_rate::{
+    CreateExchangeRateDto, DbExchangeRate, ExchangeRateFilters, ExchangeRateImportResponse,
+    ExchangeRateResponse, RATE_SOURCE_CSV, RATE_SOURCE_MANUAL,
+};
+use crate::schema::exchange_rates;
+use crate::services::exchange_rates as rate_service;
+use actix_web::{web, HttpResponse};
+use diesel::prelude::*;
+use uuid::Uuid;
+
+fn parse_rate_id(id: &str) -> Result<Uuid, AppError> {
+    Uuid::parse_str(id).map_err(|_| AppError::BadRequest("Invalid exchange rate ID".to_string()))
+}
+
+// List the user's exchange rates, newest first
+pub async fn get_exchange_rates(
+    pool: web::Data<DbPool>,
+    access: LedgerViewer,
+    filters: web::Query<ExchangeRateFilters>,
+) -> Result<HttpResponse, AppError> {
+    let filters = filters.into_inner();
+
+    let response = db::run(&pool, move |conn| {
+        let mut query = exchange_rates::table
+            .filter(exchange_rates::ledger_id.eq(access.ledger_id))
+            .into_boxed();
+        if let Some(from_currency) = filters.from_currency {
+            query = query.filter(exchange_rates::from_currency.eq(from_currency.to_string()));
+        }
+        if let Some(to_currency) = filters.to_currency {
+            query = query.filter(exchange_rates::to_currency.eq(to_currency.to_string()));
+        }
+        if let Some(start_date) = filters.start_date {
+            query = query.filter(exchange_rates::rate_date.ge(start_date));
+        }
+        if let Some(end_date) = filters.end_date {
+            query = query.filter(exchange_rates::rate_date.le(end_date));
+        }
+
+        Ok(query
+            .order((exchange_rates::rate_date.desc(), exchange_rates::from_currency.asc()))
+            .load::<DbExchangeRate>(conn)?
+            .into_iter()
+            .map(ExchangeRateResponse::from)
+            .collect::<Vec<_>>())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+// Enter a rate by hand; an existing rate for the same pair and date is replaced
+pub async fn create_exchange_rate(
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
+    rate_data: web::Json<CreateExchangeRateDto>,
+) -> Result<HttpResponse, AppError> {
+    let rate = rate_data.into_inner();
+    rate_service::validate_rate(&rate)?;
+
+    let response = db::run(&pool, move |conn| {
+        let saved = rate_service::upsert_rate(conn, access.ledger_id, &rate, RATE_SOURCE_MANUAL)?;
+        Ok(ExchangeRateResponse::from(saved))
+    })
+    .await?;
+
+    Ok(HttpResponse::Created().json(response))
+}
+
+// Import rates from a CSV body with date, from, to and rate columns; nothing is saved if a row is invalid
+pub async fn import_exchange_rates(
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
+    body: String,
+) -> Result<HttpResponse, AppError> {
+    let rates = rate_service::parse_rates_csv(&body)?;
+
+    let imported = db::run(&pool, move |conn| {
+        conn.transaction(|conn| {
+            for rate in &rates {
+                rate_service::upsert_rate(conn, access.ledger_id, rate, RATE_SOURCE_CSV)?;
+            }
+            Ok(rates.len())
+        })
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(ExchangeRateImportResponse { imported }))
+}
+
+// Delete an exchange rate
+pub async fn delete_exchange_rate(
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let rate_id = parse_rate_id(&path.into_inner())?;
+
+    db::run(&pool, move |conn| {
+        let deleted = diesel::delete(
+            exchange_rates::table
+                .filter(exchange_rates::id.eq(rate_id))
+                .filter(exchange_rates::ledger_id.eq(access.ledger_id)),
+        )
+        .execute(conn)?;
+        if deleted == 0 {
+            return Err(AppError::NotFound(format!("Exchange rate {} not found", rate_id)));
+        }
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
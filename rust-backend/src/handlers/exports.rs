@@ -0,0 +1,135 @@
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::handlers::auth::{LedgerEditor, LedgerViewer};
+use crate::models::export::ExportFormat;
+use crate::models::ledger::SetLedgerAccountsDto;
+use crate::models::transaction::TransactionFilters;
+use crate::services::exchange_rates::base_currency;
+use crate::services::export::{self as export_service, ExportRange, EXPORT_CHUNK_SIZE};
+use crate::services::ledger_export::{self, Dialect, LedgerNames};
+use actix_web::http::header;
+use actix_web::{rt, web, HttpResponse};
+use chrono::Utc;
+use futures::channel::mpsc;
+use futures::SinkExt;
+use std::sync::Arc;
+
+fn parse_format(format: &str) -> Result<ExportFormat, AppError> {
+    ExportFormat::parse(format)
+        .ok_or_else(|| AppError::BadRequest(format!("Unsupported export format: {}", format)))
+}
+
+// Export the transactions the filters pick, oldest first, as csv, xlsx, jsonl or a ledger,
+// hledger or beancount journal. CSV and JSON Lines are streamed a chunk at a time; a workbook is
+// a zip archive and a journal declares its accounts up front, so those are built whole
+pub async fn export_transactions(
+    pool: web::Data<DbPool>,
+    access: LedgerViewer,
+    path: web::Path<String>,
+    filters: web::Query<TransactionFilters>,
+) -> Result<HttpResponse, AppError> {
+    let format = parse_format(&path.into_inner())?;
+    let filters = filters.into_inner();
+    let range = ExportRange::new(&filters);
+    let ledger_id = access.ledger_id;
+    let disposition = format!(
+        "attachment; filename=\"transactions-{}.{}\"",
+        Utc::now().format("%Y-%m-%d"),
+        format.extension()
+    );
+    let mut response = HttpResponse::Ok();
+    response
+        .content_type(format.content_type())
+        .insert_header((header::CONTENT_DISPOSITION, disposition));
+
+    if format == ExportFormat::Xlsx {
+        let workbook = db::run(&pool, move |conn| {
+            let accounts = export_service::account_names(conn, ledger_id)?;
+            let transactions = export_service::export_all(conn, ledger_id, &filters, &range)?;
+            export_service::xlsx_workbook(&transactions, &accounts)
+        })
+        .await?;
+        return Ok(response.body(workbook));
+    }
+
+    let dialect = match format {
+        ExportFormat::Ledger => Some(Dialect::Ledger),
+        ExportFormat::Hledger => Some(Dialect::Hledger),
+        ExportFormat::Beancount => Some(Dialect::Beancount),
+        _ => None,
+    };
+    if let Some(dialect) = dialect {
+        let journal = db::run(&pool, move |conn| {
+            let names = LedgerNames::load(conn, ledger_id)?;
+            let currency = base_currency(conn, ledger_id)?;
+            let transactions = export_service::export_all(conn, ledger_id, &filters, &range)?;
+            Ok(ledger_export::render_journal(dialect, &transactions, &names, currency))
+        })
+        .await?;
+        return Ok(response.body(journal));
+    }
+
+    let accounts = Arc::new(db::run(&pool, move |conn| export_service::account_names(conn, ledger_id)).await?);
+    let filters = Arc::new(filters);
+    // Holds one chunk at a time, so reading waits while the client catches up
+    let (mut sender, receiver) = mpsc::channel::<Result<web::Bytes, AppError>>(1);
+    let pool = pool.clone();
+
+    rt::spawn(async move {
+        let mut done = 0;
+        loop {
+            let (filters, accounts) = (filters.clone(), accounts.clone());
+            let chunk = db::run(&pool, move |conn| {
+                let transactions = export_service::export_chunk(conn, ledger_id, &filters, &range, done)?;
+                let count = transactions.len() as u64;
+                let bytes = match format {
+                    ExportFormat::Csv => export_service::csv_chunk(&transactions, &accounts, done == 0)?,
+                    _ => export_service::jsonl_chunk(transactions, &accounts)?,
+                };
+                Ok((bytes, count))
+            })
+            .await;
+
+            let (bytes, count) = match chunk {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    log::error!("Transaction export failed: {}", e);
+                    let _ = sender.send(Err(e)).await;
+                    return;
+                }
+            };
+            // A send fails once the client has gone away
+            if !bytes.is_empty() && sender.send(Ok(web::Bytes::from(bytes))).await.is_err() {
+                return;
+            }
+            done += count;
+            if count < EXPORT_CHUNK_SIZE {
+                return;
+            }
+        }
+    });
+
+    Ok(response.streaming(receiver))
+}
+
+// Every category and account with the ledger account it is exported as, and whether the user
+// chose that name
+pub async fn get_ledger_accounts(pool: web::Data<DbPool>, access: LedgerViewer) -> Result<HttpResponse, AppError> {
+    let accounts = db::run(&pool, move |conn| ledger_export::ledger_accounts(conn, access.ledger_id)).await?;
+    Ok(HttpResponse::Ok().json(accounts))
+}
+
+// Rename the ledger accounts of categories and accounts; an empty name restores the default
+pub async fn set_ledger_accounts(
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
+    data: web::Json<SetLedgerAccountsDto>,
+) -> Result<HttpResponse, AppError> {
+    let ledger_id = access.ledger_id;
+    let accounts = db::run(&pool, move |conn| {
+        ledger_export::set_ledger_accounts(conn, ledger_id, &data)?;
+        ledger_export::ledger_accounts(conn, ledger_id)
+    })
+    .await?;
+    Ok(HttpResponse::Ok().json(accounts))
+}
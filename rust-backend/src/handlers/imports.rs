@@ -0,0 +1,386 @@
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::handlers::auth::{LedgerEditor, LedgerViewer};
+use crate::models::account::DbAccount;
+use crate::models::import::{
+    BalanceCheck, CreateImportProfileDto, CsvMapping, DbImportBatch, DbImportProfile, ImportBatchResponse,
+    ImportProfileChanges, ImportProfileResponse, ImportQuery, ImportResult, ImportRowError, NewImportBatch,
+    NewImportProfile, ParsedRow, StatementFormat, UpdateImportProfileDto,
+};
+use crate::money::Currency;
+use crate::schema::{import_batches, import_profiles};
+use crate::services::accounts as account_service;
+use crate::services::csv_import;
+use crate::services::exchange_rates::base_currency;
+use crate::services::imports as import_service;
+use crate::services::{camt_import, mt940_import, ofx_import, qif_import};
+use actix_multipart::Multipart;
+use actix_web::{web, HttpResponse};
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel::PgConnection;
+use futures::{StreamExt, TryStreamExt};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+// Statements are small; anything bigger is probably the wrong file
+const MAX_STATEMENT_SIZE: usize = 10 * 1024 * 1024;
+
+fn parse_profile_id(id: &str) -> Result<Uuid, AppError> {
+    Uuid::parse_str(id).map_err(|_| AppError::BadRequest("Invalid import profile ID".to_string()))
+}
+
+fn parse_batch_id(id: &str) -> Result<Uuid, AppError> {
+    Uuid::parse_str(id).map_err(|_| AppError::BadRequest("Invalid import ID".to_string()))
+}
+
+// An uploaded file with the form's other fields
+pub struct Upload {
+    pub file_name: String,
+    pub bytes: Vec<u8>,
+    pub fields: HashMap<String, String>,
+}
+
+pub async fn read_upload(mut payload: Multipart, max_size: usize) -> Result<Upload, AppError> {
+    let mut file = None;
+    let mut fields = HashMap::new();
+
+    while let Ok(Some(mut field)) = payload.try_next().await {
+        let content_disposition = field.content_disposition();
+        let file_name = content_disposition.get_filename().map(str::to_string);
+        let name = content_disposition.get_name().unwrap_or_default().to_string();
+
+        let mut bytes = Vec::new();
+        while let Some(chunk) = field.next().await {
+            let data = chunk.map_err(|e| AppError::BadRequest(format!("Error reading multipart data: {}", e)))?;
+            bytes.extend_from_slice(&data);
+            if bytes.len() > max_size {
+                return Err(AppError::BadRequest(format!(
+                    "File size exceeds the maximum allowed size of {}MB",
+                    max_size / 1024 / 1024
+                )));
+            }
+        }
+
+        match file_name {
+            Some(file_name) => file = Some((file_name, bytes)),
+            None => {
+                fields.insert(name, String::from_utf8_lossy(&bytes).into_owned());
+            }
+        }
+    }
+
+    let (file_name, bytes) =
+        file.ok_or_else(|| AppError::BadRequest("No file found in the request".to_string()))?;
+    Ok(Upload { file_name, bytes, fields })
+}
+
+fn parse_format(format: &str) -> Result<StatementFormat, AppError> {
+    StatementFormat::parse(format)
+        .ok_or_else(|| AppError::BadRequest(format!("Unsupported statement format: {}", format)))
+}
+
+// An upload read into rows, with where they should be recorded
+struct Statement {
+    format: StatementFormat,
+    profile_id: Option<Uuid>,
+    account: Option<DbAccount>,
+    statement_account: Option<String>,
+    rows: Vec<ParsedRow>,
+    errors: Vec<ImportRowError>,
+    balance_check: Option<BalanceCheck>,
+}
+
+// A CSV file is read with the `mapping` form field, or else the saved profile's. The query's
+// account wins over the profile's; a statement naming an account number imported before goes to
+// the same account again
+fn read_statement(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    format: StatementFormat,
+    query: &ImportQuery,
+    upload: &Upload,
+) -> Result<Statement, AppError> {
+    let profile = query
+        .profile_id
+        .map(|id| import_service::find_profile(conn, ledger_id, id))
+        .transpose()?;
+    let mut account = query
+        .account_id
+        .or(profile.as_ref().and_then(|p| p.account_id))
+        .map(|id| account_service::find_account(conn, ledger_id, id))
+        .transpose()?;
+    if let Some(account) = account.as_ref().filter(|a| a.archived) {
+        return Err(AppError::BadRequest(format!("Account {} is archived", account.name)));
+    }
+    let default_currency = match &account {
+        Some(account) => Currency::from_code(&account.currency),
+        None => base_currency(conn, ledger_id)?,
+    };
+
+    let (rows, mut errors, statement_account, balances) = match format {
+        StatementFormat::Csv => {
+            let mapping = match (upload.fields.get("mapping"), &profile) {
+                (Some(mapping), _) => serde_json::from_str::<CsvMapping>(mapping)?,
+                (None, Some(profile)) => profile.csv_mapping().ok_or_else(|| {
+                    AppError::BadRequest(format!("Import profile {} has no CSV mapping", profile.name))
+                })?,
+                (None, None) => {
+                    return Err(AppError::BadRequest(
+                        "Choose an import profile or send a mapping with the file".to_string(),
+                    ))
+                }
+            };
+            let currency = mapping.currency.unwrap_or(default_currency);
+            let (rows, errors) = csv_import::parse_csv(&upload.bytes, &mapping, currency)?;
+            (rows, errors, None, None)
+        }
+        StatementFormat::Ofx | StatementFormat::Qfx => {
+            let statement = ofx_import::parse_ofx(&upload.bytes, default_currency)?;
+            (statement.rows, statement.errors, statement.account, None)
+        }
+        StatementFormat::Qif => {
+            let date_format = query.date_format.as_deref().unwrap_or(qif_import::DEFAULT_QIF_DATE_FORMAT);
+            let (rows, errors) = qif_import::parse_qif(&upload.bytes, date_format, default_currency)?;
+            (rows, errors, None, None)
+        }
+        StatementFormat::Camt053 | StatementFormat::Mt940 => {
+            let statement = match format {
+                StatementFormat::Camt053 => camt_import::parse_camt053(&upload.bytes)?,
+                _ => mt940_import::parse_mt940(&upload.bytes)?,
+            };
+            (statement.rows, statement.errors, statement.account, statement.balances)
+        }
+    };
+    let balance_check = balances.map(|balances| import_service::check_balances(&balances, &rows));
+
+    if let (None, Some(number)) = (&account, &statement_account) {
+        account = import_service::account_for_statement(conn, ledger_id, number)?
+            .map(|id| account_service::find_account(conn, ledger_id, id))
+            .transpose()?
+            .filter(|a| !a.archived);
+    }
+    let rows = import_service::check_account(rows, account.as_ref(), &mut errors);
+
+    Ok(Statement {
+        format,
+        profile_id: profile.map(|p| p.id),
+        account,
+        statement_account,
+        rows,
+        errors,
+        balance_check,
+    })
+}
+
+// Read a statement (csv, ofx, qfx, qif, camt053 or mt940) and show what importing it would do,
+// duplicates and whether its balances add up included, without saving
+pub async fn preview_statement(
+    pool: web::Data<DbPool>,
+    access: LedgerViewer,
+    path: web::Path<String>,
+    query: web::Query<ImportQuery>,
+    payload: Multipart,
+) -> Result<HttpResponse, AppError> {
+    let format = parse_format(&path.into_inner())?;
+    let query = query.into_inner();
+    let upload = read_upload(payload, MAX_STATEMENT_SIZE).await?;
+
+    let preview = db::run(&pool, move |conn| {
+        let statement = read_statement(conn, access.ledger_id, format, &query, &upload)?;
+        import_service::preview(
+            conn,
+            access.ledger_id,
+            statement.account.map(|a| a.id),
+            statement.statement_account,
+            statement.rows,
+            statement.errors,
+            statement.balance_check,
+        )
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(preview))
+}
+
+// Import a statement as one batch; lines that look already recorded are skipped unless
+// `include_duplicates` is set, and lines imported before under the same bank ID always are
+pub async fn import_statement(
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
+    path: web::Path<String>,
+    query: web::Query<ImportQuery>,
+    payload: Multipart,
+) -> Result<HttpResponse, AppError> {
+    let format = parse_format(&path.into_inner())?;
+    let query = query.into_inner();
+    let upload = read_upload(payload, MAX_STATEMENT_SIZE).await?;
+
+    let result = db::run(&pool, move |conn| {
+        let statement = read_statement(conn, access.ledger_id, format, &query, &upload)?;
+        let batch = NewImportBatch {
+            id: Uuid::new_v4(),
+            ledger_id: access.ledger_id,
+            profile_id: statement.profile_id,
+            account_id: statement.account.map(|a| a.id),
+            format: statement.format.as_str().to_string(),
+            file_name: upload.file_name.clone(),
+            row_count: 0,
+            imported_count: 0,
+            duplicate_count: 0,
+            error_count: 0,
+            created_at: Utc::now(),
+            statement_account: statement.statement_account,
+        };
+        let batch = import_service::import_rows(
+            conn,
+            access.ledger_id,
+            batch,
+            &statement.rows,
+            statement.errors.len(),
+            query.include_duplicates,
+        )?;
+        Ok(ImportResult {
+            batch: ImportBatchResponse::from(batch),
+            errors: statement.errors,
+            balance_check: statement.balance_check,
+        })
+    })
+    .await?;
+
+    Ok(HttpResponse::Created().json(result))
+}
+
+// The user's imports, newest first
+pub async fn get_imports(pool: web::Data<DbPool>, access: LedgerViewer) -> Result<HttpResponse, AppError> {
+    let batches = db::run(&pool, move |conn| {
+        Ok(import_batches::table
+            .filter(import_batches::ledger_id.eq(access.ledger_id))
+            .order(import_batches::created_at.desc())
+            .load::<DbImportBatch>(conn)?)
+    })
+    .await?;
+
+    let response: Vec<ImportBatchResponse> = batches.into_iter().map(ImportBatchResponse::from).collect();
+    Ok(HttpResponse::Ok().json(response))
+}
+
+// Roll an import back, moving the transactions it created to the trash; importing the same
+// statement again takes them back out
+pub async fn rollback_import(
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let batch_id = parse_batch_id(&path.into_inner())?;
+
+    let batch = db::run(&pool, move |conn| import_service::rollback_batch(conn, access.ledger_id, batch_id)).await?;
+
+    Ok(HttpResponse::Ok().json(ImportBatchResponse::from(batch)))
+}
+
+pub async fn get_profiles(pool: web::Data<DbPool>, access: LedgerViewer) -> Result<HttpResponse, AppError> {
+    let profiles = db::run(&pool, move |conn| {
+        Ok(import_profiles::table
+            .filter(import_profiles::ledger_id.eq(access.ledger_id))
+            .order(import_profiles::name.asc())
+            .load::<DbImportProfile>(conn)?)
+    })
+    .await?;
+
+    let response: Vec<ImportProfileResponse> = profiles.into_iter().map(ImportProfileResponse::from).collect();
+    Ok(HttpResponse::Ok().json(response))
+}
+
+// Save how one bank's export is read, e.g. "KBank savings"
+pub async fn create_profile(
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
+    profile_data: web::Json<CreateImportProfileDto>,
+) -> Result<HttpResponse, AppError> {
+    let data = profile_data.into_inner();
+    let name = data.name.trim().to_string();
+    if name.is_empty() {
+        return Err(AppError::BadRequest("Import profile name is required".to_string()));
+    }
+    csv_import::validate_mapping(&data.mapping)?;
+
+    let profile = db::run(&pool, move |conn| {
+        import_service::ensure_name_available(conn, access.ledger_id, &name, None)?;
+        if let Some(account_id) = data.account_id {
+            account_service::find_account(conn, access.ledger_id, account_id)?;
+        }
+        let new_profile = NewImportProfile {
+            id: Uuid::new_v4(),
+            ledger_id: access.ledger_id,
+            name,
+            account_id: data.account_id,
+            mapping: serde_json::to_value(&data.mapping)?,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+        Ok(diesel::insert_into(import_profiles::table)
+            .values(&new_profile)
+            .get_result::<DbImportProfile>(conn)?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Created().json(ImportProfileResponse::from(profile)))
+}
+
+pub async fn update_profile(
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
+    path: web::Path<String>,
+    profile_data: web::Json<UpdateImportProfileDto>,
+) -> Result<HttpResponse, AppError> {
+    let profile_id = parse_profile_id(&path.into_inner())?;
+    let data = profile_data.into_inner();
+    let name = data.name.map(|n| n.trim().to_string());
+    if name.as_deref() == Some("") {
+        return Err(AppError::BadRequest("Import profile name is required".to_string()));
+    }
+    if let Some(mapping) = &data.mapping {
+        csv_import::validate_mapping(mapping)?;
+    }
+
+    let profile = db::run(&pool, move |conn| {
+        let existing = import_service::find_profile(conn, access.ledger_id, profile_id)?;
+        if let Some(name) = &name {
+            import_service::ensure_name_available(conn, access.ledger_id, name, Some(existing.id))?;
+        }
+        if let Some(account_id) = data.account_id {
+            account_service::find_account(conn, access.ledger_id, account_id)?;
+        }
+        let changes = ImportProfileChanges {
+            name,
+            account_id: data.account_id,
+            mapping: data.mapping.as_ref().map(serde_json::to_value).transpose()?,
+            updated_at: Utc::now(),
+        };
+        Ok(diesel::update(import_profiles::table.find(existing.id))
+            .set(&changes)
+            .get_result::<DbImportProfile>(conn)?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(ImportProfileResponse::from(profile)))
+}
+
+// Deleting a profile keeps the imports made with it
+pub async fn delete_profile(
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let profile_id = parse_profile_id(&path.into_inner())?;
+
+    db::run(&pool, move |conn| {
+        let existing = import_service::find_profile(conn, access.ledger_id, profile_id)?;
+        diesel::delete(import_profiles::table.find(existing.id)).execute(conn)?;
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,173 @@
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::handlers::auth::AuthUser;
+use crate::models::shared_ledger::{CreateInvitationDto, CreateLedgerDto, SetMemberRoleDto, UpdateLedgerDto};
+use crate::services::ledgers as ledger_service;
+use actix_web::{web, HttpResponse};
+use chrono::Utc;
+use std::fs;
+use uuid::Uuid;
+
+// Roles are checked by the ledger service, against the ledger in the path rather than the
+// X-Ledger-Id header
+fn parse_id(id: &str, what: &str) -> Result<Uuid, AppError> {
+    Uuid::parse_str(id).map_err(|_| AppError::BadRequest(format!("Invalid {} ID", what)))
+}
+
+// Every ledger the caller belongs to and their role in it
+pub async fn get_ledgers(pool: web::Data<DbPool>, user: AuthUser) -> Result<HttpResponse, AppError> {
+    let ledgers = db::run(&pool, move |conn| ledger_service::list_ledgers(conn, user.user_id)).await?;
+    Ok(HttpResponse::Ok().json(ledgers))
+}
+
+pub async fn get_ledger(
+    pool: web::Data<DbPool>,
+    user: AuthUser,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let ledger_id = parse_id(&path.into_inner(), "ledger")?;
+    let ledger = db::run(&pool, move |conn| ledger_service::get_ledger(conn, user.user_id, ledger_id)).await?;
+    Ok(HttpResponse::Ok().json(ledger))
+}
+
+// Start a ledger to share, e.g. for a household or a team's petty cash
+pub async fn create_ledger(
+    pool: web::Data<DbPool>,
+    user: AuthUser,
+    data: web::Json<CreateLedgerDto>,
+) -> Result<HttpResponse, AppError> {
+    let data = data.into_inner();
+    let ledger = db::run(&pool, move |conn| ledger_service::create_ledger(conn, user.user_id, &data)).await?;
+    Ok(HttpResponse::Created().json(ledger))
+}
+
+pub async fn update_ledger(
+    pool: web::Data<DbPool>,
+    user: AuthUser,
+    path: web::Path<String>,
+    data: web::Json<UpdateLedgerDto>,
+) -> Result<HttpResponse, AppError> {
+    let ledger_id = parse_id(&path.into_inner(), "ledger")?;
+    let data = data.into_inner();
+    let ledger =
+        db::run(&pool, move |conn| ledger_service::update_ledger(conn, user.user_id, ledger_id, &data)).await?;
+    Ok(HttpResponse::Ok().json(ledger))
+}
+
+// Delete a shared ledger with all of its data and stored images
+pub async fn delete_ledger(
+    pool: web::Data<DbPool>,
+    user: AuthUser,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let ledger_id = parse_id(&path.into_inner(), "ledger")?;
+    let files = db::run(&pool, move |conn| ledger_service::delete_ledger(conn, user.user_id, ledger_id)).await?;
+
+    for file in &files {
+        if let Err(e) = fs::remove_file(file) {
+            log::warn!("Failed to remove bill image {}: {}", file, e);
+        }
+    }
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn get_members(
+    pool: web::Data<DbPool>,
+    user: AuthUser,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let ledger_id = parse_id(&path.into_inner(), "ledger")?;
+    let members = db::run(&pool, move |conn| ledger_service::list_members(conn, user.user_id, ledger_id)).await?;
+    Ok(HttpResponse::Ok().json(members))
+}
+
+pub async fn set_member_role(
+    pool: web::Data<DbPool>,
+    user: AuthUser,
+    path: web::Path<(String, String)>,
+    data: web::Json<SetMemberRoleDto>,
+) -> Result<HttpResponse, AppError> {
+    let (ledger_id, member_id) = path.into_inner();
+    let ledger_id = parse_id(&ledger_id, "ledger")?;
+    let member_id = parse_id(&member_id, "user")?;
+    let role = data.into_inner().role;
+
+    let members = db::run(&pool, move |conn| {
+        ledger_service::set_member_role(conn, user.user_id, ledger_id, member_id, role)
+    })
+    .await?;
+    Ok(HttpResponse::Ok().json(members))
+}
+
+// Remove a member, or leave the ledger when it's the caller
+pub async fn remove_member(
+    pool: web::Data<DbPool>,
+    user: AuthUser,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, AppError> {
+    let (ledger_id, member_id) = path.into_inner();
+    let ledger_id = parse_id(&ledger_id, "ledger")?;
+    let member_id = parse_id(&member_id, "user")?;
+
+    db::run(&pool, move |conn| ledger_service::remove_member(conn, user.user_id, ledger_id, member_id)).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn get_invitations(
+    pool: web::Data<DbPool>,
+    user: AuthUser,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let ledger_id = parse_id(&path.into_inner(), "ledger")?;
+    let invitations = db::run(&pool, move |conn| {
+        ledger_service::list_invitations(conn, user.user_id, ledger_id, Utc::now())
+    })
+    .await?;
+    Ok(HttpResponse::Ok().json(invitations))
+}
+
+// Invite someone by email; the response's token is what they accept the invitation with
+pub async fn create_invitation(
+    pool: web::Data<DbPool>,
+    user: AuthUser,
+    path: web::Path<String>,
+    data: web::Json<CreateInvitationDto>,
+) -> Result<HttpResponse, AppError> {
+    let ledger_id = parse_id(&path.into_inner(), "ledger")?;
+    let data = data.into_inner();
+    let invitation = db::run(&pool, move |conn| {
+        ledger_service::create_invitation(conn, user.user_id, ledger_id, &data, Utc::now())
+    })
+    .await?;
+    Ok(HttpResponse::Created().json(invitation))
+}
+
+pub async fn revoke_invitation(
+    pool: web::Data<DbPool>,
+    user: AuthUser,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, AppError> {
+    let (ledger_id, invitation_id) = path.into_inner();
+    let ledger_id = parse_id(&ledger_id, "ledger")?;
+    let invitation_id = parse_id(&invitation_id, "invitation")?;
+
+    let invitation = db::run(&pool, move |conn| {
+        ledger_service::revoke_invitation(conn, user.user_id, ledger_id, invitation_id, Utc::now())
+    })
+    .await?;
+    Ok(HttpResponse::Ok().json(invitation))
+}
+
+// Join the ledger an invitation was sent to the caller for
+pub async fn accept_invitation(
+    pool: web::Data<DbPool>,
+    user: AuthUser,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let token = path.into_inner();
+    let ledger = db::run(&pool, move |conn| {
+        ledger_service::accept_invitation(conn, user.user_id, &token, Utc::now())
+    })
+    .await?;
+    Ok(HttpResponse::Ok().json(ledger))
+}
@@ -0,0 +1,260 @@
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::handlers::auth::{LedgerEditor, LedgerViewer};
+use crate::models::merchant::{
+    CreateMerchantAliasDto, CreateMerchantDto, DbMerchant, DbMerchantAlias, MergeMerchantsDto,
+    MerchantChanges, MerchantResponse, ResolveMerchantQuery, UpdateMerchantDto,
+};
+use crate::schema::{merchant_aliases, merchants};
+use crate::services::merchants as merchant_service;
+use actix_web::{web, HttpResponse};
+use chrono::Utc;
+use diesel::prelude::*;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+fn parse_id(id: &str, what: &str) -> Result<Uuid, AppError> {
+    Uuid::parse_str(id).map_err(|_| AppError::BadRequest(format!("Invalid {} ID", what)))
+}
+
+// List the user's merchants with their aliases
+pub async fn get_merchants(
+    pool: web::Data<DbPool>,
+    access: LedgerViewer,
+) -> Result<HttpResponse, AppError> {
+    let response = db::run(&pool, move |conn| {
+        let all = merchants::table
+            .filter(merchants::ledger_id.eq(access.ledger_id))
+            .order(merchants::name.asc())
+            .load::<DbMerchant>(conn)?;
+
+        let ids: Vec<Uuid> = all.iter().map(|m| m.id).collect();
+        let mut aliases_by_merchant: HashMap<Uuid, Vec<DbMerchantAlias>> = HashMap::new();
+        for alias in merchant_service::load_aliases(conn, &ids)? {
+            aliases_by_merchant.entry(alias.merchant_id).or_default().push(alias);
+        }
+
+        Ok(all
+            .into_iter()
+            .map(|merchant| {
+                let aliases = aliases_by_merchant.remove(&merchant.id).unwrap_or_default();
+                MerchantResponse::new(merchant, aliases)
+            })
+            .collect::<Vec<_>>())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+// Get a single merchant by ID
+pub async fn get_merchant(
+    pool: web::Data<DbPool>,
+    access: LedgerViewer,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let merchant_id = parse_id(&path.into_inner(), "merchant")?;
+
+    let response = db::run(&pool, move |conn| {
+        let merchant = merchant_service::find_merchant(conn, access.ledger_id, merchant_id)?;
+        let aliases = merchant_service::load_aliases(conn, &[merchant.id])?;
+        Ok(MerchantResponse::new(merchant, aliases))
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+// Create a canonical merchant, optionally with initial aliases
+pub async fn create_merchant(
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
+    merchant_data: web::Json<CreateMerchantDto>,
+) -> Result<HttpResponse, AppError> {
+    let merchant_data = merchant_data.into_inner();
+
+    let response = db::run(&pool, move |conn| {
+        conn.transaction(|conn| {
+            let merchant = merchant_service::create_merchant(
+                conn,
+                access.ledger_id,
+                &merchant_data.name,
+                merchant_data.tax_id,
+                merchant_data.default_category_id,
+            )?;
+
+            for alias in merchant_data.aliases.unwrap_or_default() {
+                merchant_service::add_alias(conn, &merchant, &alias)?;
+            }
+
+            let aliases = merchant_service::load_aliases(conn, &[merchant.id])?;
+            Ok(MerchantResponse::new(merchant, aliases))
+        })
+    })
+    .await?;
+
+    Ok(HttpResponse::Created().json(response))
+}
+
+// Update a merchant's canonical name, tax ID or default category
+pub async fn update_merchant(
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
+    path: web::Path<String>,
+    merchant_data: web::Json<UpdateMerchantDto>,
+) -> Result<HttpResponse, AppError> {
+    let merchant_id = parse_id(&path.into_inner(), "merchant")?;
+    let merchant_data = merchant_data.into_inner();
+
+    let response = db::run(&pool, move |conn| {
+        let merchant = merchant_service::find_merchant(conn, access.ledger_id, merchant_id)?;
+
+        let changes = MerchantChanges {
+            normalized_name: merchant_data
+                .name
+                .as_deref()
+                .map(merchant_service::normalize_merchant_name),
+            name: merchant_data.name.map(|n| n.trim().to_string()),
+            tax_id: merchant_data.tax_id,
+            default_category_id: merchant_data.default_category_id,
+            updated_at: Utc::now(),
+        };
+
+        if changes.normalized_name.as_deref() == Some("") {
+            return Err(AppError::BadRequest("Merchant name is empty".to_string()));
+        }
+
+        let updated = diesel::update(merchants::table.find(merchant.id))
+            .set(&changes)
+            .get_result::<DbMerchant>(conn)?;
+        let aliases = merchant_service::load_aliases(conn, &[updated.id])?;
+        Ok(MerchantResponse::new(updated, aliases))
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+// Delete a merchant; its transactions keep their merchant text but lose the link
+pub async fn delete_merchant(
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let merchant_id = parse_id(&path.into_inner(), "merchant")?;
+
+    db::run(&pool, move |conn| {
+        let merchant = merchant_service::find_merchant(conn, access.ledger_id, merchant_id)?;
+        diesel::delete(merchants::table.find(merchant.id)).execute(conn)?;
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+// Merge other merchants into this one
+pub async fn merge_merchants(
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
+    path: web::Path<String>,
+    merge_data: web::Json<MergeMerchantsDto>,
+) -> Result<HttpResponse, AppError> {
+    let target_id = parse_id(&path.into_inner(), "merchant")?;
+    let source_ids = merge_data.into_inner().source_ids;
+
+    if source_ids.is_empty() {
+        return Err(AppError::BadRequest("No merchants to merge".to_string()));
+    }
+
+    let response = db::run(&pool, move |conn| {
+        let merged = merchant_service::merge_merchants(conn, access.ledger_id, target_id, &source_ids)?;
+        let aliases = merchant_service::load_aliases(conn, &[merged.id])?;
+        Ok(MerchantResponse::new(merged, aliases))
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+// Add an alias to a merchant
+pub async fn add_alias(
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
+    path: web::Path<String>,
+    alias_data: web::Json<CreateMerchantAliasDto>,
+) -> Result<HttpResponse, AppError> {
+    let merchant_id = parse_id(&path.into_inner(), "merchant")?;
+    let alias = alias_data.into_inner().alias;
+
+    let response = db::run(&pool, move |conn| {
+        let merchant = merchant_service::find_merchant(conn, access.ledger_id, merchant_id)?;
+
+        // An alias can only point at one of the user's merchants
+        if let Some(existing) = merchant_service::resolve_merchant(conn, access.ledger_id, &alias, None)? {
+            if existing.merchant.id != merchant.id
+                && existing.match_type != merchant_service::MatchType::Fuzzy
+            {
+                return Err(AppError::BadRequest(format!(
+                    "Alias already belongs to merchant {}",
+                    existing.merchant.name
+                )));
+            }
+        }
+
+        merchant_service::add_alias(conn, &merchant, &alias)?;
+        let aliases = merchant_service::load_aliases(conn, &[merchant.id])?;
+        Ok(MerchantResponse::new(merchant, aliases))
+    })
+    .await?;
+
+    Ok(HttpResponse::Created().json(response))
+}
+
+// Remove an alias from a merchant
+pub async fn delete_alias(
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, AppError> {
+    let (merchant_id, alias_id) = path.into_inner();
+    let merchant_id = parse_id(&merchant_id, "merchant")?;
+    let alias_id = parse_id(&alias_id, "alias")?;
+
+    db::run(&pool, move |conn| {
+        let merchant = merchant_service::find_merchant(conn, access.ledger_id, merchant_id)?;
+        let deleted = diesel::delete(
+            merchant_aliases::table
+                .filter(merchant_aliases::id.eq(alias_id))
+                .filter(merchant_aliases::merchant_id.eq(merchant.id)),
+        )
+        .execute(conn)?;
+
+        if deleted == 0 {
+            return Err(AppError::NotFound(format!("Alias {} not found", alias_id)));
+        }
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+// Resolve a raw merchant string without creating anything
+pub async fn resolve_merchant(
+    pool: web::Data<DbPool>,
+    access: LedgerViewer,
+    query: web::Query<ResolveMerchantQuery>,
+) -> Result<HttpResponse, AppError> {
+    let query = query.into_inner();
+
+    let found = db::run(&pool, move |conn| {
+        merchant_service::resolve_merchant(conn, access.ledger_id, &query.name, query.tax_id.as_deref())
+    })
+    .await?;
+
+    match found {
+        Some(found) => Ok(HttpResponse::Ok().json(found)),
+        None => Err(AppError::NotFound("No matching merchant".to_string())),
+    }
+}
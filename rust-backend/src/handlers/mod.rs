@@ -1,4 +1,22 @@
+pub mod accounts;
+pub mod audit;
 pub mod auth;
+pub mod backups;
+pub mod bills;
+pub mod budgets;
+pub mod categories;
+pub mod dashboard;
+pub mod exchange_rates;
+pub mod exports;
+pub mod imports;
+pub mod ledgers;
+pub mod merchants;
 pub mod ocr;
+pub mod reconciliation;
+pub mod recurring;
+pub mod reports;
+pub mod rules;
+pub mod tags;
 pub mod transactions;
+pub mod trash;
 pub mod users; 
\ No newline at end of file
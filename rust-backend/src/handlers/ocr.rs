@@ -1,11 +1,24 @@
+use crate::config::Config;
+use crate::db::{self, DbPool};
 use crate::error::AppError;
+use crate::handlers::auth::LedgerEditor;
+use crate::models::bill::{NewBill, BILL_STATUS_PENDING_REVIEW};
+use crate::models::rule::RuleEffects;
+use crate::models::transaction::{TransactionItem, SOURCE_OCR};
+use crate::money::Money;
 use crate::ocr::processor::{OcrProcessor, OcrResult};
+use crate::schema::bills;
+use crate::services::categorizer::{self, CategorySuggestion};
+use crate::services::merchants::{self as merchant_service, MerchantMatch};
+use crate::services::rules::{self as rule_service, RuleInput};
 use actix_multipart::Multipart;
 use actix_web::{web, HttpResponse};
+use chrono::Utc;
+use diesel::prelude::*;
 use futures::{StreamExt, TryStreamExt};
 use std::fs;
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use uuid::Uuid;
 use serde::Deserialize;
 
@@ -15,24 +28,32 @@ pub struct OcrEngineQuery {
 }
 
 // Existing function that uses hybrid processing by default
-pub async fn process_image(mut payload: Multipart) -> Result<HttpResponse, AppError> {
+pub async fn process_image(
+    pool: web::Data<DbPool>,
+    access: Option<LedgerEditor>,
+    payload: Multipart,
+) -> Result<HttpResponse, AppError> {
     // Use hybrid as the default engine
     let engine = "hybrid".to_string();
-    process_image_with_engine_internal(payload, engine).await
+    process_image_with_engine_internal(pool, access, payload, engine).await
 }
 
 // New function that allows specifying the OCR engine via query parameter
 pub async fn process_image_with_engine(
-    mut payload: Multipart,
+    pool: web::Data<DbPool>,
+    access: Option<LedgerEditor>,
+    payload: Multipart,
     query: web::Query<OcrEngineQuery>,
 ) -> Result<HttpResponse, AppError> {
     // Extract engine preference from query params or use hybrid by default
     let engine = query.engine.clone().unwrap_or_else(|| "hybrid".to_string());
-    process_image_with_engine_internal(payload, engine).await
+    process_image_with_engine_internal(pool, access, payload, engine).await
 }
 
 // Internal function that handles the actual processing with the specified engine
 async fn process_image_with_engine_internal(
+    pool: web::Data<DbPool>,
+    access: Option<LedgerEditor>,
     mut payload: Multipart,
     engine: String,
 ) -> Result<HttpResponse, AppError> {
@@ -45,11 +66,12 @@ async fn process_image_with_engine_internal(
     let upload_dir = Path::new("./temp");
     if !upload_dir.exists() {
         fs::create_dir_all(upload_dir)
-            .map_err(|e| AppError::IoError(e))?;
+            .map_err(AppError::IoError)?;
     }
     
     // Process multipart form data
     let mut temp_file_path = None;
+    let mut upload = None;
     
     while let Ok(Some(mut field)) = payload.try_next().await {
         let content_disposition = field.content_disposition();
@@ -61,6 +83,7 @@ async fn process_image_with_engine_internal(
                 .and_then(|ext| ext.to_str())
                 .unwrap_or("unknown")
                 .to_lowercase();
+            let original_name = filename.to_string();
                 
             // Only allow specific image formats
             if !["jpg", "jpeg", "png", "pdf"].contains(&file_ext.as_str()) {
@@ -73,7 +96,7 @@ async fn process_image_with_engine_internal(
             let file_id = Uuid::new_v4();
             let file_path = upload_dir.join(format!("{}.{}", file_id, file_ext));
             let mut file = fs::File::create(&file_path)
-                .map_err(|e| AppError::IoError(e))?;
+                .map_err(AppError::IoError)?;
                 
             // Set a reasonable file size limit (10MB)
             const MAX_FILE_SIZE: usize = 10 * 1024 * 1024; // 10MB
@@ -95,10 +118,15 @@ async fn process_image_with_engine_internal(
                 }
                 
                 file.write_all(&data)
-                    .map_err(|e| AppError::IoError(e))?;
+                    .map_err(AppError::IoError)?;
             }
             
             temp_file_path = Some(file_path);
+            upload = Some(UploadedFile {
+                file_name: original_name,
+                file_type: file_ext,
+                file_size: total_size as i64,
+            });
         }
     }
     
@@ -110,7 +138,7 @@ async fn process_image_with_engine_internal(
         let result = match engine.as_str() {
             "tesseract" => {
                 let file_bytes = fs::read(&file_path)
-                    .map_err(|e| AppError::IoError(e))?;
+                    .map_err(AppError::IoError)?;
                 let extracted_data = processor.process_image(&file_bytes)?;
                 OcrResult {
                     text: "".to_string(),
@@ -123,32 +151,188 @@ async fn process_image_with_engine_internal(
             _ => processor.process_image_hybrid(&file_path).await?, // Default to hybrid
         };
         
-        // Clean up temp file
-        if let Err(e) = fs::remove_file(&file_path) {
-            log::warn!("Failed to remove temp file: {}", e);
-        }
+        // Anonymous callers and viewers of the selected ledger only get the raw OCR result
+        let (enrichment, bill_id) = match (access, upload) {
+            (Some(access), Some(upload)) => {
+                let enrichment = enrich_for_user(&pool, access, &result).await?;
+                let bill_id = store_bill(&pool, access, &file_path, upload, &result).await?;
+                (enrichment, Some(bill_id))
+            }
+            _ => {
+                // Anonymous uploads are not kept
+                if let Err(e) = fs::remove_file(&file_path) {
+                    log::warn!("Failed to remove temp file: {}", e);
+                }
+                (OcrEnrichment::default(), None)
+            }
+        };
         
-        return Ok(HttpResponse::Ok().json(serialize_ocr_result(result, engine)));
+        return Ok(HttpResponse::Ok().json(serialize_ocr_result(result, engine, &enrichment, bill_id)));
     }
     
     Err(AppError::BadRequest("No image file found in the request".to_string()))
 }
 
+struct UploadedFile {
+    file_name: String,
+    file_type: String,
+    file_size: i64,
+}
+
+// Keep the uploaded image and its OCR result as a bill waiting for the user to review it
+async fn store_bill(
+    pool: &web::Data<DbPool>,
+    access: LedgerEditor,
+    temp_path: &Path,
+    upload: UploadedFile,
+    result: &OcrResult,
+) -> Result<Uuid, AppError> {
+    let bill_dir = PathBuf::from(Config::from_env().server.upload_dir).join("bills");
+    fs::create_dir_all(&bill_dir)
+        .map_err(AppError::IoError)?;
+    
+    let bill_id = Uuid::new_v4();
+    let stored_path = bill_dir.join(format!("{}.{}", bill_id, upload.file_type));
+    // Rename fails across file systems, so fall back to copying
+    if fs::rename(temp_path, &stored_path).is_err() {
+        fs::copy(temp_path, &stored_path)
+            .map_err(AppError::IoError)?;
+        let _ = fs::remove_file(temp_path);
+    }
+    
+    let new_bill = NewBill {
+        id: bill_id,
+        ledger_id: access.ledger_id,
+        file_path: stored_path.to_string_lossy().to_string(),
+        file_name: upload.file_name,
+        file_size: upload.file_size,
+        file_type: upload.file_type,
+        ocr_text: Some(result.text.clone()).filter(|text| !text.is_empty()),
+        ocr_confidence: Some(result.confidence),
+        extracted_data: Some(serde_json::to_value(&result.extracted_data)?),
+        status: BILL_STATUS_PENDING_REVIEW.to_string(),
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+    };
+    
+    db::run(pool, move |conn| {
+        diesel::insert_into(bills::table)
+            .values(&new_bill)
+            .execute(conn)?;
+        Ok(())
+    })
+    .await?;
+    
+    Ok(bill_id)
+}
+
+// Per-user details layered on top of the raw OCR result
+#[derive(Default)]
+struct OcrEnrichment {
+    merchant_match: Option<MerchantMatch>,
+    suggestions: Vec<CategorySuggestion>,
+    rule_effects: Option<RuleEffects>,
+}
+
+// Map the OCR'd merchant onto a canonical merchant, suggest a category and run the user's rules
+async fn enrich_for_user(
+    pool: &web::Data<DbPool>,
+    access: LedgerEditor,
+    result: &OcrResult,
+) -> Result<OcrEnrichment, AppError> {
+    let raw_merchant = result.extracted_data.merchant.clone();
+    let tax_id = result.extracted_data.tax_id.clone();
+    let total = result.extracted_data.total.clone().map(Money::into_amount).unwrap_or_default();
+    let currency = result.extracted_data.currency;
+    let items: Vec<TransactionItem> = result.extracted_data.items.iter()
+        .map(|item| TransactionItem {
+            name: item.name.clone(),
+            price: item.price.clone(),
+            quantity: item.quantity,
+        })
+        .collect();
+    
+    db::run(pool, move |conn| {
+        let merchant_match = match raw_merchant.as_deref() {
+            Some(raw) => merchant_service::resolve_merchant(conn, access.ledger_id, raw, tax_id.as_deref())?,
+            None => None,
+        };
+        let merchant_name = merchant_match.as_ref()
+            .map(|m| m.merchant.name.clone())
+            .or(raw_merchant)
+            .unwrap_or_default();
+        
+        let item_names: Vec<String> = items.iter().map(|item| item.name.clone()).collect();
+        let suggestions = categorizer::suggest_categories(
+            conn,
+            access.ledger_id,
+            Some(merchant_name.as_str()).filter(|m| !m.is_empty()),
+            &item_names,
+            categorizer::DEFAULT_SUGGESTION_LIMIT,
+        )?;
+        
+        // Rules are deterministic, so they override the suggested category
+        let mut effects = RuleEffects {
+            category: suggestions.first().map(|s| s.name.clone()).unwrap_or_default(),
+            tags: Vec::new(),
+            notes: None,
+            excluded: false,
+        };
+        let rules = rule_service::load_rules(conn, access.ledger_id)?;
+        let input = RuleInput {
+            merchant: &merchant_name,
+            amount: &total,
+            currency,
+            items: &items,
+            source: SOURCE_OCR,
+        };
+        rule_service::apply_rules(&rules, &input, &mut effects);
+        
+        Ok(OcrEnrichment {
+            merchant_match,
+            suggestions,
+            rule_effects: Some(effects),
+        })
+    })
+    .await
+}
+
 // Helper function to convert OcrResult to a serializable response
-fn serialize_ocr_result(result: OcrResult, engine: String) -> serde_json::Value {
+fn serialize_ocr_result(
+    result: OcrResult,
+    engine: String,
+    enrichment: &OcrEnrichment,
+    bill_id: Option<Uuid>,
+) -> serde_json::Value {
     let source = match engine.as_str() {
         "tesseract" => "Tesseract OCR",
         "google" => "Google Vision API",
         _ => if result.confidence <= 0.7 { "Google Vision API" } else { "Tesseract OCR" },
     };
     
+    let merchant_match = enrichment.merchant_match.as_ref();
+    let merchant = merchant_match
+        .map(|m| m.merchant.name.clone())
+        .or_else(|| result.extracted_data.merchant.clone());
+    let effects = enrichment.rule_effects.as_ref();
+    
     serde_json::json!({
+        "billId": bill_id,
         "text": result.text,
         "extractedData": {
             "total": result.extracted_data.total,
+            "currency": result.extracted_data.currency,
             "date": result.extracted_data.date,
-            "merchant": result.extracted_data.merchant,
-            "items": result.extracted_data.items
+            "merchant": merchant,
+            "merchantId": merchant_match.map(|m| m.merchant.id),
+            "rawMerchant": result.extracted_data.merchant,
+            "taxId": result.extracted_data.tax_id,
+            "items": result.extracted_data.items,
+            "category": effects.map(|e| e.category.clone()).filter(|c| !c.is_empty()),
+            "suggestedCategories": enrichment.suggestions,
+            "tags": effects.map(|e| e.tags.clone()).unwrap_or_default(),
+            "notes": effects.and_then(|e| e.notes.clone()),
+            "excluded": effects.is_some_and(|e| e.excluded)
         },
         "confidence": result.confidence,
         "processingTime": result.processing_time,
@@ -0,0 +1,57 @@
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::handlers::auth::{LedgerEditor, LedgerViewer};
+use crate::models::reconciliation::{MatchQuery, ReconcileDto};
+use crate::services::reconciliation as reconciliation_service;
+use actix_web::{web, HttpResponse};
+use uuid::Uuid;
+
+fn parse_match_id(id: &str) -> Result<Uuid, AppError> {
+    Uuid::parse_str(id).map_err(|_| AppError::BadRequest("Invalid reconciliation match ID".to_string()))
+}
+
+// Pair receipts with the bank lines they were paid with, replacing earlier unconfirmed proposals
+pub async fn run_reconciliation(
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
+    data: web::Json<ReconcileDto>,
+) -> Result<HttpResponse, AppError> {
+    let data = data.into_inner();
+    let response = db::run(&pool, move |conn| reconciliation_service::reconcile(conn, access.ledger_id, &data)).await?;
+    Ok(HttpResponse::Ok().json(response))
+}
+
+// Proposed, confirmed and rejected pairs, best scores first
+pub async fn get_matches(
+    pool: web::Data<DbPool>,
+    access: LedgerViewer,
+    query: web::Query<MatchQuery>,
+) -> Result<HttpResponse, AppError> {
+    let status = query.into_inner().status;
+    let matches =
+        db::run(&pool, move |conn| reconciliation_service::list_matches(conn, access.ledger_id, status)).await?;
+    Ok(HttpResponse::Ok().json(matches))
+}
+
+pub async fn confirm_match(
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let match_id = parse_match_id(&path.into_inner())?;
+    let response =
+        db::run(&pool, move |conn| reconciliation_service::confirm_match(conn, access.ledger_id, match_id)).await?;
+    Ok(HttpResponse::Ok().json(response))
+}
+
+// Reject a proposed pair, or undo a confirmed one
+pub async fn reject_match(
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let match_id = parse_match_id(&path.into_inner())?;
+    let response =
+        db::run(&pool, move |conn| reconciliation_service::reject_match(conn, access.ledger_id, match_id)).await?;
+    Ok(HttpResponse::Ok().json(response))
+}
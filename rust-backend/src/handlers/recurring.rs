@@ -0,0 +1,314 @@
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::handlers::auth::{LedgerEditor, LedgerViewer};
+use crate::models::category::CategoryKind;
+use crate::money::Currency;
+use crate::models::recurring::{
+    CreateRecurringDto, DbRecurringTransaction, Frequency, NewRecurringTransaction, RecurringResponse,
+    RecurringTransactionChanges, Schedule, UpcomingQuery, UpdateRecurringDto,
+};
+use crate::schema::recurring_transactions;
+use crate::services::merchants as merchant_service;
+use crate::services::recurring as recurring_service;
+use crate::services::transactions::{category_names, resolve_category_id};
+use actix_web::{web, HttpResponse};
+use chrono::{NaiveDate, Utc};
+use diesel::prelude::*;
+use diesel::PgConnection;
+use uuid::Uuid;
+
+const DEFAULT_UPCOMING_DAYS: i64 = 30;
+const MAX_UPCOMING_DAYS: i64 = 366;
+
+fn parse_recurring_id(id: &str) -> Result<Uuid, AppError> {
+    Uuid::parse_str(id).map_err(|_| AppError::BadRequest("Invalid recurring transaction ID".to_string()))
+}
+
+fn validate_schedule(
+    interval: i32,
+    day_of_month: Option<i32>,
+    start_date: NaiveDate,
+    end_date: Option<NaiveDate>,
+) -> Result<(), AppError> {
+    if interval < 1 {
+        return Err(AppError::BadRequest("Interval must be at least 1".to_string()));
+    }
+    if day_of_month.is_some_and(|day| !(1..=31).contains(&day)) {
+        return Err(AppError::BadRequest("Day of month must be between 1 and 31".to_string()));
+    }
+    if end_date.is_some_and(|end| end < start_date) {
+        return Err(AppError::BadRequest("End date must not be before the start date".to_string()));
+    }
+    Ok(())
+}
+
+fn to_response(
+    conn: &mut PgConnection,
+    template: DbRecurringTransaction,
+) -> Result<RecurringResponse, AppError> {
+    let names = category_names(conn, &template.category_id.into_iter().collect::<Vec<_>>())?;
+    let category = template.category_id.and_then(|id| names.get(&id).cloned()).unwrap_or_default();
+    Ok(RecurringResponse::new(template, category))
+}
+
+// List the user's recurring transaction templates
+pub async fn get_recurring(
+    pool: web::Data<DbPool>,
+    access: LedgerViewer,
+) -> Result<HttpResponse, AppError> {
+    let response = db::run(&pool, move |conn| {
+        let all = recurring_transactions::table
+            .filter(recurring_transactions::ledger_id.eq(access.ledger_id))
+            .order((recurring_transactions::next_run.asc(), recurring_transactions::name.asc()))
+            .load::<DbRecurringTransaction>(conn)?;
+
+        let category_ids: Vec<Uuid> = all.iter().filter_map(|t| t.category_id).collect();
+        let names = category_names(conn, &category_ids)?;
+
+        Ok(all
+            .into_iter()
+            .map(|template| {
+                let category = template.category_id.and_then(|id| names.get(&id).cloned()).unwrap_or_default();
+                RecurringResponse::new(template, category)
+            })
+            .collect::<Vec<_>>())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+// Get a single recurring transaction template by ID
+pub async fn get_recurring_by_id(
+    pool: web::Data<DbPool>,
+    access: LedgerViewer,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let recurring_id = parse_recurring_id(&path.into_inner())?;
+
+    let response = db::run(&pool, move |conn| {
+        let template = recurring_service::find_recurring(conn, access.ledger_id, recurring_id)?;
+        to_response(conn, template)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+// Create a template; occurrences already due are materialized straight away
+pub async fn create_recurring(
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
+    recurring_data: web::Json<CreateRecurringDto>,
+) -> Result<HttpResponse, AppError> {
+    let data = recurring_data.into_inner();
+    if data.merchant.trim().is_empty() {
+        return Err(AppError::BadRequest("Merchant is required".to_string()));
+    }
+    let interval = data.interval.unwrap_or(1);
+    validate_schedule(interval, data.day_of_month, data.start_date, data.end_date)?;
+    let currency = data.currency.unwrap_or_default();
+    let amount = currency.round(&data.amount);
+    let currency = currency.to_string();
+    // Only monthly schedules pin a day; the others repeat from the start date
+    let day_of_month = data.day_of_month.filter(|_| data.frequency == Frequency::Monthly);
+
+    let response = db::run(&pool, move |conn| {
+        conn.transaction(|conn| {
+            let merchant = merchant_service::resolve_or_create_merchant(conn, access.ledger_id, &data.merchant, None)?
+                .merchant;
+            let category_id = match data.category.as_deref() {
+                Some(name) => resolve_category_id(conn, access.ledger_id, name, CategoryKind::Expense)?,
+                None => merchant.default_category_id,
+            };
+
+            let schedule = Schedule {
+                frequency: data.frequency,
+                interval,
+                day_of_month: day_of_month.map(|d| d as u32),
+                start_date: data.start_date,
+            };
+
+            let new_template = NewRecurringTransaction {
+                id: Uuid::new_v4(),
+                ledger_id: access.ledger_id,
+                name: data
+                    .name
+                    .map(|n| n.trim().to_string())
+                    .filter(|n| !n.is_empty())
+                    .unwrap_or_else(|| merchant.name.clone()),
+                merchant: merchant.name.clone(),
+                merchant_id: Some(merchant.id),
+                amount,
+                currency,
+                category_id,
+                notes: data.notes,
+                tags: data.tags.unwrap_or_default(),
+                frequency: data.frequency.as_str().to_string(),
+                interval_count: interval,
+                day_of_month,
+                start_date: data.start_date,
+                end_date: data.end_date,
+                next_run: recurring_service::first_occurrence(&schedule),
+                active: true,
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+            };
+
+            let created = diesel::insert_into(recurring_transactions::table)
+                .values(&new_template)
+                .get_result::<DbRecurringTransaction>(conn)?;
+            recurring_service::materialize_template(conn, &created, Utc::now().date_naive())?;
+
+            let template = recurring_service::find_recurring(conn, access.ledger_id, created.id)?;
+            to_response(conn, template)
+        })
+    })
+    .await?;
+
+    Ok(HttpResponse::Created().json(response))
+}
+
+// Update a template; schedule changes recompute the next run from the last one
+pub async fn update_recurring(
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
+    path: web::Path<String>,
+    recurring_data: web::Json<UpdateRecurringDto>,
+) -> Result<HttpResponse, AppError> {
+    let recurring_id = parse_recurring_id(&path.into_inner())?;
+    let data = recurring_data.into_inner();
+    let currency = data.currency.map(|currency| currency.to_string());
+
+    let response = db::run(&pool, move |conn| {
+        conn.transaction(|conn| {
+            let existing = recurring_service::find_recurring(conn, access.ledger_id, recurring_id)?;
+            let amount = data
+                .amount
+                .as_ref()
+                .map(|amount| Currency::from_code(currency.as_deref().unwrap_or(&existing.currency)).round(amount));
+            let current = existing.schedule();
+
+            let frequency = data.frequency.unwrap_or(current.frequency);
+            let interval = data.interval.unwrap_or(current.interval);
+            let day_of_month = data
+                .day_of_month
+                .or(existing.day_of_month)
+                .filter(|_| frequency == Frequency::Monthly);
+            let end_date = data.end_date.or(existing.end_date);
+            validate_schedule(interval, day_of_month, existing.start_date, end_date)?;
+
+            let merchant = match data.merchant.as_deref() {
+                Some(raw_merchant) => Some(
+                    merchant_service::resolve_or_create_merchant(conn, access.ledger_id, raw_merchant, None)?.merchant,
+                ),
+                None => None,
+            };
+            let category_id = match data.category.as_deref() {
+                Some(name) => resolve_category_id(conn, access.ledger_id, name, CategoryKind::Expense)?,
+                None => None,
+            };
+
+            let schedule = Schedule {
+                frequency,
+                interval,
+                day_of_month: day_of_month.map(|d| d as u32),
+                start_date: existing.start_date,
+            };
+            let schedule_changed = data.frequency.is_some() || data.interval.is_some() || data.day_of_month.is_some();
+            let resumed = data.active == Some(true) && !existing.active;
+            let today = Utc::now().date_naive();
+
+            // Resuming a paused template starts from today instead of back-filling the pause
+            let next_run = if resumed {
+                Some(recurring_service::occurrence_on_or_after(&schedule, today))
+            } else if schedule_changed {
+                Some(match existing.last_run {
+                    Some(last_run) => recurring_service::next_occurrence(&schedule, last_run),
+                    None => recurring_service::first_occurrence(&schedule),
+                })
+            } else {
+                None
+            };
+
+            let changes = RecurringTransactionChanges {
+                name: data.name.map(|n| n.trim().to_string()).filter(|n| !n.is_empty()),
+                merchant: merchant.as_ref().map(|m| m.name.clone()),
+                merchant_id: merchant.as_ref().map(|m| m.id),
+                amount,
+                currency,
+                category_id,
+                notes: data.notes,
+                tags: data.tags,
+                frequency: data.frequency.map(|f| f.as_str().to_string()),
+                interval_count: data.interval,
+                day_of_month: data.day_of_month.filter(|_| frequency == Frequency::Monthly),
+                end_date: data.end_date,
+                next_run,
+                active: data.active,
+                updated_at: Some(Utc::now()),
+                ..Default::default()
+            };
+
+            let updated = diesel::update(recurring_transactions::table.find(existing.id))
+                .set(&changes)
+                .get_result::<DbRecurringTransaction>(conn)?;
+            if updated.active {
+                recurring_service::materialize_template(conn, &updated, today)?;
+            }
+
+            let template = recurring_service::find_recurring(conn, access.ledger_id, updated.id)?;
+            to_response(conn, template)
+        })
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+// Delete a template; transactions it already created are kept
+pub async fn delete_recurring(
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let recurring_id = parse_recurring_id(&path.into_inner())?;
+
+    db::run(&pool, move |conn| {
+        let template = recurring_service::find_recurring(conn, access.ledger_id, recurring_id)?;
+        diesel::delete(recurring_transactions::table.find(template.id)).execute(conn)?;
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+// Bills expected in the next `days` days (default 30)
+pub async fn get_upcoming(
+    pool: web::Data<DbPool>,
+    access: LedgerViewer,
+    query: web::Query<UpcomingQuery>,
+) -> Result<HttpResponse, AppError> {
+    let days = query.days.unwrap_or(DEFAULT_UPCOMING_DAYS).clamp(1, MAX_UPCOMING_DAYS);
+
+    let bills = db::run(&pool, move |conn| {
+        recurring_service::upcoming_bills(conn, access.ledger_id, Utc::now().date_naive(), days)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(bills))
+}
+
+// Subscriptions detected in transaction history that have no template yet
+pub async fn detect_subscriptions(
+    pool: web::Data<DbPool>,
+    access: LedgerViewer,
+) -> Result<HttpResponse, AppError> {
+    let candidates = db::run(&pool, move |conn| {
+        recurring_service::detect_subscriptions(conn, access.ledger_id, Utc::now().date_naive())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(candidates))
+}
@@ -0,0 +1,166 @@
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::handlers::auth::LedgerViewer;
+use crate::models::report::{
+    Granularity, MonthlySpendingQuery, SpendingByCategoryQuery, TransactionTrendsQuery,
+};
+use crate::services::reports as report_service;
+use actix_web::{web, HttpResponse};
+use chrono::{Datelike, Duration, NaiveDate};
+use diesel::PgConnection;
+
+// Longest range a single report may cover
+const MAX_REPORT_DAYS: i64 = 366 * 10;
+
+// Number of buckets shown by default for each trend granularity
+fn default_bucket_count(granularity: Granularity) -> i64 {
+    match granularity {
+        Granularity::Day => 30,
+        Granularity::Week => 12,
+        Granularity::Month => 12,
+        Granularity::Quarter => 8,
+        Granularity::Year => 5,
+    }
+}
+
+fn validate_range(start: NaiveDate, end: NaiveDate) -> Result<(), AppError> {
+    if end < start {
+        return Err(AppError::BadRequest("End date must not be before the start date".to_string()));
+    }
+    if (end - start).num_days() > MAX_REPORT_DAYS {
+        return Err(AppError::BadRequest("Report range is too long".to_string()));
+    }
+    Ok(())
+}
+
+// Spending per category between two dates (default: this month so far), with the previous period
+pub async fn spending_by_category(
+    pool: web::Data<DbPool>,
+    access: LedgerViewer,
+    query: web::Query<SpendingByCategoryQuery>,
+) -> Result<HttpResponse, AppError> {
+    let query = query.into_inner();
+    let tz = report_service::timezone_or_default(query.tz.as_deref());
+
+    let report = db::run(&pool, move |conn| {
+        let today = report_service::local_today(conn, &tz)?;
+        let start = query.start_date.unwrap_or_else(|| report_service::truncate(Granularity::Month, today));
+        let end = query.end_date.unwrap_or(today);
+        validate_range(start, end)?;
+
+        report_service::spending_by_category(conn, access.ledger_id, start, end + Duration::days(1), &tz)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(report))
+}
+
+// Spending per tag between two dates (default: this month so far)
+pub async fn spending_by_tag(
+    pool: web::Data<DbPool>,
+    access: LedgerViewer,
+    query: web::Query<SpendingByCategoryQuery>,
+) -> Result<HttpResponse, AppError> {
+    let query = query.into_inner();
+    let tz = report_service::timezone_or_default(query.tz.as_deref());
+
+    let report = db::run(&pool, move |conn| {
+        let today = report_service::local_today(conn, &tz)?;
+        let start = query.start_date.unwrap_or_else(|| report_service::truncate(Granularity::Month, today));
+        let end = query.end_date.unwrap_or(today);
+        validate_range(start, end)?;
+
+        report_service::spending_by_tag(conn, access.ledger_id, start, end + Duration::days(1), &tz)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(report))
+}
+
+// Spending per month of a year (default: this year), with the same months of the year before
+pub async fn monthly_spending(
+    pool: web::Data<DbPool>,
+    access: LedgerViewer,
+    query: web::Query<MonthlySpendingQuery>,
+) -> Result<HttpResponse, AppError> {
+    let query = query.into_inner();
+    let tz = report_service::timezone_or_default(query.tz.as_deref());
+
+    let report = db::run(&pool, move |conn| {
+        let year = query.year.unwrap_or(report_service::local_today(conn, &tz)?.year());
+        let start = NaiveDate::from_ymd_opt(year, 1, 1)
+            .ok_or_else(|| AppError::BadRequest("Invalid year".to_string()))?;
+        let end = NaiveDate::from_ymd_opt(year + 1, 1, 1)
+            .ok_or_else(|| AppError::BadRequest("Invalid year".to_string()))?;
+
+        report_service::series_report(conn, access.ledger_id, Granularity::Month, start, end, &tz)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(report))
+}
+
+fn parse_granularity(period: Option<&str>) -> Result<Granularity, AppError> {
+    match period {
+        Some(period) => Granularity::parse(period).ok_or_else(|| {
+            AppError::BadRequest("Period must be one of day, week, month, quarter or year".to_string())
+        }),
+        None => Ok(Granularity::Month),
+    }
+}
+
+// The [start, end_exclusive) range of a bucketed report; without a start date, the default number
+// of whole buckets ending with the current one
+fn bucketed_range(
+    conn: &mut PgConnection,
+    query: &TransactionTrendsQuery,
+    granularity: Granularity,
+    tz: &str,
+) -> Result<(NaiveDate, NaiveDate), AppError> {
+    let today = report_service::local_today(conn, tz)?;
+    let end = query.end_date.unwrap_or(today);
+    let start = query.start_date.unwrap_or_else(|| {
+        let last_bucket = report_service::truncate(granularity, end);
+        report_service::shift(granularity, last_bucket, 1 - default_bucket_count(granularity))
+    });
+    validate_range(start, end)?;
+    Ok((start, end + Duration::days(1)))
+}
+
+// Spending bucketed by day, week, month, quarter or year, with the previous run of buckets
+pub async fn transaction_trends(
+    pool: web::Data<DbPool>,
+    access: LedgerViewer,
+    query: web::Query<TransactionTrendsQuery>,
+) -> Result<HttpResponse, AppError> {
+    let query = query.into_inner();
+    let granularity = parse_granularity(query.period.as_deref())?;
+    let tz = report_service::timezone_or_default(query.tz.as_deref());
+
+    let report = db::run(&pool, move |conn| {
+        let (start, end_exclusive) = bucketed_range(conn, &query, granularity, &tz)?;
+        report_service::series_report(conn, access.ledger_id, granularity, start, end_exclusive, &tz)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(report))
+}
+
+// Income, spending, net cash flow and savings rate per bucket; takes the same parameters as the trends
+pub async fn cash_flow(
+    pool: web::Data<DbPool>,
+    access: LedgerViewer,
+    query: web::Query<TransactionTrendsQuery>,
+) -> Result<HttpResponse, AppError> {
+    let query = query.into_inner();
+    let granularity = parse_granularity(query.period.as_deref())?;
+    let tz = report_service::timezone_or_default(query.tz.as_deref());
+
+    let report = db::run(&pool, move |conn| {
+        let (start, end_exclusive) = bucketed_range(conn, &query, granularity, &tz)?;
+        report_service::cash_flow_report(conn, access.ledger_id, granularity, start, end_exclusive, &tz)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(report))
+}
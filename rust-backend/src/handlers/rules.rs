@@ -0,0 +1,248 @@
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::handlers::auth::{LedgerEditor, LedgerViewer};
+use crate::models::rule::{
+    CreateRuleDto, DbRule, MatchMode, NewRule, ReorderRulesDto, Rule, RuleChanges, UpdateRuleDto,
+};
+use crate::schema::rules;
+use crate::services::rules as rule_service;
+use actix_web::{web, HttpResponse};
+use chrono::Utc;
+use diesel::dsl::max;
+use diesel::prelude::*;
+use uuid::Uuid;
+
+fn parse_rule_id(id: &str) -> Result<Uuid, AppError> {
+    Uuid::parse_str(id).map_err(|_| AppError::BadRequest("Invalid rule ID".to_string()))
+}
+
+fn validate_rule(name: &str, conditions_len: usize, actions_len: usize) -> Result<(), AppError> {
+    if name.trim().is_empty() {
+        return Err(AppError::BadRequest("Rule name is required".to_string()));
+    }
+    if conditions_len == 0 {
+        return Err(AppError::BadRequest("A rule needs at least one condition".to_string()));
+    }
+    if actions_len == 0 {
+        return Err(AppError::BadRequest("A rule needs at least one action".to_string()));
+    }
+    Ok(())
+}
+
+// Build an unsaved rule from a request body, used for dry runs of drafts
+fn draft_rule(rule_data: CreateRuleDto) -> Rule {
+    Rule {
+        id: Uuid::nil(),
+        name: rule_data.name,
+        position: 0,
+        enabled: true,
+        match_mode: rule_data.match_mode.unwrap_or(MatchMode::All),
+        stop_processing: rule_data.stop_processing.unwrap_or(false),
+        conditions: rule_data.conditions,
+        actions: rule_data.actions,
+        created_at: Utc::now(),
+    }
+}
+
+// List the user's rules in evaluation order
+pub async fn get_rules(
+    pool: web::Data<DbPool>,
+    access: LedgerViewer,
+) -> Result<HttpResponse, AppError> {
+    let all = db::run(&pool, move |conn| rule_service::load_rules(conn, access.ledger_id)).await?;
+    Ok(HttpResponse::Ok().json(all))
+}
+
+// Get a single rule by ID
+pub async fn get_rule(
+    pool: web::Data<DbPool>,
+    access: LedgerViewer,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let rule_id = parse_rule_id(&path.into_inner())?;
+    let rule = db::run(&pool, move |conn| rule_service::find_rule(conn, access.ledger_id, rule_id)).await?;
+    Ok(HttpResponse::Ok().json(rule))
+}
+
+// Create a rule at the end of the evaluation order
+pub async fn create_rule(
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
+    rule_data: web::Json<CreateRuleDto>,
+) -> Result<HttpResponse, AppError> {
+    let rule_data = rule_data.into_inner();
+    validate_rule(&rule_data.name, rule_data.conditions.len(), rule_data.actions.len())?;
+
+    let rule = db::run(&pool, move |conn| {
+        let mut conditions = rule_data.conditions;
+        rule_service::set_condition_currencies(conn, access.ledger_id, &mut conditions)?;
+        let last_position = rules::table
+            .filter(rules::ledger_id.eq(access.ledger_id))
+            .select(max(rules::position))
+            .first::<Option<i32>>(conn)?;
+
+        let new_rule = NewRule {
+            id: Uuid::new_v4(),
+            ledger_id: access.ledger_id,
+            name: rule_data.name.trim().to_string(),
+            position: last_position.map_or(0, |p| p + 1),
+            enabled: rule_data.enabled.unwrap_or(true),
+            match_mode: rule_data.match_mode.unwrap_or(MatchMode::All).as_str().to_string(),
+            stop_processing: rule_data.stop_processing.unwrap_or(false),
+            conditions: serde_json::to_value(&conditions)?,
+            actions: serde_json::to_value(&rule_data.actions)?,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+
+        let created = diesel::insert_into(rules::table)
+            .values(&new_rule)
+            .get_result::<DbRule>(conn)?;
+        Rule::try_from(created)
+    })
+    .await?;
+
+    Ok(HttpResponse::Created().json(rule))
+}
+
+// Update a rule
+pub async fn update_rule(
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
+    path: web::Path<String>,
+    rule_data: web::Json<UpdateRuleDto>,
+) -> Result<HttpResponse, AppError> {
+    let rule_id = parse_rule_id(&path.into_inner())?;
+    let rule_data = rule_data.into_inner();
+
+    let rule = db::run(&pool, move |conn| {
+        let existing = rule_service::find_rule(conn, access.ledger_id, rule_id)?;
+        validate_rule(
+            rule_data.name.as_deref().unwrap_or(&existing.name),
+            rule_data.conditions.as_ref().map_or(existing.conditions.len(), Vec::len),
+            rule_data.actions.as_ref().map_or(existing.actions.len(), Vec::len),
+        )?;
+
+        let mut conditions = rule_data.conditions;
+        if let Some(conditions) = conditions.as_mut() {
+            rule_service::set_condition_currencies(conn, access.ledger_id, conditions)?;
+        }
+        let changes = RuleChanges {
+            name: rule_data.name.map(|n| n.trim().to_string()),
+            enabled: rule_data.enabled,
+            match_mode: rule_data.match_mode.map(|m| m.as_str().to_string()),
+            stop_processing: rule_data.stop_processing,
+            conditions: conditions.map(serde_json::to_value).transpose()?,
+            actions: rule_data.actions.map(serde_json::to_value).transpose()?,
+            updated_at: Utc::now(),
+        };
+
+        let updated = diesel::update(rules::table.find(existing.id))
+            .set(&changes)
+            .get_result::<DbRule>(conn)?;
+        Rule::try_from(updated)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(rule))
+}
+
+// Delete a rule
+pub async fn delete_rule(
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let rule_id = parse_rule_id(&path.into_inner())?;
+
+    db::run(&pool, move |conn| {
+        let rule = rule_service::find_rule(conn, access.ledger_id, rule_id)?;
+        diesel::delete(rules::table.find(rule.id)).execute(conn)?;
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+// Set the evaluation order; rules not listed keep their relative order after the listed ones
+pub async fn reorder_rules(
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
+    order: web::Json<ReorderRulesDto>,
+) -> Result<HttpResponse, AppError> {
+    let rule_ids = order.into_inner().rule_ids;
+
+    let all = db::run(&pool, move |conn| {
+        conn.transaction(|conn| {
+            let current = rule_service::load_rules(conn, access.ledger_id)?;
+            if let Some(unknown) = rule_ids.iter().find(|id| !current.iter().any(|r| r.id == **id)) {
+                return Err(AppError::NotFound(format!("Rule {} not found", unknown)));
+            }
+
+            let remaining = current.iter().map(|r| r.id).filter(|id| !rule_ids.contains(id));
+            for (position, id) in rule_ids.iter().copied().chain(remaining).enumerate() {
+                diesel::update(rules::table.find(id))
+                    .set((rules::position.eq(position as i32), rules::updated_at.eq(Utc::now())))
+                    .execute(conn)?;
+            }
+
+            rule_service::load_rules(conn, access.ledger_id)
+        })
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(all))
+}
+
+// Show which existing transactions an unsaved rule would change
+pub async fn dry_run_draft(
+    pool: web::Data<DbPool>,
+    access: LedgerViewer,
+    rule_data: web::Json<CreateRuleDto>,
+) -> Result<HttpResponse, AppError> {
+    let rule_data = rule_data.into_inner();
+    validate_rule(&rule_data.name, rule_data.conditions.len(), rule_data.actions.len())?;
+    let mut rule = draft_rule(rule_data);
+
+    let report = db::run(&pool, move |conn| {
+        rule_service::set_condition_currencies(conn, access.ledger_id, &mut rule.conditions)?;
+        rule_service::dry_run(conn, access.ledger_id, &rule)
+    })
+    .await?;
+    Ok(HttpResponse::Ok().json(report))
+}
+
+// Show which existing transactions a saved rule would change
+pub async fn dry_run_rule(
+    pool: web::Data<DbPool>,
+    access: LedgerViewer,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let rule_id = parse_rule_id(&path.into_inner())?;
+
+    let report = db::run(&pool, move |conn| {
+        let rule = rule_service::find_rule(conn, access.ledger_id, rule_id)?;
+        rule_service::dry_run(conn, access.ledger_id, &rule)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(report))
+}
+
+// Apply a saved rule to existing transactions
+pub async fn apply_rule(
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let rule_id = parse_rule_id(&path.into_inner())?;
+
+    let report = db::run(&pool, move |conn| {
+        let rule = rule_service::find_rule(conn, access.ledger_id, rule_id)?;
+        rule_service::apply_to_existing(conn, access.ledger_id, &rule)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(report))
+}
@@ -0,0 +1,178 @@
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::handlers::auth::{LedgerEditor, LedgerViewer};
+use crate::models::tag::{
+    BulkTagDto, BulkTagResponse, CreateTagDto, DbTag, MergeTagsDto, NewTag, TagChanges, TagResponse, UpdateTagDto,
+};
+use crate::schema::{tags, transactions};
+use crate::services::tags as tag_service;
+use crate::services::transactions as transaction_service;
+use actix_web::{web, HttpResponse};
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel::PgConnection;
+use uuid::Uuid;
+
+fn parse_tag_id(id: &str) -> Result<Uuid, AppError> {
+    Uuid::parse_str(id).map_err(|_| AppError::BadRequest("Invalid tag ID".to_string()))
+}
+
+fn tag_response(conn: &mut PgConnection, tag: DbTag) -> Result<TagResponse, AppError> {
+    let count = tag_service::tag_counts(conn, tag.ledger_id)?.get(&tag.id).copied().unwrap_or(0);
+    Ok(TagResponse::new(tag, count))
+}
+
+// List the user's tags with how many transactions carry each
+pub async fn get_tags(pool: web::Data<DbPool>, access: LedgerViewer) -> Result<HttpResponse, AppError> {
+    let response = db::run(&pool, move |conn| tag_service::list_tags(conn, access.ledger_id)).await?;
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+// Create a tag ahead of using it; tags are also created on first use
+pub async fn create_tag(
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
+    tag_data: web::Json<CreateTagDto>,
+) -> Result<HttpResponse, AppError> {
+    let data = tag_data.into_inner();
+    let name = data.name.trim().to_string();
+    if name.is_empty() {
+        return Err(AppError::BadRequest("Tag name is required".to_string()));
+    }
+
+    let response = db::run(&pool, move |conn| {
+        tag_service::ensure_name_available(conn, access.ledger_id, &name, None)?;
+        let new_tag = NewTag {
+            id: Uuid::new_v4(),
+            ledger_id: access.ledger_id,
+            name,
+            color: data.color,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+        let created = diesel::insert_into(tags::table)
+            .values(&new_tag)
+            .get_result::<DbTag>(conn)
+            .map_err(|e| tag_service::name_error(e, &new_tag.name))?;
+        Ok(TagResponse::new(created, 0))
+    })
+    .await?;
+
+    Ok(HttpResponse::Created().json(response))
+}
+
+// Rename or recolor a tag; the new name shows up on every transaction carrying it
+pub async fn update_tag(
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
+    path: web::Path<String>,
+    tag_data: web::Json<UpdateTagDto>,
+) -> Result<HttpResponse, AppError> {
+    let tag_id = parse_tag_id(&path.into_inner())?;
+    let data = tag_data.into_inner();
+
+    let response = db::run(&pool, move |conn| {
+        conn.transaction(|conn| {
+            let existing = tag_service::find_tag(conn, access.ledger_id, tag_id)?;
+
+            let name = data.name.map(|n| n.trim().to_string());
+            if let Some(name) = &name {
+                if name.is_empty() {
+                    return Err(AppError::BadRequest("Tag name is required".to_string()));
+                }
+                tag_service::ensure_name_available(conn, access.ledger_id, name, Some(existing.id))?;
+            }
+
+            let changes = TagChanges {
+                name,
+                color: data.color,
+                updated_at: Utc::now(),
+            };
+            let updated = diesel::update(tags::table.find(existing.id))
+                .set(&changes)
+                .get_result::<DbTag>(conn)
+                .map_err(|e| tag_service::name_error(e, changes.name.as_deref().unwrap_or(&existing.name)))?;
+            tag_response(conn, updated)
+        })
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+// Delete a tag and take it off every transaction
+pub async fn delete_tag(
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let tag_id = parse_tag_id(&path.into_inner())?;
+
+    db::run(&pool, move |conn| {
+        conn.transaction(|conn| {
+            let tag = tag_service::find_tag(conn, access.ledger_id, tag_id)?;
+            tag_service::delete_tag(conn, &tag)
+        })
+    })
+    .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+// Merge other tags into this one
+pub async fn merge_tags(
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
+    path: web::Path<String>,
+    merge_data: web::Json<MergeTagsDto>,
+) -> Result<HttpResponse, AppError> {
+    let target_id = parse_tag_id(&path.into_inner())?;
+    let source_ids = merge_data.into_inner().source_ids;
+
+    if source_ids.is_empty() {
+        return Err(AppError::BadRequest("No tags to merge".to_string()));
+    }
+
+    let response = db::run(&pool, move |conn| {
+        let merged = tag_service::merge_tags(conn, access.ledger_id, target_id, &source_ids)?;
+        tag_response(conn, merged)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+// Add and remove tags on every transaction matching the filters
+pub async fn bulk_tag(
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
+    bulk_data: web::Json<BulkTagDto>,
+) -> Result<HttpResponse, AppError> {
+    let data = bulk_data.into_inner();
+    if tag_service::normalize_names(&data.add).is_empty() && tag_service::normalize_names(&data.remove).is_empty() {
+        return Err(AppError::BadRequest("Name at least one tag to add or remove".to_string()));
+    }
+
+    let response = db::run(&pool, move |conn| {
+        conn.transaction(|conn| {
+            let transaction_ids = transaction_service::filtered_query(access.ledger_id, &data.filters)
+                .select(transactions::id)
+                .load::<Uuid>(conn)?;
+
+            let added = tag_service::resolve_tags(conn, access.ledger_id, &data.add)?;
+            let tagged = tag_service::add_tags(conn, &transaction_ids, &added)?;
+            let removed = tag_service::existing_tag_ids(conn, access.ledger_id, &data.remove)?;
+            let untagged = tag_service::remove_tags(conn, &transaction_ids, &removed)?;
+
+            Ok(BulkTagResponse {
+                matched: transaction_ids.len(),
+                tagged,
+                untagged,
+            })
+        })
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(response))
+}
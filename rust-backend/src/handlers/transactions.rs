@@ -1,299 +1,557 @@
+use crate::config::Config;
+use crate::db;
 use crate::error::AppError;
-use crate::models::category::{default_categories};
+use crate::handlers::auth::{LedgerEditor, LedgerViewer};
+use crate::models::bulk::{BulkOperationDto, BulkOperationResponse, UndoBulkOperationResponse};
+use crate::models::rule::RuleEffects;
+use crate::models::search::{SearchResponse, SearchResult};
+use crate::models::split::SetSplitsDto;
 use crate::models::transaction::{
-    CreateTransactionDto, Transaction, TransactionFilters, TransactionItem,
-    TransactionResponse, TransactionsListResponse, UpdateTransactionDto,
+    CreateTransactionDto, DbTransaction, NewTransaction, TransactionChanges, TransactionFilters, TransactionType,
+    TransactionsListResponse, UpdateTransactionDto, SOURCE_MANUAL, SOURCE_OCR,
 };
+use crate::schema::transactions;
 use actix_web::{web, HttpResponse};
+use bigdecimal::{BigDecimal, Signed};
 use chrono::Utc;
-use serde::Deserialize;
+use diesel::prelude::*;
+use std::fs;
 use uuid::Uuid;
-use chrono::DateTime;
 use crate::db::DbPool as RealDbPool;
+use crate::services::accounts as account_service;
+use crate::services::bills as bill_service;
+use crate::services::bulk as bulk_service;
+use crate::services::categorizer;
+use crate::services::merchants as merchant_service;
+use crate::services::rules::{self as rule_service, RuleInput};
+use crate::services::search as search_service;
+use crate::services::splits as split_service;
+use crate::services::tags as tag_service;
+use crate::money::Currency;
+use crate::services::transactions as transaction_service;
 
 // Type alias for the database pool
 type DbPool = RealDbPool;
 
+fn parse_transaction_id(id: &str) -> Result<Uuid, AppError> {
+    Uuid::parse_str(id).map_err(|_| AppError::BadRequest("Invalid transaction ID".to_string()))
+}
+
+// Transfers are only made in pairs through the accounts API, and only refunds point at a purchase
+fn validate_transaction_type(transaction_type: TransactionType, refund_of: Option<Uuid>) -> Result<(), AppError> {
+    if transaction_type == TransactionType::Transfer {
+        return Err(AppError::BadRequest("Record transfers through /api/accounts/transfers".to_string()));
+    }
+    if refund_of.is_some() && transaction_type != TransactionType::Refund {
+        return Err(AppError::BadRequest("Only a refund can point at an original purchase".to_string()));
+    }
+    Ok(())
+}
+
+// The direction carries the sign, so an amount itself must be positive
+fn validate_amount(amount: &BigDecimal) -> Result<(), AppError> {
+    if !amount.is_positive() {
+        return Err(AppError::BadRequest("Amount must be greater than zero".to_string()));
+    }
+    Ok(())
+}
+
 // Get all transactions for a user
 pub async fn get_transactions(
-    _pool: web::Data<DbPool>,
+    pool: web::Data<DbPool>,
+    access: LedgerViewer,
+    filters: web::Query<TransactionFilters>,
+) -> Result<HttpResponse, AppError> {
+    let filters = filters.into_inner();
+    let page = filters.page.unwrap_or(1).max(1);
+    let limit = filters
+        .limit
+        .unwrap_or(transaction_service::DEFAULT_PAGE_SIZE)
+        .clamp(1, transaction_service::MAX_PAGE_SIZE);
+
+    let response = db::run(&pool, move |conn| {
+        let total = transaction_service::filtered_query(access.ledger_id, &filters)
+            .count()
+            .get_result::<i64>(conn)? as u64;
+
+        // With a search, the best matches come first
+        let mut query = transaction_service::filtered_query(access.ledger_id, &filters);
+        query = match transaction_service::search_term(&filters) {
+            Some(term) => query.order((search_service::search_rank(term).desc(), transactions::date.desc())),
+            None => query.order((transactions::date.desc(), transactions::created_at.desc())),
+        };
+        let rows = query
+            .limit(limit as i64)
+            .offset(((page - 1) * limit) as i64)
+            .load(conn)?;
+
+        Ok(TransactionsListResponse {
+            transactions: transaction_service::to_responses(conn, rows)?,
+            total,
+            page,
+            pages: total.div_ceil(limit),
+        })
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+// Full-text search over merchant, notes, item names and receipt text, best matches first, with
+// highlighted snippets; the other transaction filters narrow the results
+pub async fn search_transactions(
+    pool: web::Data<DbPool>,
+    access: LedgerViewer,
     filters: web::Query<TransactionFilters>,
 ) -> Result<HttpResponse, AppError> {
-    // In a real application, you would query the database
-    // For now, return mock data
-    let mock_transactions = vec![
-        TransactionResponse {
-            id: Uuid::new_v4(),
-            amount: 42.99,
-            date: Utc::now(),
-            merchant: "Grocery Store".to_string(),
-            category: "Groceries".to_string(),
-            notes: Some("Weekly shopping".to_string()),
-            items: None,
-            bill_image: None,
-            created_at: Utc::now(),
-        },
-        TransactionResponse {
-            id: Uuid::new_v4(),
-            amount: 15.50,
-            date: Utc::now(),
-            merchant: "Coffee Shop".to_string(),
-            category: "Dining".to_string(),
-            notes: None,
-            items: None,
-            bill_image: None,
-            created_at: Utc::now(),
-        },
-    ];
-
-    let response = TransactionsListResponse {
-        transactions: mock_transactions,
-        total: 2,
-        page: filters.page.unwrap_or(1),
-        pages: 1,
-    };
+    let filters = filters.into_inner();
+    let term = transaction_service::search_term(&filters)
+        .ok_or_else(|| AppError::BadRequest("Search text is required".to_string()))?
+        .to_string();
+    let page = filters.page.unwrap_or(1).max(1);
+    let limit = filters
+        .limit
+        .unwrap_or(transaction_service::DEFAULT_PAGE_SIZE)
+        .clamp(1, transaction_service::MAX_PAGE_SIZE);
+
+    let response = db::run(&pool, move |conn| {
+        let total = transaction_service::filtered_query(access.ledger_id, &filters)
+            .count()
+            .get_result::<i64>(conn)? as u64;
+
+        let ranked = transaction_service::filtered_query(access.ledger_id, &filters)
+            .select((transactions::all_columns, search_service::search_rank(&term)))
+            .order((search_service::search_rank(&term).desc(), transactions::date.desc()))
+            .limit(limit as i64)
+            .offset(((page - 1) * limit) as i64)
+            .load::<(DbTransaction, f32)>(conn)?;
+        let (rows, ranks): (Vec<DbTransaction>, Vec<f32>) = ranked.into_iter().unzip();
+
+        let mut matches = search_service::matches_by_transaction(conn, &rows, &term)?;
+        let results = transaction_service::to_responses(conn, rows)?
+            .into_iter()
+            .zip(ranks)
+            .map(|(transaction, rank)| SearchResult {
+                matches: matches.remove(&transaction.id).unwrap_or_default(),
+                transaction,
+                rank,
+            })
+            .collect();
+
+        Ok(SearchResponse {
+            results,
+            total,
+            page,
+            pages: total.div_ceil(limit),
+        })
+    })
+    .await?;
 
     Ok(HttpResponse::Ok().json(response))
 }
 
 // Get a single transaction by ID
 pub async fn get_transaction(
-    _pool: web::Data<DbPool>,
+    pool: web::Data<DbPool>,
+    access: LedgerViewer,
     path: web::Path<String>,
 ) -> Result<HttpResponse, AppError> {
-    let transaction_id = path.into_inner();
-    let uuid = Uuid::parse_str(&transaction_id)
-        .map_err(|_| AppError::BadRequest("Invalid transaction ID".to_string()))?;
-
-    // In a real application, you would query the database
-    // For now, return mock data
-    let transaction = TransactionResponse {
-        id: uuid,
-        amount: 42.99,
-        date: Utc::now(),
-        merchant: "Grocery Store".to_string(),
-        category: "Groceries".to_string(),
-        notes: Some("Weekly shopping".to_string()),
-        items: None,
-        bill_image: None,
-        created_at: Utc::now(),
-    };
+    let transaction_id = parse_transaction_id(&path.into_inner())?;
+
+    let transaction = db::run(&pool, move |conn| {
+        let row = transaction_service::find_transaction(conn, access.ledger_id, transaction_id)?;
+        Ok(transaction_service::to_responses(conn, vec![row])?.remove(0))
+    })
+    .await?;
 
     Ok(HttpResponse::Ok().json(transaction))
 }
 
+// Download the bill image a transaction was created from. Only files in the upload directory are
+// served, whatever path the transaction records
+pub async fn get_transaction_image(
+    pool: web::Data<DbPool>,
+    access: LedgerViewer,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let transaction_id = parse_transaction_id(&path.into_inner())?;
+
+    let image_path = db::run(&pool, move |conn| {
+        transaction_service::find_transaction(conn, access.ledger_id, transaction_id)?
+            .image_path
+            .ok_or_else(|| AppError::NotFound(format!("Transaction {} has no bill image", transaction_id)))
+    })
+    .await?;
+
+    let image = web::block(move || {
+        let missing = || AppError::NotFound("The bill image is no longer available".to_string());
+        let upload_dir = fs::canonicalize(Config::from_env().server.upload_dir).map_err(|_| missing())?;
+        let file = fs::canonicalize(&image_path).map_err(|_| missing())?;
+        if !file.starts_with(&upload_dir) {
+            return Err(missing());
+        }
+        let content_type = match file.extension().and_then(|e| e.to_str()).map(str::to_lowercase).as_deref() {
+            Some("png") => "image/png",
+            Some("pdf") => "application/pdf",
+            _ => "image/jpeg",
+        };
+        Ok((fs::read(&file)?, content_type))
+    })
+    .await
+    .map_err(|e| AppError::InternalServerError(format!("Blocking task failed: {}", e)))??;
+
+    Ok(HttpResponse::Ok().content_type(image.1).body(image.0))
+}
+
 // Create a new transaction
 pub async fn create_transaction(
-    _pool: web::Data<DbPool>,
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
     transaction_data: web::Json<CreateTransactionDto>,
 ) -> Result<HttpResponse, AppError> {
-    // In a real application, we would insert the transaction into the database
-    let transaction = TransactionResponse {
-        id: Uuid::new_v4(),
-        amount: transaction_data.amount,
-        date: transaction_data.date,
-        merchant: transaction_data.merchant.clone(),
-        category: transaction_data.category.clone(),
-        notes: transaction_data.notes.clone(),
-        items: transaction_data.items.clone(),
-        bill_image: transaction_data.bill_image.clone(),
-        created_at: Utc::now(),
-    };
+    let data = transaction_data.into_inner();
+    let currency = data.currency.unwrap_or_default();
+    let amount = currency.round(&data.amount);
+    validate_amount(&amount)?;
+    let currency = currency.to_string();
+    let transaction_type = data.transaction_type.unwrap_or(if data.refund_of.is_some() {
+        TransactionType::Refund
+    } else {
+        TransactionType::Expense
+    });
+    validate_transaction_type(transaction_type, data.refund_of)?;
+
+    let transaction = db::run(&pool, move |conn| {
+        conn.transaction(|conn| {
+            if let Some(account_id) = data.account_id {
+                account_service::account_for_transaction(conn, access.ledger_id, account_id, &currency)?;
+            }
+            let original = data
+                .refund_of
+                .map(|id| {
+                    transaction_service::refundable_purchase(conn, access.ledger_id, id, &amount, &currency, None)
+                })
+                .transpose()?;
+            // Resolve the typed merchant to its canonical merchant so spellings don't multiply
+            let merchant = merchant_service::resolve_or_create_merchant(conn, access.ledger_id, &data.merchant, None)?
+                .merchant;
+            // A transaction confirmed from an OCR'd bill closes that bill's review
+            let bill = data.bill_id.map(|id| bill_service::find_bill(conn, access.ledger_id, id)).transpose()?;
+            let source = data
+                .source
+                .clone()
+                .or_else(|| bill.as_ref().map(|_| SOURCE_OCR.to_string()))
+                .unwrap_or_else(|| SOURCE_MANUAL.to_string());
+            let items = data.items.clone().unwrap_or_default();
+
+            // User-defined rules run first; a category or note the user typed still wins over them
+            let mut effects = RuleEffects {
+                category: String::new(),
+                tags: data.tags.clone().unwrap_or_default(),
+                notes: data.notes.clone(),
+                excluded: false,
+            };
+            let rules = rule_service::load_rules(conn, access.ledger_id)?;
+            let input = RuleInput {
+                merchant: &merchant.name,
+                amount: &amount,
+                currency: Currency::from_code(&currency),
+                items: &items,
+                source: &source,
+            };
+            rule_service::apply_rules(&rules, &input, &mut effects);
+
+            let notes = data.notes.clone().filter(|n| !n.trim().is_empty()).or(effects.notes);
+            let mut category = Some(data.category.trim().to_string())
+                .filter(|c| !c.is_empty())
+                .or(Some(effects.category).filter(|c| !c.is_empty()));
+
+            // A refund counts against the purchase's category unless told otherwise
+            if let (None, Some(category_id)) = (&category, original.as_ref().and_then(|o| o.category_id)) {
+                category = transaction_service::category_names(conn, &[category_id])?.remove(&category_id);
+            }
+
+            // Nothing picked and no rule fired: fall back to the best suggestion for a purchase
+            if category.is_none() && transaction_type == TransactionType::Expense {
+                let item_names: Vec<String> = items.iter().map(|item| item.name.clone()).collect();
+                category =
+                    categorizer::suggest_categories(conn, access.ledger_id, Some(&merchant.name), &item_names, 1)?
+                        .into_iter()
+                        .next()
+                        .map(|suggestion| suggestion.name);
+            }
+
+            let category_id = match category {
+                Some(name) => {
+                    let kind = transaction_type.category_kind();
+                    transaction_service::resolve_category_id(conn, access.ledger_id, &name, kind)?
+                }
+                None => None,
+            };
+
+            let new_transaction = NewTransaction {
+                id: Uuid::new_v4(),
+                amount: amount.clone(),
+                date: data.date,
+                merchant: merchant.name.clone(),
+                category_id,
+                notes,
+                items: data.items.as_ref().map(serde_json::to_value).transpose()?,
+                image_path: data.bill_image.clone().or_else(|| bill.as_ref().map(|b| b.file_path.clone())),
+                ledger_id: access.ledger_id,
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+                merchant_id: Some(merchant.id),
+                excluded: effects.excluded,
+                source,
+                recurring_id: None,
+                currency,
+                account_id: data.account_id,
+                direction: transaction_type.direction().as_str().to_string(),
+                transfer_id: None,
+                transaction_type: transaction_type.as_str().to_string(),
+                refund_of: data.refund_of,
+                import_batch_id: None,
+                external_id: None,
+                value_date: None,
+                bank_reference: None,
+            };
+
+            let row = diesel::insert_into(transactions::table)
+                .values(&new_transaction)
+                .get_result::<DbTransaction>(conn)?;
+            tag_service::set_transaction_tags(conn, access.ledger_id, row.id, &effects.tags)?;
+            if let Some(bill) = &bill {
+                bill_service::mark_reviewed(conn, bill.id, row.id)?;
+            }
+            Ok(transaction_service::to_responses(conn, vec![row])?.remove(0))
+        })
+    })
+    .await?;
 
     Ok(HttpResponse::Created().json(transaction))
 }
 
 // Update an existing transaction
 pub async fn update_transaction(
-    _pool: web::Data<DbPool>,
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
     path: web::Path<String>,
     transaction_data: web::Json<UpdateTransactionDto>,
 ) -> Result<HttpResponse, AppError> {
-    let transaction_id = path.into_inner();
-    let uuid = Uuid::parse_str(&transaction_id)
-        .map_err(|_| AppError::BadRequest("Invalid transaction ID".to_string()))?;
-
-    // In a real application, we would update the transaction in the database
-    let transaction = TransactionResponse {
-        id: uuid,
-        amount: transaction_data.amount.unwrap_or(42.99),
-        date: transaction_data.date.unwrap_or(Utc::now()),
-        merchant: transaction_data.merchant.clone().unwrap_or("Grocery Store".to_string()),
-        category: transaction_data.category.clone().unwrap_or("Groceries".to_string()),
-        notes: transaction_data.notes.clone(),
-        items: transaction_data.items.clone(),
-        bill_image: None,
-        created_at: Utc::now(),
-    };
+    let transaction_id = parse_transaction_id(&path.into_inner())?;
+    let data = transaction_data.into_inner();
+    let currency = data.currency.map(|currency| currency.to_string());
+
+    let transaction = db::run(&pool, move |conn| {
+        conn.transaction(|conn| {
+            let existing = transaction_service::find_transaction(conn, access.ledger_id, transaction_id)?;
+            let amount = data
+                .amount
+                .as_ref()
+                .map(|amount| Currency::from_code(currency.as_deref().unwrap_or(&existing.currency)).round(amount));
+            if let Some(amount) = &amount {
+                validate_amount(amount)?;
+            }
+
+            // Editing one leg alone would leave the two sides of a transfer disagreeing
+            if existing.transfer_id.is_some()
+                && (amount.is_some() || data.account_id.is_some() || data.transaction_type.is_some())
+            {
+                return Err(AppError::BadRequest(
+                    "Delete and re-create a transfer to change its amount, accounts or type".to_string(),
+                ));
+            }
+            let transaction_type = match data.transaction_type {
+                Some(transaction_type) => transaction_type,
+                None => TransactionType::parse(&existing.transaction_type).unwrap_or(TransactionType::Expense),
+            };
+            if existing.transfer_id.is_none() {
+                validate_transaction_type(transaction_type, existing.refund_of)?;
+            }
+            if let (Some(original_id), true) = (existing.refund_of, amount.is_some() || currency.is_some()) {
+                transaction_service::refundable_purchase(
+                    conn,
+                    access.ledger_id,
+                    original_id,
+                    amount.as_ref().unwrap_or(&existing.amount),
+                    currency.as_deref().unwrap_or(&existing.currency),
+                    Some(existing.id),
+                )?;
+            }
+            // Splits were checked against the amount and items, so those can't change underneath them
+            let splits = split_service::find_splits(conn, existing.id)?;
+            if !splits.is_empty() && (amount.is_some() || currency.is_some()) {
+                return Err(AppError::BadRequest(
+                    "Update or remove the splits before changing the amount".to_string(),
+                ));
+            }
+            if data.items.is_some() && splits.iter().any(|split| !split.item_indexes.is_empty()) {
+                return Err(AppError::BadRequest(
+                    "Update or remove the splits before changing the items they allocate".to_string(),
+                ));
+            }
+            if let Some(account_id) = data.account_id.or(existing.account_id) {
+                let currency = currency.as_deref().unwrap_or(&existing.currency);
+                if data.account_id.is_some() || currency != existing.currency {
+                    account_service::account_for_transaction(conn, access.ledger_id, account_id, currency)?;
+                }
+            }
+
+            let merchant = match data.merchant.as_deref() {
+                Some(raw_merchant) => Some(
+                    merchant_service::resolve_or_create_merchant(conn, access.ledger_id, raw_merchant, None)?.merchant,
+                ),
+                None => None,
+            };
+            let category_id = match data.category.as_deref() {
+                Some(name) => {
+                    let kind = transaction_type.category_kind();
+                    transaction_service::resolve_category_id(conn, access.ledger_id, name, kind)?
+                }
+                None => None,
+            };
+
+            let changes = TransactionChanges {
+                amount,
+                currency,
+                date: data.date,
+                merchant: merchant.as_ref().map(|m| m.name.clone()),
+                merchant_id: merchant.as_ref().map(|m| m.id),
+                category_id,
+                notes: data.notes,
+                items: data.items.as_ref().map(serde_json::to_value).transpose()?,
+                excluded: data.excluded,
+                account_id: data.account_id,
+                direction: data.transaction_type.map(|t| t.direction().as_str().to_string()),
+                transaction_type: data.transaction_type.map(|t| t.as_str().to_string()),
+                updated_at: Some(Utc::now()),
+            };
+
+            let row = diesel::update(transactions::table.find(existing.id))
+                .set(&changes)
+                .get_result(conn)?;
+            if let Some(tags) = &data.tags {
+                tag_service::set_transaction_tags(conn, access.ledger_id, existing.id, tags)?;
+            }
+            Ok(transaction_service::to_responses(conn, vec![row])?.remove(0))
+        })
+    })
+    .await?;
 
     Ok(HttpResponse::Ok().json(transaction))
 }
 
-// Delete a transaction
+// Move a transaction to the trash; deleting either leg of a transfer deletes both
 pub async fn delete_transaction(
-    _pool: web::Data<DbPool>,
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
     path: web::Path<String>,
 ) -> Result<HttpResponse, AppError> {
-    let transaction_id = path.into_inner();
-    let _uuid = Uuid::parse_str(&transaction_id)
-        .map_err(|_| AppError::BadRequest("Invalid transaction ID".to_string()))?;
+    let transaction_id = parse_transaction_id(&path.into_inner())?;
+
+    db::run(&pool, move |conn| {
+        let existing = transaction_service::find_transaction(conn, access.ledger_id, transaction_id)?;
+        let mut query = transactions::table
+            .filter(transactions::ledger_id.eq(access.ledger_id))
+            .filter(transactions::deleted_at.is_null())
+            .into_boxed();
+        query = match existing.transfer_id {
+            Some(transfer_id) => query.filter(transactions::transfer_id.eq(transfer_id)),
+            None => query.filter(transactions::id.eq(existing.id)),
+        };
+        let ids = query.select(transactions::id).load::<Uuid>(conn)?;
+        diesel::update(transactions::table.filter(transactions::id.eq_any(ids)))
+            .set(transactions::deleted_at.eq(Some(Utc::now())))
+            .execute(conn)?;
+        Ok(())
+    })
+    .await?;
 
-    // In a real application, we would delete the transaction from the database
-    // For now, just return a success response
     Ok(HttpResponse::NoContent().finish())
 }
 
-// Get all categories
-pub async fn get_categories(_pool: web::Data<DbPool>) -> Result<HttpResponse, AppError> {
-    // In a real application, you would query the database
-    // For now, return default categories
-    let categories = default_categories();
-    
-    Ok(HttpResponse::Ok().json(categories))
-}
-
-#[derive(Deserialize)]
-pub struct CreateTransactionRequest {
-    pub amount: f64,
-    pub date: DateTime<Utc>,
-    pub merchant: String,
-    pub category: String,
-    pub notes: Option<String>,
-    pub items: Option<Vec<TransactionItem>>,
-    pub bill_image: Option<String>,
-}
-
-#[derive(Deserialize)]
-pub struct UpdateTransactionRequest {
-    pub amount: Option<f64>,
-    pub date: Option<DateTime<Utc>>,
-    pub merchant: Option<String>,
-    pub category: Option<String>,
-    pub notes: Option<String>,
-    pub items: Option<Vec<TransactionItem>>,
-}
-
-pub async fn get_transactions_mock(_pool: DbPool) -> Result<HttpResponse, AppError> {
-    // Mock data
-    let transactions = vec![
-        Transaction {
-            id: Uuid::new_v4(),
-            amount: 42.50,
-            date: Utc::now(),
-            merchant: "Grocery Store".to_string(),
-            category: "Food".to_string(),
-            notes: Some("Weekly groceries".to_string()),
-            items: None,
-            bill_image: None,
-            created_at: Utc::now(),
-            updated_at: Utc::now(),
-            user_id: Uuid::new_v4(),
-            bill_id: None,
-        },
-        Transaction {
-            id: Uuid::new_v4(),
-            amount: 29.99,
-            date: Utc::now(),
-            merchant: "Bookstore".to_string(),
-            category: "Entertainment".to_string(),
-            notes: None,
-            items: None,
-            bill_image: None,
-            created_at: Utc::now(),
-            updated_at: Utc::now(),
-            user_id: Uuid::new_v4(),
-            bill_id: None,
-        },
-    ];
-
-    Ok(HttpResponse::Ok().json(transactions))
-}
-
-pub async fn get_transaction_mock(id: web::Path<Uuid>, _pool: DbPool) -> Result<HttpResponse, AppError> {
-    // Mock data
-    let transaction = Transaction {
-        id: *id,
-        amount: 42.50,
-        date: Utc::now(),
-        merchant: "Grocery Store".to_string(),
-        category: "Food".to_string(),
-        notes: Some("Weekly groceries".to_string()),
-        items: None,
-        bill_image: None,
-        created_at: Utc::now(),
-        updated_at: Utc::now(),
-        user_id: Uuid::new_v4(),
-        bill_id: None,
-    };
+// Split a transaction across categories, replacing any earlier splits; an empty list removes them
+pub async fn set_splits(
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
+    path: web::Path<String>,
+    splits_data: web::Json<SetSplitsDto>,
+) -> Result<HttpResponse, AppError> {
+    let transaction_id = parse_transaction_id(&path.into_inner())?;
+    let splits = splits_data.into_inner().splits;
+
+    let transaction = db::run(&pool, move |conn| {
+        conn.transaction(|conn| {
+            let existing = transaction_service::find_transaction(conn, access.ledger_id, transaction_id)?;
+            split_service::replace_splits(conn, &existing, &splits)?;
+            Ok(transaction_service::to_responses(conn, vec![existing])?.remove(0))
+        })
+    })
+    .await?;
 
     Ok(HttpResponse::Ok().json(transaction))
 }
 
-pub async fn create_transaction_mock(
-    _pool: web::Data<DbPool>,
-    transaction_data: web::Json<CreateTransactionRequest>,
+// Remove a transaction's splits so it counts towards its own category again
+pub async fn delete_splits(
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
+    path: web::Path<String>,
 ) -> Result<HttpResponse, AppError> {
-    // In a real application, you would:
-    // 1. Validate the data
-    // 2. Store the transaction in the database
-    // 3. Return the created transaction
-
-    let transaction = TransactionResponse {
-        id: Uuid::new_v4(),
-        amount: transaction_data.amount,
-        date: transaction_data.date,
-        merchant: transaction_data.merchant.clone(),
-        category: transaction_data.category.clone(),
-        notes: transaction_data.notes.clone(),
-        items: transaction_data.items.clone(),
-        bill_image: transaction_data.bill_image.clone(),
-        created_at: Utc::now(),
-    };
+    let transaction_id = parse_transaction_id(&path.into_inner())?;
 
-    Ok(HttpResponse::Created().json(transaction))
+    db::run(&pool, move |conn| {
+        let existing = transaction_service::find_transaction(conn, access.ledger_id, transaction_id)?;
+        split_service::replace_splits(conn, &existing, &[])
+    })
+    .await?;
+
+    Ok(HttpResponse::NoContent().finish())
 }
 
-pub async fn update_transaction_mock(
-    _pool: web::Data<DbPool>,
-    path: web::Path<(String,)>,
-    transaction_data: web::Json<UpdateTransactionRequest>,
+// Apply one operation to many transactions at once, all or nothing. A dry run only reports how
+// many transactions would change; otherwise the response carries a token that undoes the operation
+pub async fn bulk_operation(
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
+    bulk_data: web::Json<BulkOperationDto>,
 ) -> Result<HttpResponse, AppError> {
-    let transaction_id = path.into_inner().0;
-    let uuid = Uuid::parse_str(&transaction_id)
-        .map_err(|_| AppError::BadRequest("Invalid transaction ID".to_string()))?;
-
-    // In a real application, you would:
-    // 1. Find the transaction in the database
-    // 2. Update it with the new data
-    // 3. Return the updated transaction
-
-    // For now, return mock data with updated fields
-    let transaction = TransactionResponse {
-        id: uuid,
-        amount: transaction_data.amount.unwrap_or(42.99),
-        date: transaction_data.date.unwrap_or(Utc::now()),
-        merchant: transaction_data.merchant.clone().unwrap_or("Grocery Store".to_string()),
-        category: transaction_data.category.clone().unwrap_or("Groceries".to_string()),
-        notes: transaction_data.notes.clone(),
-        items: transaction_data.items.clone(),
-        bill_image: None,
-        created_at: Utc::now(),
-    };
+    let data = bulk_data.into_inner();
+
+    let response = db::run(&pool, move |conn| {
+        conn.transaction(|conn| {
+            let rows = bulk_service::select_transactions(conn, access.ledger_id, &data)?;
+            bulk_service::validate_operation(conn, access.ledger_id, &data.operation, &rows)?;
+
+            let undo_token = if data.dry_run || rows.is_empty() {
+                None
+            } else {
+                Some(bulk_service::apply_operation(conn, access.ledger_id, &data.operation, &rows)?)
+            };
+            Ok(BulkOperationResponse {
+                affected: rows.len(),
+                operation: data.operation,
+                dry_run: data.dry_run,
+                undo_token,
+            })
+        })
+    })
+    .await?;
 
-    Ok(HttpResponse::Ok().json(transaction))
+    Ok(HttpResponse::Ok().json(response))
 }
 
-pub async fn delete_transaction_mock(
-    _pool: web::Data<DbPool>,
-    path: web::Path<(String,)>,
+// Reverse a bulk operation from its undo token
+pub async fn undo_bulk_operation(
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
+    path: web::Path<String>,
 ) -> Result<HttpResponse, AppError> {
-    let transaction_id = path.into_inner().0;
-    let _uuid = Uuid::parse_str(&transaction_id)
-        .map_err(|_| AppError::BadRequest("Invalid transaction ID".to_string()))?;
+    let undo_token = Uuid::parse_str(&path.into_inner())
+        .map_err(|_| AppError::BadRequest("Invalid undo token".to_string()))?;
 
-    // In a real application, you would delete the transaction from the database
-    // For now, just return a success response
+    let restored = db::run(&pool, move |conn| bulk_service::undo_operation(conn, access.ledger_id, undo_token)).await?;
 
-    Ok(HttpResponse::NoContent().finish())
+    Ok(HttpResponse::Ok().json(UndoBulkOperationResponse { undo_token, restored }))
 }
-
@@ -0,0 +1,42 @@
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::handlers::auth::{LedgerEditor, LedgerViewer};
+use crate::models::trash::TrashEntity;
+use crate::services::trash as trash_service;
+use actix_web::{web, HttpResponse};
+use uuid::Uuid;
+
+// Deleted transactions, bills and categories, with when each will be purged
+pub async fn get_trash(pool: web::Data<DbPool>, access: LedgerViewer) -> Result<HttpResponse, AppError> {
+    let trash = db::run(&pool, move |conn| trash_service::list_trash(conn, access.ledger_id)).await?;
+    Ok(HttpResponse::Ok().json(trash))
+}
+
+pub async fn restore_from_trash(
+    pool: web::Data<DbPool>,
+    access: LedgerEditor,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, AppError> {
+    let (entity_type, id) = path.into_inner();
+    let entity = TrashEntity::parse(&entity_type)
+        .ok_or_else(|| AppError::BadRequest(format!("{} can't be put in the trash", entity_type)))?;
+    let id = Uuid::parse_str(&id).map_err(|_| AppError::BadRequest("Invalid ID".to_string()))?;
+
+    let response = match entity {
+        TrashEntity::Transactions => {
+            let restored =
+                db::run(&pool, move |conn| trash_service::restore_transaction(conn, access.ledger_id, id)).await?;
+            HttpResponse::Ok().json(restored)
+        }
+        TrashEntity::Bills => {
+            let restored = db::run(&pool, move |conn| trash_service::restore_bill(conn, access.ledger_id, id)).await?;
+            HttpResponse::Ok().json(restored)
+        }
+        TrashEntity::Categories => {
+            let restored =
+                db::run(&pool, move |conn| trash_service::restore_category(conn, access.ledger_id, id)).await?;
+            HttpResponse::Ok().json(restored)
+        }
+    };
+    Ok(response)
+}
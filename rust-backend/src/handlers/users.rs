@@ -1,39 +1,66 @@
-use crate::db::DbPool;
+use crate::db::{self, DbPool};
 use crate::error::AppError;
-use crate::models::user::{UpdateUserDto, UserResponse};
+use crate::handlers::auth::AuthUser;
+use crate::models::user::{DbUser, UpdateUserDto, UserChanges, UserResponse};
+use crate::schema::users;
+use crate::services::ledgers as ledger_service;
 use actix_web::{web, HttpResponse};
 use chrono::Utc;
+use diesel::prelude::*;
+use diesel::PgConnection;
 use uuid::Uuid;
 
+fn find_user(conn: &mut PgConnection, user_id: Uuid) -> Result<DbUser, AppError> {
+    users::table
+        .find(user_id)
+        .first::<DbUser>(conn)
+        .optional()?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))
+}
+
 // Get current user profile
 pub async fn get_profile(
-    _pool: web::Data<DbPool>,
-    // In a real app, you would extract user_id from request
+    pool: web::Data<DbPool>,
+    user: AuthUser,
 ) -> Result<HttpResponse, AppError> {
-    // Mock user data (in a real app, this would come from the database)
-    let user = UserResponse {
-        id: Uuid::new_v4(),
-        email: "user@example.com".to_string(),
-        name: "Test User".to_string(),
-        created_at: Utc::now(),
-    };
-    
-    Ok(HttpResponse::Ok().json(user))
+    let profile = db::run(&pool, move |conn| Ok(UserResponse::from(find_user(conn, user.user_id)?))).await?;
+
+    Ok(HttpResponse::Ok().json(profile))
 }
 
-// Update user profile
+// Update user profile; the base currency is what the personal ledger's reports and budgets are
+// shown in
 pub async fn update_profile(
-    _pool: web::Data<DbPool>,
+    pool: web::Data<DbPool>,
+    user: AuthUser,
     user_data: web::Json<UpdateUserDto>,
-    // In a real app, you would extract user_id from request
 ) -> Result<HttpResponse, AppError> {
-    // Mock user data with updates from request
-    let user = UserResponse {
-        id: Uuid::new_v4(),
-        email: user_data.email.clone().unwrap_or_else(|| "user@example.com".to_string()),
-        name: user_data.name.clone().unwrap_or_else(|| "Test User".to_string()),
-        created_at: Utc::now(),
-    };
-    
-    Ok(HttpResponse::Ok().json(user))
-} 
\ No newline at end of file
+    let user_data = user_data.into_inner();
+    if user_data.password.is_some() {
+        return Err(AppError::BadRequest("Password changes are not supported on the profile".to_string()));
+    }
+    if user_data.name.as_deref().is_some_and(|name| name.trim().is_empty()) {
+        return Err(AppError::BadRequest("Name must not be empty".to_string()));
+    }
+
+    let profile = db::run(&pool, move |conn| {
+        let existing = find_user(conn, user.user_id)?;
+        let changes = UserChanges {
+            name: user_data.name.map(|name| name.trim().to_string()),
+            email: user_data.email.map(|email| email.trim().to_string()),
+            base_currency: user_data.base_currency.map(|currency| currency.to_string()),
+            updated_at: Utc::now(),
+        };
+
+        let updated = diesel::update(users::table.find(existing.id))
+            .set(&changes)
+            .get_result::<DbUser>(conn)?;
+        if let Some(currency) = user_data.base_currency {
+            ledger_service::set_base_currency(conn, updated.id, currency)?;
+        }
+        Ok(UserResponse::from(updated))
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(profile))
+}
@@ -0,0 +1,81 @@
+use std::env;
+use std::fs;
+use std::time::Duration;
+
+use actix_web::{rt, web};
+use chrono::Utc;
+use log::{error, info, warn};
+
+use crate::db::DbPool;
+use crate::error::AppError;
+use crate::services::{recurring, trash};
+
+// How often background jobs run, overridable with JOB_INTERVAL_SECS
+const DEFAULT_JOB_INTERVAL_SECS: u64 = 3600;
+
+fn job_interval() -> Duration {
+    let secs = env::var("JOB_INTERVAL_SECS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .filter(|secs| *secs > 0)
+        .unwrap_or(DEFAULT_JOB_INTERVAL_SECS);
+    Duration::from_secs(secs)
+}
+
+// Materialize recurring transactions that have come due
+async fn run_recurring(pool: DbPool) {
+    let result = web::block(move || {
+        let mut conn = pool.get()
+            .map_err(|e| AppError::InternalServerError(format!("Failed to get database connection: {}", e)))?;
+        recurring::materialize_due(&mut conn, Utc::now().date_naive())
+    })
+    .await;
+
+    match result {
+        Ok(Ok(0)) => {}
+        Ok(Ok(created)) => info!("Created {} recurring transactions", created),
+        Ok(Err(e)) => error!("Recurring transaction job failed: {}", e),
+        Err(e) => error!("Recurring transaction job panicked: {}", e),
+    }
+}
+
+// Delete for good what has been in the trash past the retention period, with the stored images
+// nothing points at any more
+async fn run_trash_purge(pool: DbPool) {
+    let result = web::block(move || {
+        let mut conn = pool.get()
+            .map_err(|e| AppError::InternalServerError(format!("Failed to get database connection: {}", e)))?;
+        trash::purge_expired(&mut conn, Utc::now())
+    })
+    .await;
+
+    match result {
+        Ok(Ok(report)) => {
+            for path in &report.files {
+                if let Err(e) = fs::remove_file(path) {
+                    warn!("Failed to remove bill image {}: {}", path, e);
+                }
+            }
+            if report.transactions + report.bills + report.categories > 0 {
+                info!(
+                    "Purged {} transactions, {} bills and {} categories from the trash",
+                    report.transactions, report.bills, report.categories
+                );
+            }
+        }
+        Ok(Err(e)) => error!("Trash purge job failed: {}", e),
+        Err(e) => error!("Trash purge job panicked: {}", e),
+    }
+}
+
+// Start the periodic background jobs; the first run happens right away
+pub fn spawn(pool: DbPool) {
+    rt::spawn(async move {
+        let mut ticker = rt::time::interval(job_interval());
+        loop {
+            ticker.tick().await;
+            run_recurring(pool.clone()).await;
+            run_trash_purge(pool.clone()).await;
+        }
+    });
+}
@@ -1,15 +1,21 @@
+mod audit;
 mod config;
 mod db;
 mod routes;
 mod error;
 mod models;
 mod handlers;
+mod jobs;
+mod money;
 mod ocr;
+mod services;
 mod fixtures;
 mod schema;
 
 use actix_cors::Cors;
+use actix_web::dev::Service;
 use actix_web::{middleware, App, HttpServer, web};
+use models::audit::AuditContext;
 use std::env;
 use log::info;
 
@@ -41,6 +47,9 @@ async fn main() -> std::io::Result<()> {
     // Initialize database connection
     let pool = db::establish_connection();
 
+    // Start background jobs such as materializing recurring transactions
+    jobs::spawn(pool.clone());
+
     HttpServer::new(move || {
         let cors = Cors::default()
             .allow_any_origin()
@@ -50,6 +59,7 @@ async fn main() -> std::io::Result<()> {
             
         App::new()
             .app_data(web::Data::new(pool.clone()))
+            .wrap_fn(|req, srv| audit::scope(AuditContext::from_request(req.request()), srv.call(req)))
             .wrap(middleware::Logger::default())
             .wrap(cors)
             .configure(routes::configure)
@@ -0,0 +1,172 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use diesel::prelude::*;
+use crate::models::transaction::TransactionResponse;
+use crate::money::{Currency, Money};
+use crate::schema::accounts;
+
+#[derive(Queryable, Identifiable, Insertable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = accounts)]
+pub struct DbAccount {
+    pub id: Uuid,
+    #[serde(alias = "user_id")]
+    pub ledger_id: Uuid,
+    pub name: String,
+    pub account_type: String,
+    pub currency: String,
+    pub opening_balance: BigDecimal,
+    pub institution: Option<String>,
+    pub archived: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = accounts)]
+pub struct NewAccount {
+    pub id: Uuid,
+    pub ledger_id: Uuid,
+    pub name: String,
+    pub account_type: String,
+    pub currency: String,
+    pub opening_balance: BigDecimal,
+    pub institution: Option<String>,
+    pub archived: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(AsChangeset, Debug)]
+#[diesel(table_name = accounts)]
+pub struct AccountChanges {
+    pub name: Option<String>,
+    pub account_type: Option<String>,
+    pub opening_balance: Option<BigDecimal>,
+    pub institution: Option<String>,
+    pub archived: Option<bool>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountType {
+    Cash,
+    Bank,
+    CreditCard,
+    EWallet,
+}
+
+impl AccountType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AccountType::Cash => "cash",
+            AccountType::Bank => "bank",
+            AccountType::CreditCard => "credit_card",
+            AccountType::EWallet => "e_wallet",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateAccountDto {
+    pub name: String,
+    pub account_type: AccountType,
+    // Defaults to the user's base currency
+    pub currency: Option<Currency>,
+    // In the account currency
+    pub opening_balance: Option<BigDecimal>,
+    pub institution: Option<String>,
+}
+
+// The currency can't change once transactions are recorded against the account
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateAccountDto {
+    pub name: Option<String>,
+    pub account_type: Option<AccountType>,
+    pub opening_balance: Option<BigDecimal>,
+    pub institution: Option<String>,
+    pub archived: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AccountResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub account_type: String,
+    pub currency: Currency,
+    pub opening_balance: Money,
+    pub balance: Money,
+    pub institution: Option<String>,
+    pub archived: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+impl AccountResponse {
+    pub fn new(account: DbAccount, net: BigDecimal) -> Self {
+        let currency = Currency::from_code(&account.currency);
+        AccountResponse {
+            id: account.id,
+            name: account.name,
+            account_type: account.account_type,
+            currency,
+            balance: Money::new(&account.opening_balance + net, currency),
+            opening_balance: Money::new(account.opening_balance, currency),
+            institution: account.institution,
+            archived: account.archived,
+            created_at: account.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AccountFilters {
+    pub include_archived: Option<bool>,
+}
+
+// Money moved between two of the user's accounts. `amount` is in the currency of the account it
+// leaves and `to_amount` in that of the account it goes to; it's needed when the two differ
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateTransferDto {
+    pub from_account_id: Uuid,
+    pub to_account_id: Uuid,
+    pub amount: BigDecimal,
+    pub to_amount: Option<BigDecimal>,
+    pub date: DateTime<Utc>,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TransferResponse {
+    pub transfer_id: Uuid,
+    pub from: TransactionResponse,
+    pub to: TransactionResponse,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BalanceQuery {
+    pub start_date: Option<DateTime<Utc>>,
+    pub end_date: Option<DateTime<Utc>>,
+}
+
+// One transaction on the account with the balance right after it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BalanceEntry {
+    pub transaction_id: Uuid,
+    pub date: DateTime<Utc>,
+    pub merchant: String,
+    pub direction: String,
+    pub amount: Money,
+    pub balance: Money,
+    pub transfer_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AccountBalance {
+    pub account: AccountResponse,
+    // Balance before the first entry in the range
+    pub starting_balance: Money,
+    pub ending_balance: Money,
+    pub entries: Vec<BalanceEntry>,
+}
@@ -0,0 +1,100 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use uuid::Uuid;
+use diesel::prelude::*;
+use crate::schema::audit_log;
+
+// The tables whose changes are audited, named as in the log
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditEntity {
+    Transactions,
+    Categories,
+    Bills,
+    Users,
+}
+
+impl AuditEntity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditEntity::Transactions => "transactions",
+            AuditEntity::Categories => "categories",
+            AuditEntity::Bills => "bills",
+            AuditEntity::Users => "users",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "transactions" => Some(AuditEntity::Transactions),
+            "categories" => Some(AuditEntity::Categories),
+            "bills" => Some(AuditEntity::Bills),
+            "users" => Some(AuditEntity::Users),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    Create,
+    Update,
+    Delete,
+}
+
+impl AuditAction {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "create" => Some(AuditAction::Create),
+            "update" => Some(AuditAction::Update),
+            "delete" => Some(AuditAction::Delete),
+            _ => None,
+        }
+    }
+}
+
+// One change to one row. Creates carry the new row and deletes the old one; updates carry only
+// the columns that changed, before and after
+#[derive(Queryable, Identifiable, Serialize, Debug, Clone)]
+#[diesel(table_name = audit_log)]
+pub struct DbAuditEntry {
+    pub id: i64,
+    pub occurred_at: DateTime<Utc>,
+    // None for changes made outside a request, e.g. by background jobs
+    pub actor_id: Option<Uuid>,
+    pub owner_id: Option<Uuid>,
+    pub action: String,
+    pub entity_type: String,
+    pub entity_id: Uuid,
+    pub old_values: Option<JsonValue>,
+    pub new_values: Option<JsonValue>,
+    pub request: Option<JsonValue>,
+}
+
+// The request a change was made in, recorded with it
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditContext {
+    #[serde(skip)]
+    pub actor_id: Option<Uuid>,
+    pub request_id: Uuid,
+    pub method: String,
+    pub path: String,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HistoryQuery {
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RestoreVersionResponse {
+    pub entity_type: String,
+    pub entity_id: Uuid,
+    pub restored_from: i64,
+    // The row as it is now, in the same shape as the log's snapshots
+    pub current: JsonValue,
+}
@@ -0,0 +1,95 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::models::account::DbAccount;
+use crate::models::bill::DbBill;
+use crate::models::budget::DbBudget;
+use crate::models::category::{DbCategory, DbCategoryKeyword};
+use crate::models::exchange_rate::DbExchangeRate;
+use crate::models::import::{DbImportBatch, DbImportProfile};
+use crate::models::ledger::DbLedgerAccount;
+use crate::models::merchant::{DbMerchant, DbMerchantAlias};
+use crate::models::reconciliation::DbReconciliationMatch;
+use crate::models::recurring::DbRecurringTransaction;
+use crate::models::rule::DbRule;
+use crate::models::split::DbSplit;
+use crate::models::tag::{DbTag, NewSplitTag, NewTransactionTag};
+use crate::models::transaction::DbTransaction;
+
+// The ledger the backup was taken from, with its creator's email; passwords never leave the
+// instance
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BackupUser {
+    pub id: Uuid,
+    pub email: String,
+    pub name: String,
+    pub base_currency: String,
+}
+
+// manifest.json at the root of a backup archive: every row the user owns, as stored. Stored
+// files are in the archive too, under the names `files` maps their original paths to
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub format_version: u32,
+    // The last migration the instance that took the backup had run
+    pub schema_version: String,
+    pub app_version: String,
+    pub created_at: DateTime<Utc>,
+    pub user: BackupUser,
+    pub accounts: Vec<DbAccount>,
+    pub categories: Vec<DbCategory>,
+    pub category_keywords: Vec<DbCategoryKeyword>,
+    pub merchants: Vec<DbMerchant>,
+    pub merchant_aliases: Vec<DbMerchantAlias>,
+    pub tags: Vec<DbTag>,
+    pub recurring_transactions: Vec<DbRecurringTransaction>,
+    pub import_profiles: Vec<DbImportProfile>,
+    pub import_batches: Vec<DbImportBatch>,
+    pub transactions: Vec<DbTransaction>,
+    pub transaction_tags: Vec<NewTransactionTag>,
+    pub transaction_splits: Vec<DbSplit>,
+    // Not in backups from before split tags were shared tags
+    #[serde(default)]
+    pub split_tags: Vec<NewSplitTag>,
+    pub bills: Vec<DbBill>,
+    pub rules: Vec<DbRule>,
+    pub budgets: Vec<DbBudget>,
+    pub exchange_rates: Vec<DbExchangeRate>,
+    pub ledger_accounts: Vec<DbLedgerAccount>,
+    // Not in backups from before reconciliation
+    #[serde(default)]
+    pub reconciliation_matches: Vec<DbReconciliationMatch>,
+    pub files: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RestoreQuery {
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+// What happened, or would happen, to one table's rows. Rows that match one the user already
+// has, e.g. a category of the same name, are merged into it rather than copied
+#[derive(Debug, Default, Serialize)]
+pub struct RestoreTableReport {
+    pub table: String,
+    pub in_backup: usize,
+    pub created: usize,
+    pub matched: usize,
+    pub skipped: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RestoreReport {
+    pub dry_run: bool,
+    pub format_version: u32,
+    pub schema_version: String,
+    pub backup_created_at: DateTime<Utc>,
+    pub backup_user: String,
+    pub tables: Vec<RestoreTableReport>,
+    pub files_restored: usize,
+    pub warnings: Vec<String>,
+}
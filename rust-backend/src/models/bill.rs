@@ -1,48 +1,83 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
 use uuid::Uuid;
+use diesel::prelude::*;
 
 use crate::ocr::processor::ExtractedData;
+use crate::schema::bills;
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Bill {
+// A bill starts out waiting for the user to confirm the OCR result as a transaction
+pub const BILL_STATUS_PENDING_REVIEW: &str = "pending_review";
+pub const BILL_STATUS_REVIEWED: &str = "reviewed";
+
+#[derive(Queryable, Identifiable, Insertable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = bills)]
+pub struct DbBill {
     pub id: Uuid,
-    pub user_id: Uuid,
+    #[serde(alias = "user_id")]
+    pub ledger_id: Uuid,
     pub file_path: String,
     pub file_name: String,
     pub file_size: i64,
     pub file_type: String,
     pub ocr_text: Option<String>,
     pub ocr_confidence: Option<f32>,
-    pub extracted_data: Option<ExtractedData>,
+    pub extracted_data: Option<JsonValue>,
     pub transaction_id: Option<Uuid>,
+    pub status: String,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    // Set while the bill is in the trash
+    #[serde(default)]
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct BillResponse {
+#[derive(Insertable, Debug)]
+#[diesel(table_name = bills)]
+pub struct NewBill {
     pub id: Uuid,
+    pub ledger_id: Uuid,
+    pub file_path: String,
     pub file_name: String,
+    pub file_size: i64,
     pub file_type: String,
+    pub ocr_text: Option<String>,
     pub ocr_confidence: Option<f32>,
-    pub extracted_data: Option<ExtractedData>,
-    pub transaction_id: Option<Uuid>,
+    pub extracted_data: Option<JsonValue>,
+    pub status: String,
     pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct CreateBillDto {
+pub struct BillResponse {
+    pub id: Uuid,
     pub file_name: String,
     pub file_type: String,
-    pub file_size: i64,
-    pub file_path: String,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct UpdateBillDto {
-    pub ocr_text: Option<String>,
     pub ocr_confidence: Option<f32>,
     pub extracted_data: Option<ExtractedData>,
     pub transaction_id: Option<Uuid>,
-} 
\ No newline at end of file
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<DbBill> for BillResponse {
+    fn from(bill: DbBill) -> Self {
+        BillResponse {
+            id: bill.id,
+            file_name: bill.file_name,
+            file_type: bill.file_type,
+            ocr_confidence: bill.ocr_confidence,
+            extracted_data: bill.extracted_data.and_then(|data| serde_json::from_value(data).ok()),
+            transaction_id: bill.transaction_id,
+            status: bill.status,
+            created_at: bill.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BillFilters {
+    pub status: Option<String>,
+}
@@ -0,0 +1,162 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use diesel::prelude::*;
+use crate::money::{Currency, Money};
+use crate::schema::budgets;
+
+#[derive(Queryable, Identifiable, Insertable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = budgets)]
+pub struct DbBudget {
+    pub id: Uuid,
+    #[serde(alias = "user_id")]
+    pub ledger_id: Uuid,
+    pub category_id: Uuid,
+    pub amount: BigDecimal,
+    pub period: String,
+    pub start_date: NaiveDate,
+    pub end_date: Option<NaiveDate>,
+    pub rollover: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = budgets)]
+pub struct NewBudget {
+    pub id: Uuid,
+    pub ledger_id: Uuid,
+    pub category_id: Uuid,
+    pub amount: BigDecimal,
+    pub period: String,
+    pub start_date: NaiveDate,
+    pub end_date: Option<NaiveDate>,
+    pub rollover: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(AsChangeset, Debug)]
+#[diesel(table_name = budgets)]
+pub struct BudgetChanges {
+    pub category_id: Option<Uuid>,
+    pub amount: Option<BigDecimal>,
+    pub period: Option<String>,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+    pub rollover: Option<bool>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetPeriod {
+    Weekly,
+    Monthly,
+    Yearly,
+    Custom,
+}
+
+impl BudgetPeriod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BudgetPeriod::Weekly => "weekly",
+            BudgetPeriod::Monthly => "monthly",
+            BudgetPeriod::Yearly => "yearly",
+            BudgetPeriod::Custom => "custom",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "weekly" => Some(BudgetPeriod::Weekly),
+            "monthly" => Some(BudgetPeriod::Monthly),
+            "yearly" => Some(BudgetPeriod::Yearly),
+            "custom" => Some(BudgetPeriod::Custom),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateBudgetDto {
+    pub category_id: Uuid,
+    pub amount: BigDecimal,
+    // Budgets are in the base currency; this defaults to it and can't be anything else
+    pub currency: Option<Currency>,
+    pub period: BudgetPeriod,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+    pub rollover: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateBudgetDto {
+    pub category_id: Option<Uuid>,
+    pub amount: Option<BigDecimal>,
+    pub currency: Option<Currency>,
+    pub period: Option<BudgetPeriod>,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+    pub rollover: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BudgetResponse {
+    pub id: Uuid,
+    pub category_id: Uuid,
+    pub category: String,
+    pub amount: Money,
+    pub currency: Currency,
+    pub period: String,
+    pub start_date: NaiveDate,
+    pub end_date: Option<NaiveDate>,
+    pub rollover: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+impl BudgetResponse {
+    pub fn new(budget: DbBudget, category: String, currency: Currency) -> Self {
+        BudgetResponse {
+            id: budget.id,
+            category_id: budget.category_id,
+            category,
+            amount: Money::new(budget.amount, currency),
+            currency,
+            period: budget.period,
+            start_date: budget.start_date,
+            end_date: budget.end_date,
+            rollover: budget.rollover,
+            created_at: budget.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BudgetStatusQuery {
+    pub date: Option<NaiveDate>,
+    pub category_id: Option<Uuid>,
+}
+
+// Spent vs budget for the period containing the requested date
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BudgetStatus {
+    pub budget_id: Uuid,
+    pub category_id: Uuid,
+    pub category: String,
+    pub period: String,
+    pub period_start: NaiveDate,
+    pub period_end: NaiveDate,
+    pub currency: Currency,
+    pub amount: Money,
+    pub rollover: Money,
+    pub available: Money,
+    pub spent: Money,
+    pub remaining: Money,
+    pub percent_used: f64,
+    pub projected: Money,
+    pub projected_remaining: Money,
+    pub days_elapsed: i64,
+    pub days_total: i64,
+}
@@ -0,0 +1,68 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use uuid::Uuid;
+use diesel::prelude::*;
+use crate::models::transaction::TransactionFilters;
+use crate::schema::bulk_operations;
+
+#[derive(Queryable, Identifiable, Debug, Clone)]
+#[diesel(table_name = bulk_operations)]
+pub struct DbBulkOperation {
+    pub id: Uuid,
+    pub ledger_id: Uuid,
+    pub operation: JsonValue,
+    pub transaction_ids: Vec<Uuid>,
+    pub snapshot: JsonValue,
+    pub applied_at: DateTime<Utc>,
+    pub undone_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = bulk_operations)]
+pub struct NewBulkOperation {
+    pub id: Uuid,
+    pub ledger_id: Uuid,
+    pub operation: JsonValue,
+    pub transaction_ids: Vec<Uuid>,
+    pub snapshot: JsonValue,
+    pub applied_at: DateTime<Utc>,
+}
+
+// e.g. { "type": "recategorize", "category": "Groceries" } or { "type": "shift_date", "days": -1 }
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BulkOperation {
+    Recategorize { category: String },
+    SetMerchant { merchant: String },
+    AddTag { tag: String },
+    RemoveTag { tag: String },
+    Delete,
+    ChangeAccount { account_id: Uuid },
+    ShiftDate { days: i64 },
+}
+
+// The transactions are either listed by ID or picked by filters, not both
+#[derive(Debug, Deserialize)]
+pub struct BulkOperationDto {
+    pub transaction_ids: Option<Vec<Uuid>>,
+    pub filters: Option<TransactionFilters>,
+    pub operation: BulkOperation,
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BulkOperationResponse {
+    pub operation: BulkOperation,
+    pub dry_run: bool,
+    pub affected: usize,
+    // Pass to the undo endpoint to reverse the operation; absent for dry runs
+    pub undo_token: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UndoBulkOperationResponse {
+    pub undo_token: Uuid,
+    pub restored: usize,
+}
@@ -2,10 +2,10 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 use diesel::prelude::*;
-use crate::schema::categories;
+use crate::schema::{categories, category_keywords};
 
 // Add Queryable trait for database operations
-#[derive(Queryable, Identifiable, Serialize, Deserialize, Debug)]
+#[derive(Queryable, Identifiable, Insertable, Serialize, Deserialize, Debug, Clone)]
 #[diesel(table_name = categories)]
 pub struct DbCategory {
     pub id: Uuid,
@@ -13,35 +13,56 @@ pub struct DbCategory {
     pub description: Option<String>,
     pub color: Option<String>,
     pub icon: Option<String>,
-    pub user_id: Uuid,
+    #[serde(alias = "user_id")]
+    pub ledger_id: Uuid,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub kind: String,
+    // Set while the category is in the trash
+    #[serde(default)]
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Category {
-    pub id: Uuid,
-    pub name: String,
-    pub description: Option<String>,
-    pub color: Option<String>,
-    pub icon: Option<String>,
-    pub user_id: Option<Uuid>,
+// Spending categories and income categories (Salary, Freelance, ...) are kept apart
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CategoryKind {
+    Expense,
+    Income,
+}
+
+impl CategoryKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CategoryKind::Expense => "expense",
+            CategoryKind::Income => "income",
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct CreateCategoryDto {
+pub struct Category {
+    pub id: Uuid,
     pub name: String,
     pub description: Option<String>,
     pub color: Option<String>,
     pub icon: Option<String>,
+    pub ledger_id: Option<Uuid>,
+    pub kind: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct UpdateCategoryDto {
-    pub name: Option<String>,
-    pub description: Option<String>,
-    pub color: Option<String>,
-    pub icon: Option<String>,
+impl From<DbCategory> for Category {
+    fn from(category: DbCategory) -> Self {
+        Category {
+            id: category.id,
+            name: category.name,
+            description: category.description,
+            color: category.color,
+            icon: category.icon,
+            ledger_id: Some(category.ledger_id),
+            kind: category.kind,
+        }
+    }
 }
 
 #[derive(Insertable, Debug)]
@@ -52,77 +73,50 @@ pub struct NewCategory {
     pub description: Option<String>,
     pub color: Option<String>,
     pub icon: Option<String>,
-    pub user_id: Uuid,
+    pub ledger_id: Uuid,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub kind: String,
 }
 
-// Default categories
-pub fn default_categories() -> Vec<Category> {
-    vec![
-        Category {
-            id: Uuid::new_v4(),
-            name: "Groceries".to_string(),
-            description: Some("Food and household items".to_string()),
-            color: Some("#4CAF50".to_string()),
-            icon: Some("shopping_cart".to_string()),
-            user_id: None,
-        },
-        Category {
-            id: Uuid::new_v4(),
-            name: "Dining".to_string(),
-            description: Some("Restaurants and take-out".to_string()),
-            color: Some("#FF9800".to_string()),
-            icon: Some("restaurant".to_string()),
-            user_id: None,
-        },
-        Category {
-            id: Uuid::new_v4(),
-            name: "Transportation".to_string(),
-            description: Some("Public transport, gas, etc.".to_string()),
-            color: Some("#2196F3".to_string()),
-            icon: Some("directions_car".to_string()),
-            user_id: None,
-        },
-        Category {
-            id: Uuid::new_v4(),
-            name: "Entertainment".to_string(),
-            description: Some("Movies, concerts, events".to_string()),
-            color: Some("#9C27B0".to_string()),
-            icon: Some("local_movies".to_string()),
-            user_id: None,
-        },
-        Category {
-            id: Uuid::new_v4(),
-            name: "Utilities".to_string(),
-            description: Some("Water, electricity, internet".to_string()),
-            color: Some("#607D8B".to_string()),
-            icon: Some("power".to_string()),
-            user_id: None,
-        },
-        Category {
-            id: Uuid::new_v4(),
-            name: "Healthcare".to_string(),
-            description: Some("Medical expenses".to_string()),
-            color: Some("#F44336".to_string()),
-            icon: Some("local_hospital".to_string()),
-            user_id: None,
-        },
-        Category {
-            id: Uuid::new_v4(),
-            name: "Shopping".to_string(),
-            description: Some("Clothing, electronics, etc.".to_string()),
-            color: Some("#E91E63".to_string()),
-            icon: Some("shopping_bag".to_string()),
-            user_id: None,
-        },
-        Category {
-            id: Uuid::new_v4(),
-            name: "Other".to_string(),
-            description: Some("Miscellaneous expenses".to_string()),
-            color: Some("#9E9E9E".to_string()),
-            icon: Some("more_horiz".to_string()),
-            user_id: None,
-        },
-    ]
-} 
\ No newline at end of file
+// Keyword weight learned from the user's category corrections
+#[derive(Queryable, Identifiable, Insertable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = category_keywords)]
+pub struct DbCategoryKeyword {
+    pub id: Uuid,
+    #[serde(alias = "user_id")]
+    pub ledger_id: Uuid,
+    pub keyword: String,
+    pub category_id: Uuid,
+    pub weight: f32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = category_keywords)]
+pub struct NewCategoryKeyword {
+    pub id: Uuid,
+    pub ledger_id: Uuid,
+    pub keyword: String,
+    pub category_id: Uuid,
+    pub weight: f32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SuggestCategoriesDto {
+    pub merchant: Option<String>,
+    pub items: Option<Vec<String>>,
+    pub limit: Option<usize>,
+}
+
+// Sent when the user keeps or overrides a suggested category
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CategoryFeedbackDto {
+    pub merchant: Option<String>,
+    pub items: Option<Vec<String>>,
+    pub suggested_category_id: Option<Uuid>,
+    pub category_id: Uuid,
+}
@@ -0,0 +1,62 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::models::transaction::TransactionResponse;
+use crate::money::{Currency, Money};
+
+#[derive(Debug, Deserialize)]
+pub struct DashboardQuery {
+    #[serde(alias = "timezone")]
+    pub tz: Option<String>,
+}
+
+// Spending over an inclusive date range
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PeriodTotal {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    pub total: Money,
+    pub count: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MerchantTotal {
+    pub merchant: String,
+    pub amount: Money,
+    pub count: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CategoryTotal {
+    pub category_id: Option<Uuid>,
+    pub category: String,
+    pub amount: Money,
+    pub count: i64,
+    pub percent: f64,
+}
+
+// Everything the dashboard shows, for the current month in the user's time zone
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DashboardSummary {
+    pub timezone: String,
+    pub today: NaiveDate,
+    // The ledger's base currency, which every total is in
+    pub currency: Currency,
+    pub this_month: PeriodTotal,
+    pub last_month: PeriodTotal,
+    // Last month up to the same day of the month, for a like-for-like comparison
+    pub last_month_to_date: PeriodTotal,
+    pub change_percent: Option<f64>,
+    pub daily_average: Money,
+    pub projected_month_total: Money,
+    // Income received this month and what's left of it after spending
+    pub income: Money,
+    pub net_cash_flow: Money,
+    pub savings_rate: Option<f64>,
+    pub largest_transactions: Vec<TransactionResponse>,
+    pub top_merchants: Vec<MerchantTotal>,
+    pub top_categories: Vec<CategoryTotal>,
+    pub uncategorized_count: i64,
+    pub pending_review_count: i64,
+}
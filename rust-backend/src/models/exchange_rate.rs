@@ -0,0 +1,88 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use diesel::prelude::*;
+
+use crate::money::Currency;
+use crate::schema::exchange_rates;
+
+// How a rate got into the table
+pub const RATE_SOURCE_MANUAL: &str = "manual";
+pub const RATE_SOURCE_CSV: &str = "csv";
+
+#[derive(Queryable, Identifiable, Insertable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = exchange_rates)]
+pub struct DbExchangeRate {
+    pub id: Uuid,
+    #[serde(alias = "user_id")]
+    pub ledger_id: Uuid,
+    pub from_currency: String,
+    pub to_currency: String,
+    pub rate: BigDecimal,
+    pub rate_date: NaiveDate,
+    pub source: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = exchange_rates)]
+pub struct NewExchangeRate {
+    pub id: Uuid,
+    pub ledger_id: Uuid,
+    pub from_currency: String,
+    pub to_currency: String,
+    pub rate: BigDecimal,
+    pub rate_date: NaiveDate,
+    pub source: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// One unit of `from_currency` is worth `rate` units of `to_currency` on `rate_date`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateExchangeRateDto {
+    pub from_currency: Currency,
+    pub to_currency: Currency,
+    pub rate: BigDecimal,
+    pub rate_date: NaiveDate,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExchangeRateResponse {
+    pub id: Uuid,
+    pub from_currency: Currency,
+    pub to_currency: Currency,
+    pub rate: BigDecimal,
+    pub rate_date: NaiveDate,
+    pub source: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<DbExchangeRate> for ExchangeRateResponse {
+    fn from(rate: DbExchangeRate) -> Self {
+        ExchangeRateResponse {
+            id: rate.id,
+            from_currency: Currency::from_code(&rate.from_currency),
+            to_currency: Currency::from_code(&rate.to_currency),
+            rate: rate.rate,
+            rate_date: rate.rate_date,
+            source: rate.source,
+            created_at: rate.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExchangeRateFilters {
+    pub from_currency: Option<Currency>,
+    pub to_currency: Option<Currency>,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExchangeRateImportResponse {
+    pub imported: usize,
+}
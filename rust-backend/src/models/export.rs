@@ -0,0 +1,67 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::transaction::TransactionResponse;
+
+// File formats transactions can be exported to: spreadsheets and data files, or the journals of
+// the plain-text accounting tools
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    Csv,
+    Xlsx,
+    Jsonl,
+    Ledger,
+    Hledger,
+    Beancount,
+}
+
+impl ExportFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Xlsx => "xlsx",
+            ExportFormat::Jsonl => "jsonl",
+            ExportFormat::Ledger => "ledger",
+            ExportFormat::Hledger => "hledger",
+            ExportFormat::Beancount => "beancount",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "csv" => Some(ExportFormat::Csv),
+            "xlsx" => Some(ExportFormat::Xlsx),
+            "jsonl" => Some(ExportFormat::Jsonl),
+            "ledger" => Some(ExportFormat::Ledger),
+            "hledger" => Some(ExportFormat::Hledger),
+            "beancount" => Some(ExportFormat::Beancount),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Xlsx => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+            ExportFormat::Jsonl => "application/x-ndjson",
+            ExportFormat::Ledger | ExportFormat::Hledger | ExportFormat::Beancount => "text/plain; charset=utf-8",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Hledger => "journal",
+            _ => self.as_str(),
+        }
+    }
+}
+
+// One JSON Lines record: the transaction as the API returns it, with its account's name and a
+// link to download the bill image
+#[derive(Debug, Serialize)]
+pub struct ExportedTransaction {
+    #[serde(flatten)]
+    pub transaction: TransactionResponse,
+    pub account: Option<String>,
+    pub bill_image_url: Option<String>,
+}
@@ -0,0 +1,355 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use uuid::Uuid;
+use diesel::prelude::*;
+use crate::money::{Currency, Money};
+use crate::schema::{import_batches, import_profiles};
+
+// File formats a statement can be imported from; QFX is OFX with a few Intuit extensions, and
+// camt.053 and MT940 are the ISO 20022 and SWIFT statements business accounts export
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatementFormat {
+    Csv,
+    Ofx,
+    Qfx,
+    Qif,
+    Camt053,
+    Mt940,
+}
+
+impl StatementFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StatementFormat::Csv => "csv",
+            StatementFormat::Ofx => "ofx",
+            StatementFormat::Qfx => "qfx",
+            StatementFormat::Qif => "qif",
+            StatementFormat::Camt053 => "camt053",
+            StatementFormat::Mt940 => "mt940",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "csv" => Some(StatementFormat::Csv),
+            "ofx" => Some(StatementFormat::Ofx),
+            "qfx" => Some(StatementFormat::Qfx),
+            "qif" => Some(StatementFormat::Qif),
+            "camt053" => Some(StatementFormat::Camt053),
+            "mt940" => Some(StatementFormat::Mt940),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Queryable, Identifiable, Insertable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = import_profiles)]
+pub struct DbImportProfile {
+    pub id: Uuid,
+    #[serde(alias = "user_id")]
+    pub ledger_id: Uuid,
+    pub name: String,
+    pub account_id: Option<Uuid>,
+    pub mapping: JsonValue,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl DbImportProfile {
+    pub fn csv_mapping(&self) -> Option<CsvMapping> {
+        serde_json::from_value(self.mapping.clone()).ok()
+    }
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = import_profiles)]
+pub struct NewImportProfile {
+    pub id: Uuid,
+    pub ledger_id: Uuid,
+    pub name: String,
+    pub account_id: Option<Uuid>,
+    pub mapping: JsonValue,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(AsChangeset, Debug)]
+#[diesel(table_name = import_profiles)]
+pub struct ImportProfileChanges {
+    pub name: Option<String>,
+    pub account_id: Option<Uuid>,
+    pub mapping: Option<JsonValue>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Queryable, Identifiable, Insertable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = import_batches)]
+pub struct DbImportBatch {
+    pub id: Uuid,
+    #[serde(alias = "user_id")]
+    pub ledger_id: Uuid,
+    pub profile_id: Option<Uuid>,
+    pub account_id: Option<Uuid>,
+    pub format: String,
+    pub file_name: String,
+    pub row_count: i32,
+    pub imported_count: i32,
+    pub duplicate_count: i32,
+    pub error_count: i32,
+    pub created_at: DateTime<Utc>,
+    pub rolled_back_at: Option<DateTime<Utc>>,
+    pub statement_account: Option<String>,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = import_batches)]
+pub struct NewImportBatch {
+    pub id: Uuid,
+    pub ledger_id: Uuid,
+    pub profile_id: Option<Uuid>,
+    pub account_id: Option<Uuid>,
+    pub format: String,
+    pub file_name: String,
+    pub row_count: i32,
+    pub imported_count: i32,
+    pub duplicate_count: i32,
+    pub error_count: i32,
+    pub created_at: DateTime<Utc>,
+    pub statement_account: Option<String>,
+}
+
+// Thai bank exports are often TIS-620 or its Windows-874 superset rather than UTF-8
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FileEncoding {
+    Utf8,
+    #[serde(rename = "tis-620")]
+    Tis620,
+    #[serde(rename = "windows-874")]
+    Windows874,
+}
+
+// A column by its header text, or by its zero-based position when the file has no header row
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ColumnRef {
+    Index(usize),
+    Name(String),
+}
+
+fn default_delimiter() -> char {
+    ','
+}
+
+fn default_has_header() -> bool {
+    true
+}
+
+// How to read one bank's CSV export. Amounts come either from one signed column, where negative
+// means money out, or from separate debit (out) and credit (in) columns
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CsvMapping {
+    #[serde(default = "default_delimiter")]
+    pub delimiter: char,
+    // Detected when left out: UTF-8 if the file decodes as UTF-8, otherwise Windows-874
+    pub encoding: Option<FileEncoding>,
+    // Lines before the header, e.g. the account summary some banks print first
+    #[serde(default)]
+    pub skip_rows: usize,
+    #[serde(default = "default_has_header")]
+    pub has_header: bool,
+    pub date_column: ColumnRef,
+    // chrono format, e.g. "%d/%m/%Y"; Buddhist Era years like 2567 are converted
+    pub date_format: String,
+    pub amount_column: Option<ColumnRef>,
+    pub debit_column: Option<ColumnRef>,
+    pub credit_column: Option<ColumnRef>,
+    pub description_column: ColumnRef,
+    pub notes_column: Option<ColumnRef>,
+    pub currency_column: Option<ColumnRef>,
+    // Used when there is no currency column; defaults to the account's currency
+    pub currency: Option<Currency>,
+    // For card statements that list spending as positive amounts
+    #[serde(default)]
+    pub negate_amounts: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateImportProfileDto {
+    pub name: String,
+    pub account_id: Option<Uuid>,
+    pub mapping: CsvMapping,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateImportProfileDto {
+    pub name: Option<String>,
+    pub account_id: Option<Uuid>,
+    pub mapping: Option<CsvMapping>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImportProfileResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub account_id: Option<Uuid>,
+    pub mapping: JsonValue,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<DbImportProfile> for ImportProfileResponse {
+    fn from(profile: DbImportProfile) -> Self {
+        ImportProfileResponse {
+            id: profile.id,
+            name: profile.name,
+            account_id: profile.account_id,
+            mapping: profile.mapping,
+            created_at: profile.created_at,
+        }
+    }
+}
+
+// A saved profile or a `mapping` form field says how to read a CSV file; `account_id` overrides
+// the profile's account
+#[derive(Debug, Deserialize)]
+pub struct ImportQuery {
+    pub profile_id: Option<Uuid>,
+    pub account_id: Option<Uuid>,
+    // Import rows that look like transactions already recorded instead of skipping them
+    #[serde(default)]
+    pub include_duplicates: bool,
+    // QIF files don't say how their dates are written; defaults to US month/day/year
+    pub date_format: Option<String>,
+}
+
+// One statement line, read but not yet saved
+#[derive(Debug, Clone)]
+pub struct ParsedRow {
+    pub line: usize,
+    pub date: NaiveDate,
+    pub amount: Money,
+    // Money in rather than out
+    pub income: bool,
+    pub description: String,
+    pub notes: Option<String>,
+    // The bank's ID for the line, e.g. the OFX FITID
+    pub external_id: Option<String>,
+    // A category the file already assigns, as QIF does
+    pub category: Option<String>,
+    // When the money took value, if the file says and it differs from the booking date
+    pub value_date: Option<NaiveDate>,
+    // The payer's reference, e.g. the end-to-end ID of a SEPA transfer
+    pub reference: Option<String>,
+    // The bank took back an earlier line; money coming back this way is a refund
+    pub reversal: bool,
+}
+
+// The balances a statement opens and closes with
+#[derive(Debug, Clone)]
+pub struct StatementBalances {
+    pub opening: Money,
+    pub closing: Money,
+}
+
+// A camt.053 or MT940 file read into lines, with the account and balances it states
+#[derive(Debug)]
+pub struct BankStatement {
+    pub account: Option<String>,
+    pub balances: Option<StatementBalances>,
+    pub rows: Vec<ParsedRow>,
+    pub errors: Vec<ImportRowError>,
+}
+
+// Whether the statement's lines add up from its opening to its closing balance. A difference
+// usually means lines that couldn't be read
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BalanceCheck {
+    pub currency: Currency,
+    pub opening: Money,
+    pub closing: Money,
+    // The opening balance plus every line read
+    pub computed_closing: Money,
+    pub difference: BigDecimal,
+    pub balanced: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImportRowError {
+    pub line: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PreviewRow {
+    pub line: usize,
+    pub date: NaiveDate,
+    pub amount: Money,
+    pub currency: Currency,
+    pub transaction_type: String,
+    pub description: String,
+    pub notes: Option<String>,
+    // An existing transaction on the same amount and about the same day
+    pub duplicate_of: Option<Uuid>,
+    // The line's bank ID was imported before, or an earlier line of the file has it; it is skipped
+    // even when duplicates are included. A line whose earlier copy is in the trash is taken back out
+    pub already_imported: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImportPreview {
+    pub statement_account: Option<String>,
+    // The account the rows would be recorded against
+    pub account_id: Option<Uuid>,
+    pub rows: Vec<PreviewRow>,
+    pub errors: Vec<ImportRowError>,
+    pub duplicate_count: usize,
+    // For formats that state balances
+    pub balance_check: Option<BalanceCheck>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImportBatchResponse {
+    pub id: Uuid,
+    pub format: String,
+    pub file_name: String,
+    pub profile_id: Option<Uuid>,
+    pub account_id: Option<Uuid>,
+    pub row_count: i32,
+    pub imported_count: i32,
+    pub duplicate_count: i32,
+    pub error_count: i32,
+    // The account number named in the file, for formats that carry one
+    pub statement_account: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub rolled_back_at: Option<DateTime<Utc>>,
+}
+
+impl From<DbImportBatch> for ImportBatchResponse {
+    fn from(batch: DbImportBatch) -> Self {
+        ImportBatchResponse {
+            id: batch.id,
+            format: batch.format,
+            file_name: batch.file_name,
+            profile_id: batch.profile_id,
+            account_id: batch.account_id,
+            row_count: batch.row_count,
+            imported_count: batch.imported_count,
+            duplicate_count: batch.duplicate_count,
+            error_count: batch.error_count,
+            statement_account: batch.statement_account,
+            created_at: batch.created_at,
+            rolled_back_at: batch.rolled_back_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImportResult {
+    pub batch: ImportBatchResponse,
+    // Lines that couldn't be read and were left out
+    pub errors: Vec<ImportRowError>,
+    pub balance_check: Option<BalanceCheck>,
+}
@@ -0,0 +1,55 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use diesel::prelude::*;
+use crate::schema::ledger_accounts;
+
+#[derive(Queryable, Identifiable, Insertable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = ledger_accounts)]
+pub struct DbLedgerAccount {
+    pub id: Uuid,
+    #[serde(alias = "user_id")]
+    pub ledger_id: Uuid,
+    pub category_id: Option<Uuid>,
+    pub account_id: Option<Uuid>,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = ledger_accounts)]
+pub struct NewLedgerAccount {
+    pub id: Uuid,
+    pub ledger_id: Uuid,
+    pub category_id: Option<Uuid>,
+    pub account_id: Option<Uuid>,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// The ledger account for one category or one account, e.g. "Expenses:Food:Groceries"; leaving
+// out the name goes back to the default
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LedgerAccountMappingDto {
+    pub category_id: Option<Uuid>,
+    pub account_id: Option<Uuid>,
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SetLedgerAccountsDto {
+    pub mappings: Vec<LedgerAccountMappingDto>,
+}
+
+// A category or account with the ledger account it is exported as
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LedgerAccountResponse {
+    pub category_id: Option<Uuid>,
+    pub account_id: Option<Uuid>,
+    pub name: String,
+    pub ledger_account: String,
+    // Chosen by the user rather than derived from the name
+    pub custom: bool,
+}
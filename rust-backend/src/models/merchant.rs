@@ -0,0 +1,130 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use diesel::prelude::*;
+use crate::schema::{merchant_aliases, merchants};
+
+#[derive(Queryable, Identifiable, Insertable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = merchants)]
+pub struct DbMerchant {
+    pub id: Uuid,
+    #[serde(alias = "user_id")]
+    pub ledger_id: Uuid,
+    pub name: String,
+    pub normalized_name: String,
+    pub tax_id: Option<String>,
+    pub default_category_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = merchants)]
+pub struct NewMerchant {
+    pub id: Uuid,
+    pub ledger_id: Uuid,
+    pub name: String,
+    pub normalized_name: String,
+    pub tax_id: Option<String>,
+    pub default_category_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(AsChangeset, Debug)]
+#[diesel(table_name = merchants)]
+pub struct MerchantChanges {
+    pub name: Option<String>,
+    pub normalized_name: Option<String>,
+    pub tax_id: Option<String>,
+    pub default_category_id: Option<Uuid>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Queryable, Identifiable, Insertable, Associations, Serialize, Deserialize, Debug, Clone)]
+#[diesel(belongs_to(DbMerchant, foreign_key = merchant_id))]
+#[diesel(table_name = merchant_aliases)]
+pub struct DbMerchantAlias {
+    pub id: Uuid,
+    pub merchant_id: Uuid,
+    pub alias: String,
+    pub normalized_alias: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = merchant_aliases)]
+pub struct NewMerchantAlias {
+    pub id: Uuid,
+    pub merchant_id: Uuid,
+    pub alias: String,
+    pub normalized_alias: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateMerchantDto {
+    pub name: String,
+    pub tax_id: Option<String>,
+    pub default_category_id: Option<Uuid>,
+    pub aliases: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateMerchantDto {
+    pub name: Option<String>,
+    pub tax_id: Option<String>,
+    pub default_category_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateMerchantAliasDto {
+    pub alias: String,
+}
+
+// Merge the listed merchants into the merchant addressed by the path
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MergeMerchantsDto {
+    pub source_ids: Vec<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResolveMerchantQuery {
+    pub name: String,
+    pub tax_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MerchantAliasResponse {
+    pub id: Uuid,
+    pub alias: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MerchantResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub tax_id: Option<String>,
+    pub default_category_id: Option<Uuid>,
+    pub aliases: Vec<MerchantAliasResponse>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl MerchantResponse {
+    pub fn new(merchant: DbMerchant, aliases: Vec<DbMerchantAlias>) -> Self {
+        MerchantResponse {
+            id: merchant.id,
+            name: merchant.name,
+            tax_id: merchant.tax_id,
+            default_category_id: merchant.default_category_id,
+            aliases: aliases
+                .into_iter()
+                .map(|alias| MerchantAliasResponse {
+                    id: alias.id,
+                    alias: alias.alias,
+                })
+                .collect(),
+            created_at: merchant.created_at,
+        }
+    }
+}
@@ -1,4 +1,24 @@
 pub mod transaction;
 pub mod user;
 pub mod category;
-pub mod bill; 
\ No newline at end of file
+pub mod bill;
+pub mod merchant;
+pub mod rule;
+pub mod budget;
+pub mod dashboard;
+pub mod recurring;
+pub mod report;
+pub mod exchange_rate;
+pub mod account;
+pub mod split;
+pub mod tag;
+pub mod search;
+pub mod bulk;
+pub mod import;
+pub mod export;
+pub mod ledger;
+pub mod backup;
+pub mod reconciliation;
+pub mod audit;
+pub mod trash;
+pub mod shared_ledger;
\ No newline at end of file
@@ -0,0 +1,95 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use diesel::prelude::*;
+use crate::models::transaction::TransactionResponse;
+use crate::money::{Currency, Money};
+use crate::schema::reconciliation_matches;
+
+// A proposed pair waits for the user; confirming marks both transactions reconciled, and a
+// rejected pair isn't proposed again
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchStatus {
+    Proposed,
+    Confirmed,
+    Rejected,
+}
+
+impl MatchStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MatchStatus::Proposed => "proposed",
+            MatchStatus::Confirmed => "confirmed",
+            MatchStatus::Rejected => "rejected",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "proposed" => Some(MatchStatus::Proposed),
+            "confirmed" => Some(MatchStatus::Confirmed),
+            "rejected" => Some(MatchStatus::Rejected),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Queryable, Identifiable, Insertable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = reconciliation_matches)]
+pub struct DbReconciliationMatch {
+    pub id: Uuid,
+    #[serde(alias = "user_id")]
+    pub ledger_id: Uuid,
+    pub receipt_transaction_id: Uuid,
+    pub bank_transaction_id: Uuid,
+    pub score: f32,
+    // The bank line's amount less the receipt's, in the bank line's currency
+    pub amount_difference: BigDecimal,
+    // The bank line's date less the receipt's
+    pub days_apart: i32,
+    pub merchant_similarity: f32,
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// How closely a receipt and a bank line must agree to be proposed as a pair. Amounts in the
+// same currency must be equal; a receipt in another currency may differ from the bank line by
+// up to `amount_tolerance_percent` once converted, for the card's FX fees
+#[derive(Debug, Default, Deserialize)]
+pub struct ReconcileDto {
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+    pub date_window_days: Option<i64>,
+    pub amount_tolerance_percent: Option<BigDecimal>,
+    pub min_score: Option<f32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MatchQuery {
+    pub status: Option<MatchStatus>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReconciliationMatchResponse {
+    pub id: Uuid,
+    pub status: String,
+    pub score: f32,
+    // In the bank line's currency
+    pub amount_difference: Money,
+    pub currency: Currency,
+    pub days_apart: i32,
+    pub merchant_similarity: f32,
+    pub receipt: TransactionResponse,
+    pub bank_line: TransactionResponse,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReconcileResponse {
+    pub proposed: Vec<ReconciliationMatchResponse>,
+    pub unmatched_receipts: usize,
+    pub unmatched_bank_lines: usize,
+}
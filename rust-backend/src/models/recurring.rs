@@ -0,0 +1,246 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use diesel::prelude::*;
+use crate::money::{Currency, Money};
+use crate::schema::recurring_transactions;
+
+#[derive(Queryable, Identifiable, Insertable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = recurring_transactions)]
+pub struct DbRecurringTransaction {
+    pub id: Uuid,
+    #[serde(alias = "user_id")]
+    pub ledger_id: Uuid,
+    pub name: String,
+    pub merchant: String,
+    pub merchant_id: Option<Uuid>,
+    pub amount: BigDecimal,
+    pub category_id: Option<Uuid>,
+    pub notes: Option<String>,
+    pub tags: Vec<String>,
+    pub frequency: String,
+    pub interval_count: i32,
+    pub day_of_month: Option<i32>,
+    pub start_date: NaiveDate,
+    pub end_date: Option<NaiveDate>,
+    pub next_run: NaiveDate,
+    pub last_run: Option<NaiveDate>,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub currency: String,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = recurring_transactions)]
+pub struct NewRecurringTransaction {
+    pub id: Uuid,
+    pub ledger_id: Uuid,
+    pub name: String,
+    pub merchant: String,
+    pub merchant_id: Option<Uuid>,
+    pub amount: BigDecimal,
+    pub category_id: Option<Uuid>,
+    pub notes: Option<String>,
+    pub tags: Vec<String>,
+    pub frequency: String,
+    pub interval_count: i32,
+    pub day_of_month: Option<i32>,
+    pub start_date: NaiveDate,
+    pub end_date: Option<NaiveDate>,
+    pub next_run: NaiveDate,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub currency: String,
+}
+
+#[derive(AsChangeset, Debug, Default)]
+#[diesel(table_name = recurring_transactions)]
+pub struct RecurringTransactionChanges {
+    pub name: Option<String>,
+    pub merchant: Option<String>,
+    pub merchant_id: Option<Uuid>,
+    pub amount: Option<BigDecimal>,
+    pub currency: Option<String>,
+    pub category_id: Option<Uuid>,
+    pub notes: Option<String>,
+    pub tags: Option<Vec<String>>,
+    pub frequency: Option<String>,
+    pub interval_count: Option<i32>,
+    pub day_of_month: Option<i32>,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+    pub next_run: Option<NaiveDate>,
+    pub last_run: Option<NaiveDate>,
+    pub active: Option<bool>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+impl Frequency {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Frequency::Daily => "daily",
+            Frequency::Weekly => "weekly",
+            Frequency::Monthly => "monthly",
+            Frequency::Yearly => "yearly",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "daily" => Some(Frequency::Daily),
+            "weekly" => Some(Frequency::Weekly),
+            "monthly" => Some(Frequency::Monthly),
+            "yearly" => Some(Frequency::Yearly),
+            _ => None,
+        }
+    }
+}
+
+// When a template fires: every `interval` days/weeks/months/years, monthly ones on `day_of_month`
+#[derive(Debug, Clone, Copy)]
+pub struct Schedule {
+    pub frequency: Frequency,
+    pub interval: i32,
+    pub day_of_month: Option<u32>,
+    pub start_date: NaiveDate,
+}
+
+impl DbRecurringTransaction {
+    pub fn schedule(&self) -> Schedule {
+        Schedule {
+            frequency: Frequency::parse(&self.frequency).unwrap_or(Frequency::Monthly),
+            interval: self.interval_count.max(1),
+            day_of_month: self.day_of_month.map(|d| d as u32),
+            start_date: self.start_date,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateRecurringDto {
+    pub name: Option<String>,
+    pub merchant: String,
+    pub amount: BigDecimal,
+    // Defaults to THB
+    pub currency: Option<Currency>,
+    pub category: Option<String>,
+    pub notes: Option<String>,
+    pub tags: Option<Vec<String>>,
+    pub frequency: Frequency,
+    pub interval: Option<i32>,
+    pub day_of_month: Option<i32>,
+    pub start_date: NaiveDate,
+    pub end_date: Option<NaiveDate>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateRecurringDto {
+    pub name: Option<String>,
+    pub merchant: Option<String>,
+    pub amount: Option<BigDecimal>,
+    pub currency: Option<Currency>,
+    pub category: Option<String>,
+    pub notes: Option<String>,
+    pub tags: Option<Vec<String>>,
+    pub frequency: Option<Frequency>,
+    pub interval: Option<i32>,
+    pub day_of_month: Option<i32>,
+    pub end_date: Option<NaiveDate>,
+    pub active: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecurringResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub merchant: String,
+    pub merchant_id: Option<Uuid>,
+    pub amount: Money,
+    pub currency: Currency,
+    pub category: String,
+    pub notes: Option<String>,
+    pub tags: Vec<String>,
+    pub frequency: String,
+    pub interval: i32,
+    pub day_of_month: Option<i32>,
+    pub start_date: NaiveDate,
+    pub end_date: Option<NaiveDate>,
+    pub next_run: NaiveDate,
+    pub last_run: Option<NaiveDate>,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+impl RecurringResponse {
+    pub fn new(template: DbRecurringTransaction, category: String) -> Self {
+        let currency = Currency::from_code(&template.currency);
+        RecurringResponse {
+            id: template.id,
+            name: template.name,
+            merchant: template.merchant,
+            merchant_id: template.merchant_id,
+            amount: Money::new(template.amount, currency),
+            currency,
+            category,
+            notes: template.notes,
+            tags: template.tags,
+            frequency: template.frequency,
+            interval: template.interval_count,
+            day_of_month: template.day_of_month,
+            start_date: template.start_date,
+            end_date: template.end_date,
+            next_run: template.next_run,
+            last_run: template.last_run,
+            active: template.active,
+            created_at: template.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpcomingQuery {
+    pub days: Option<i64>,
+}
+
+// A bill expected in the upcoming window, from a template or from a detected subscription
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpcomingBill {
+    pub date: NaiveDate,
+    pub name: String,
+    pub merchant: String,
+    pub amount: Money,
+    pub currency: Currency,
+    pub category: String,
+    pub recurring_id: Option<Uuid>,
+    pub detected: bool,
+}
+
+// A repeating merchant/amount pattern found in history that has no template yet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubscriptionCandidate {
+    pub merchant: String,
+    pub merchant_id: Option<Uuid>,
+    pub category: String,
+    pub amount: Money,
+    pub currency: Currency,
+    pub frequency: Frequency,
+    pub interval: i32,
+    pub day_of_month: Option<i32>,
+    pub occurrences: usize,
+    pub first_date: NaiveDate,
+    pub last_date: NaiveDate,
+    pub next_date: NaiveDate,
+    pub confidence: f64,
+}
@@ -0,0 +1,175 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::money::{Currency, Money};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Granularity {
+    Day,
+    Week,
+    Month,
+    Quarter,
+    Year,
+}
+
+impl Granularity {
+    // Also matches the unit names Postgres' date_trunc expects
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Granularity::Day => "day",
+            Granularity::Week => "week",
+            Granularity::Month => "month",
+            Granularity::Quarter => "quarter",
+            Granularity::Year => "year",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "day" | "daily" => Some(Granularity::Day),
+            "week" | "weekly" => Some(Granularity::Week),
+            "month" | "monthly" => Some(Granularity::Month),
+            "quarter" | "quarterly" => Some(Granularity::Quarter),
+            "year" | "yearly" => Some(Granularity::Year),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SpendingByCategoryQuery {
+    #[serde(alias = "startDate")]
+    pub start_date: Option<NaiveDate>,
+    #[serde(alias = "endDate")]
+    pub end_date: Option<NaiveDate>,
+    #[serde(alias = "timezone")]
+    pub tz: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MonthlySpendingQuery {
+    pub year: Option<i32>,
+    #[serde(alias = "timezone")]
+    pub tz: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TransactionTrendsQuery {
+    pub period: Option<String>,
+    #[serde(alias = "startDate")]
+    pub start_date: Option<NaiveDate>,
+    #[serde(alias = "endDate")]
+    pub end_date: Option<NaiveDate>,
+    #[serde(alias = "timezone")]
+    pub tz: Option<String>,
+}
+
+// An inclusive date range in the report's time zone
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportPeriod {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    pub timezone: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CategorySpending {
+    pub category_id: Option<Uuid>,
+    pub category: String,
+    pub color: Option<String>,
+    pub amount: Money,
+    pub count: i64,
+    pub percent: f64,
+    pub previous_amount: Money,
+    pub change_percent: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CategoryReport {
+    pub period: ReportPeriod,
+    pub previous_period: ReportPeriod,
+    // The ledger's base currency, which every amount in the report is in
+    pub currency: Currency,
+    pub total: Money,
+    pub previous_total: Money,
+    pub change_percent: Option<f64>,
+    pub categories: Vec<CategorySpending>,
+    // Transactions left out of the totals because no exchange rate covers them
+    pub unconverted_count: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TagSpending {
+    pub tag_id: Uuid,
+    pub tag: String,
+    pub color: Option<String>,
+    pub amount: Money,
+    pub count: i64,
+    // Share of all spending in the period
+    pub percent: f64,
+}
+
+// Spending broken down by tag; a transaction with several tags shows up under each
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TagReport {
+    pub period: ReportPeriod,
+    pub currency: Currency,
+    pub total: Money,
+    pub untagged: Money,
+    pub untagged_count: i64,
+    pub tags: Vec<TagSpending>,
+    pub unconverted_count: i64,
+}
+
+// One bucket of a time series; `previous_amount` is the matching bucket of the previous period
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SeriesPoint {
+    pub period_start: NaiveDate,
+    pub label: String,
+    pub amount: Money,
+    pub count: i64,
+    pub previous_amount: Money,
+    pub change_percent: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SeriesReport {
+    pub granularity: Granularity,
+    pub period: ReportPeriod,
+    pub previous_period: ReportPeriod,
+    pub currency: Currency,
+    pub total: Money,
+    pub previous_total: Money,
+    pub change_percent: Option<f64>,
+    pub average: Money,
+    pub points: Vec<SeriesPoint>,
+    // Transactions left out of the totals because no exchange rate covers them
+    pub unconverted_count: i64,
+}
+
+// Income against spending (purchases less refunds) for one bucket; transfers count as neither
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CashFlowPoint {
+    pub period_start: NaiveDate,
+    pub label: String,
+    pub income: Money,
+    pub expenses: Money,
+    pub net: Money,
+    // Share of income left after spending; None without income
+    pub savings_rate: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CashFlowReport {
+    pub granularity: Granularity,
+    pub period: ReportPeriod,
+    pub currency: Currency,
+    pub income: Money,
+    pub expenses: Money,
+    pub net: Money,
+    pub savings_rate: Option<f64>,
+    pub points: Vec<CashFlowPoint>,
+    pub unconverted_count: i64,
+}
@@ -0,0 +1,206 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use uuid::Uuid;
+use diesel::prelude::*;
+use crate::error::AppError;
+use crate::money::{Currency, Money};
+use crate::schema::rules;
+
+#[derive(Queryable, Identifiable, Insertable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = rules)]
+pub struct DbRule {
+    pub id: Uuid,
+    #[serde(alias = "user_id")]
+    pub ledger_id: Uuid,
+    pub name: String,
+    pub position: i32,
+    pub enabled: bool,
+    pub match_mode: String,
+    pub stop_processing: bool,
+    pub conditions: JsonValue,
+    pub actions: JsonValue,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = rules)]
+pub struct NewRule {
+    pub id: Uuid,
+    pub ledger_id: Uuid,
+    pub name: String,
+    pub position: i32,
+    pub enabled: bool,
+    pub match_mode: String,
+    pub stop_processing: bool,
+    pub conditions: JsonValue,
+    pub actions: JsonValue,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(AsChangeset, Debug)]
+#[diesel(table_name = rules)]
+pub struct RuleChanges {
+    pub name: Option<String>,
+    pub enabled: Option<bool>,
+    pub match_mode: Option<String>,
+    pub stop_processing: Option<bool>,
+    pub conditions: Option<JsonValue>,
+    pub actions: Option<JsonValue>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchMode {
+    All,
+    Any,
+}
+
+impl MatchMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MatchMode::All => "all",
+            MatchMode::Any => "any",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleField {
+    Merchant,
+    Amount,
+    Items,
+    Notes,
+    Source,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleOperator {
+    Contains,
+    NotContains,
+    Equals,
+    StartsWith,
+    EndsWith,
+    Regex,
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+}
+
+// e.g. { "field": "merchant", "operator": "contains", "value": "GRAB" }
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleCondition {
+    pub field: RuleField,
+    pub operator: RuleOperator,
+    pub value: JsonValue,
+    // The currency an amount is compared in; transactions in another currency don't match. Left
+    // out, it is the ledger's base currency
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub currency: Option<Currency>,
+}
+
+// e.g. { "type": "set_category", "category": "Transportation" }
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RuleAction {
+    SetCategory { category: String },
+    AddTag { tag: String },
+    SetNote { note: String },
+    MarkExcluded,
+}
+
+// A rule with its JSON conditions and actions decoded
+#[derive(Debug, Clone, Serialize)]
+pub struct Rule {
+    pub id: Uuid,
+    pub name: String,
+    pub position: i32,
+    pub enabled: bool,
+    pub match_mode: MatchMode,
+    pub stop_processing: bool,
+    pub conditions: Vec<RuleCondition>,
+    pub actions: Vec<RuleAction>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl TryFrom<DbRule> for Rule {
+    type Error = AppError;
+
+    fn try_from(rule: DbRule) -> Result<Self, Self::Error> {
+        let match_mode = match rule.match_mode.as_str() {
+            "any" => MatchMode::Any,
+            _ => MatchMode::All,
+        };
+
+        Ok(Rule {
+            id: rule.id,
+            name: rule.name,
+            position: rule.position,
+            enabled: rule.enabled,
+            match_mode,
+            stop_processing: rule.stop_processing,
+            conditions: serde_json::from_value(rule.conditions)?,
+            actions: serde_json::from_value(rule.actions)?,
+            created_at: rule.created_at,
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateRuleDto {
+    pub name: String,
+    pub enabled: Option<bool>,
+    pub match_mode: Option<MatchMode>,
+    pub stop_processing: Option<bool>,
+    pub conditions: Vec<RuleCondition>,
+    pub actions: Vec<RuleAction>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateRuleDto {
+    pub name: Option<String>,
+    pub enabled: Option<bool>,
+    pub match_mode: Option<MatchMode>,
+    pub stop_processing: Option<bool>,
+    pub conditions: Option<Vec<RuleCondition>>,
+    pub actions: Option<Vec<RuleAction>>,
+}
+
+// New evaluation order: rule IDs first to last
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReorderRulesDto {
+    pub rule_ids: Vec<Uuid>,
+}
+
+// The parts of a transaction a rule is allowed to change
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuleEffects {
+    pub category: String,
+    pub tags: Vec<String>,
+    pub notes: Option<String>,
+    pub excluded: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RuleChangePreview {
+    pub transaction_id: Uuid,
+    pub date: DateTime<Utc>,
+    pub merchant: String,
+    pub amount: Money,
+    pub currency: Currency,
+    pub before: RuleEffects,
+    pub after: RuleEffects,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RuleDryRunResponse {
+    pub matched: usize,
+    pub changed: usize,
+    pub changes: Vec<RuleChangePreview>,
+}
@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::transaction::TransactionResponse;
+
+// Where in a transaction the search words were found
+pub const FIELD_MERCHANT: &str = "merchant";
+pub const FIELD_NOTES: &str = "notes";
+pub const FIELD_ITEMS: &str = "items";
+pub const FIELD_RECEIPT: &str = "receipt";
+
+// Text around the first hit in one field, HTML-escaped, with the hits wrapped in <mark>
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchMatch {
+    pub field: String,
+    pub snippet: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchResult {
+    pub transaction: TransactionResponse,
+    pub rank: f32,
+    // Empty when the transaction only matched as a near miss, e.g. a typo
+    pub matches: Vec<SearchMatch>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchResponse {
+    pub results: Vec<SearchResult>,
+    pub total: u64,
+    pub page: u64,
+    pub pages: u64,
+}
@@ -0,0 +1,189 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use diesel::prelude::*;
+use crate::money::Currency;
+use crate::schema::{ledger_invitations, ledger_members, ledgers};
+
+// What a member may do in a ledger; each role can do everything the ones below it can
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LedgerRole {
+    Owner,
+    Editor,
+    Viewer,
+}
+
+impl LedgerRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LedgerRole::Owner => "owner",
+            LedgerRole::Editor => "editor",
+            LedgerRole::Viewer => "viewer",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "owner" => Some(LedgerRole::Owner),
+            "editor" => Some(LedgerRole::Editor),
+            "viewer" => Some(LedgerRole::Viewer),
+            _ => None,
+        }
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            LedgerRole::Owner => 2,
+            LedgerRole::Editor => 1,
+            LedgerRole::Viewer => 0,
+        }
+    }
+
+    // Whether a member with this role may do what `required` may
+    pub fn allows(&self, required: LedgerRole) -> bool {
+        self.rank() >= required.rank()
+    }
+}
+
+#[derive(Queryable, Identifiable, Insertable, Debug, Clone)]
+#[diesel(table_name = ledgers)]
+pub struct DbLedger {
+    pub id: Uuid,
+    pub name: String,
+    pub base_currency: String,
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl DbLedger {
+    // Every user's own ledger shares their id and can't be deleted or left
+    pub fn is_personal(&self) -> bool {
+        self.id == self.created_by
+    }
+}
+
+#[derive(Queryable, Insertable, Debug, Clone)]
+#[diesel(table_name = ledger_members)]
+pub struct DbLedgerMember {
+    pub ledger_id: Uuid,
+    pub user_id: Uuid,
+    pub role: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Queryable, Identifiable, Debug, Clone)]
+#[diesel(table_name = ledger_invitations)]
+pub struct DbLedgerInvitation {
+    pub id: Uuid,
+    pub ledger_id: Uuid,
+    pub email: String,
+    pub role: String,
+    pub token_hash: String,
+    pub invited_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub accepted_at: Option<DateTime<Utc>>,
+    pub accepted_by: Option<Uuid>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateLedgerDto {
+    pub name: String,
+    // Defaults to the creator's base currency
+    pub base_currency: Option<Currency>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateLedgerDto {
+    pub name: Option<String>,
+    pub base_currency: Option<Currency>,
+}
+
+// A ledger as one of its members sees it
+#[derive(Debug, Serialize)]
+pub struct LedgerResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub base_currency: Currency,
+    pub personal: bool,
+    pub role: LedgerRole,
+    pub member_count: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LedgerMemberResponse {
+    pub user_id: Uuid,
+    pub name: String,
+    pub email: String,
+    pub role: LedgerRole,
+    pub joined_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetMemberRoleDto {
+    pub role: LedgerRole,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateInvitationDto {
+    pub email: String,
+    // Defaults to editor
+    pub role: Option<LedgerRole>,
+    pub expires_in_days: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InvitationStatus {
+    Pending,
+    Accepted,
+    Revoked,
+    Expired,
+}
+
+#[derive(Debug, Serialize)]
+pub struct InvitationResponse {
+    pub id: Uuid,
+    pub ledger_id: Uuid,
+    pub email: String,
+    pub role: LedgerRole,
+    pub status: InvitationStatus,
+    pub invited_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub accepted_at: Option<DateTime<Utc>>,
+    // Only when the invitation is created; it is what the invitee accepts with and isn't stored
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+impl InvitationResponse {
+    pub fn from_db(invitation: DbLedgerInvitation, now: DateTime<Utc>) -> Self {
+        let status = if invitation.accepted_at.is_some() {
+            InvitationStatus::Accepted
+        } else if invitation.revoked_at.is_some() {
+            InvitationStatus::Revoked
+        } else if invitation.expires_at <= now {
+            InvitationStatus::Expired
+        } else {
+            InvitationStatus::Pending
+        };
+        InvitationResponse {
+            id: invitation.id,
+            ledger_id: invitation.ledger_id,
+            email: invitation.email,
+            role: LedgerRole::parse(&invitation.role).unwrap_or(LedgerRole::Viewer),
+            status,
+            invited_by: invitation.invited_by,
+            created_at: invitation.created_at,
+            expires_at: invitation.expires_at,
+            accepted_at: invitation.accepted_at,
+            token: None,
+        }
+    }
+}
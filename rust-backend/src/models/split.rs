@@ -0,0 +1,81 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use diesel::prelude::*;
+use crate::money::{Currency, Money};
+use crate::schema::transaction_splits;
+
+#[derive(Queryable, Identifiable, Insertable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = transaction_splits)]
+pub struct DbSplit {
+    pub id: Uuid,
+    pub transaction_id: Uuid,
+    #[serde(alias = "user_id")]
+    pub ledger_id: Uuid,
+    pub category_id: Option<Uuid>,
+    pub amount: BigDecimal,
+    pub item_indexes: Vec<i32>,
+    pub notes: Option<String>,
+    pub position: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = transaction_splits)]
+pub struct NewSplit {
+    pub id: Uuid,
+    pub transaction_id: Uuid,
+    pub ledger_id: Uuid,
+    pub category_id: Option<Uuid>,
+    pub amount: BigDecimal,
+    pub item_indexes: Vec<i32>,
+    pub notes: Option<String>,
+    pub position: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// One allocation: either an amount, the transaction items at the given positions, or neither for
+// whatever the other allocations leave over
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SplitDto {
+    pub category: String,
+    // In the transaction's currency
+    pub amount: Option<BigDecimal>,
+    pub items: Option<Vec<usize>>,
+    pub notes: Option<String>,
+    pub tags: Option<Vec<String>>,
+}
+
+// Replaces all of a transaction's splits; the allocations must add up to the transaction amount
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SetSplitsDto {
+    pub splits: Vec<SplitDto>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SplitResponse {
+    pub id: Uuid,
+    pub category_id: Option<Uuid>,
+    pub category: String,
+    pub amount: Money,
+    pub items: Vec<i32>,
+    pub notes: Option<String>,
+    pub tags: Vec<String>,
+}
+
+impl SplitResponse {
+    pub fn new(split: DbSplit, category: String, tags: Vec<String>, currency: Currency) -> Self {
+        SplitResponse {
+            id: split.id,
+            category_id: split.category_id,
+            category,
+            amount: Money::new(split.amount, currency),
+            items: split.item_indexes,
+            notes: split.notes,
+            tags,
+        }
+    }
+}
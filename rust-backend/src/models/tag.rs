@@ -0,0 +1,109 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use diesel::prelude::*;
+use crate::models::transaction::TransactionFilters;
+use crate::schema::{split_tags, tags, transaction_tags};
+
+#[derive(Queryable, Identifiable, Insertable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = tags)]
+pub struct DbTag {
+    pub id: Uuid,
+    #[serde(alias = "user_id")]
+    pub ledger_id: Uuid,
+    pub name: String,
+    pub color: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = tags)]
+pub struct NewTag {
+    pub id: Uuid,
+    pub ledger_id: Uuid,
+    pub name: String,
+    pub color: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(AsChangeset, Debug)]
+#[diesel(table_name = tags)]
+pub struct TagChanges {
+    pub name: Option<String>,
+    pub color: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Insertable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = transaction_tags)]
+pub struct NewTransactionTag {
+    pub transaction_id: Uuid,
+    pub tag_id: Uuid,
+}
+
+#[derive(Insertable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = split_tags)]
+pub struct NewSplitTag {
+    pub split_id: Uuid,
+    pub tag_id: Uuid,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateTagDto {
+    pub name: String,
+    pub color: Option<String>,
+}
+
+// Renaming onto another tag's name is refused; merge the two instead
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateTagDto {
+    pub name: Option<String>,
+    pub color: Option<String>,
+}
+
+// Tags folded into the one in the path; their transactions keep a single copy of it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MergeTagsDto {
+    pub source_ids: Vec<Uuid>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TagResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub color: Option<String>,
+    pub transaction_count: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+impl TagResponse {
+    pub fn new(tag: DbTag, transaction_count: i64) -> Self {
+        TagResponse {
+            id: tag.id,
+            name: tag.name,
+            color: tag.color,
+            transaction_count,
+            created_at: tag.created_at,
+        }
+    }
+}
+
+// Add and remove tags on every transaction matching the filters
+#[derive(Debug, Deserialize)]
+pub struct BulkTagDto {
+    #[serde(default)]
+    pub filters: TransactionFilters,
+    #[serde(default)]
+    pub add: Vec<String>,
+    #[serde(default)]
+    pub remove: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BulkTagResponse {
+    pub matched: usize,
+    pub tagged: usize,
+    pub untagged: usize,
+}
@@ -1,88 +1,249 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use diesel::prelude::*;
 use crate::schema::transactions;
 use serde_json::Value as JsonValue;
-// Temporarily comment out these imports
-// use diesel::sql_types::Numeric;
-// use bigdecimal::BigDecimal;
+use bigdecimal::BigDecimal;
+use crate::models::category::CategoryKind;
+use crate::models::split::SplitResponse;
+use crate::money::{Currency, Money};
 
-/*
-#[derive(Queryable, Identifiable, Debug)]
+// Where a transaction came from; rules can match on it
+pub const SOURCE_MANUAL: &str = "manual";
+pub const SOURCE_OCR: &str = "ocr";
+pub const SOURCE_IMPORT: &str = "import";
+pub const SOURCE_RECURRING: &str = "recurring";
+
+// Whether money left or entered the account; amounts themselves are always positive
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    Expense,
+    Income,
+}
+
+impl Direction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Direction::Expense => "expense",
+            Direction::Income => "income",
+        }
+    }
+}
+
+// Types that make up spending: purchases, less any refunds
+pub const SPENDING_TYPES: [&str; 2] = ["expense", "refund"];
+
+// What a transaction means for reports: refunds reduce spending, transfers are neither spending nor income
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransactionType {
+    Expense,
+    Income,
+    Refund,
+    Transfer,
+}
+
+impl TransactionType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransactionType::Expense => "expense",
+            TransactionType::Income => "income",
+            TransactionType::Refund => "refund",
+            TransactionType::Transfer => "transfer",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "expense" => Some(TransactionType::Expense),
+            "income" => Some(TransactionType::Income),
+            "refund" => Some(TransactionType::Refund),
+            "transfer" => Some(TransactionType::Transfer),
+            _ => None,
+        }
+    }
+
+    // Which way the money moves; each transfer leg carries its own direction
+    pub fn direction(&self) -> Direction {
+        match self {
+            TransactionType::Expense | TransactionType::Transfer => Direction::Expense,
+            TransactionType::Income | TransactionType::Refund => Direction::Income,
+        }
+    }
+
+    pub fn category_kind(&self) -> CategoryKind {
+        match self {
+            TransactionType::Income => CategoryKind::Income,
+            _ => CategoryKind::Expense,
+        }
+    }
+}
+
+#[derive(Queryable, Identifiable, Insertable, Serialize, Deserialize, Debug, Clone)]
 #[diesel(table_name = transactions)]
 pub struct DbTransaction {
     pub id: Uuid,
-    pub amount: String, // Store numeric as string
+    pub amount: BigDecimal,
     pub date: DateTime<Utc>,
     pub merchant: String,
     pub category_id: Option<Uuid>,
     pub notes: Option<String>,
     pub items: Option<JsonValue>,
     pub image_path: Option<String>,
-    pub user_id: Uuid,
+    #[serde(alias = "user_id")]
+    pub ledger_id: Uuid,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub merchant_id: Option<Uuid>,
+    pub excluded: bool,
+    pub source: String,
+    pub recurring_id: Option<Uuid>,
+    pub currency: String,
+    pub account_id: Option<Uuid>,
+    pub direction: String,
+    pub transfer_id: Option<Uuid>,
+    pub transaction_type: String,
+    pub refund_of: Option<Uuid>,
+    pub import_batch_id: Option<Uuid>,
+    // The bank's ID for the statement line it was imported from
+    pub external_id: Option<String>,
+    // The day the bank gave the money value, when it differs from the booking date
+    pub value_date: Option<NaiveDate>,
+    // The payer's reference from the statement line, e.g. an invoice number
+    pub bank_reference: Option<String>,
+    // Paired with the receipt or bank line for the same spend; backups from before this was
+    // added have none
+    #[serde(default)]
+    pub reconciled: bool,
+    // Set while the transaction is in the trash
+    #[serde(default)]
+    pub deleted_at: Option<DateTime<Utc>>,
 }
-*/
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Transaction {
-    pub id: Uuid,
-    pub user_id: Uuid,
-    pub amount: f64,
-    pub date: DateTime<Utc>,
-    pub merchant: String,
-    pub category: String,
-    pub notes: Option<String>,
-    pub bill_id: Option<Uuid>,
-    pub created_at: DateTime<Utc>,
-    pub updated_at: DateTime<Utc>,
-    pub items: Option<Vec<TransactionItem>>,
-    pub bill_image: Option<String>,
+impl DbTransaction {
+    pub fn item_list(&self) -> Vec<TransactionItem> {
+        self.items
+            .clone()
+            .and_then(|items| serde_json::from_value(items).ok())
+            .unwrap_or_default()
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateTransactionDto {
-    pub amount: f64,
+    pub amount: BigDecimal,
+    // Defaults to THB
+    pub currency: Option<Currency>,
     pub date: DateTime<Utc>,
     pub merchant: String,
     pub category: String,
     pub notes: Option<String>,
     pub bill_image: Option<String>,
     pub items: Option<Vec<TransactionItem>>,
+    pub tags: Option<Vec<String>>,
+    pub source: Option<String>,
+    pub bill_id: Option<Uuid>,
+    pub account_id: Option<Uuid>,
+    // Defaults to expense, or to refund when `refund_of` names the original purchase
+    pub transaction_type: Option<TransactionType>,
+    pub refund_of: Option<Uuid>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UpdateTransactionDto {
-    pub amount: Option<f64>,
+    pub amount: Option<BigDecimal>,
+    pub currency: Option<Currency>,
     pub date: Option<DateTime<Utc>>,
     pub merchant: Option<String>,
     pub category: Option<String>,
     pub notes: Option<String>,
     pub items: Option<Vec<TransactionItem>>,
+    pub tags: Option<Vec<String>>,
+    pub excluded: Option<bool>,
+    pub account_id: Option<Uuid>,
+    pub transaction_type: Option<TransactionType>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TransactionItem {
     pub name: String,
-    pub price: Option<f64>,
+    pub price: Option<Money>,
     pub quantity: Option<u32>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TransactionResponse {
     pub id: Uuid,
-    pub amount: f64,
+    pub amount: Money,
+    pub currency: Currency,
     pub date: DateTime<Utc>,
     pub merchant: String,
+    pub merchant_id: Option<Uuid>,
     pub category: String,
     pub notes: Option<String>,
     pub items: Option<Vec<TransactionItem>>,
     pub bill_image: Option<String>,
+    pub tags: Vec<String>,
+    pub excluded: bool,
+    pub source: String,
+    pub recurring_id: Option<Uuid>,
+    pub account_id: Option<Uuid>,
+    pub direction: String,
+    // Shared by both legs of a transfer between accounts
+    pub transfer_id: Option<Uuid>,
+    pub transaction_type: String,
+    // The purchase a refund gives money back for
+    pub refund_of: Option<Uuid>,
+    // Allocations to other categories; category reports count these instead of `category`
+    pub splits: Vec<SplitResponse>,
+    // The statement import that created the transaction
+    pub import_batch_id: Option<Uuid>,
+    pub value_date: Option<NaiveDate>,
+    pub bank_reference: Option<String>,
+    pub reconciled: bool,
     pub created_at: DateTime<Utc>,
 }
 
+impl TransactionResponse {
+    pub fn new(transaction: DbTransaction, category: String, tags: Vec<String>, splits: Vec<SplitResponse>) -> Self {
+        let items = transaction
+            .items
+            .clone()
+            .and_then(|items| serde_json::from_value(items).ok());
+        let currency = Currency::from_code(&transaction.currency);
+
+        TransactionResponse {
+            id: transaction.id,
+            amount: Money::new(transaction.amount, currency),
+            currency,
+            date: transaction.date,
+            merchant: transaction.merchant,
+            merchant_id: transaction.merchant_id,
+            category,
+            notes: transaction.notes,
+            items,
+            bill_image: transaction.image_path,
+            tags,
+            excluded: transaction.excluded,
+            source: transaction.source,
+            recurring_id: transaction.recurring_id,
+            account_id: transaction.account_id,
+            direction: transaction.direction,
+            transfer_id: transaction.transfer_id,
+            transaction_type: transaction.transaction_type,
+            refund_of: transaction.refund_of,
+            splits,
+            import_batch_id: transaction.import_batch_id,
+            value_date: transaction.value_date,
+            bank_reference: transaction.bank_reference,
+            reconciled: transaction.reconciled,
+            created_at: transaction.created_at,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TransactionsListResponse {
     pub transactions: Vec<TransactionResponse>,
@@ -91,44 +252,73 @@ pub struct TransactionsListResponse {
     pub pages: u64,
 }
 
-// Categories for transactions
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Category {
-    pub id: Uuid,
-    pub name: String,
-    pub description: Option<String>,
-    pub color: Option<String>,
-    pub icon: Option<String>,
-}
-
-// For filtering transactions
-#[derive(Debug, Deserialize)]
+// For filtering transactions; tag filters take comma-separated tag names
+#[derive(Debug, Deserialize, Default)]
 pub struct TransactionFilters {
     pub start_date: Option<DateTime<Utc>>,
     pub end_date: Option<DateTime<Utc>>,
-    pub min_amount: Option<f64>,
-    pub max_amount: Option<f64>,
+    pub min_amount: Option<BigDecimal>,
+    pub max_amount: Option<BigDecimal>,
     pub merchant: Option<String>,
     pub category: Option<String>,
     pub search: Option<String>,
+    pub account_id: Option<Uuid>,
+    pub direction: Option<Direction>,
+    pub transaction_type: Option<TransactionType>,
+    // Tagged with at least one of, every one of, or none of the listed tags
+    pub tags_any: Option<String>,
+    pub tags_all: Option<String>,
+    pub tags_none: Option<String>,
+    pub import_batch_id: Option<Uuid>,
+    pub reconciled: Option<bool>,
     pub page: Option<u64>,
     pub limit: Option<u64>,
 }
 
-/*
 #[derive(Insertable, Debug)]
 #[diesel(table_name = transactions)]
 pub struct NewTransaction {
     pub id: Uuid,
-    pub amount: String, // Store as string to avoid BigDecimal issues
+    pub amount: BigDecimal,
     pub date: DateTime<Utc>,
     pub merchant: String,
     pub category_id: Option<Uuid>,
     pub notes: Option<String>,
     pub items: Option<JsonValue>,
     pub image_path: Option<String>,
-    pub user_id: Uuid,
+    pub ledger_id: Uuid,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub merchant_id: Option<Uuid>,
+    pub excluded: bool,
+    pub source: String,
+    pub recurring_id: Option<Uuid>,
+    pub currency: String,
+    pub account_id: Option<Uuid>,
+    pub direction: String,
+    pub transfer_id: Option<Uuid>,
+    pub transaction_type: String,
+    pub refund_of: Option<Uuid>,
+    pub import_batch_id: Option<Uuid>,
+    pub external_id: Option<String>,
+    pub value_date: Option<NaiveDate>,
+    pub bank_reference: Option<String>,
+}
+
+#[derive(AsChangeset, Debug, Default)]
+#[diesel(table_name = transactions)]
+pub struct TransactionChanges {
+    pub amount: Option<BigDecimal>,
+    pub currency: Option<String>,
+    pub date: Option<DateTime<Utc>>,
+    pub merchant: Option<String>,
+    pub merchant_id: Option<Uuid>,
+    pub category_id: Option<Uuid>,
+    pub notes: Option<String>,
+    pub items: Option<JsonValue>,
+    pub excluded: Option<bool>,
+    pub account_id: Option<Uuid>,
+    pub direction: Option<String>,
+    pub transaction_type: Option<String>,
+    pub updated_at: Option<DateTime<Utc>>,
 }
-*/ 
\ No newline at end of file
@@ -0,0 +1,51 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::models::bill::BillResponse;
+use crate::models::category::Category;
+use crate::models::transaction::TransactionResponse;
+
+// What can be put in the trash, named as in the API
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrashEntity {
+    Transactions,
+    Bills,
+    Categories,
+}
+
+impl TrashEntity {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "transactions" => Some(TrashEntity::Transactions),
+            "bills" => Some(TrashEntity::Bills),
+            "categories" => Some(TrashEntity::Categories),
+            _ => None,
+        }
+    }
+}
+
+// A trashed row as the API shows it live, with when it went in and when it will be purged
+#[derive(Debug, Serialize)]
+pub struct TrashEntry<T> {
+    #[serde(flatten)]
+    pub item: T,
+    pub deleted_at: DateTime<Utc>,
+    pub purge_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TrashResponse {
+    pub retention_days: i64,
+    pub transactions: Vec<TrashEntry<TransactionResponse>>,
+    pub bills: Vec<TrashEntry<BillResponse>>,
+    pub categories: Vec<TrashEntry<Category>>,
+}
+
+// Rows purged for good, and the stored files no row points at any more
+#[derive(Debug, Default)]
+pub struct PurgeReport {
+    pub transactions: usize,
+    pub bills: usize,
+    pub categories: usize,
+    pub files: Vec<String>,
+}
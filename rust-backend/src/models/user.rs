@@ -2,6 +2,7 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use diesel::prelude::*;
+use crate::money::Currency;
 use crate::schema::users;
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -26,6 +27,7 @@ pub struct UpdateUserDto {
     pub name: Option<String>,
     pub email: Option<String>,
     pub password: Option<String>,
+    pub base_currency: Option<Currency>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -33,9 +35,22 @@ pub struct UserResponse {
     pub id: Uuid,
     pub email: String,
     pub name: String,
+    pub base_currency: Currency,
     pub created_at: DateTime<Utc>,
 }
 
+impl From<DbUser> for UserResponse {
+    fn from(user: DbUser) -> Self {
+        UserResponse {
+            id: user.id,
+            email: user.email,
+            name: user.name,
+            base_currency: Currency::from_code(&user.base_currency),
+            created_at: user.created_at,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct LoginDto {
     pub email: String,
@@ -57,6 +72,7 @@ pub struct DbUser {
     pub password_hash: String,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub base_currency: String,
 }
 
 #[derive(Insertable, Debug)]
@@ -68,4 +84,13 @@ pub struct NewUser {
     pub password_hash: String,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+}
+
+#[derive(AsChangeset, Debug)]
+#[diesel(table_name = users)]
+pub struct UserChanges {
+    pub name: Option<String>,
+    pub email: Option<String>,
+    pub base_currency: Option<String>,
+    pub updated_at: DateTime<Utc>,
 } 
\ No newline at end of file
@@ -0,0 +1,247 @@
+use std::fmt;
+use std::str::FromStr;
+
+use bigdecimal::{BigDecimal, Zero};
+use serde::de::{self, Deserializer};
+use serde::ser::Serializer;
+use serde::{Deserialize, Serialize};
+
+// ISO 4217 currencies we accept, with the number of minor-unit digits amounts are rounded to
+const CURRENCIES: &[(&str, i64)] = &[
+    ("THB", 2), ("USD", 2), ("EUR", 2), ("GBP", 2), ("JPY", 0), ("CNY", 2), ("KRW", 0),
+    ("SGD", 2), ("MYR", 2), ("HKD", 2), ("TWD", 2), ("VND", 0), ("IDR", 2), ("PHP", 2),
+    ("INR", 2), ("AUD", 2), ("NZD", 2), ("CAD", 2), ("CHF", 2), ("LAK", 2), ("KHR", 2),
+    ("MMK", 2),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Currency(&'static str);
+
+impl Currency {
+    pub const THB: Currency = Currency("THB");
+
+    pub fn parse(code: &str) -> Option<Currency> {
+        let code = code.trim().to_uppercase();
+        CURRENCIES
+            .iter()
+            .find(|(known, _)| *known == code)
+            .map(|(known, _)| Currency(known))
+    }
+
+    // Codes read back from the database were validated when they were written
+    pub fn from_code(code: &str) -> Currency {
+        Currency::parse(code).unwrap_or_default()
+    }
+
+    pub fn minor_units(&self) -> i64 {
+        CURRENCIES
+            .iter()
+            .find(|(known, _)| *known == self.0)
+            .map_or(2, |(_, digits)| *digits)
+    }
+
+    // Round to the currency's minor unit, e.g. satang for THB, whole yen for JPY
+    pub fn round(&self, amount: &BigDecimal) -> BigDecimal {
+        let digits = self.minor_units();
+        amount.round(digits).with_scale(digits)
+    }
+}
+
+impl Default for Currency {
+    fn default() -> Self {
+        Currency::THB
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+impl Serialize for Currency {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Currency {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = String::deserialize(deserializer)?;
+        Currency::parse(&code).ok_or_else(|| de::Error::custom(format!("unsupported currency: {}", code)))
+    }
+}
+
+// Symbols printed or typed next to an amount, e.g. "฿ 99" or "$12.50"
+const CURRENCY_SYMBOLS: &[char] = &['฿', '$', '€', '£', '¥', '₩', '₫', '₹', '₱', '₭', '៛'];
+
+// Thousands separators, e.g. "1,234.50", "1 234.50" or "1'234.50"
+fn is_separator(c: char) -> bool {
+    c == ',' || c == '\'' || c.is_whitespace()
+}
+
+// An exact amount in a currency, always rounded to that currency's minor unit.
+// Serialized as a decimal string such as "12.50" so clients never see a float; DTOs carry the
+// currency in a field of its own.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Money {
+    amount: BigDecimal,
+    currency: Currency,
+}
+
+impl Money {
+    pub fn new(amount: BigDecimal, currency: Currency) -> Self {
+        Money {
+            amount: currency.round(&amount),
+            currency,
+        }
+    }
+
+    pub fn zero(currency: Currency) -> Self {
+        Money::new(BigDecimal::zero(), currency)
+    }
+
+    // Parse an amount as printed on a receipt or typed by a user: "1,234.50", "฿ 99", "-12.5",
+    // "99.00 THB". Only separators, currency symbols and the currency's own code are dropped;
+    // anything else makes the text not an amount
+    pub fn parse(text: &str, currency: Currency) -> Option<Self> {
+        let code = currency.0;
+        let mut text = text.trim();
+        if text.get(..code.len()).is_some_and(|prefix| prefix.eq_ignore_ascii_case(code)) {
+            text = &text[code.len()..];
+        }
+        if let Some(split) = text.len().checked_sub(code.len()) {
+            if text.get(split..).is_some_and(|suffix| suffix.eq_ignore_ascii_case(code)) {
+                text = &text[..split];
+            }
+        }
+
+        let cleaned: String = text
+            .chars()
+            .filter(|c| !is_separator(*c) && !CURRENCY_SYMBOLS.contains(c))
+            .collect();
+        let digits = cleaned.strip_prefix('-').unwrap_or(&cleaned);
+        if !digits.chars().any(|c| c.is_ascii_digit()) || !digits.chars().all(|c| c.is_ascii_digit() || c == '.') {
+            return None;
+        }
+        BigDecimal::from_str(&cleaned).ok().map(|amount| Money::new(amount, currency))
+    }
+
+    pub fn amount(&self) -> &BigDecimal {
+        &self.amount
+    }
+
+    pub fn into_amount(self) -> BigDecimal {
+        self.amount
+    }
+
+    pub fn currency(&self) -> Currency {
+        self.currency
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.amount, self.currency)
+    }
+}
+
+impl Serialize for Money {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.amount.to_string())
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DecimalRepr {
+    Text(String),
+    Number(serde_json::Number),
+}
+
+impl DecimalRepr {
+    fn into_decimal<E: de::Error>(self) -> Result<BigDecimal, E> {
+        let text = match self {
+            DecimalRepr::Text(text) => text,
+            DecimalRepr::Number(number) => number.to_string(),
+        };
+        BigDecimal::from_str(text.trim()).map_err(|_| E::custom(format!("invalid amount: {}", text)))
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum MoneyRepr {
+    // Item prices and receipt totals stored before amounts were plain strings
+    Full {
+        amount: DecimalRepr,
+        #[serde(default)]
+        currency: Option<Currency>,
+    },
+    // "12.50" or 12.5; the currency defaults to THB
+    Bare(DecimalRepr),
+}
+
+impl<'de> Deserialize<'de> for Money {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (amount, currency) = match MoneyRepr::deserialize(deserializer)? {
+            MoneyRepr::Full { amount, currency } => (amount, currency.unwrap_or_default()),
+            MoneyRepr::Bare(amount) => (amount, Currency::default()),
+        };
+        Ok(Money::new(amount.into_decimal()?, currency))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decimal(text: &str) -> BigDecimal {
+        BigDecimal::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn rounds_to_the_currency_minor_unit() {
+        assert_eq!(Money::new(decimal("12.345"), Currency::THB).amount().to_string(), "12.35");
+        assert_eq!(Money::new(decimal("12"), Currency::THB).amount().to_string(), "12.00");
+        assert_eq!(Money::new(decimal("1234.5"), Currency::parse("jpy").unwrap()).amount().to_string(), "1235");
+    }
+
+    #[test]
+    fn parses_printed_amounts() {
+        let parse = |text| Money::parse(text, Currency::THB).map(|m| m.amount().to_string());
+        assert_eq!(parse("1,234.50").as_deref(), Some("1234.50"));
+        assert_eq!(parse("฿ 99").as_deref(), Some("99.00"));
+        assert_eq!(parse("-12.5").as_deref(), Some("-12.50"));
+        assert_eq!(parse("99.00 THB").as_deref(), Some("99.00"));
+        assert_eq!(parse("thb1 234").as_deref(), Some("1234.00"));
+    }
+
+    #[test]
+    fn rejects_text_that_is_not_an_amount() {
+        for text in ["", "-", ".", "abc", "12abc", "1e5", "12-3", "1.2.3", "99 USD", "Total 99"] {
+            assert!(Money::parse(text, Currency::THB).is_none(), "{:?} parsed", text);
+        }
+    }
+
+    #[test]
+    fn serializes_as_a_decimal_string() {
+        let money = Money::new(decimal("12.5"), Currency::THB);
+        assert_eq!(serde_json::to_value(&money).unwrap(), serde_json::json!("12.50"));
+    }
+
+    #[test]
+    fn deserializes_strings_numbers_and_stored_objects() {
+        let money: Money = serde_json::from_value(serde_json::json!("12.345")).unwrap();
+        assert_eq!(money, Money::new(decimal("12.35"), Currency::THB));
+
+        let money: Money = serde_json::from_value(serde_json::json!(7)).unwrap();
+        assert_eq!(money.amount().to_string(), "7.00");
+
+        let money: Money = serde_json::from_value(serde_json::json!({"amount": "1500", "currency": "JPY"})).unwrap();
+        assert_eq!(money.currency(), Currency::parse("JPY").unwrap());
+        assert_eq!(money.amount().to_string(), "1500");
+
+        assert!(serde_json::from_value::<Money>(serde_json::json!("twelve")).is_err());
+    }
+}
@@ -1,46 +0,0 @@
-use image::{DynamicImage, GrayImage};
-use image::imageops::{contrast, grayscale};
-
-// Main function for preprocessing an image before OCR
-pub fn preprocess_for_ocr(img: &DynamicImage) -> DynamicImage {
-    // Convert to grayscale
-    let grayscale_img = grayscale(img);
-    
-    // Increase contrast
-    let contrast_img = increase_contrast(&grayscale_img);
-    
-    // Convert back to DynamicImage
-    DynamicImage::ImageLuma8(contrast_img)
-}
-
-// Helper function to increase contrast
-fn increase_contrast(gray_img: &GrayImage) -> GrayImage {
-    contrast(gray_img, 25.0)
-}
-
-// Additional preprocessing steps (to be implemented as needed)
-// pub fn denoise(img: &GrayImage) -> GrayImage {
-//     // Apply noise reduction filter
-//     // This would use a library like imageproc for operations like
-//     // median filtering, gaussian blur, etc.
-//     // For now, just return the original
-//     img.clone()
-// }
-// 
-// pub fn binarize(img: &GrayImage) -> GrayImage {
-//     // Convert to pure black and white
-//     // This would use adaptive thresholding or similar
-//     // For now, use a simple threshold
-//     let mut result = img.clone();
-//     for pixel in result.iter_mut() {
-//         *pixel = if *pixel > Luma([128u8]) { Luma([255u8]) } else { Luma([0u8]) };
-//     }
-//     result
-// }
-// 
-// pub fn deskew(img: &GrayImage) -> GrayImage {
-//     // Correct skew in the image
-//     // Would require detecting lines and rotation
-//     // Complex to implement, would typically use a specialized library
-//     img.clone()
-// } 
\ No newline at end of file
@@ -1,4 +1 @@
 pub mod processor;
-pub mod parser;
-pub mod image_preprocessing;
-pub mod data_extraction; 
\ No newline at end of file
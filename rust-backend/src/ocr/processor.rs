@@ -2,7 +2,7 @@ use std::path::Path;
 use std::fs;
 use std::io::Cursor;
 use regex::Regex;
-use image::{DynamicImage, GenericImageView};
+use image::GenericImageView;
 use image::imageops;
 use imageproc::contrast;
 use leptess::LepTess;
@@ -10,6 +10,10 @@ use serde::{Serialize, Deserialize};
 use base64;
 use log;
 use crate::error::AppError;
+use crate::money::{Currency, Money};
+
+// The whole receipt, then its bottom (totals) and top (merchant), each encoded as PNG
+type ReceiptImages = (Vec<u8>, Vec<u8>, Vec<u8>);
 
 pub struct OcrProcessor {
     bottom_crop: Option<Vec<u8>>,
@@ -27,15 +31,20 @@ pub struct OcrResult {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ItemData {
     pub name: String,
-    pub price: Option<f64>,
+    pub price: Option<Money>,
     pub quantity: Option<u32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExtractedData {
-    pub total: Option<f64>,
+    pub total: Option<Money>,
+    // The currency the receipt is printed in; the total and item prices are in it
+    #[serde(default)]
+    pub currency: Currency,
     pub date: Option<String>,
     pub merchant: Option<String>,
+    #[serde(default)]
+    pub tax_id: Option<String>,
     pub items: Vec<ItemData>,
     pub confidence: f32,
     pub ocr_source: String,
@@ -45,8 +54,10 @@ impl Default for ExtractedData {
     fn default() -> Self {
         Self {
             total: None,
+            currency: Currency::default(),
             date: None,
             merchant: None,
+            tax_id: None,
             items: Vec::new(),
             confidence: 0.0,
             ocr_source: "tesseract".to_string(),
@@ -79,22 +90,26 @@ impl OcrProcessor {
             .map_err(|e| AppError::OcrError(format!("Failed to get text: {}", e)))?;
         
         // Extract data from the text
-        let total = self.extract_total(&text);
+        let currency = self.find_currency_in_text(&text).unwrap_or_default();
+        let total = self.extract_total(&text, currency);
         let date = self.extract_date(&text);
         let merchant = self.extract_merchant(&text);
-        let items = self.extract_items(&text);
+        let tax_id = self.find_tax_id_in_text(&text);
+        let items = self.extract_items(&text, currency);
         
         Ok(ExtractedData {
             total,
+            currency,
             date,
             merchant,
+            tax_id,
             items,
             confidence: 0.7, // Default confidence for Tesseract
             ocr_source: "tesseract".to_string(),
         })
     }
     
-    fn preprocess_image(&self, image_data: &[u8]) -> Result<(Vec<u8>, Vec<u8>, Vec<u8>), AppError> {
+    fn preprocess_image(&self, image_data: &[u8]) -> Result<ReceiptImages, AppError> {
         // Load the image from bytes
         let img = image::load_from_memory(image_data)
             .map_err(|e| AppError::OcrError(format!("Failed to load image: {}", e)))?;
@@ -140,70 +155,58 @@ impl OcrProcessor {
         Ok((full_buffer, bottom_buffer, top_buffer))
     }
     
-    fn process_image_parts(&self, img: DynamicImage) -> (DynamicImage, Vec<u8>, Vec<u8>) {
-        // Get image dimensions
-        let (width, height) = img.dimensions();
-        
-        // Create enhanced version of the full image
-        let mut enhanced = img.to_luma8();
-        for pixel in enhanced.iter_mut() {
-            // Increase contrast
-            if *pixel > 127 {
-                *pixel = 255.min(*pixel + 30);
-            } else {
-                *pixel = 0.max(*pixel - 30);
+    // The currency the receipt is printed in, by counting symbols and codes; None if nothing stands out
+    fn find_currency_in_text(&self, text: &str) -> Option<Currency> {
+        // Prefixed dollars come before the bare "$" so "S$" isn't also counted as USD
+        let markers = [
+            ("USD", r"US\$|\bUSD\b"),
+            ("SGD", r"S\$|\bSGD\b"),
+            ("HKD", r"HK\$|\bHKD\b"),
+            ("TWD", r"NT\$|\bTWD\b"),
+            ("AUD", r"A\$|\bAUD\b"),
+            ("NZD", r"NZ\$|\bNZD\b"),
+            ("CAD", r"C\$|\bCAD\b"),
+            ("THB", r"฿|บาท|\bTHB\b"),
+            ("JPY", r"¥|円|\bJPY\b"),
+            ("CNY", r"元|\bRMB\b|\bCNY\b"),
+            ("EUR", r"€|\bEUR\b"),
+            ("GBP", r"£|\bGBP\b"),
+            ("KRW", r"₩|\bKRW\b"),
+            ("VND", r"₫|\bVND\b"),
+            ("MYR", r"\bRM\s?\d|\bMYR\b"),
+            ("IDR", r"\bRp\.?\s?\d|\bIDR\b"),
+            ("PHP", r"₱|\bPHP\b"),
+            ("INR", r"₹|\bINR\b"),
+        ];
+
+        let mut remaining = text.to_string();
+        let mut best: Option<(Currency, usize)> = None;
+        for (code, pattern) in markers {
+            let Ok(regex) = Regex::new(pattern) else { continue };
+            let count = regex.find_iter(&remaining).count();
+            remaining = regex.replace_all(&remaining, " ").into_owned();
+            if count > best.map_or(0, |(_, most)| most) {
+                best = Currency::parse(code).map(|currency| (currency, count));
             }
         }
-        let enhanced_img = DynamicImage::ImageLuma8(enhanced);
-        
-        // Create cropped versions for specific processing
-        // Bottom part (for totals)
-        let bottom_height = height / 3;
-        let bottom_img = img.crop_imm(0, height - bottom_height, width, bottom_height);
-        
-        // Top part (for merchant info)
-        let top_height = height / 4;
-        let top_img = img.crop_imm(0, 0, width, top_height);
-        
-        // Convert cropped images to bytes
-        let mut bottom_buffer = Vec::new();
-        let mut bottom_cursor = std::io::Cursor::new(&mut bottom_buffer);
-        bottom_img.write_to(&mut bottom_cursor, image::ImageFormat::Jpeg)
-            .expect("Failed to encode bottom crop");
-            
-        let mut top_buffer = Vec::new();
-        let mut top_cursor = std::io::Cursor::new(&mut top_buffer);
-        top_img.write_to(&mut top_cursor, image::ImageFormat::Jpeg)
-            .expect("Failed to encode top crop");
-        
-        (enhanced_img, bottom_buffer, top_buffer)
-    }
-    
-    fn extract_data(&self, text: &str) -> Result<ExtractedData, AppError> {
-        let total = self.extract_total(text);
-        let date = self.extract_date(text);
-        let merchant = self.extract_merchant(text);
-        let items = self.extract_items(text);
-        
-        Ok(ExtractedData {
-            total,
-            date,
-            merchant,
-            items,
-            confidence: 0.0,
-            ocr_source: "tesseract".to_string(),
-        })
+
+        // Whatever "$" is left is most likely US dollars
+        let bare_dollars = remaining.matches('$').count();
+        if bare_dollars > best.map_or(0, |(_, most)| most) {
+            return Currency::parse("USD");
+        }
+        best.map(|(currency, _)| currency)
     }
-    
-    fn extract_total(&self, text: &str) -> Option<f64> {
+
+    fn extract_total(&self, text: &str, currency: Currency) -> Option<Money> {
         // First try looking at the bottom crop where totals often appear
         if let Some(bottom_data) = &self.bottom_crop {
             // Initialize a new tesseract instance specifically for the bottom crop
             if let Ok(mut crop_tesseract) = LepTess::new(None, "eng+tha") {
-                if crop_tesseract.set_image_from_mem(&bottom_data).is_ok() {
+                if crop_tesseract.set_image_from_mem(bottom_data).is_ok() {
                     if let Ok(crop_text) = crop_tesseract.get_utf8_text() {
                         // Try to find total in the bottom crop
-                        if let Some(total) = self.find_total_in_text(&crop_text) {
+                        if let Some(total) = self.find_total_in_text(&crop_text, currency) {
                             return Some(total);
                         }
                     }
@@ -212,20 +215,20 @@ impl OcrProcessor {
         }
         
         // Fallback to full text
-        self.find_total_in_text(text)
+        self.find_total_in_text(text, currency)
     }
     
-    fn find_total_in_text(&self, text: &str) -> Option<f64> {
-        // Look for total amount patterns in Thai receipts
+    fn find_total_in_text(&self, text: &str, currency: Currency) -> Option<Money> {
+        // Look for total amount patterns in Thai and foreign receipts
         let total_indicators = [
-            "total", "รวม", "ทั้งหมด", "รวมทั้งสิ้น", "รวมเงิน", "จำนวนเงิน", "ยอดรวม", "ยอดเงิน"
+            "total", "รวม", "ทั้งหมด", "รวมทั้งสิ้น", "รวมเงิน", "จำนวนเงิน", "ยอดรวม", "ยอดเงิน", "合計"
         ];
         
-        // Thai currency symbols and formats
+        // Currency symbols and ISO codes before or after the amount
         let currency_patterns = [
-            r"(?:฿|บาท|บ\.|THB)\s*(\d+(?:[,.]\d{1,3})*(?:\.\d{1,2})?)",  // ฿100.00, 100.00บาท
-            r"(\d+(?:[,.]\d{1,3})*(?:\.\d{1,2})?)\s*(?:฿|บาท|บ\.)",      // 100.00฿, 100.00 บาท
-            r"(?:total|รวม|ทั้งหมด|รวมทั้งสิ้น|รวมเงิน|จำนวนเงิน|ยอดรวม|ยอดเงิน)[^\d]*(\d+(?:[,.]\d{1,3})*(?:\.\d{1,2})?)" // total: 100.00
+            r"(?:฿|บาท|บ\.|[$€£¥₩₫₱₹]|RM|Rp|\b[A-Z]{3}\b)\s*(\d+(?:[,.]\d{1,3})*(?:\.\d{1,2})?)",  // ฿100.00, US$ 12.50, EUR 9.90
+            r"(\d+(?:[,.]\d{1,3})*(?:\.\d{1,2})?)\s*(?:฿|บาท|บ\.|[€¥₩₫円元]|\b[A-Z]{3}\b)",      // 100.00฿, 100.00 บาท, 1200円
+            r"(?:total|รวม|ทั้งหมด|รวมทั้งสิ้น|รวมเงิน|จำนวนเงิน|ยอดรวม|ยอดเงิน|合計)[^\d]*(\d+(?:[,.]\d{1,3})*(?:\.\d{1,2})?)" // total: 100.00
         ];
         
         // First look for lines with total indicators
@@ -240,15 +243,10 @@ impl OcrProcessor {
                 // Try each currency pattern
                 for pattern in &currency_patterns {
                     if let Ok(regex) = Regex::new(pattern) {
-                        if let Some(cap) = regex.captures(&line) {
+                        if let Some(cap) = regex.captures(line) {
                             if let Some(amount_str) = cap.get(1) {
-                                // Clean up the amount string (remove commas, spaces)
-                                let clean_amount = amount_str.as_str()
-                                    .replace(",", "")
-                                    .replace(" ", "");
-                                
-                                // Parse as float
-                                if let Ok(amount) = clean_amount.parse::<f64>() {
+                                // Parse exactly; commas and spaces are dropped by Money::parse
+                                if let Some(amount) = Money::parse(amount_str.as_str(), currency) {
                                     return Some(amount);
                                 }
                             }
@@ -262,13 +260,9 @@ impl OcrProcessor {
         for line in text.lines() {
             for pattern in &currency_patterns {
                 if let Ok(regex) = Regex::new(pattern) {
-                    if let Some(cap) = regex.captures(&line) {
+                    if let Some(cap) = regex.captures(line) {
                         if let Some(amount_str) = cap.get(1) {
-                            let clean_amount = amount_str.as_str()
-                                .replace(",", "")
-                                .replace(" ", "");
-                            
-                            if let Ok(amount) = clean_amount.parse::<f64>() {
+                            if let Some(amount) = Money::parse(amount_str.as_str(), currency) {
                                 return Some(amount);
                             }
                         }
@@ -284,7 +278,7 @@ impl OcrProcessor {
         // First try using the top part of the receipt for merchant name
         if let Some(top_data) = &self.top_crop {
             if let Ok(mut crop_tesseract) = LepTess::new(None, "eng+tha") {
-                if crop_tesseract.set_image_from_mem(&top_data).is_ok() {
+                if crop_tesseract.set_image_from_mem(top_data).is_ok() {
                     if let Ok(crop_text) = crop_tesseract.get_utf8_text() {
                         // Try to find merchant in the top crop
                         if let Some(merchant) = self.find_merchant_in_text(&crop_text) {
@@ -299,11 +293,38 @@ impl OcrProcessor {
         self.find_merchant_in_text(text)
     }
     
+    fn find_tax_id_in_text(&self, text: &str) -> Option<String> {
+        // Thai tax IDs are 13 digits, often printed with dashes or spaces (0-1055-36000-46-3)
+        let tax_id_regex = Regex::new(r"\d(?:[\s-]?\d){12}").ok()?;
+        let tax_id_indicators = [
+            "เลขประจำตัวผู้เสียภาษี", "เลขที่ผู้เสียภาษี", "ผู้เสียภาษี", "tax id", "taxid", "tax no"
+        ];
+        
+        let candidates = |line: &str| -> Option<String> {
+            tax_id_regex.find_iter(line)
+                .map(|m| m.as_str().chars().filter(|c| c.is_ascii_digit()).collect::<String>())
+                .find(|digits| digits.len() == 13)
+        };
+        
+        // Prefer lines that are labelled as a tax ID
+        for line in text.lines() {
+            let line_lower = line.to_lowercase();
+            if tax_id_indicators.iter().any(|&indicator| line_lower.contains(indicator)) {
+                if let Some(tax_id) = candidates(line) {
+                    return Some(tax_id);
+                }
+            }
+        }
+        
+        // Fallback: any standalone 13-digit number
+        text.lines().find_map(candidates)
+    }
+    
     fn extract_date(&self, text: &str) -> Option<String> {
         // First try using the top part of the receipt for date
         if let Some(top_data) = &self.top_crop {
             if let Ok(mut crop_tesseract) = LepTess::new(None, "eng+tha") {
-                if crop_tesseract.set_image_from_mem(&top_data).is_ok() {
+                if crop_tesseract.set_image_from_mem(top_data).is_ok() {
                     if let Ok(crop_text) = crop_tesseract.get_utf8_text() {
                         // Try to find date in the top crop
                         if let Some(date) = self.find_date_in_text(&crop_text) {
@@ -387,7 +408,7 @@ impl OcrProcessor {
         None
     }
     
-    fn extract_items(&self, text: &str) -> Vec<ItemData> {
+    fn extract_items(&self, text: &str, currency: Currency) -> Vec<ItemData> {
         let mut items = Vec::new();
         
         // This is a simplified approach - in reality, we'd need more sophisticated parsing
@@ -406,12 +427,12 @@ impl OcrProcessor {
                             // We have both quantity and price
                             let qty = cap.get(2)
                                 .and_then(|q| q.as_str().parse::<u32>().ok());
-                            let prc = price_match.as_str().parse::<f64>().ok();
+                            let prc = Money::parse(price_match.as_str(), currency);
                             (qty, prc)
                         } else {
                             // Just a price in group 2
                             let prc = cap.get(2)
-                                .and_then(|p| p.as_str().parse::<f64>().ok());
+                                .and_then(|p| Money::parse(p.as_str(), currency));
                             (Some(1), prc)
                         };
                         
@@ -441,7 +462,7 @@ impl OcrProcessor {
         let start = std::time::Instant::now();
         
         // Read image file to bytes
-        let img_bytes = fs::read(image_path).map_err(|e| AppError::IoError(e))?;
+        let img_bytes = fs::read(image_path).map_err(AppError::IoError)?;
         
         // Set up the Vision API request
         let client = reqwest::Client::new();
@@ -522,7 +543,7 @@ impl OcrProcessor {
     // Add a hybrid processing method that uses Tesseract first, then Google Vision if confidence is low
     pub async fn process_image_hybrid(&mut self, image_path: &Path) -> Result<OcrResult, AppError> {
         // First try with Tesseract
-        let img_bytes = fs::read(image_path).map_err(|e| AppError::IoError(e))?;
+        let img_bytes = fs::read(image_path).map_err(AppError::IoError)?;
         let tesseract_data = self.process_image(&img_bytes)?;
         
         // Create OcrResult from ExtractedData
@@ -554,15 +575,19 @@ impl OcrProcessor {
     
     pub fn process_google_vision(&self, text: &str, confidence: f32) -> Result<ExtractedData, AppError> {
         // Extract data from the Google Vision API text
-        let total = self.find_total_in_text(text);
+        let currency = self.find_currency_in_text(text).unwrap_or_default();
+        let total = self.find_total_in_text(text, currency);
         let date = self.find_date_in_text(text);
         let merchant = self.find_merchant_in_text(text);
-        let items = self.extract_items(text);
+        let tax_id = self.find_tax_id_in_text(text);
+        let items = self.extract_items(text, currency);
         
         Ok(ExtractedData {
             total,
+            currency,
             date,
             merchant,
+            tax_id,
             items,
             confidence,
             ocr_source: "google_vision".to_string(),
@@ -612,7 +637,7 @@ impl OcrProcessor {
             if !line_trimmed.is_empty() && 
                !line_trimmed.contains("/") && 
                !line_trimmed.contains(":") && 
-               !line_trimmed.chars().all(|c| c.is_digit(10) || c == '.' || c == ',' || c == '-') {
+               !line_trimmed.chars().all(|c| c.is_ascii_digit() || c == '.' || c == ',' || c == '-') {
                 return Some(line_trimmed.to_string());
             }
         }
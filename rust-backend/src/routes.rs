@@ -1,5 +1,5 @@
 use actix_web::web;
-use crate::handlers::{auth, ocr, transactions, users};
+use crate::handlers::{accounts, audit, auth, backups, bills, budgets, categories, dashboard, exchange_rates, exports, imports, ledgers, merchants, ocr, reconciliation, recurring, reports, rules, tags, transactions, trash, users};
 
 pub fn configure(cfg: &mut web::ServiceConfig) {
     cfg.service(
@@ -14,18 +14,161 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
                     .route("/process", web::post().to(ocr::process_image))
                     .route("/process/engine", web::post().to(ocr::process_image_with_engine))
             )
+            .service(
+                web::scope("/bills")
+                    .route("", web::get().to(bills::get_bills))
+                    .route("/{id}", web::delete().to(bills::delete_bill))
+            )
+            .route("/dashboard", web::get().to(dashboard::get_dashboard))
             .service(
                 web::scope("/transactions")
                     .route("", web::get().to(transactions::get_transactions))
                     .route("", web::post().to(transactions::create_transaction))
+                    .route("/search", web::get().to(transactions::search_transactions))
+                    .route("/bulk", web::post().to(transactions::bulk_operation))
+                    .route("/bulk/{token}/undo", web::post().to(transactions::undo_bulk_operation))
+                    .route("/export/{format}", web::get().to(exports::export_transactions))
                     .route("/{id}", web::get().to(transactions::get_transaction))
                     .route("/{id}", web::put().to(transactions::update_transaction))
                     .route("/{id}", web::delete().to(transactions::delete_transaction))
+                    .route("/{id}/image", web::get().to(transactions::get_transaction_image))
+                    .route("/{id}/splits", web::put().to(transactions::set_splits))
+                    .route("/{id}/splits", web::delete().to(transactions::delete_splits))
             )
             .service(
                 web::scope("/categories")
-                    .route("", web::get().to(transactions::get_categories))
+                    .route("", web::get().to(categories::get_categories))
+                    .route("/suggest", web::post().to(categories::suggest_categories))
+                    .route("/suggest/feedback", web::post().to(categories::category_feedback))
+                    .route("/{id}", web::delete().to(categories::delete_category))
+            )
+            .service(
+                web::scope("/merchants")
+                    .route("", web::get().to(merchants::get_merchants))
+                    .route("", web::post().to(merchants::create_merchant))
+                    .route("/resolve", web::get().to(merchants::resolve_merchant))
+                    .route("/{id}", web::get().to(merchants::get_merchant))
+                    .route("/{id}", web::put().to(merchants::update_merchant))
+                    .route("/{id}", web::delete().to(merchants::delete_merchant))
+                    .route("/{id}/merge", web::post().to(merchants::merge_merchants))
+                    .route("/{id}/aliases", web::post().to(merchants::add_alias))
+                    .route("/{id}/aliases/{alias_id}", web::delete().to(merchants::delete_alias))
+            )
+            .service(
+                web::scope("/rules")
+                    .route("", web::get().to(rules::get_rules))
+                    .route("", web::post().to(rules::create_rule))
+                    .route("/order", web::put().to(rules::reorder_rules))
+                    .route("/dry-run", web::post().to(rules::dry_run_draft))
+                    .route("/{id}", web::get().to(rules::get_rule))
+                    .route("/{id}", web::put().to(rules::update_rule))
+                    .route("/{id}", web::delete().to(rules::delete_rule))
+                    .route("/{id}/dry-run", web::post().to(rules::dry_run_rule))
+                    .route("/{id}/apply", web::post().to(rules::apply_rule))
+            )
+            .service(
+                web::scope("/tags")
+                    .route("", web::get().to(tags::get_tags))
+                    .route("", web::post().to(tags::create_tag))
+                    .route("/bulk", web::post().to(tags::bulk_tag))
+                    .route("/{id}", web::put().to(tags::update_tag))
+                    .route("/{id}", web::delete().to(tags::delete_tag))
+                    .route("/{id}/merge", web::post().to(tags::merge_tags))
+            )
+            .service(
+                web::scope("/budgets")
+                    .route("", web::get().to(budgets::get_budgets))
+                    .route("", web::post().to(budgets::create_budget))
+                    .route("/status", web::get().to(budgets::budget_status))
+                    .route("/{id}", web::get().to(budgets::get_budget))
+                    .route("/{id}", web::put().to(budgets::update_budget))
+                    .route("/{id}", web::delete().to(budgets::delete_budget))
+            )
+            .service(
+                web::scope("/recurring")
+                    .route("", web::get().to(recurring::get_recurring))
+                    .route("", web::post().to(recurring::create_recurring))
+                    .route("/upcoming", web::get().to(recurring::get_upcoming))
+                    .route("/subscriptions", web::get().to(recurring::detect_subscriptions))
+                    .route("/{id}", web::get().to(recurring::get_recurring_by_id))
+                    .route("/{id}", web::put().to(recurring::update_recurring))
+                    .route("/{id}", web::delete().to(recurring::delete_recurring))
+            )
+            .service(
+                web::scope("/accounts")
+                    .route("", web::get().to(accounts::get_accounts))
+                    .route("", web::post().to(accounts::create_account))
+                    .route("/transfers", web::post().to(accounts::create_transfer))
+                    .route("/{id}", web::get().to(accounts::get_account))
+                    .route("/{id}", web::put().to(accounts::update_account))
+                    .route("/{id}", web::delete().to(accounts::delete_account))
+                    .route("/{id}/balance", web::get().to(accounts::get_account_balance))
+            )
+            .service(
+                web::scope("/imports")
+                    .route("", web::get().to(imports::get_imports))
+                    .route("/profiles", web::get().to(imports::get_profiles))
+                    .route("/profiles", web::post().to(imports::create_profile))
+                    .route("/profiles/{id}", web::put().to(imports::update_profile))
+                    .route("/profiles/{id}", web::delete().to(imports::delete_profile))
+                    .route("/{format}", web::post().to(imports::import_statement))
+                    .route("/{format}/preview", web::post().to(imports::preview_statement))
+                    .route("/{id}", web::delete().to(imports::rollback_import))
+            )
+            .service(
+                web::scope("/reconciliation")
+                    .route("/run", web::post().to(reconciliation::run_reconciliation))
+                    .route("/matches", web::get().to(reconciliation::get_matches))
+                    .route("/matches/{id}/confirm", web::post().to(reconciliation::confirm_match))
+                    .route("/matches/{id}/reject", web::post().to(reconciliation::reject_match))
+            )
+            .service(
+                web::scope("/exchange-rates")
+                    .route("", web::get().to(exchange_rates::get_exchange_rates))
+                    .route("", web::post().to(exchange_rates::create_exchange_rate))
+                    .route("/import", web::post().to(exchange_rates::import_exchange_rates))
+                    .route("/{id}", web::delete().to(exchange_rates::delete_exchange_rate))
+            )
+            .route("/ledger-accounts", web::get().to(exports::get_ledger_accounts))
+            .route("/ledger-accounts", web::put().to(exports::set_ledger_accounts))
+            .service(
+                web::scope("/reports")
+                    .route("/spending-by-category", web::get().to(reports::spending_by_category))
+                    .route("/spending-by-tag", web::get().to(reports::spending_by_tag))
+                    .route("/monthly-spending", web::get().to(reports::monthly_spending))
+                    .route("/transaction-trends", web::get().to(reports::transaction_trends))
+                    .route("/cash-flow", web::get().to(reports::cash_flow))
+            )
+            .service(
+                web::scope("/trash")
+                    .route("", web::get().to(trash::get_trash))
+                    .route("/{entity_type}/{id}/restore", web::post().to(trash::restore_from_trash))
+            )
+            .service(
+                web::scope("/audit")
+                    .route("/entries/{id}/restore", web::post().to(audit::restore_version))
+                    .route("/{entity_type}/{id}", web::get().to(audit::get_history))
+            )
+            .service(
+                web::scope("/backup")
+                    .route("", web::get().to(backups::download_backup))
+                    .route("/restore", web::post().to(backups::restore_backup))
+            )
+            .service(
+                web::scope("/ledgers")
+                    .route("", web::get().to(ledgers::get_ledgers))
+                    .route("", web::post().to(ledgers::create_ledger))
+                    .route("/{id}", web::get().to(ledgers::get_ledger))
+                    .route("/{id}", web::put().to(ledgers::update_ledger))
+                    .route("/{id}", web::delete().to(ledgers::delete_ledger))
+                    .route("/{id}/members", web::get().to(ledgers::get_members))
+                    .route("/{id}/members/{user_id}", web::put().to(ledgers::set_member_role))
+                    .route("/{id}/members/{user_id}", web::delete().to(ledgers::remove_member))
+                    .route("/{id}/invitations", web::get().to(ledgers::get_invitations))
+                    .route("/{id}/invitations", web::post().to(ledgers::create_invitation))
+                    .route("/{id}/invitations/{invitation_id}", web::delete().to(ledgers::revoke_invitation))
             )
+            .route("/invitations/{token}/accept", web::post().to(ledgers::accept_invitation))
             .service(
                 web::scope("/users")
                     .route("/profile", web::get().to(users::get_profile))
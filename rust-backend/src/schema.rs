@@ -8,6 +8,7 @@ diesel::table! {
         password_hash -> Varchar,
         created_at -> Timestamptz,
         updated_at -> Timestamptz,
+        base_currency -> Varchar,
     }
 }
 
@@ -18,12 +19,15 @@ diesel::table! {
         description -> Nullable<Text>,
         color -> Nullable<Varchar>,
         icon -> Nullable<Varchar>,
-        user_id -> Uuid,
+        ledger_id -> Uuid,
         created_at -> Timestamptz,
         updated_at -> Timestamptz,
+        kind -> Varchar,
+        deleted_at -> Nullable<Timestamptz>,
     }
 }
 
+// search_text and search_vector are kept up to date by triggers and only read from SQL
 diesel::table! {
     transactions (id) {
         id -> Uuid,
@@ -34,14 +38,387 @@ diesel::table! {
         notes -> Nullable<Text>,
         items -> Nullable<Jsonb>,
         image_path -> Nullable<Varchar>,
+        ledger_id -> Uuid,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        merchant_id -> Nullable<Uuid>,
+        excluded -> Bool,
+        source -> Varchar,
+        recurring_id -> Nullable<Uuid>,
+        currency -> Varchar,
+        account_id -> Nullable<Uuid>,
+        direction -> Varchar,
+        transfer_id -> Nullable<Uuid>,
+        transaction_type -> Varchar,
+        refund_of -> Nullable<Uuid>,
+        import_batch_id -> Nullable<Uuid>,
+        external_id -> Nullable<Varchar>,
+        value_date -> Nullable<Date>,
+        bank_reference -> Nullable<Varchar>,
+        reconciled -> Bool,
+        deleted_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    merchants (id) {
+        id -> Uuid,
+        ledger_id -> Uuid,
+        name -> Varchar,
+        normalized_name -> Varchar,
+        tax_id -> Nullable<Varchar>,
+        default_category_id -> Nullable<Uuid>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    merchant_aliases (id) {
+        id -> Uuid,
+        merchant_id -> Uuid,
+        alias -> Varchar,
+        normalized_alias -> Varchar,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    category_keywords (id) {
+        id -> Uuid,
+        ledger_id -> Uuid,
+        keyword -> Varchar,
+        category_id -> Uuid,
+        weight -> Float4,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    rules (id) {
+        id -> Uuid,
+        ledger_id -> Uuid,
+        name -> Varchar,
+        position -> Int4,
+        enabled -> Bool,
+        match_mode -> Varchar,
+        stop_processing -> Bool,
+        conditions -> Jsonb,
+        actions -> Jsonb,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    budgets (id) {
+        id -> Uuid,
+        ledger_id -> Uuid,
+        category_id -> Uuid,
+        amount -> Numeric,
+        period -> Varchar,
+        start_date -> Date,
+        end_date -> Nullable<Date>,
+        rollover -> Bool,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    recurring_transactions (id) {
+        id -> Uuid,
+        ledger_id -> Uuid,
+        name -> Varchar,
+        merchant -> Varchar,
+        merchant_id -> Nullable<Uuid>,
+        amount -> Numeric,
+        category_id -> Nullable<Uuid>,
+        notes -> Nullable<Text>,
+        tags -> Array<Text>,
+        frequency -> Varchar,
+        interval_count -> Int4,
+        day_of_month -> Nullable<Int4>,
+        start_date -> Date,
+        end_date -> Nullable<Date>,
+        next_run -> Date,
+        last_run -> Nullable<Date>,
+        active -> Bool,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        currency -> Varchar,
+    }
+}
+
+diesel::table! {
+    bills (id) {
+        id -> Uuid,
+        ledger_id -> Uuid,
+        file_path -> Varchar,
+        file_name -> Varchar,
+        file_size -> Int8,
+        file_type -> Varchar,
+        ocr_text -> Nullable<Text>,
+        ocr_confidence -> Nullable<Float4>,
+        extracted_data -> Nullable<Jsonb>,
+        transaction_id -> Nullable<Uuid>,
+        status -> Varchar,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        deleted_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    exchange_rates (id) {
+        id -> Uuid,
+        ledger_id -> Uuid,
+        from_currency -> Varchar,
+        to_currency -> Varchar,
+        rate -> Numeric,
+        rate_date -> Date,
+        source -> Varchar,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    accounts (id) {
+        id -> Uuid,
+        ledger_id -> Uuid,
+        name -> Varchar,
+        account_type -> Varchar,
+        currency -> Varchar,
+        opening_balance -> Numeric,
+        institution -> Nullable<Varchar>,
+        archived -> Bool,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    transaction_splits (id) {
+        id -> Uuid,
+        transaction_id -> Uuid,
+        ledger_id -> Uuid,
+        category_id -> Nullable<Uuid>,
+        amount -> Numeric,
+        item_indexes -> Array<Int4>,
+        notes -> Nullable<Text>,
+        position -> Int4,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+// A view: one row per split, or per transaction for transactions that aren't split
+diesel::table! {
+    transaction_allocations (id) {
+        id -> Uuid,
+        transaction_id -> Uuid,
+        ledger_id -> Uuid,
+        category_id -> Nullable<Uuid>,
+        amount -> Numeric,
+        currency -> Varchar,
+        date -> Timestamptz,
+        transaction_type -> Varchar,
+        excluded -> Bool,
+    }
+}
+
+diesel::table! {
+    tags (id) {
+        id -> Uuid,
+        ledger_id -> Uuid,
+        name -> Varchar,
+        color -> Nullable<Varchar>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    transaction_tags (transaction_id, tag_id) {
+        transaction_id -> Uuid,
+        tag_id -> Uuid,
+    }
+}
+
+diesel::table! {
+    split_tags (split_id, tag_id) {
+        split_id -> Uuid,
+        tag_id -> Uuid,
+    }
+}
+
+diesel::table! {
+    bulk_operations (id) {
+        id -> Uuid,
+        ledger_id -> Uuid,
+        operation -> Jsonb,
+        transaction_ids -> Array<Uuid>,
+        snapshot -> Jsonb,
+        applied_at -> Timestamptz,
+        undone_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    import_profiles (id) {
+        id -> Uuid,
+        ledger_id -> Uuid,
+        name -> Varchar,
+        account_id -> Nullable<Uuid>,
+        mapping -> Jsonb,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    import_batches (id) {
+        id -> Uuid,
+        ledger_id -> Uuid,
+        profile_id -> Nullable<Uuid>,
+        account_id -> Nullable<Uuid>,
+        format -> Varchar,
+        file_name -> Varchar,
+        row_count -> Int4,
+        imported_count -> Int4,
+        duplicate_count -> Int4,
+        error_count -> Int4,
+        created_at -> Timestamptz,
+        rolled_back_at -> Nullable<Timestamptz>,
+        statement_account -> Nullable<Varchar>,
+    }
+}
+
+diesel::table! {
+    ledger_accounts (id) {
+        id -> Uuid,
+        ledger_id -> Uuid,
+        category_id -> Nullable<Uuid>,
+        account_id -> Nullable<Uuid>,
+        name -> Varchar,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    reconciliation_matches (id) {
+        id -> Uuid,
+        ledger_id -> Uuid,
+        receipt_transaction_id -> Uuid,
+        bank_transaction_id -> Uuid,
+        score -> Float4,
+        amount_difference -> Numeric,
+        days_apart -> Int4,
+        merchant_similarity -> Float4,
+        status -> Varchar,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+// Written only by the audit triggers; updates and deletes are refused
+diesel::table! {
+    audit_log (id) {
+        id -> Int8,
+        occurred_at -> Timestamptz,
+        actor_id -> Nullable<Uuid>,
+        owner_id -> Nullable<Uuid>,
+        action -> Varchar,
+        entity_type -> Varchar,
+        entity_id -> Uuid,
+        old_values -> Nullable<Jsonb>,
+        new_values -> Nullable<Jsonb>,
+        request -> Nullable<Jsonb>,
+    }
+}
+
+// Every data table's ledger_id points here; a user's personal ledger has the user's id
+diesel::table! {
+    ledgers (id) {
+        id -> Uuid,
+        name -> Varchar,
+        base_currency -> Varchar,
+        created_by -> Uuid,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    ledger_members (ledger_id, user_id) {
+        ledger_id -> Uuid,
         user_id -> Uuid,
+        role -> Varchar,
         created_at -> Timestamptz,
         updated_at -> Timestamptz,
     }
 }
 
+diesel::table! {
+    ledger_invitations (id) {
+        id -> Uuid,
+        ledger_id -> Uuid,
+        email -> Varchar,
+        role -> Varchar,
+        token_hash -> Varchar,
+        invited_by -> Nullable<Uuid>,
+        created_at -> Timestamptz,
+        expires_at -> Timestamptz,
+        accepted_at -> Nullable<Timestamptz>,
+        accepted_by -> Nullable<Uuid>,
+        revoked_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::joinable!(merchant_aliases -> merchants (merchant_id));
+diesel::joinable!(category_keywords -> categories (category_id));
+diesel::joinable!(budgets -> categories (category_id));
+diesel::joinable!(bills -> transactions (transaction_id));
+diesel::joinable!(transactions -> accounts (account_id));
+diesel::joinable!(transaction_splits -> transactions (transaction_id));
+diesel::joinable!(transaction_tags -> transactions (transaction_id));
+diesel::joinable!(transaction_tags -> tags (tag_id));
+diesel::joinable!(split_tags -> transaction_splits (split_id));
+diesel::joinable!(split_tags -> tags (tag_id));
+diesel::joinable!(transactions -> import_batches (import_batch_id));
+diesel::joinable!(ledger_members -> ledgers (ledger_id));
+diesel::joinable!(ledger_members -> users (user_id));
+diesel::joinable!(ledger_invitations -> ledgers (ledger_id));
+
 diesel::allow_tables_to_appear_in_same_query!(
     users,
     categories,
     transactions,
-); 
\ No newline at end of file
+    merchants,
+    merchant_aliases,
+    category_keywords,
+    rules,
+    budgets,
+    recurring_transactions,
+    bills,
+    exchange_rates,
+    accounts,
+    transaction_splits,
+    transaction_allocations,
+    tags,
+    transaction_tags,
+    split_tags,
+    bulk_operations,
+    import_profiles,
+    import_batches,
+    ledger_accounts,
+    reconciliation_matches,
+    audit_log,
+    ledgers,
+    ledger_members,
+    ledger_invitations,
+);
+ 
\ No newline at end of file
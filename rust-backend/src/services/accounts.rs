@@ -0,0 +1,296 @@
+use std::collections::HashMap;
+
+use bigdecimal::{BigDecimal, Zero};
+use chrono::{DateTime, Utc};
+use diesel::dsl::sum;
+use diesel::prelude::*;
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use diesel::PgConnection;
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::models::account::{
+    AccountBalance, AccountResponse, BalanceEntry, CreateTransferDto, DbAccount, TransferResponse,
+};
+use crate::models::transaction::{DbTransaction, Direction, NewTransaction, TransactionType, SOURCE_MANUAL};
+use crate::money::{Currency, Money};
+use crate::schema::{accounts, transactions};
+use crate::services::transactions::to_responses;
+
+pub fn find_account(conn: &mut PgConnection, ledger_id: Uuid, account_id: Uuid) -> Result<DbAccount, AppError> {
+    accounts::table
+        .filter(accounts::id.eq(account_id))
+        .filter(accounts::ledger_id.eq(ledger_id))
+        .first::<DbAccount>(conn)
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("Account {} not found", account_id)))
+}
+
+// Account names are unique per user, ignoring case
+pub fn ensure_name_available(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    name: &str,
+    except: Option<Uuid>,
+) -> Result<(), AppError> {
+    let taken = accounts::table
+        .filter(accounts::ledger_id.eq(ledger_id))
+        .select((accounts::id, accounts::name))
+        .load::<(Uuid, String)>(conn)?
+        .into_iter()
+        .any(|(id, existing)| Some(id) != except && existing.to_lowercase() == name.to_lowercase());
+    if taken {
+        return Err(AppError::BadRequest(format!("Account already exists: {}", name)));
+    }
+    Ok(())
+}
+
+// Another request can take the name between the check above and the write; the unique index
+// then turns it away with the same error
+pub fn name_error(e: DieselError, name: &str) -> AppError {
+    match e {
+        DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => {
+            AppError::BadRequest(format!("Account already exists: {}", name))
+        }
+        e => AppError::DbError(e),
+    }
+}
+
+// The account a transaction is recorded against; it must be open and in the transaction's currency
+fn open_account(conn: &mut PgConnection, ledger_id: Uuid, account_id: Uuid) -> Result<DbAccount, AppError> {
+    let account = find_account(conn, ledger_id, account_id)?;
+    if account.archived {
+        return Err(AppError::BadRequest(format!("Account {} is archived", account.name)));
+    }
+    Ok(account)
+}
+
+pub fn account_for_transaction(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    account_id: Uuid,
+    currency: &str,
+) -> Result<DbAccount, AppError> {
+    let account = open_account(conn, ledger_id, account_id)?;
+    if account.currency != currency {
+        return Err(AppError::BadRequest(format!(
+            "Account {} is in {}, not {}",
+            account.name, account.currency, currency
+        )));
+    }
+    Ok(account)
+}
+
+fn signed(direction: &str, amount: &BigDecimal) -> BigDecimal {
+    if direction == Direction::Income.as_str() {
+        amount.clone()
+    } else {
+        -amount
+    }
+}
+
+// Income minus expenses per account, optionally only for transactions before `before`
+pub fn net_by_account(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    account_ids: &[Uuid],
+    before: Option<DateTime<Utc>>,
+) -> Result<HashMap<Uuid, BigDecimal>, AppError> {
+    let mut query = transactions::table
+        .filter(transactions::ledger_id.eq(ledger_id))
+        .filter(transactions::account_id.eq_any(account_ids))
+        .filter(transactions::deleted_at.is_null())
+        .group_by((transactions::account_id, transactions::direction))
+        .select((transactions::account_id, transactions::direction, sum(transactions::amount)))
+        .into_boxed();
+    if let Some(before) = before {
+        query = query.filter(transactions::date.lt(before));
+    }
+
+    let rows = query.load::<(Option<Uuid>, String, Option<BigDecimal>)>(conn)?;
+
+    let mut totals: HashMap<Uuid, BigDecimal> = HashMap::new();
+    for (account_id, direction, amount) in rows {
+        if let (Some(account_id), Some(amount)) = (account_id, amount) {
+            *totals.entry(account_id).or_insert_with(BigDecimal::zero) += signed(&direction, &amount);
+        }
+    }
+    Ok(totals)
+}
+
+pub fn to_responses_with_balances(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    accounts: Vec<DbAccount>,
+) -> Result<Vec<AccountResponse>, AppError> {
+    let account_ids: Vec<Uuid> = accounts.iter().map(|a| a.id).collect();
+    let mut net = net_by_account(conn, ledger_id, &account_ids, None)?;
+
+    Ok(accounts
+        .into_iter()
+        .map(|account| {
+            let account_net = net.remove(&account.id).unwrap_or_else(BigDecimal::zero);
+            AccountResponse::new(account, account_net)
+        })
+        .collect())
+}
+
+// Every transaction on the account in [start, end] with the running balance after each one
+pub fn account_balance(
+    conn: &mut PgConnection,
+    account: DbAccount,
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+) -> Result<AccountBalance, AppError> {
+    let currency = Currency::from_code(&account.currency);
+    let before_start = match start {
+        Some(start) => net_by_account(conn, account.ledger_id, &[account.id], Some(start))?
+            .remove(&account.id)
+            .unwrap_or_else(BigDecimal::zero),
+        None => BigDecimal::zero(),
+    };
+
+    let mut query = transactions::table
+        .filter(transactions::ledger_id.eq(account.ledger_id))
+        .filter(transactions::account_id.eq(account.id))
+        .filter(transactions::deleted_at.is_null())
+        .into_boxed();
+    if let Some(start) = start {
+        query = query.filter(transactions::date.ge(start));
+    }
+    if let Some(end) = end {
+        query = query.filter(transactions::date.le(end));
+    }
+    let rows = query
+        .order((transactions::date.asc(), transactions::created_at.asc(), transactions::id.asc()))
+        .load::<DbTransaction>(conn)?;
+
+    let starting_balance = &account.opening_balance + before_start;
+    let mut balance = starting_balance.clone();
+    let entries: Vec<BalanceEntry> = rows
+        .into_iter()
+        .map(|row| {
+            balance += signed(&row.direction, &row.amount);
+            BalanceEntry {
+                transaction_id: row.id,
+                date: row.date,
+                merchant: row.merchant,
+                direction: row.direction,
+                amount: Money::new(row.amount, currency),
+                balance: Money::new(balance.clone(), currency),
+                transfer_id: row.transfer_id,
+            }
+        })
+        .collect();
+
+    let account_net = net_by_account(conn, account.ledger_id, &[account.id], None)?
+        .remove(&account.id)
+        .unwrap_or_else(BigDecimal::zero);
+    Ok(AccountBalance {
+        account: AccountResponse::new(account, account_net),
+        starting_balance: Money::new(starting_balance, currency),
+        ending_balance: Money::new(balance, currency),
+        entries,
+    })
+}
+
+fn transfer_leg(
+    ledger_id: Uuid,
+    account: &DbAccount,
+    direction: Direction,
+    amount: &Money,
+    merchant: String,
+    transfer: &CreateTransferDto,
+    transfer_id: Uuid,
+) -> NewTransaction {
+    NewTransaction {
+        id: Uuid::new_v4(),
+        amount: amount.amount().clone(),
+        date: transfer.date,
+        merchant,
+        category_id: None,
+        notes: transfer.notes.clone(),
+        items: None,
+        image_path: None,
+        ledger_id,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+        merchant_id: None,
+        excluded: false,
+        source: SOURCE_MANUAL.to_string(),
+        recurring_id: None,
+        currency: amount.currency().to_string(),
+        account_id: Some(account.id),
+        direction: direction.as_str().to_string(),
+        transfer_id: Some(transfer_id),
+        transaction_type: TransactionType::Transfer.as_str().to_string(),
+        refund_of: None,
+        import_batch_id: None,
+        external_id: None,
+        value_date: None,
+        bank_reference: None,
+    }
+}
+
+// Record a transfer as an expense on one account and income on the other, linked by a transfer ID
+pub fn create_transfer(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    transfer: &CreateTransferDto,
+) -> Result<TransferResponse, AppError> {
+    if transfer.from_account_id == transfer.to_account_id {
+        return Err(AppError::BadRequest("A transfer needs two different accounts".to_string()));
+    }
+
+    let from = open_account(conn, ledger_id, transfer.from_account_id)?;
+    let to = open_account(conn, ledger_id, transfer.to_account_id)?;
+    let amount = Money::new(transfer.amount.clone(), Currency::from_code(&from.currency));
+    if amount.amount() <= &BigDecimal::zero() {
+        return Err(AppError::BadRequest("Transfer amount must be greater than zero".to_string()));
+    }
+    let to_amount = match &transfer.to_amount {
+        Some(to_amount) => Money::new(to_amount.clone(), Currency::from_code(&to.currency)),
+        None if from.currency == to.currency => amount.clone(),
+        None => {
+            return Err(AppError::BadRequest(
+                "Transfers between currencies need the amount received".to_string(),
+            ))
+        }
+    };
+    if to_amount.amount() <= &BigDecimal::zero() {
+        return Err(AppError::BadRequest("The amount received must be greater than zero".to_string()));
+    }
+
+    let transfer_id = Uuid::new_v4();
+    let legs = vec![
+        transfer_leg(
+            ledger_id,
+            &from,
+            Direction::Expense,
+            &amount,
+            format!("Transfer to {}", to.name),
+            transfer,
+            transfer_id,
+        ),
+        transfer_leg(
+            ledger_id,
+            &to,
+            Direction::Income,
+            &to_amount,
+            format!("Transfer from {}", from.name),
+            transfer,
+            transfer_id,
+        ),
+    ];
+    let rows = diesel::insert_into(transactions::table)
+        .values(&legs)
+        .get_results::<DbTransaction>(conn)?;
+
+    let (mut outgoing, mut incoming): (Vec<_>, Vec<_>) = to_responses(conn, rows)?
+        .into_iter()
+        .partition(|leg| leg.direction == Direction::Expense.as_str());
+    match (outgoing.pop(), incoming.pop()) {
+        (Some(from), Some(to)) => Ok(TransferResponse { transfer_id, from, to }),
+        _ => Err(AppError::InternalServerError("Transfer legs were not saved".to_string())),
+    }
+}
@@ -0,0 +1,234 @@
+use diesel::prelude::*;
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use diesel::sql_query;
+use diesel::sql_types::{Jsonb, Uuid as SqlUuid};
+use diesel::PgConnection;
+use serde_json::{Map, Value as JsonValue};
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::models::audit::{AuditAction, AuditEntity, DbAuditEntry, RestoreVersionResponse};
+use crate::schema::{audit_log, transaction_splits};
+
+pub const DEFAULT_HISTORY_LIMIT: i64 = 100;
+pub const MAX_HISTORY_LIMIT: i64 = 1000;
+
+// Columns a restore leaves alone: the row's identity, and the password, which the log never holds
+const FIXED_COLUMNS: [&str; 5] = ["id", "ledger_id", "created_at", "updated_at", "password_hash"];
+
+#[derive(QueryableByName)]
+struct RowSnapshot {
+    #[diesel(sql_type = Jsonb)]
+    row: JsonValue,
+}
+
+// A row as it is now, shaped like the snapshots the audit triggers take
+fn current_row(
+    conn: &mut PgConnection,
+    entity: AuditEntity,
+    entity_id: Uuid,
+) -> Result<Option<Map<String, JsonValue>>, AppError> {
+    let snapshot = sql_query(format!(
+        "SELECT TO_JSONB(t) - 'search_text' - 'search_vector' - 'password_hash' AS row \
+         FROM {} t WHERE t.id = $1",
+        entity.as_str()
+    ))
+    .bind::<SqlUuid, _>(entity_id)
+    .get_result::<RowSnapshot>(conn)
+    .optional()?;
+    Ok(snapshot.and_then(|snapshot| match snapshot.row {
+        JsonValue::Object(row) => Some(row),
+        _ => None,
+    }))
+}
+
+fn owner_of(entity: AuditEntity, row: &Map<String, JsonValue>) -> Option<Uuid> {
+    let key = if entity == AuditEntity::Users { "id" } else { "ledger_id" };
+    row.get(key).and_then(JsonValue::as_str).and_then(|id| Uuid::parse_str(id).ok())
+}
+
+// Changes to one of the owner's rows, newest first
+pub fn history(
+    conn: &mut PgConnection,
+    owner_id: Uuid,
+    entity: AuditEntity,
+    entity_id: Uuid,
+    limit: Option<i64>,
+) -> Result<Vec<DbAuditEntry>, AppError> {
+    let limit = limit.unwrap_or(DEFAULT_HISTORY_LIMIT).clamp(1, MAX_HISTORY_LIMIT);
+    Ok(audit_log::table
+        .filter(audit_log::owner_id.eq(owner_id))
+        .filter(audit_log::entity_type.eq(entity.as_str()))
+        .filter(audit_log::entity_id.eq(entity_id))
+        .order(audit_log::id.desc())
+        .limit(limit)
+        .load::<DbAuditEntry>(conn)?)
+}
+
+// A foreign key or unique constraint the old version no longer satisfies, e.g. a category that
+// has since been deleted, is the caller's problem rather than the server's
+fn restore_error(e: DieselError) -> AppError {
+    let reason = match &e {
+        DieselError::DatabaseError(DatabaseErrorKind::ForeignKeyViolation, _) => {
+            "it refers to something that no longer exists"
+        }
+        DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => "it clashes with another row",
+        _ => return AppError::DbError(e),
+    };
+    AppError::BadRequest(format!("The version can't be restored because {}", reason))
+}
+
+// A transfer leg or a split transaction was checked against rows the log doesn't cover, the other
+// leg and the splits, so a restore may only change what an update could
+fn check_transaction_restore(
+    conn: &mut PgConnection,
+    transaction_id: Uuid,
+    current: Option<&Map<String, JsonValue>>,
+    version: &Map<String, JsonValue>,
+) -> Result<(), AppError> {
+    let is_transfer = |row: &Map<String, JsonValue>| row.get("transfer_id").is_some_and(|id| !id.is_null());
+    if current.is_some_and(is_transfer) || is_transfer(version) {
+        return Err(AppError::BadRequest(
+            "A transfer can't be restored; delete and re-create it instead".to_string(),
+        ));
+    }
+    let Some(current) = current else {
+        return Ok(());
+    };
+    let changes = |column: &str| version.contains_key(column) && version.get(column) != current.get(column);
+    if !changes("amount") && !changes("currency") && !changes("items") {
+        return Ok(());
+    }
+    let has_splits = diesel::select(diesel::dsl::exists(
+        transaction_splits::table.filter(transaction_splits::transaction_id.eq(transaction_id)),
+    ))
+    .get_result::<bool>(conn)?;
+    if has_splits {
+        return Err(AppError::BadRequest(
+            "Update or remove the splits before restoring a version with another amount or items".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+// Put a row back the way it was right after the entry's change. The version is rebuilt from the
+// row as it is now by undoing every later change, newest first, so rows that existed before
+// auditing began can be restored too. A deleted row is recreated; restoring is itself audited.
+// Ledger rows are found through the ledger and a profile only through its own user, so a member
+// of someone's personal ledger can't roll back their profile
+pub fn restore_version(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    user_id: Uuid,
+    entry_id: i64,
+) -> Result<RestoreVersionResponse, AppError> {
+    conn.transaction(|conn| {
+        let users = AuditEntity::Users.as_str();
+        let entry = audit_log::table
+            .find(entry_id)
+            .filter(
+                audit_log::entity_type
+                    .ne(users)
+                    .and(audit_log::owner_id.eq(ledger_id))
+                    .or(audit_log::entity_type.eq(users).and(audit_log::owner_id.eq(user_id))),
+            )
+            .first::<DbAuditEntry>(conn)
+            .optional()?
+            .ok_or_else(|| AppError::NotFound(format!("Audit entry {} not found", entry_id)))?;
+        let entity = AuditEntity::parse(&entry.entity_type)
+            .ok_or_else(|| AppError::BadRequest(format!("{} can't be restored", entry.entity_type)))?;
+        let owner_id = if entity == AuditEntity::Users { user_id } else { ledger_id };
+        if AuditAction::parse(&entry.action) == Some(AuditAction::Delete) {
+            return Err(AppError::BadRequest(
+                "The entry deleted the row; restore the version before it instead".to_string(),
+            ));
+        }
+
+        let current = current_row(conn, entity, entry.entity_id)?;
+        let later = audit_log::table
+            .filter(audit_log::entity_type.eq(&entry.entity_type))
+            .filter(audit_log::entity_id.eq(entry.entity_id))
+            .filter(audit_log::id.gt(entry.id))
+            .order(audit_log::id.desc())
+            .load::<DbAuditEntry>(conn)?;
+
+        let mut version = current.clone();
+        for change in later {
+            let old_values = match change.old_values {
+                Some(JsonValue::Object(values)) => values,
+                _ => Map::new(),
+            };
+            match AuditAction::parse(&change.action) {
+                Some(AuditAction::Create) => version = None,
+                Some(AuditAction::Update) => {
+                    if let Some(version) = version.as_mut() {
+                        version.extend(old_values);
+                    }
+                }
+                Some(AuditAction::Delete) => version = Some(old_values),
+                None => {}
+            }
+        }
+        let mut version = version
+            .ok_or_else(|| AppError::BadRequest("The version can't be rebuilt from the log".to_string()))?;
+        // Rows logged before ledger_id was named still carry it as user_id
+        if entity != AuditEntity::Users {
+            if let Some(ledger_id) = version.remove("user_id") {
+                version.entry("ledger_id").or_insert(ledger_id);
+            }
+        }
+        if owner_of(entity, &version) != Some(owner_id) {
+            return Err(AppError::NotFound(format!("Audit entry {} not found", entry_id)));
+        }
+        if entity == AuditEntity::Transactions {
+            check_transaction_restore(conn, entry.entity_id, current.as_ref(), &version)?;
+        }
+
+        let columns: Vec<&String> = version
+            .keys()
+            .filter(|column| column.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_'))
+            .collect();
+        let table = entity.as_str();
+        let query = if current.is_some() {
+            let columns: Vec<&str> = columns
+                .iter()
+                .map(|column| column.as_str())
+                .filter(|column| !FIXED_COLUMNS.contains(column))
+                .collect();
+            if columns.is_empty() {
+                return Err(AppError::BadRequest("The version has nothing to restore".to_string()));
+            }
+            let columns = columns.join(", ");
+            format!(
+                "UPDATE {0} AS t SET ({1}, updated_at) = \
+                 (SELECT {1}, NOW() FROM JSONB_POPULATE_RECORD(NULL::{0}, $1)) \
+                 WHERE t.id = $2",
+                table, columns
+            )
+        } else {
+            // Without the password a deleted user can't sign in again
+            if entity == AuditEntity::Users {
+                return Err(AppError::BadRequest("A deleted user can't be restored".to_string()));
+            }
+            let columns = columns.iter().map(|column| column.as_str()).collect::<Vec<_>>().join(", ");
+            format!(
+                "INSERT INTO {0} ({1}) \
+                 SELECT {1} FROM JSONB_POPULATE_RECORD(NULL::{0}, $1) AS t WHERE t.id = $2",
+                table, columns
+            )
+        };
+        sql_query(query)
+            .bind::<Jsonb, _>(JsonValue::Object(version))
+            .bind::<SqlUuid, _>(entry.entity_id)
+            .execute(conn)
+            .map_err(restore_error)?;
+
+        let current = current_row(conn, entity, entry.entity_id)?.unwrap_or_default();
+        Ok(RestoreVersionResponse {
+            entity_type: entry.entity_type,
+            entity_id: entry.entity_id,
+            restored_from: entry.id,
+            current: JsonValue::Object(current),
+        })
+    })
+}
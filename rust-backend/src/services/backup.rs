@@ -0,0 +1,1144 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io::{Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, NaiveDate, Utc};
+use diesel::dsl::max;
+use diesel::prelude::*;
+use diesel::PgConnection;
+use serde_json::Value as JsonValue;
+use uuid::Uuid;
+use zip::result::ZipError;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::error::AppError;
+use crate::models::account::DbAccount;
+use crate::models::backup::{BackupManifest, BackupUser, RestoreReport, RestoreTableReport};
+use crate::models::bill::DbBill;
+use crate::models::budget::DbBudget;
+use crate::models::category::{DbCategory, DbCategoryKeyword};
+use crate::models::exchange_rate::DbExchangeRate;
+use crate::models::import::{DbImportBatch, DbImportProfile};
+use crate::models::ledger::DbLedgerAccount;
+use crate::models::merchant::{DbMerchant, DbMerchantAlias};
+use crate::models::reconciliation::DbReconciliationMatch;
+use crate::models::recurring::DbRecurringTransaction;
+use crate::models::rule::DbRule;
+use crate::models::shared_ledger::DbLedger;
+use crate::models::split::DbSplit;
+use crate::models::tag::{DbTag, NewSplitTag, NewTransactionTag};
+use crate::models::transaction::DbTransaction;
+use crate::money::Currency;
+use crate::schema::{
+    accounts, bills, budgets, categories, category_keywords, exchange_rates, import_batches, import_profiles,
+    ledger_accounts, ledgers, merchant_aliases, merchants, reconciliation_matches, recurring_transactions, rules,
+    split_tags, tags, transaction_splits, transaction_tags, transactions, users,
+};
+use crate::services::ledgers as ledger_service;
+use crate::services::recurring::next_occurrence;
+
+// Bumped when the manifest changes shape in a way older instances can't read
+pub const BACKUP_FORMAT_VERSION: u32 = 1;
+// The last migration that changed a table in the backup; bump it with the next one
+pub const SCHEMA_VERSION: &str = "2026-10-18-000024";
+
+const MANIFEST_NAME: &str = "manifest.json";
+// Postgres allows 65535 bind parameters per statement; the widest table has 25 columns
+const INSERT_CHUNK_SIZE: usize = 1000;
+// A stored file bigger than this once unpacked is refused, so a crafted archive can't fill the disk
+const MAX_FILE_SIZE: u64 = 50 * 1024 * 1024;
+
+// Insert rows a chunk at a time
+macro_rules! insert_rows {
+    ($conn:expr, $table:expr, $rows:expr) => {
+        for chunk in $rows.chunks(INSERT_CHUNK_SIZE) {
+            diesel::insert_into($table).values(chunk).execute($conn)?;
+        }
+    };
+}
+
+fn archive_error(e: ZipError) -> AppError {
+    AppError::BadRequest(format!("The file is not a valid backup archive: {}", e))
+}
+
+// The file at `path` if it exists inside the upload directory. Image paths can be set by the
+// client, so anything else is left out of the backup
+fn stored_file(upload_dir: &Path, path: &str) -> Option<PathBuf> {
+    let file = fs::canonicalize(path).ok()?;
+    Some(file).filter(|file| file.starts_with(upload_dir) && file.is_file())
+}
+
+// Every row in the ledger, with the stored files the bills and transactions point at. What's in
+// the trash is left out
+pub fn create_manifest(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    upload_dir: &Path,
+) -> Result<BackupManifest, AppError> {
+    let ledger = ledgers::table.find(ledger_id).first::<DbLedger>(conn)?;
+    let email = users::table.find(ledger.created_by).select(users::email).first::<String>(conn)?;
+    let transactions = transactions::table
+        .filter(transactions::ledger_id.eq(ledger_id))
+        .filter(transactions::deleted_at.is_null())
+        .order((transactions::date.asc(), transactions::created_at.asc()))
+        .load::<DbTransaction>(conn)?;
+    let bills = bills::table
+        .filter(bills::ledger_id.eq(ledger_id))
+        .filter(bills::deleted_at.is_null())
+        .load::<DbBill>(conn)?;
+
+    // Archive entries are named after the files, e.g. files/3f0c...e1.jpg
+    let upload_dir = fs::canonicalize(upload_dir).unwrap_or_else(|_| upload_dir.to_path_buf());
+    let mut files = BTreeMap::new();
+    let mut entries = HashSet::new();
+    let paths = bills
+        .iter()
+        .map(|bill| &bill.file_path)
+        .chain(transactions.iter().filter_map(|t| t.image_path.as_ref()));
+    for path in paths {
+        if files.contains_key(path) {
+            continue;
+        }
+        if let Some(file) = stored_file(&upload_dir, path) {
+            let name = file.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+            let mut entry = format!("files/{}", name);
+            let mut copy = 1;
+            while !entries.insert(entry.clone()) {
+                copy += 1;
+                entry = format!("files/{}-{}", copy, name);
+            }
+            files.insert(path.clone(), entry);
+        }
+    }
+
+    Ok(BackupManifest {
+        format_version: BACKUP_FORMAT_VERSION,
+        schema_version: SCHEMA_VERSION.to_string(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at: Utc::now(),
+        user: BackupUser {
+            id: ledger.id,
+            email,
+            name: ledger.name,
+            base_currency: ledger.base_currency,
+        },
+        accounts: accounts::table.filter(accounts::ledger_id.eq(ledger_id)).load(conn)?,
+        categories: categories::table
+            .filter(categories::ledger_id.eq(ledger_id))
+            .filter(categories::deleted_at.is_null())
+            .load(conn)?,
+        category_keywords: category_keywords::table
+            .filter(category_keywords::ledger_id.eq(ledger_id))
+            .load(conn)?,
+        merchants: merchants::table.filter(merchants::ledger_id.eq(ledger_id)).load(conn)?,
+        merchant_aliases: merchant_aliases::table
+            .inner_join(merchants::table)
+            .filter(merchants::ledger_id.eq(ledger_id))
+            .select(merchant_aliases::all_columns)
+            .load(conn)?,
+        tags: tags::table.filter(tags::ledger_id.eq(ledger_id)).load(conn)?,
+        recurring_transactions: recurring_transactions::table
+            .filter(recurring_transactions::ledger_id.eq(ledger_id))
+            .load(conn)?,
+        import_profiles: import_profiles::table
+            .filter(import_profiles::ledger_id.eq(ledger_id))
+            .load(conn)?,
+        import_batches: import_batches::table.filter(import_batches::ledger_id.eq(ledger_id)).load(conn)?,
+        transactions,
+        transaction_tags: transaction_tags::table
+            .inner_join(tags::table)
+            .inner_join(transactions::table)
+            .filter(tags::ledger_id.eq(ledger_id))
+            .filter(transactions::deleted_at.is_null())
+            .select((transaction_tags::transaction_id, transaction_tags::tag_id))
+            .load::<(Uuid, Uuid)>(conn)?
+            .into_iter()
+            .map(|(transaction_id, tag_id)| NewTransactionTag { transaction_id, tag_id })
+            .collect(),
+        transaction_splits: transaction_splits::table
+            .inner_join(transactions::table)
+            .filter(transaction_splits::ledger_id.eq(ledger_id))
+            .filter(transactions::deleted_at.is_null())
+            .order((transaction_splits::transaction_id, transaction_splits::position))
+            .select(transaction_splits::all_columns)
+            .load(conn)?,
+        split_tags: split_tags::table
+            .inner_join(transaction_splits::table.inner_join(transactions::table))
+            .filter(transaction_splits::ledger_id.eq(ledger_id))
+            .filter(transactions::deleted_at.is_null())
+            .select((split_tags::split_id, split_tags::tag_id))
+            .load::<(Uuid, Uuid)>(conn)?
+            .into_iter()
+            .map(|(split_id, tag_id)| NewSplitTag { split_id, tag_id })
+            .collect(),
+        bills,
+        rules: rules::table.filter(rules::ledger_id.eq(ledger_id)).order(rules::position.asc()).load(conn)?,
+        budgets: budgets::table.filter(budgets::ledger_id.eq(ledger_id)).load(conn)?,
+        exchange_rates: exchange_rates::table.filter(exchange_rates::ledger_id.eq(ledger_id)).load(conn)?,
+        ledger_accounts: ledger_accounts::table.filter(ledger_accounts::ledger_id.eq(ledger_id)).load(conn)?,
+        reconciliation_matches: reconciliation_matches::table
+            .filter(reconciliation_matches::ledger_id.eq(ledger_id))
+            .load(conn)?,
+        files,
+    })
+}
+
+// A zip archive with manifest.json and the stored files. Images are already compressed, so
+// only the manifest is deflated
+pub fn write_archive(manifest: &BackupManifest) -> Result<Vec<u8>, AppError> {
+    let json = serde_json::to_vec_pretty(manifest)?;
+    let write = || -> Result<Vec<u8>, ZipError> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        zip.start_file(MANIFEST_NAME, FileOptions::default().compression_method(CompressionMethod::Deflated))?;
+        zip.write_all(&json)?;
+        for (path, entry) in &manifest.files {
+            zip.start_file(entry.as_str(), FileOptions::default().compression_method(CompressionMethod::Stored))?;
+            zip.write_all(&fs::read(path)?)?;
+        }
+        Ok(zip.finish()?.into_inner())
+    };
+    write().map_err(|e| AppError::InternalServerError(format!("Failed to write the backup: {}", e)))
+}
+
+pub type BackupArchive = ZipArchive<Cursor<Vec<u8>>>;
+
+// Open a backup and read its manifest. The versions are checked before the rest is read, so a
+// backup from a newer instance is refused with a clear reason rather than a parse error
+pub fn read_archive(bytes: Vec<u8>) -> Result<(BackupManifest, BackupArchive), AppError> {
+    let mut archive = ZipArchive::new(Cursor::new(bytes)).map_err(archive_error)?;
+    let mut json = Vec::new();
+    archive
+        .by_name(MANIFEST_NAME)
+        .map_err(|_| AppError::BadRequest("The archive has no manifest.json; it isn't a backup".to_string()))?
+        .read_to_end(&mut json)?;
+    let manifest: serde_json::Value = serde_json::from_slice(&json)?;
+
+    let format_version = manifest["format_version"].as_u64().unwrap_or_default();
+    let schema_version = manifest["schema_version"].as_str().unwrap_or_default();
+    if format_version == 0 || schema_version.is_empty() {
+        return Err(AppError::BadRequest("The manifest has no format or schema version".to_string()));
+    }
+    if format_version > BACKUP_FORMAT_VERSION as u64 || schema_version > SCHEMA_VERSION {
+        return Err(AppError::BadRequest(format!(
+            "The backup was made by a newer version (format {}, schema {}) than this instance (format {}, schema {}); \
+             upgrade before restoring it",
+            format_version, schema_version, BACKUP_FORMAT_VERSION, SCHEMA_VERSION
+        )));
+    }
+    Ok((serde_json::from_value(manifest)?, archive))
+}
+
+fn table_report(table: &str, in_backup: usize) -> RestoreTableReport {
+    RestoreTableReport {
+        table: table.to_string(),
+        in_backup,
+        ..Default::default()
+    }
+}
+
+// A transaction is taken to be one the user already has when these match, so restoring the
+// same backup twice doesn't double it
+fn transaction_key(
+    date: &DateTime<Utc>,
+    amount: &BigDecimal,
+    currency: &str,
+    merchant: &str,
+    direction: &str,
+) -> (i64, String, String, String, String) {
+    (
+        date.timestamp(),
+        amount.normalized().to_string(),
+        currency.to_string(),
+        merchant.trim().to_lowercase(),
+        direction.to_string(),
+    )
+}
+
+fn recurring_key(name: &str, amount: &BigDecimal, frequency: &str) -> (String, String, String) {
+    (name.trim().to_lowercase(), amount.normalized().to_string(), frequency.to_string())
+}
+
+fn rule_key(match_mode: &str, conditions: &JsonValue, actions: &JsonValue) -> (String, String, String) {
+    (match_mode.to_string(), conditions.to_string(), actions.to_string())
+}
+
+// Restoring: backed-up IDs mapped to the new rows, or to the user's own rows they were merged
+// into, and the stored files to unpack
+struct Restore {
+    ids: HashMap<Uuid, Uuid>,
+    files: Vec<(String, PathBuf)>,
+    entries: HashSet<String>,
+    bill_dir: PathBuf,
+    missing_files: usize,
+}
+
+impl Restore {
+    fn create(&mut self, old: Uuid) -> Uuid {
+        let id = Uuid::new_v4();
+        self.ids.insert(old, id);
+        id
+    }
+
+    fn map(&self, old: Option<Uuid>) -> Option<Uuid> {
+        old.and_then(|old| self.ids.get(&old).copied())
+    }
+
+    // Where a backed-up file will be unpacked to, if the archive has it
+    fn file(&mut self, manifest: &BackupManifest, path: Option<&String>) -> Option<String> {
+        let path = path?;
+        let entry = manifest.files.get(path).filter(|entry| self.entries.contains(*entry));
+        let Some(entry) = entry else {
+            self.missing_files += 1;
+            return None;
+        };
+        if let Some((_, stored)) = self.files.iter().find(|(e, _)| e == entry) {
+            return Some(stored.to_string_lossy().into_owned());
+        }
+        let extension = Path::new(entry).extension().and_then(|e| e.to_str()).unwrap_or("bin");
+        let stored = self.bill_dir.join(format!("{}.{}", Uuid::new_v4(), extension));
+        self.files.push((entry.clone(), stored.clone()));
+        Some(stored.to_string_lossy().into_owned())
+    }
+}
+
+// Restore a backup into a ledger. Every row gets a new ID, with the references between
+// rows remapped, so a backup can go into any instance, even the one it came from. Categories,
+// accounts, tags, merchants and import profiles merge into the user's own of the same name, and
+// recurring templates, rules and budgets into the user's own that do the same thing; transactions
+// the user already has are skipped along with their splits, tags and bills.
+// A dry run reports the same counts without writing anything
+pub fn restore(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    manifest: BackupManifest,
+    mut archive: BackupArchive,
+    upload_dir: &Path,
+    dry_run: bool,
+) -> Result<RestoreReport, AppError> {
+    let mut restore = Restore {
+        ids: HashMap::new(),
+        files: Vec::new(),
+        entries: archive.file_names().map(str::to_string).collect(),
+        bill_dir: upload_dir.join("bills"),
+        missing_files: 0,
+    };
+    let mut written: Vec<PathBuf> = Vec::new();
+
+    let result = conn.transaction(|conn| {
+        let report = restore_rows(conn, ledger_id, &manifest, &mut restore, dry_run)?;
+        if !dry_run {
+            fs::create_dir_all(&restore.bill_dir)?;
+            for (entry, stored) in &restore.files {
+                let mut file = archive.by_name(entry).map_err(archive_error)?;
+                if file.size() > MAX_FILE_SIZE {
+                    return Err(AppError::BadRequest(format!("{} in the archive is too big", entry)));
+                }
+                let mut bytes = Vec::new();
+                file.by_ref().take(MAX_FILE_SIZE).read_to_end(&mut bytes)?;
+                written.push(stored.clone());
+                fs::write(stored, bytes)?;
+            }
+        }
+        Ok(report)
+    });
+
+    // Files unpacked for a restore that was rolled back would belong to nothing
+    if result.is_err() {
+        for path in &written {
+            let _ = fs::remove_file(path);
+        }
+    }
+    result
+}
+
+fn restore_rows(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    manifest: &BackupManifest,
+    restore: &mut Restore,
+    dry_run: bool,
+) -> Result<RestoreReport, AppError> {
+    let now = Utc::now();
+    let mut tables = Vec::new();
+    let mut warnings = Vec::new();
+
+    // Accounts, matched by name
+    let existing: HashMap<String, Uuid> = accounts::table
+        .filter(accounts::ledger_id.eq(ledger_id))
+        .select((accounts::name, accounts::id))
+        .load::<(String, Uuid)>(conn)?
+        .into_iter()
+        .collect();
+    let mut report = table_report("accounts", manifest.accounts.len());
+    let mut rows = Vec::new();
+    for account in &manifest.accounts {
+        if let Some(id) = existing.get(&account.name) {
+            restore.ids.insert(account.id, *id);
+            report.matched += 1;
+            continue;
+        }
+        rows.push(DbAccount {
+            id: restore.create(account.id),
+            ledger_id,
+            updated_at: now,
+            ..account.clone()
+        });
+    }
+    report.created = rows.len();
+    if !dry_run {
+        insert_rows!(conn, accounts::table, rows);
+    }
+    tables.push(report);
+
+    // Categories, matched by name and kind
+    let existing: HashMap<(String, String), Uuid> = categories::table
+        .filter(categories::ledger_id.eq(ledger_id))
+        .filter(categories::deleted_at.is_null())
+        .select((categories::name, categories::kind, categories::id))
+        .load::<(String, String, Uuid)>(conn)?
+        .into_iter()
+        .map(|(name, kind, id)| ((name.to_lowercase(), kind), id))
+        .collect();
+    let mut report = table_report("categories", manifest.categories.len());
+    let mut rows = Vec::new();
+    for category in &manifest.categories {
+        if let Some(id) = existing.get(&(category.name.to_lowercase(), category.kind.clone())) {
+            restore.ids.insert(category.id, *id);
+            report.matched += 1;
+            continue;
+        }
+        rows.push(DbCategory {
+            id: restore.create(category.id),
+            ledger_id,
+            updated_at: now,
+            ..category.clone()
+        });
+    }
+    report.created = rows.len();
+    if !dry_run {
+        insert_rows!(conn, categories::table, rows);
+    }
+    tables.push(report);
+
+    let existing: HashSet<(String, Uuid)> = category_keywords::table
+        .filter(category_keywords::ledger_id.eq(ledger_id))
+        .select((category_keywords::keyword, category_keywords::category_id))
+        .load::<(String, Uuid)>(conn)?
+        .into_iter()
+        .collect();
+    let mut report = table_report("category_keywords", manifest.category_keywords.len());
+    let mut rows = Vec::new();
+    for keyword in &manifest.category_keywords {
+        let Some(category_id) = restore.map(Some(keyword.category_id)) else {
+            report.skipped += 1;
+            continue;
+        };
+        if existing.contains(&(keyword.keyword.clone(), category_id)) {
+            report.matched += 1;
+            continue;
+        }
+        rows.push(DbCategoryKeyword {
+            id: restore.create(keyword.id),
+            ledger_id,
+            category_id,
+            updated_at: now,
+            ..keyword.clone()
+        });
+    }
+    report.created = rows.len();
+    if !dry_run {
+        insert_rows!(conn, category_keywords::table, rows);
+    }
+    tables.push(report);
+
+    // Merchants, matched by normalized name
+    let existing: HashMap<String, Uuid> = merchants::table
+        .filter(merchants::ledger_id.eq(ledger_id))
+        .select((merchants::normalized_name, merchants::id))
+        .load::<(String, Uuid)>(conn)?
+        .into_iter()
+        .collect();
+    let mut report = table_report("merchants", manifest.merchants.len());
+    let mut rows = Vec::new();
+    for merchant in &manifest.merchants {
+        if let Some(id) = existing.get(&merchant.normalized_name) {
+            restore.ids.insert(merchant.id, *id);
+            report.matched += 1;
+            continue;
+        }
+        rows.push(DbMerchant {
+            id: restore.create(merchant.id),
+            ledger_id,
+            default_category_id: restore.map(merchant.default_category_id),
+            updated_at: now,
+            ..merchant.clone()
+        });
+    }
+    report.created = rows.len();
+    if !dry_run {
+        insert_rows!(conn, merchants::table, rows);
+    }
+    tables.push(report);
+
+    let existing: HashSet<(Uuid, String)> = merchant_aliases::table
+        .inner_join(merchants::table)
+        .filter(merchants::ledger_id.eq(ledger_id))
+        .select((merchant_aliases::merchant_id, merchant_aliases::normalized_alias))
+        .load::<(Uuid, String)>(conn)?
+        .into_iter()
+        .collect();
+    let mut report = table_report("merchant_aliases", manifest.merchant_aliases.len());
+    let mut rows = Vec::new();
+    for alias in &manifest.merchant_aliases {
+        let Some(merchant_id) = restore.map(Some(alias.merchant_id)) else {
+            report.skipped += 1;
+            continue;
+        };
+        if existing.contains(&(merchant_id, alias.normalized_alias.clone())) {
+            report.matched += 1;
+            continue;
+        }
+        rows.push(DbMerchantAlias {
+            id: restore.create(alias.id),
+            merchant_id,
+            ..alias.clone()
+        });
+    }
+    report.created = rows.len();
+    if !dry_run {
+        insert_rows!(conn, merchant_aliases::table, rows);
+    }
+    tables.push(report);
+
+    // Tags, matched by name ignoring case
+    let existing: HashMap<String, Uuid> = tags::table
+        .filter(tags::ledger_id.eq(ledger_id))
+        .select((tags::name, tags::id))
+        .load::<(String, Uuid)>(conn)?
+        .into_iter()
+        .map(|(name, id)| (name.to_lowercase(), id))
+        .collect();
+    let mut report = table_report("tags", manifest.tags.len());
+    let mut rows = Vec::new();
+    for tag in &manifest.tags {
+        if let Some(id) = existing.get(&tag.name.to_lowercase()) {
+            restore.ids.insert(tag.id, *id);
+            report.matched += 1;
+            continue;
+        }
+        rows.push(DbTag {
+            id: restore.create(tag.id),
+            ledger_id,
+            updated_at: now,
+            ..tag.clone()
+        });
+    }
+    report.created = rows.len();
+    if !dry_run {
+        insert_rows!(conn, tags::table, rows);
+    }
+    tables.push(report);
+
+    // Recurring templates, matched by name, amount and frequency. A new template picks up after
+    // the newest occurrence in the backup, so the recurring job doesn't fill in occurrences that
+    // were matched to transactions the user already has
+    let existing: HashMap<(String, String, String), Uuid> = recurring_transactions::table
+        .filter(recurring_transactions::ledger_id.eq(ledger_id))
+        .select((
+            recurring_transactions::name,
+            recurring_transactions::amount,
+            recurring_transactions::frequency,
+            recurring_transactions::id,
+        ))
+        .load::<(String, BigDecimal, String, Uuid)>(conn)?
+        .into_iter()
+        .map(|(name, amount, frequency, id)| (recurring_key(&name, &amount, &frequency), id))
+        .collect();
+    let mut newest_occurrence: HashMap<Uuid, NaiveDate> = HashMap::new();
+    for transaction in &manifest.transactions {
+        if let Some(recurring_id) = transaction.recurring_id {
+            let date = transaction.date.date_naive();
+            let newest = newest_occurrence.entry(recurring_id).or_insert(date);
+            *newest = (*newest).max(date);
+        }
+    }
+    let mut report = table_report("recurring_transactions", manifest.recurring_transactions.len());
+    let mut rows = Vec::new();
+    for recurring in &manifest.recurring_transactions {
+        if let Some(id) = existing.get(&recurring_key(&recurring.name, &recurring.amount, &recurring.frequency)) {
+            restore.ids.insert(recurring.id, *id);
+            report.matched += 1;
+            continue;
+        }
+        let mut row = DbRecurringTransaction {
+            id: restore.create(recurring.id),
+            ledger_id,
+            merchant_id: restore.map(recurring.merchant_id),
+            category_id: restore.map(recurring.category_id),
+            updated_at: now,
+            ..recurring.clone()
+        };
+        if let Some(&newest) = newest_occurrence.get(&recurring.id) {
+            if row.next_run <= newest {
+                row.next_run = next_occurrence(&row.schedule(), newest);
+            }
+        }
+        rows.push(row);
+    }
+    report.created = rows.len();
+    if !dry_run {
+        insert_rows!(conn, recurring_transactions::table, rows);
+    }
+    tables.push(report);
+
+    // Import profiles, matched by name ignoring case
+    let existing: HashMap<String, Uuid> = import_profiles::table
+        .filter(import_profiles::ledger_id.eq(ledger_id))
+        .select((import_profiles::name, import_profiles::id))
+        .load::<(String, Uuid)>(conn)?
+        .into_iter()
+        .map(|(name, id)| (name.to_lowercase(), id))
+        .collect();
+    let mut report = table_report("import_profiles", manifest.import_profiles.len());
+    let mut rows = Vec::new();
+    for profile in &manifest.import_profiles {
+        if let Some(id) = existing.get(&profile.name.to_lowercase()) {
+            restore.ids.insert(profile.id, *id);
+            report.matched += 1;
+            continue;
+        }
+        rows.push(DbImportProfile {
+            id: restore.create(profile.id),
+            ledger_id,
+            account_id: restore.map(profile.account_id),
+            updated_at: now,
+            ..profile.clone()
+        });
+    }
+    report.created = rows.len();
+    if !dry_run {
+        insert_rows!(conn, import_profiles::table, rows);
+    }
+    tables.push(report);
+
+    let mut report = table_report("import_batches", manifest.import_batches.len());
+    let mut rows = Vec::new();
+    for batch in &manifest.import_batches {
+        rows.push(DbImportBatch {
+            id: restore.create(batch.id),
+            ledger_id,
+            profile_id: restore.map(batch.profile_id),
+            account_id: restore.map(batch.account_id),
+            ..batch.clone()
+        });
+    }
+    report.created = rows.len();
+    if !dry_run {
+        insert_rows!(conn, import_batches::table, rows);
+    }
+    tables.push(report);
+
+    // Transactions. A refund can come before the transaction it refunds, so refunds are linked
+    // once every row is in
+    let existing: HashMap<_, Uuid> = transactions::table
+        .filter(transactions::ledger_id.eq(ledger_id))
+        .filter(transactions::deleted_at.is_null())
+        .select((
+            transactions::id,
+            transactions::date,
+            transactions::amount,
+            transactions::currency,
+            transactions::merchant,
+            transactions::direction,
+        ))
+        .load::<(Uuid, DateTime<Utc>, BigDecimal, String, String, String)>(conn)?
+        .into_iter()
+        .map(|(id, date, amount, currency, merchant, direction)| {
+            (transaction_key(&date, &amount, &currency, &merchant, &direction), id)
+        })
+        .collect();
+    let had_transactions = !existing.is_empty();
+    let mut report = table_report("transactions", manifest.transactions.len());
+    let mut rows = Vec::new();
+    let mut created = HashSet::new();
+    let mut transfer_ids: HashMap<Uuid, Uuid> = HashMap::new();
+    let mut refunds = Vec::new();
+    for transaction in &manifest.transactions {
+        let key = transaction_key(
+            &transaction.date,
+            &transaction.amount,
+            &transaction.currency,
+            &transaction.merchant,
+            &transaction.direction,
+        );
+        if let Some(id) = existing.get(&key) {
+            restore.ids.insert(transaction.id, *id);
+            report.matched += 1;
+            continue;
+        }
+        let id = restore.create(transaction.id);
+        created.insert(id);
+        if let Some(refund_of) = transaction.refund_of {
+            refunds.push((id, refund_of));
+        }
+        rows.push(DbTransaction {
+            id,
+            ledger_id,
+            category_id: restore.map(transaction.category_id),
+            merchant_id: restore.map(transaction.merchant_id),
+            recurring_id: restore.map(transaction.recurring_id),
+            account_id: restore.map(transaction.account_id),
+            import_batch_id: restore.map(transaction.import_batch_id),
+            transfer_id: transaction
+                .transfer_id
+                .map(|transfer_id| *transfer_ids.entry(transfer_id).or_insert_with(Uuid::new_v4)),
+            refund_of: None,
+            image_path: restore.file(manifest, transaction.image_path.as_ref()),
+            updated_at: now,
+            ..transaction.clone()
+        });
+    }
+    report.created = rows.len();
+    if !dry_run {
+        insert_rows!(conn, transactions::table, rows);
+        for (id, refund_of) in refunds {
+            if let Some(refund_of) = restore.map(Some(refund_of)) {
+                diesel::update(transactions::table.find(id))
+                    .set(transactions::refund_of.eq(refund_of))
+                    .execute(conn)?;
+            }
+        }
+    }
+    tables.push(report);
+
+    // Tags, splits and bills go only with transactions this restore created
+    let mut report = table_report("transaction_tags", manifest.transaction_tags.len());
+    let mut rows = Vec::new();
+    for link in &manifest.transaction_tags {
+        match (restore.map(Some(link.transaction_id)), restore.map(Some(link.tag_id))) {
+            (Some(transaction_id), Some(tag_id)) if created.contains(&transaction_id) => {
+                rows.push(NewTransactionTag { transaction_id, tag_id })
+            }
+            _ => report.skipped += 1,
+        }
+    }
+    report.created = rows.len();
+    if !dry_run {
+        insert_rows!(conn, transaction_tags::table, rows);
+    }
+    tables.push(report);
+
+    let mut report = table_report("transaction_splits", manifest.transaction_splits.len());
+    let mut rows = Vec::new();
+    for split in &manifest.transaction_splits {
+        match restore.map(Some(split.transaction_id)) {
+            Some(transaction_id) if created.contains(&transaction_id) => rows.push(DbSplit {
+                id: restore.create(split.id),
+                transaction_id,
+                ledger_id,
+                category_id: restore.map(split.category_id),
+                updated_at: now,
+                ..split.clone()
+            }),
+            _ => report.skipped += 1,
+        }
+    }
+    report.created = rows.len();
+    let created_splits: HashSet<Uuid> = rows.iter().map(|split| split.id).collect();
+    if !dry_run {
+        insert_rows!(conn, transaction_splits::table, rows);
+    }
+    tables.push(report);
+
+    let mut report = table_report("split_tags", manifest.split_tags.len());
+    let mut rows = Vec::new();
+    for link in &manifest.split_tags {
+        match (restore.map(Some(link.split_id)), restore.map(Some(link.tag_id))) {
+            (Some(split_id), Some(tag_id)) if created_splits.contains(&split_id) => {
+                rows.push(NewSplitTag { split_id, tag_id })
+            }
+            _ => report.skipped += 1,
+        }
+    }
+    report.created = rows.len();
+    if !dry_run {
+        insert_rows!(conn, split_tags::table, rows);
+    }
+    tables.push(report);
+
+    // Bills waiting for review have no transaction; they're matched by file name and upload time
+    let existing: HashSet<(String, DateTime<Utc>)> = bills::table
+        .filter(bills::ledger_id.eq(ledger_id))
+        .filter(bills::deleted_at.is_null())
+        .select((bills::file_name, bills::created_at))
+        .load::<(String, DateTime<Utc>)>(conn)?
+        .into_iter()
+        .collect();
+    let mut report = table_report("bills", manifest.bills.len());
+    let mut rows = Vec::new();
+    for bill in &manifest.bills {
+        let transaction_id = restore.map(bill.transaction_id);
+        let duplicate = match transaction_id {
+            Some(transaction_id) => !created.contains(&transaction_id),
+            None => bill.transaction_id.is_some() || existing.contains(&(bill.file_name.clone(), bill.created_at)),
+        };
+        if duplicate {
+            report.skipped += 1;
+            continue;
+        }
+        let Some(file_path) = restore.file(manifest, Some(&bill.file_path)) else {
+            report.skipped += 1;
+            continue;
+        };
+        rows.push(DbBill {
+            id: restore.create(bill.id),
+            ledger_id,
+            file_path,
+            transaction_id,
+            updated_at: now,
+            ..bill.clone()
+        });
+    }
+    report.created = rows.len();
+    if !dry_run {
+        insert_rows!(conn, bills::table, rows);
+    }
+    tables.push(report);
+
+    // Rules, matched by what they match and what they do. They run in order, so restored rules
+    // go after the user's own
+    let existing: HashMap<(String, String, String), Uuid> = rules::table
+        .filter(rules::ledger_id.eq(ledger_id))
+        .select((rules::match_mode, rules::conditions, rules::actions, rules::id))
+        .load::<(String, JsonValue, JsonValue, Uuid)>(conn)?
+        .into_iter()
+        .map(|(match_mode, conditions, actions, id)| (rule_key(&match_mode, &conditions, &actions), id))
+        .collect();
+    let last_position = rules::table
+        .filter(rules::ledger_id.eq(ledger_id))
+        .select(max(rules::position))
+        .first::<Option<i32>>(conn)?
+        .unwrap_or(-1);
+    let mut report = table_report("rules", manifest.rules.len());
+    let mut rows = Vec::new();
+    for rule in &manifest.rules {
+        if let Some(id) = existing.get(&rule_key(&rule.match_mode, &rule.conditions, &rule.actions)) {
+            restore.ids.insert(rule.id, *id);
+            report.matched += 1;
+            continue;
+        }
+        rows.push(DbRule {
+            id: restore.create(rule.id),
+            ledger_id,
+            position: last_position + 1 + rows.len() as i32,
+            updated_at: now,
+            ..rule.clone()
+        });
+    }
+    report.created = rows.len();
+    if !dry_run {
+        insert_rows!(conn, rules::table, rows);
+    }
+    tables.push(report);
+
+    // Budgets, matched by category and period
+    let existing: HashMap<(Uuid, String), Uuid> = budgets::table
+        .filter(budgets::ledger_id.eq(ledger_id))
+        .select((budgets::category_id, budgets::period, budgets::id))
+        .load::<(Uuid, String, Uuid)>(conn)?
+        .into_iter()
+        .map(|(category_id, period, id)| ((category_id, period), id))
+        .collect();
+    let mut report = table_report("budgets", manifest.budgets.len());
+    let mut rows = Vec::new();
+    for budget in &manifest.budgets {
+        let Some(category_id) = restore.map(Some(budget.category_id)) else {
+            report.skipped += 1;
+            continue;
+        };
+        if let Some(id) = existing.get(&(category_id, budget.period.clone())) {
+            restore.ids.insert(budget.id, *id);
+            report.matched += 1;
+            continue;
+        }
+        rows.push(DbBudget {
+            id: restore.create(budget.id),
+            ledger_id,
+            category_id,
+            updated_at: now,
+            ..budget.clone()
+        });
+    }
+    report.created = rows.len();
+    if !dry_run {
+        insert_rows!(conn, budgets::table, rows);
+    }
+    tables.push(report);
+
+    // A rate the user already has for the same pair and day is kept
+    let existing: HashSet<(String, String, NaiveDate)> = exchange_rates::table
+        .filter(exchange_rates::ledger_id.eq(ledger_id))
+        .select((exchange_rates::from_currency, exchange_rates::to_currency, exchange_rates::rate_date))
+        .load::<(String, String, NaiveDate)>(conn)?
+        .into_iter()
+        .collect();
+    let mut report = table_report("exchange_rates", manifest.exchange_rates.len());
+    let mut rows = Vec::new();
+    for rate in &manifest.exchange_rates {
+        if existing.contains(&(rate.from_currency.clone(), rate.to_currency.clone(), rate.rate_date)) {
+            report.matched += 1;
+            continue;
+        }
+        rows.push(DbExchangeRate {
+            id: restore.create(rate.id),
+            ledger_id,
+            updated_at: now,
+            ..rate.clone()
+        });
+    }
+    report.created = rows.len();
+    if !dry_run {
+        insert_rows!(conn, exchange_rates::table, rows);
+    }
+    tables.push(report);
+
+    // A category or account the user already named keeps its name
+    let existing: HashSet<Uuid> = ledger_accounts::table
+        .filter(ledger_accounts::ledger_id.eq(ledger_id))
+        .select((ledger_accounts::category_id, ledger_accounts::account_id))
+        .load::<(Option<Uuid>, Option<Uuid>)>(conn)?
+        .into_iter()
+        .filter_map(|(category_id, account_id)| category_id.or(account_id))
+        .collect();
+    let mut report = table_report("ledger_accounts", manifest.ledger_accounts.len());
+    let mut rows = Vec::new();
+    for mapping in &manifest.ledger_accounts {
+        let (category_id, account_id) = (restore.map(mapping.category_id), restore.map(mapping.account_id));
+        match category_id.or(account_id) {
+            Some(target) if existing.contains(&target) => report.matched += 1,
+            Some(_) => rows.push(DbLedgerAccount {
+                id: restore.create(mapping.id),
+                ledger_id,
+                category_id,
+                account_id,
+                updated_at: now,
+                ..mapping.clone()
+            }),
+            None => report.skipped += 1,
+        }
+    }
+    report.created = rows.len();
+    if !dry_run {
+        insert_rows!(conn, ledger_accounts::table, rows);
+    }
+    tables.push(report);
+
+    // A pair goes with its transactions; pairs of transactions the user already had are left out
+    let mut report = table_report("reconciliation_matches", manifest.reconciliation_matches.len());
+    let mut rows = Vec::new();
+    for pair in &manifest.reconciliation_matches {
+        let receipt = restore.map(Some(pair.receipt_transaction_id));
+        let bank_line = restore.map(Some(pair.bank_transaction_id));
+        match (receipt, bank_line) {
+            (Some(receipt), Some(bank_line)) if created.contains(&receipt) && created.contains(&bank_line) => {
+                rows.push(DbReconciliationMatch {
+                    id: restore.create(pair.id),
+                    ledger_id,
+                    receipt_transaction_id: receipt,
+                    bank_transaction_id: bank_line,
+                    updated_at: now,
+                    ..pair.clone()
+                })
+            }
+            _ => report.skipped += 1,
+        }
+    }
+    report.created = rows.len();
+    if !dry_run {
+        insert_rows!(conn, reconciliation_matches::table, rows);
+    }
+    tables.push(report);
+
+    // Amounts are reported in the base currency, so it only follows the backup into an empty ledger
+    let base_currency = ledgers::table.find(ledger_id).select(ledgers::base_currency).first::<String>(conn)?;
+    if base_currency != manifest.user.base_currency {
+        if had_transactions {
+            warnings.push(format!(
+                "The backup's base currency is {} but yours is {}; it was left as {}",
+                manifest.user.base_currency, base_currency, base_currency
+            ));
+        } else if !dry_run {
+            ledger_service::set_base_currency(conn, ledger_id, Currency::from_code(&manifest.user.base_currency))?;
+        }
+    }
+    if restore.missing_files > 0 {
+        warnings.push(format!(
+            "{} bill or receipt images weren't in the backup and were left out",
+            restore.missing_files
+        ));
+    }
+
+    Ok(RestoreReport {
+        dry_run,
+        format_version: manifest.format_version,
+        schema_version: manifest.schema_version.clone(),
+        backup_created_at: manifest.created_at,
+        backup_user: manifest.user.email.clone(),
+        tables,
+        files_restored: restore.files.len(),
+        warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest_json(format_version: u32, schema_version: &str) -> JsonValue {
+        let empty: [JsonValue; 0] = [];
+        serde_json::json!({
+            "format_version": format_version,
+            "schema_version": schema_version,
+            "app_version": "0.1.0",
+            "created_at": "2024-01-31T12:00:00Z",
+            "user": {
+                "id": Uuid::new_v4(),
+                "email": "owner@example.com",
+                "name": "Owner",
+                "base_currency": "THB",
+            },
+            "accounts": empty, "categories": empty, "category_keywords": empty, "merchants": empty,
+            "merchant_aliases": empty, "tags": empty, "recurring_transactions": empty, "import_profiles": empty,
+            "import_batches": empty, "transactions": empty, "transaction_tags": empty, "transaction_splits": empty,
+            "bills": empty, "rules": empty, "budgets": empty, "exchange_rates": empty, "ledger_accounts": empty,
+            "files": {},
+        })
+    }
+
+    fn zip_with(name: &str, bytes: &[u8]) -> Vec<u8> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        zip.start_file(name, FileOptions::default()).unwrap();
+        zip.write_all(bytes).unwrap();
+        zip.finish().unwrap().into_inner()
+    }
+
+    fn read_error(bytes: Vec<u8>) -> String {
+        match read_archive(bytes) {
+            Err(AppError::BadRequest(message)) => message,
+            Err(e) => panic!("unexpected error {:?}", e),
+            Ok(_) => panic!("the archive was read"),
+        }
+    }
+
+    #[test]
+    fn writes_an_archive_that_reads_back() {
+        let path = std::env::temp_dir().join(format!("{}.jpg", Uuid::new_v4()));
+        fs::write(&path, b"receipt").unwrap();
+        let original = path.to_string_lossy().into_owned();
+        let mut json = manifest_json(BACKUP_FORMAT_VERSION, SCHEMA_VERSION);
+        json["files"] = serde_json::json!({ original.clone(): "files/receipt.jpg" });
+        let manifest: BackupManifest = serde_json::from_value(json).unwrap();
+
+        let bytes = write_archive(&manifest);
+        fs::remove_file(&path).unwrap();
+        let (read, mut archive) = read_archive(bytes.unwrap()).unwrap();
+        assert_eq!(read.user.email, "owner@example.com");
+        assert_eq!(read.files.get(&original).map(String::as_str), Some("files/receipt.jpg"));
+        assert!(read.split_tags.is_empty() && read.reconciliation_matches.is_empty());
+        let mut stored = Vec::new();
+        archive.by_name("files/receipt.jpg").unwrap().read_to_end(&mut stored).unwrap();
+        assert_eq!(stored, b"receipt");
+    }
+
+    #[test]
+    fn refuses_archives_from_a_newer_version() {
+        let newer_format = manifest_json(BACKUP_FORMAT_VERSION + 1, SCHEMA_VERSION).to_string();
+        assert!(read_error(zip_with(MANIFEST_NAME, newer_format.as_bytes())).contains("newer version"));
+        let newer_schema = manifest_json(BACKUP_FORMAT_VERSION, "2099-01-01-000001").to_string();
+        assert!(read_error(zip_with(MANIFEST_NAME, newer_schema.as_bytes())).contains("newer version"));
+        let older_schema = manifest_json(BACKUP_FORMAT_VERSION, "2026-10-18-000001").to_string();
+        assert!(read_archive(zip_with(MANIFEST_NAME, older_schema.as_bytes())).is_ok());
+    }
+
+    #[test]
+    fn refuses_files_that_are_not_backups() {
+        assert!(read_error(b"not a zip".to_vec()).starts_with("The file is not a valid backup archive"));
+        assert!(read_error(zip_with("other.json", b"{}")).contains("no manifest.json"));
+        assert!(read_error(zip_with(MANIFEST_NAME, b"{}")).contains("no format or schema version"));
+    }
+
+    #[test]
+    fn reads_rows_from_before_the_ledger_id_rename() {
+        let ledger_id = Uuid::new_v4();
+        let mut json = manifest_json(BACKUP_FORMAT_VERSION, "2026-10-18-000023");
+        json["categories"] = serde_json::json!([{
+            "id": Uuid::new_v4(),
+            "name": "Food",
+            "description": null,
+            "color": null,
+            "icon": null,
+            "user_id": ledger_id,
+            "created_at": "2024-01-31T12:00:00Z",
+            "updated_at": "2024-01-31T12:00:00Z",
+            "kind": "expense",
+        }]);
+        let (manifest, _) = read_archive(zip_with(MANIFEST_NAME, json.to_string().as_bytes())).unwrap();
+        assert_eq!(manifest.categories[0].ledger_id, ledger_id);
+        assert_eq!(manifest.categories[0].deleted_at, None);
+    }
+
+    #[test]
+    fn matches_rows_however_the_amount_and_name_are_written() {
+        let date = "2024-01-31T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let amount = |text: &str| text.parse::<BigDecimal>().unwrap();
+        assert_eq!(
+            transaction_key(&date, &amount("42.50"), "THB", " Coffee & Co ", "expense"),
+            transaction_key(&date, &amount("42.5"), "THB", "coffee & co", "expense")
+        );
+        assert_ne!(
+            transaction_key(&date, &amount("42.50"), "THB", "Coffee & Co", "expense"),
+            transaction_key(&date, &amount("42.50"), "THB", "Coffee & Co", "income")
+        );
+        assert_eq!(
+            recurring_key("Rent ", &amount("1000.00"), "monthly"),
+            recurring_key("rent", &amount("1000"), "monthly")
+        );
+    }
+
+    #[test]
+    fn remaps_ids_and_unpacks_each_file_once() {
+        let mut json = manifest_json(BACKUP_FORMAT_VERSION, SCHEMA_VERSION);
+        json["files"] = serde_json::json!({
+            "/uploads/a.png": "files/a.png",
+            "/uploads/b.png": "files/a.png",
+            "/uploads/gone.png": "files/gone.png",
+        });
+        let manifest: BackupManifest = serde_json::from_value(json).unwrap();
+        let mut restore = Restore {
+            ids: HashMap::new(),
+            files: Vec::new(),
+            entries: HashSet::from(["files/a.png".to_string()]),
+            bill_dir: PathBuf::from("/uploads/bills"),
+            missing_files: 0,
+        };
+
+        let old = Uuid::new_v4();
+        let new = restore.create(old);
+        assert_ne!(new, old);
+        assert_eq!(restore.map(Some(old)), Some(new));
+        assert_eq!(restore.map(Some(Uuid::new_v4())), None);
+        assert_eq!(restore.map(None), None);
+
+        let a = restore.file(&manifest, Some(&"/uploads/a.png".to_string())).unwrap();
+        assert!(a.starts_with("/uploads/bills/") && a.ends_with(".png"));
+        assert_eq!(restore.file(&manifest, Some(&"/uploads/b.png".to_string())), Some(a));
+        assert_eq!(restore.file(&manifest, Some(&"/uploads/gone.png".to_string())), None);
+        assert_eq!(restore.file(&manifest, Some(&"/elsewhere.png".to_string())), None);
+        assert_eq!(restore.file(&manifest, None), None);
+        assert_eq!(restore.files.len(), 1);
+        assert_eq!(restore.missing_files, 2);
+    }
+}
@@ -0,0 +1,30 @@
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel::PgConnection;
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::models::bill::{DbBill, BILL_STATUS_REVIEWED};
+use crate::schema::bills;
+
+pub fn find_bill(conn: &mut PgConnection, ledger_id: Uuid, bill_id: Uuid) -> Result<DbBill, AppError> {
+    bills::table
+        .filter(bills::id.eq(bill_id))
+        .filter(bills::ledger_id.eq(ledger_id))
+        .filter(bills::deleted_at.is_null())
+        .first::<DbBill>(conn)
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("Bill {} not found", bill_id)))
+}
+
+// Link a bill to the transaction created from it, which ends its review
+pub fn mark_reviewed(conn: &mut PgConnection, bill_id: Uuid, transaction_id: Uuid) -> Result<(), AppError> {
+    diesel::update(bills::table.find(bill_id))
+        .set((
+            bills::transaction_id.eq(Some(transaction_id)),
+            bills::status.eq(BILL_STATUS_REVIEWED),
+            bills::updated_at.eq(Utc::now()),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
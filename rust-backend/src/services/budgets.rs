@@ -0,0 +1,283 @@
+use bigdecimal::{BigDecimal, Zero};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+use diesel::prelude::*;
+use diesel::PgConnection;
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::money::{Currency, Money};
+use crate::models::budget::{BudgetPeriod, BudgetStatus, DbBudget};
+use crate::models::transaction::SPENDING_TYPES;
+use crate::schema::{budgets, categories, transaction_allocations};
+use crate::services::exchange_rates::{base_currency, convert_amount};
+use crate::services::reports::{percent_of, spending_amount};
+use crate::services::transactions::day_start;
+
+fn first_of_month(year: i32, month: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, month, 1).expect("first of month is a valid date")
+}
+
+fn next_month(date: NaiveDate) -> NaiveDate {
+    if date.month() == 12 {
+        first_of_month(date.year() + 1, 1)
+    } else {
+        first_of_month(date.year(), date.month() + 1)
+    }
+}
+
+// Calendar period containing `date` as [start, end); custom budgets have exactly one period
+pub fn period_bounds(period: BudgetPeriod, budget: &DbBudget, date: NaiveDate) -> (NaiveDate, NaiveDate) {
+    match period {
+        BudgetPeriod::Weekly => {
+            let start = date - Duration::days(date.weekday().num_days_from_monday() as i64);
+            (start, start + Duration::days(7))
+        }
+        BudgetPeriod::Monthly => {
+            let start = first_of_month(date.year(), date.month());
+            (start, next_month(start))
+        }
+        BudgetPeriod::Yearly => (first_of_month(date.year(), 1), first_of_month(date.year() + 1, 1)),
+        BudgetPeriod::Custom => {
+            let end = budget.end_date.unwrap_or(budget.start_date);
+            (budget.start_date, end + Duration::days(1))
+        }
+    }
+}
+
+pub fn find_budget(conn: &mut PgConnection, ledger_id: Uuid, budget_id: Uuid) -> Result<DbBudget, AppError> {
+    budgets::table
+        .filter(budgets::id.eq(budget_id))
+        .filter(budgets::ledger_id.eq(ledger_id))
+        .first::<DbBudget>(conn)
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("Budget {} not found", budget_id)))
+}
+
+// Name of one of the user's categories; budgets can't point at someone else's category
+pub fn owned_category_name(conn: &mut PgConnection, ledger_id: Uuid, category_id: Uuid) -> Result<String, AppError> {
+    categories::table
+        .filter(categories::id.eq(category_id))
+        .filter(categories::ledger_id.eq(ledger_id))
+        .filter(categories::deleted_at.is_null())
+        .select(categories::name)
+        .first::<String>(conn)
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("Category {} not found", category_id)))
+}
+
+// Budgets are kept in the user's base currency so spending in any currency can count against them
+pub fn budget_amount(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    amount: &BigDecimal,
+    currency: Option<Currency>,
+) -> Result<BigDecimal, AppError> {
+    let base = base_currency(conn, ledger_id)?;
+    match currency {
+        Some(currency) if currency != base => Err(AppError::BadRequest(format!(
+            "Budget amounts must be in your base currency {}, got {}",
+            base, currency
+        ))),
+        _ => Ok(base.round(amount)),
+    }
+}
+
+fn sum_between(rows: &[(DateTime<Utc>, BigDecimal)], start: NaiveDate, end: NaiveDate) -> BigDecimal {
+    let (start, end) = (day_start(start), day_start(end));
+    rows.iter()
+        .filter(|(date, _)| *date >= start && *date < end)
+        .fold(BigDecimal::zero(), |total, (_, amount)| total + amount)
+}
+
+// What was left unspent, or overspent, in each period from `history_start` up to `start`
+fn carried_over(
+    period: BudgetPeriod,
+    budget: &DbBudget,
+    rows: &[(DateTime<Utc>, BigDecimal)],
+    history_start: NaiveDate,
+    start: NaiveDate,
+) -> BigDecimal {
+    let mut rollover = BigDecimal::zero();
+    let mut period_start = history_start;
+    while period_start < start {
+        let (_, period_end) = period_bounds(period, budget, period_start);
+        rollover += &budget.amount - sum_between(rows, period_start, period_end);
+        period_start = period_end;
+    }
+    rollover
+}
+
+// Spending projected over [start, end) from the pace so far, with the days elapsed and in total
+fn projection(spent: &BigDecimal, start: NaiveDate, end: NaiveDate, today: NaiveDate) -> (BigDecimal, i64, i64) {
+    let days_total = (end - start).num_days();
+    let days_elapsed = ((today - start).num_days() + 1).clamp(0, days_total);
+    let projected = if days_elapsed == 0 || days_elapsed >= days_total {
+        spent.clone()
+    } else {
+        spent * BigDecimal::from(days_total) / BigDecimal::from(days_elapsed)
+    };
+    (projected, days_elapsed, days_total)
+}
+
+// Spent, remaining and projected spending for the budget period containing `date`
+pub fn budget_status(
+    conn: &mut PgConnection,
+    budget: &DbBudget,
+    category: String,
+    date: NaiveDate,
+    today: NaiveDate,
+) -> Result<BudgetStatus, AppError> {
+    let period = BudgetPeriod::parse(&budget.period).unwrap_or(BudgetPeriod::Monthly);
+    let (start, end) = period_bounds(period, budget, date);
+
+    // With rollover, every period since the budget started feeds into the current one
+    let carries_over = budget.rollover && period != BudgetPeriod::Custom && budget.start_date < start;
+    let history_start = if carries_over {
+        period_bounds(period, budget, budget.start_date).0
+    } else {
+        start
+    };
+
+    // Purchases less refunds, with split transactions counting only their splits in this category,
+    // converted at the rate for their own day; rows with no usable rate are left out
+    let currency = base_currency(conn, budget.ledger_id)?;
+    let rows: Vec<(DateTime<Utc>, BigDecimal)> = transaction_allocations::table
+        .filter(transaction_allocations::ledger_id.eq(budget.ledger_id))
+        .filter(transaction_allocations::category_id.eq(budget.category_id))
+        .filter(transaction_allocations::excluded.eq(false))
+        .filter(transaction_allocations::transaction_type.eq_any(SPENDING_TYPES))
+        .filter(transaction_allocations::date.ge(day_start(history_start)))
+        .filter(transaction_allocations::date.lt(day_start(end)))
+        .select((
+            transaction_allocations::date,
+            convert_amount(
+                transaction_allocations::ledger_id,
+                spending_amount(transaction_allocations::transaction_type, transaction_allocations::amount),
+                transaction_allocations::currency,
+                currency.to_string(),
+                transaction_allocations::date,
+                "UTC",
+            ),
+        ))
+        .load::<(DateTime<Utc>, Option<BigDecimal>)>(conn)?
+        .into_iter()
+        .filter_map(|(date, amount)| amount.map(|amount| (date, amount)))
+        .collect();
+
+    let rollover = if carries_over {
+        carried_over(period, budget, &rows, history_start, start)
+    } else {
+        BigDecimal::zero()
+    };
+
+    let spent = sum_between(&rows, start, end);
+    let available = &budget.amount + &rollover;
+    let remaining = &available - &spent;
+
+    let (projected, days_elapsed, days_total) = projection(&spent, start, end, today);
+    let percent_used = percent_of(&spent, &available);
+
+    Ok(BudgetStatus {
+        budget_id: budget.id,
+        category_id: budget.category_id,
+        category,
+        period: budget.period.clone(),
+        period_start: start,
+        period_end: end - Duration::days(1),
+        currency,
+        amount: Money::new(budget.amount.clone(), currency),
+        rollover: Money::new(rollover, currency),
+        projected_remaining: Money::new(&available - &projected, currency),
+        available: Money::new(available, currency),
+        spent: Money::new(spent, currency),
+        remaining: Money::new(remaining, currency),
+        percent_used,
+        projected: Money::new(projected, currency),
+        days_elapsed,
+        days_total,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn budget(amount: i64, start_date: NaiveDate, end_date: Option<NaiveDate>) -> DbBudget {
+        DbBudget {
+            id: Uuid::new_v4(),
+            ledger_id: Uuid::new_v4(),
+            category_id: Uuid::new_v4(),
+            amount: BigDecimal::from(amount),
+            period: "monthly".to_string(),
+            start_date,
+            end_date,
+            rollover: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn spending(day: NaiveDate, amount: i64) -> (DateTime<Utc>, BigDecimal) {
+        (day_start(day) + Duration::hours(12), BigDecimal::from(amount))
+    }
+
+    #[test]
+    fn finds_calendar_periods() {
+        let b = budget(1000, date(2024, 1, 1), None);
+        // 2024-02-29 is a Thursday
+        assert_eq!(
+            period_bounds(BudgetPeriod::Weekly, &b, date(2024, 2, 29)),
+            (date(2024, 2, 26), date(2024, 3, 4))
+        );
+        assert_eq!(
+            period_bounds(BudgetPeriod::Monthly, &b, date(2024, 12, 31)),
+            (date(2024, 12, 1), date(2025, 1, 1))
+        );
+        assert_eq!(
+            period_bounds(BudgetPeriod::Yearly, &b, date(2024, 6, 15)),
+            (date(2024, 1, 1), date(2025, 1, 1))
+        );
+    }
+
+    #[test]
+    fn custom_periods_include_their_last_day() {
+        let b = budget(1000, date(2024, 3, 10), Some(date(2024, 3, 20)));
+        assert_eq!(
+            period_bounds(BudgetPeriod::Custom, &b, date(2024, 5, 1)),
+            (date(2024, 3, 10), date(2024, 3, 21))
+        );
+        let open = budget(1000, date(2024, 3, 10), None);
+        assert_eq!(
+            period_bounds(BudgetPeriod::Custom, &open, date(2024, 5, 1)),
+            (date(2024, 3, 10), date(2024, 3, 11))
+        );
+    }
+
+    #[test]
+    fn carries_unspent_and_overspent_amounts_forward() {
+        let b = budget(1000, date(2024, 1, 15), None);
+        let rows = [
+            spending(date(2024, 1, 20), 400),
+            spending(date(2024, 2, 3), 1300),
+            spending(date(2024, 3, 2), 200),
+        ];
+        // January leaves 600 and February overspends by 300; March is the current period
+        let rollover = carried_over(BudgetPeriod::Monthly, &b, &rows, date(2024, 1, 1), date(2024, 3, 1));
+        assert_eq!(rollover, BigDecimal::from(300));
+        assert_eq!(sum_between(&rows, date(2024, 3, 1), date(2024, 4, 1)), BigDecimal::from(200));
+    }
+
+    #[test]
+    fn projects_the_pace_over_the_period() {
+        let spent = BigDecimal::from(300);
+        let (start, end) = (date(2024, 4, 1), date(2024, 5, 1));
+        assert_eq!(projection(&spent, start, end, date(2024, 4, 10)), (BigDecimal::from(900), 10, 30));
+        // Before the period and once it is over, the projection is what was spent
+        assert_eq!(projection(&spent, start, end, date(2024, 3, 20)), (spent.clone(), 0, 30));
+        assert_eq!(projection(&spent, start, end, date(2024, 6, 1)), (spent.clone(), 30, 30));
+    }
+}
@@ -0,0 +1,349 @@
+use std::collections::HashSet;
+
+use chrono::{DateTime, Duration, Utc};
+use diesel::dsl::IntervalDsl;
+use diesel::prelude::*;
+use diesel::sql_query;
+use diesel::sql_types::{Array, Jsonb};
+use diesel::PgConnection;
+use serde_json::Value as JsonValue;
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::models::bulk::{BulkOperation, BulkOperationDto, DbBulkOperation, NewBulkOperation};
+use crate::models::category::CategoryKind;
+use crate::models::transaction::{DbTransaction, TransactionType};
+use crate::schema::{bulk_operations, transaction_tags, transactions};
+use crate::services::accounts::account_for_transaction;
+use crate::services::merchants::resolve_or_create_merchant;
+use crate::services::tags::{add_tags, existing_tag_ids, remove_tags, resolve_tags};
+use crate::services::transactions::{filtered_query, resolve_category_id};
+
+// How long after a bulk operation its undo token still works
+const UNDO_WINDOW_HOURS: i64 = 24;
+
+// Everything an undo puts back: the rows themselves, their splits and tag links, and the bills and
+// refunds pointing at them, which a delete would otherwise leave unlinked
+const SNAPSHOT_SQL: &str = "
+    SELECT jsonb_build_object(
+        'transactions', COALESCE((
+            SELECT jsonb_agg(to_jsonb(t) - 'search_text' - 'search_vector')
+            FROM transactions t WHERE t.id = ANY($1)), '[]'::jsonb),
+        'splits', COALESCE((
+            SELECT jsonb_agg(to_jsonb(s)) FROM transaction_splits s WHERE s.transaction_id = ANY($1)), '[]'::jsonb),
+        'tags', COALESCE((
+            SELECT jsonb_agg(to_jsonb(l)) FROM transaction_tags l WHERE l.transaction_id = ANY($1)), '[]'::jsonb),
+        'split_tags', COALESCE((
+            SELECT jsonb_agg(to_jsonb(l))
+            FROM split_tags l JOIN transaction_splits s ON s.id = l.split_id
+            WHERE s.transaction_id = ANY($1)), '[]'::jsonb),
+        'bills', COALESCE((
+            SELECT jsonb_agg(jsonb_build_object('id', b.id, 'transaction_id', b.transaction_id))
+            FROM bills b WHERE b.transaction_id = ANY($1)), '[]'::jsonb),
+        'refunds', COALESCE((
+            SELECT jsonb_agg(jsonb_build_object('id', r.id, 'refund_of', r.refund_of))
+            FROM transactions r WHERE r.refund_of = ANY($1)), '[]'::jsonb)
+    ) AS snapshot";
+
+// The search columns are left out of the snapshot; the trigger fills them in again on insert.
+// Deletes now only move rows to the trash, so these put back what older operations or the trash
+// purge removed for good
+const RESTORE_DELETED_SQL: &str = "
+    INSERT INTO transactions
+    SELECT * FROM jsonb_populate_recordset(NULL::transactions, $1->'transactions')
+    ON CONFLICT (id) DO NOTHING";
+
+const RESTORE_SPLITS_SQL: &str = "
+    INSERT INTO transaction_splits
+    SELECT * FROM jsonb_populate_recordset(NULL::transaction_splits, $1->'splits')
+    ON CONFLICT DO NOTHING";
+
+const RESTORE_BILL_LINKS_SQL: &str = "
+    UPDATE bills b SET transaction_id = s.transaction_id
+    FROM jsonb_to_recordset($1->'bills') AS s(id UUID, transaction_id UUID)
+    WHERE b.id = s.id AND b.transaction_id IS NULL";
+
+const RESTORE_REFUND_LINKS_SQL: &str = "
+    UPDATE transactions t SET refund_of = s.refund_of
+    FROM jsonb_to_recordset($1->'refunds') AS s(id UUID, refund_of UUID)
+    WHERE t.id = s.id AND t.refund_of IS NULL";
+
+// Only the columns a bulk edit can touch are put back
+const RESTORE_FIELDS_SQL: &str = "
+    UPDATE transactions t
+    SET category_id = s.category_id, merchant = s.merchant, merchant_id = s.merchant_id,
+        account_id = s.account_id, date = s.date, updated_at = NOW()
+    FROM jsonb_populate_recordset(NULL::transactions, $1->'transactions') s
+    WHERE t.id = s.id";
+
+// Tags deleted since the operation can't be put back on
+const RESTORE_TAG_LINKS_SQL: &str = "
+    INSERT INTO transaction_tags (transaction_id, tag_id)
+    SELECT l.transaction_id, l.tag_id
+    FROM jsonb_populate_recordset(NULL::transaction_tags, $1->'tags') l
+    JOIN tags ON tags.id = l.tag_id
+    JOIN transactions t ON t.id = l.transaction_id
+    ON CONFLICT DO NOTHING";
+
+const RESTORE_SPLIT_TAG_LINKS_SQL: &str = "
+    INSERT INTO split_tags (split_id, tag_id)
+    SELECT l.split_id, l.tag_id
+    FROM jsonb_populate_recordset(NULL::split_tags, $1->'split_tags') l
+    JOIN tags ON tags.id = l.tag_id
+    JOIN transaction_splits s ON s.id = l.split_id
+    ON CONFLICT DO NOTHING";
+
+#[derive(QueryableByName)]
+struct Snapshot {
+    #[diesel(sql_type = Jsonb)]
+    snapshot: JsonValue,
+}
+
+// What a bulk request selects; deleting or moving one leg of a transfer takes the other leg along
+pub fn select_transactions(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    data: &BulkOperationDto,
+) -> Result<Vec<DbTransaction>, AppError> {
+    let mut rows = match (&data.transaction_ids, &data.filters) {
+        (Some(ids), None) => {
+            let rows = transactions::table
+                .filter(transactions::ledger_id.eq(ledger_id))
+                .filter(transactions::id.eq_any(ids))
+                .filter(transactions::deleted_at.is_null())
+                .load::<DbTransaction>(conn)?;
+            let found: HashSet<Uuid> = rows.iter().map(|t| t.id).collect();
+            if let Some(missing) = ids.iter().find(|id| !found.contains(id)) {
+                return Err(AppError::NotFound(format!("Transaction {} not found", missing)));
+            }
+            rows
+        }
+        (None, Some(filters)) => filtered_query(ledger_id, filters).load::<DbTransaction>(conn)?,
+        _ => {
+            return Err(AppError::BadRequest(
+                "Give either transaction_ids or filters to choose the transactions".to_string(),
+            ))
+        }
+    };
+
+    if matches!(data.operation, BulkOperation::Delete | BulkOperation::ShiftDate { .. }) {
+        let selected: Vec<Uuid> = rows.iter().map(|t| t.id).collect();
+        let transfer_ids: Vec<Uuid> = rows.iter().filter_map(|t| t.transfer_id).collect();
+        if !transfer_ids.is_empty() {
+            let other_legs = transactions::table
+                .filter(transactions::ledger_id.eq(ledger_id))
+                .filter(transactions::transfer_id.eq_any(transfer_ids))
+                .filter(transactions::id.ne_all(selected))
+                .filter(transactions::deleted_at.is_null())
+                .load::<DbTransaction>(conn)?;
+            rows.extend(other_legs);
+        }
+    }
+    Ok(rows)
+}
+
+// Refuse an operation that can't apply to every selected transaction, before anything is written
+pub fn validate_operation(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    operation: &BulkOperation,
+    rows: &[DbTransaction],
+) -> Result<(), AppError> {
+    let has_transfer = rows.iter().any(|t| t.transaction_type == TransactionType::Transfer.as_str());
+    match operation {
+        BulkOperation::Recategorize { .. } | BulkOperation::SetMerchant { .. } if has_transfer => Err(
+            AppError::BadRequest("Transfers have no category or merchant; leave them out of the selection".to_string()),
+        ),
+        BulkOperation::SetMerchant { merchant } if merchant.trim().is_empty() => {
+            Err(AppError::BadRequest("Merchant name is empty".to_string()))
+        }
+        BulkOperation::AddTag { tag } | BulkOperation::RemoveTag { tag } if tag.trim().is_empty() => {
+            Err(AppError::BadRequest("Tag name is empty".to_string()))
+        }
+        BulkOperation::ChangeAccount { .. } if has_transfer => Err(AppError::BadRequest(
+            "Move transfers through the accounts API; leave them out of the selection".to_string(),
+        )),
+        BulkOperation::ChangeAccount { account_id } => {
+            let currencies: HashSet<&str> = rows.iter().map(|t| t.currency.as_str()).collect();
+            for currency in currencies {
+                account_for_transaction(conn, ledger_id, *account_id, currency)?;
+            }
+            Ok(())
+        }
+        BulkOperation::ShiftDate { days } if *days == 0 => {
+            Err(AppError::BadRequest("Shift dates by at least one day".to_string()))
+        }
+        _ => Ok(()),
+    }
+}
+
+// Apply the operation and record a snapshot to undo it with; returns the undo token
+pub fn apply_operation(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    operation: &BulkOperation,
+    rows: &[DbTransaction],
+) -> Result<Uuid, AppError> {
+    let applied_at = Utc::now();
+    let ids: Vec<Uuid> = rows.iter().map(|t| t.id).collect();
+    let snapshot = sql_query(SNAPSHOT_SQL)
+        .bind::<Array<diesel::sql_types::Uuid>, _>(&ids)
+        .get_result::<Snapshot>(conn)?
+        .snapshot;
+    let selected = transactions::table.filter(transactions::id.eq_any(&ids));
+
+    match operation {
+        BulkOperation::Recategorize { category } => {
+            // Income gets an income category; anything mixed is treated as spending
+            let kind = if rows.iter().all(|t| t.transaction_type == TransactionType::Income.as_str()) {
+                CategoryKind::Income
+            } else {
+                CategoryKind::Expense
+            };
+            let category_id = resolve_category_id(conn, ledger_id, category, kind)?;
+            diesel::update(selected)
+                .set((transactions::category_id.eq(category_id), transactions::updated_at.eq(applied_at)))
+                .execute(conn)?;
+        }
+        BulkOperation::SetMerchant { merchant } => {
+            let merchant = resolve_or_create_merchant(conn, ledger_id, merchant, None)?.merchant;
+            diesel::update(selected)
+                .set((
+                    transactions::merchant.eq(&merchant.name),
+                    transactions::merchant_id.eq(Some(merchant.id)),
+                    transactions::updated_at.eq(applied_at),
+                ))
+                .execute(conn)?;
+        }
+        BulkOperation::AddTag { tag } => {
+            let tags = resolve_tags(conn, ledger_id, std::slice::from_ref(tag))?;
+            add_tags(conn, &ids, &tags)?;
+            diesel::update(selected).set(transactions::updated_at.eq(applied_at)).execute(conn)?;
+        }
+        BulkOperation::RemoveTag { tag } => {
+            let tag_ids = existing_tag_ids(conn, ledger_id, std::slice::from_ref(tag))?;
+            remove_tags(conn, &ids, &tag_ids)?;
+            diesel::update(selected).set(transactions::updated_at.eq(applied_at)).execute(conn)?;
+        }
+        BulkOperation::Delete => {
+            diesel::update(selected)
+                .set(transactions::deleted_at.eq(Some(applied_at)))
+                .execute(conn)?;
+        }
+        BulkOperation::ChangeAccount { account_id } => {
+            diesel::update(selected)
+                .set((transactions::account_id.eq(Some(*account_id)), transactions::updated_at.eq(applied_at)))
+                .execute(conn)?;
+        }
+        BulkOperation::ShiftDate { days } => {
+            diesel::update(selected)
+                .set((
+                    transactions::date.eq(transactions::date + days.days()),
+                    transactions::updated_at.eq(applied_at),
+                ))
+                .execute(conn)?;
+        }
+    }
+
+    let record = NewBulkOperation {
+        id: Uuid::new_v4(),
+        ledger_id,
+        operation: serde_json::to_value(operation)?,
+        transaction_ids: ids,
+        snapshot,
+        applied_at,
+    };
+    diesel::insert_into(bulk_operations::table).values(&record).execute(conn)?;
+    Ok(record.id)
+}
+
+pub fn find_bulk_operation(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    token: Uuid,
+) -> Result<DbBulkOperation, AppError> {
+    bulk_operations::table
+        .filter(bulk_operations::id.eq(token))
+        .filter(bulk_operations::ledger_id.eq(ledger_id))
+        .first::<DbBulkOperation>(conn)
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("Bulk operation {} not found", token)))
+}
+
+// Put the transactions back as the snapshot recorded them. Refused once any of them has been
+// edited since, so an undo never throws away later work
+pub fn undo_operation(conn: &mut PgConnection, ledger_id: Uuid, token: Uuid) -> Result<usize, AppError> {
+    conn.transaction(|conn| {
+        let record = find_bulk_operation(conn, ledger_id, token)?;
+        if record.undone_at.is_some() {
+            return Err(AppError::BadRequest("This bulk operation was already undone".to_string()));
+        }
+        if record.applied_at < Utc::now() - Duration::hours(UNDO_WINDOW_HOURS) {
+            return Err(AppError::BadRequest(format!(
+                "Bulk operations can only be undone within {} hours",
+                UNDO_WINDOW_HOURS
+            )));
+        }
+
+        let operation: BulkOperation = serde_json::from_value(record.operation.clone())?;
+        let restored = match operation {
+            BulkOperation::Delete => restore_deleted(conn, &record)?,
+            _ => restore_fields(conn, &record)?,
+        };
+
+        diesel::update(bulk_operations::table.find(record.id))
+            .set(bulk_operations::undone_at.eq(Some(Utc::now())))
+            .execute(conn)?;
+        Ok(restored)
+    })
+}
+
+fn restore_deleted(conn: &mut PgConnection, record: &DbBulkOperation) -> Result<usize, AppError> {
+    let untrashed = diesel::update(
+        transactions::table
+            .filter(transactions::id.eq_any(&record.transaction_ids))
+            .filter(transactions::deleted_at.eq(record.applied_at)),
+    )
+    .set(transactions::deleted_at.eq(None::<DateTime<Utc>>))
+    .execute(conn)?;
+    let restored = untrashed
+        + sql_query(RESTORE_DELETED_SQL)
+            .bind::<Jsonb, _>(&record.snapshot)
+            .execute(conn)?;
+    for statement in [
+        RESTORE_SPLITS_SQL,
+        RESTORE_SPLIT_TAG_LINKS_SQL,
+        RESTORE_TAG_LINKS_SQL,
+        RESTORE_BILL_LINKS_SQL,
+        RESTORE_REFUND_LINKS_SQL,
+    ] {
+        sql_query(statement).bind::<Jsonb, _>(&record.snapshot).execute(conn)?;
+    }
+    Ok(restored)
+}
+
+fn restore_fields(conn: &mut PgConnection, record: &DbBulkOperation) -> Result<usize, AppError> {
+    let changed = transactions::table
+        .filter(transactions::id.eq_any(&record.transaction_ids))
+        .filter(transactions::updated_at.gt(record.applied_at))
+        .count()
+        .get_result::<i64>(conn)?;
+    if changed > 0 {
+        return Err(AppError::BadRequest(format!(
+            "{} of the transactions were edited after the bulk operation; undoing it would overwrite that",
+            changed
+        )));
+    }
+
+    let restored = sql_query(RESTORE_FIELDS_SQL)
+        .bind::<Jsonb, _>(&record.snapshot)
+        .execute(conn)?;
+    diesel::delete(
+        transaction_tags::table.filter(transaction_tags::transaction_id.eq_any(&record.transaction_ids)),
+    )
+    .execute(conn)?;
+    sql_query(RESTORE_TAG_LINKS_SQL)
+        .bind::<Jsonb, _>(&record.snapshot)
+        .execute(conn)?;
+    Ok(restored)
+}
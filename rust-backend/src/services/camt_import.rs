@@ -0,0 +1,349 @@
+use std::collections::HashMap;
+
+use bigdecimal::{BigDecimal, Signed, Zero};
+use chrono::NaiveDate;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+
+use crate::error::AppError;
+use crate::models::import::{BankStatement, ImportRowError, ParsedRow, StatementBalances};
+use crate::money::{Currency, Money};
+
+// The values of one balance or entry read so far, keyed by their path below it, e.g.
+// "BookgDt/Dt", with attributes as "Amt@Ccy"
+type Fields = HashMap<String, String>;
+
+// A balance or entry being read, with the depth of the element that opened it
+enum Block {
+    Balance(Fields, usize),
+    Entry(Fields, usize, usize),
+}
+
+fn local_name(element: &BytesStart) -> String {
+    String::from_utf8_lossy(element.local_name().as_ref()).into_owned()
+}
+
+// Dates come as 2024-01-31 or as a date and time
+fn parse_camt_date(text: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(text.get(..10)?, "%Y-%m-%d").ok()
+}
+
+fn signed_amount(fields: &Fields) -> Result<Money, String> {
+    let field = |name: &str| fields.get(name).map(String::as_str);
+    let text = field("Amt").ok_or("There is no amount")?;
+    let amount = text.parse::<BigDecimal>().map_err(|_| format!("Can't read the amount {}", text))?;
+    let code = field("Amt@Ccy").ok_or("The amount has no currency")?;
+    let currency = Currency::parse(code).ok_or_else(|| format!("Unknown currency {}", code))?;
+    match field("CdtDbtInd") {
+        Some("CRDT") => Ok(Money::new(amount, currency)),
+        Some("DBIT") => Ok(Money::new(-amount, currency)),
+        other => Err(format!("Unknown credit/debit indicator {}", other.unwrap_or("(none)"))),
+    }
+}
+
+fn entry_row(fields: &Fields, line: usize) -> Result<ParsedRow, String> {
+    let field = |name: &str| fields.get(name).map(String::as_str).filter(|v| !v.is_empty());
+
+    // Pending and information-only entries can still change; they're imported once booked
+    let status = field("Sts/Cd").or(field("Sts")).unwrap_or("BOOK");
+    if status != "BOOK" {
+        return Err(format!("The entry is {}, not booked", status));
+    }
+    let amount = signed_amount(fields)?;
+    if amount.amount().is_zero() {
+        return Err("The amount is zero".to_string());
+    }
+    let income = amount.amount().is_positive();
+    let date_text = field("BookgDt/Dt").or(field("BookgDt/DtTm")).ok_or("The entry has no booking date")?;
+    let date = parse_camt_date(date_text).ok_or_else(|| format!("Can't read the date {}", date_text))?;
+    let value_date = field("ValDt/Dt")
+        .or(field("ValDt/DtTm"))
+        .and_then(parse_camt_date)
+        .filter(|value_date| *value_date != date);
+
+    // The other party is whoever paid for money in and whoever was paid for money out. Later
+    // versions of the schema wrap the party in <Pty>
+    let party = if income { "Dbtr" } else { "Cdtr" };
+    let counterparty = field(&format!("NtryDtls/TxDtls/RltdPties/{}/Nm", party))
+        .or(field(&format!("NtryDtls/TxDtls/RltdPties/{}/Pty/Nm", party)));
+    let remittance = field("NtryDtls/TxDtls/RmtInf/Ustrd");
+    let info = field("AddtlNtryInf").or(field("NtryDtls/TxDtls/AddtlTxInf"));
+    let description = counterparty
+        .or(remittance)
+        .or(info)
+        .ok_or("The entry has no counterparty or description")?;
+    let notes: Vec<&str> = [remittance, info].into_iter().flatten().filter(|n| *n != description).collect();
+
+    Ok(ParsedRow {
+        line,
+        date,
+        income,
+        amount: Money::new(amount.amount().abs(), amount.currency()),
+        description: description.to_string(),
+        notes: Some(notes.join(" / ")).filter(|n| !n.is_empty()),
+        external_id: field("AcctSvcrRef")
+            .or(field("NtryDtls/TxDtls/Refs/AcctSvcrRef"))
+            .or(field("NtryRef"))
+            .map(str::to_string),
+        category: None,
+        value_date,
+        reference: field("NtryDtls/TxDtls/Refs/EndToEndId")
+            .filter(|r| *r != "NOTPROVIDED")
+            .or(field("NtryDtls/TxDtls/RmtInf/Strd/CdtrRefInf/Ref"))
+            .map(str::to_string),
+        reversal: field("RvslInd").is_some_and(|v| v.eq_ignore_ascii_case("true")),
+    })
+}
+
+// Record a value or attribute under its path below the open block; remittance text spread over
+// several elements is joined
+fn record(block: &mut Option<Block>, stack: &[String], key_suffix: &str, value: String) {
+    let (fields, depth) = match block {
+        Some(Block::Balance(fields, depth)) | Some(Block::Entry(fields, depth, _)) => (fields, *depth),
+        None => return,
+    };
+    let key = format!("{}{}", stack[depth..].join("/"), key_suffix);
+    if key.ends_with("Ustrd") || key == "AddtlNtryInf" {
+        fields
+            .entry(key)
+            .and_modify(|text| {
+                text.push(' ');
+                text.push_str(&value);
+            })
+            .or_insert(value);
+    } else {
+        // Batched entries list several transactions; the first one's details describe the entry
+        fields.entry(key).or_insert(value);
+    }
+}
+
+fn record_attributes(block: &mut Option<Block>, stack: &[String], element: &BytesStart) {
+    for attribute in element.attributes().flatten() {
+        if attribute.key.local_name().as_ref() == b"Ccy" {
+            if let Ok(value) = attribute.unescape_value() {
+                record(block, stack, "@Ccy", value.trim().to_string());
+            }
+        }
+    }
+}
+
+// The booked entries of an ISO 20022 camt.053 bank-to-customer statement. A file may hold several
+// statements, e.g. one per day, as long as they are for the same account
+pub fn parse_camt053(bytes: &[u8]) -> Result<BankStatement, AppError> {
+    let text = std::str::from_utf8(bytes)
+        .map_err(|_| AppError::BadRequest("A camt.053 file must be UTF-8".to_string()))?
+        .trim_start_matches('\u{feff}');
+    let mut reader = Reader::from_str(text);
+    reader.trim_text(true);
+
+    let mut statement = BankStatement {
+        account: None,
+        balances: None,
+        rows: Vec::new(),
+        errors: Vec::new(),
+    };
+    let mut is_camt = false;
+    let mut opening: Option<Money> = None;
+    let mut closing: Option<Money> = None;
+    let mut stack: Vec<String> = Vec::new();
+    let mut block: Option<Block> = None;
+    // Lines counted up to a position in the text, so entries can be reported by line
+    let (mut line, mut counted_to) = (1, 0);
+
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|e| AppError::BadRequest(format!("The file is not valid XML: {}", e)))?;
+        match event {
+            Event::Start(element) => {
+                let name = local_name(&element);
+                stack.push(name.clone());
+                match name.as_str() {
+                    "BkToCstmrStmt" => is_camt = true,
+                    "Bal" if block.is_none() => block = Some(Block::Balance(Fields::new(), stack.len())),
+                    "Ntry" if block.is_none() => {
+                        let position = reader.buffer_position();
+                        line += text[counted_to..position].matches('\n').count();
+                        counted_to = position;
+                        block = Some(Block::Entry(Fields::new(), stack.len(), line));
+                    }
+                    _ => {}
+                }
+                record_attributes(&mut block, &stack, &element);
+            }
+            Event::Empty(element) => {
+                stack.push(local_name(&element));
+                record_attributes(&mut block, &stack, &element);
+                stack.pop();
+            }
+            Event::Text(value) => {
+                let value = value
+                    .unescape()
+                    .map_err(|e| AppError::BadRequest(format!("The file is not valid XML: {}", e)))?
+                    .trim()
+                    .to_string();
+                let path = stack.join("/");
+                let is_account = path.ends_with("Stmt/Acct/Id/IBAN") || path.ends_with("Stmt/Acct/Id/Othr/Id");
+                if block.is_none() && is_account {
+                    match &statement.account {
+                        Some(account) if *account != value => {
+                            return Err(AppError::BadRequest(
+                                "The file holds statements for several accounts; import them one at a time"
+                                    .to_string(),
+                            ))
+                        }
+                        _ => statement.account = Some(value),
+                    }
+                } else {
+                    record(&mut block, &stack, "", value);
+                }
+            }
+            Event::CData(value) => {
+                let value = String::from_utf8_lossy(&value.into_inner()).trim().to_string();
+                record(&mut block, &stack, "", value);
+            }
+            Event::End(_) => {
+                let closes_block = matches!(
+                    block,
+                    Some(Block::Balance(_, depth)) | Some(Block::Entry(_, depth, _)) if depth == stack.len()
+                );
+                if closes_block {
+                    match block.take() {
+                        Some(Block::Balance(fields, _)) => {
+                            // PRCD, the previous day's closing balance, opens statements without an OPBD
+                            let code = fields.get("Tp/CdOrPrtry/Cd").map(String::as_str);
+                            let amount = signed_amount(&fields).map_err(AppError::BadRequest)?;
+                            match code {
+                                Some("OPBD") | Some("PRCD") if opening.is_none() => opening = Some(amount),
+                                Some("CLBD") => closing = Some(amount),
+                                _ => {}
+                            }
+                        }
+                        Some(Block::Entry(fields, _, start)) => match entry_row(&fields, start) {
+                            Ok(row) => statement.rows.push(row),
+                            Err(message) => statement.errors.push(ImportRowError { line: start, message }),
+                        },
+                        None => {}
+                    }
+                }
+                stack.pop();
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    if !is_camt {
+        return Err(AppError::BadRequest("The file is not a camt.053 statement".to_string()));
+    }
+    if let (Some(opening), Some(closing)) = (opening, closing) {
+        statement.balances = Some(StatementBalances { opening, closing });
+    }
+    Ok(statement)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CAMT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Document xmlns="urn:iso:std:iso:20022:tech:xsd:camt.053.001.02">
+  <BkToCstmrStmt>
+    <Stmt>
+      <Acct><Id><IBAN>DE89370400440532013000</IBAN></Id></Acct>
+      <Bal>
+        <Tp><CdOrPrtry><Cd>PRCD</Cd></CdOrPrtry></Tp>
+        <Amt Ccy="EUR">100.00</Amt>
+        <CdtDbtInd>CRDT</CdtDbtInd>
+      </Bal>
+      <Bal>
+        <Tp><CdOrPrtry><Cd>CLBD</Cd></CdOrPrtry></Tp>
+        <Amt Ccy="EUR">1057.50</Amt>
+        <CdtDbtInd>CRDT</CdtDbtInd>
+      </Bal>
+      <Ntry>
+        <Amt Ccy="EUR">42.50</Amt>
+        <CdtDbtInd>DBIT</CdtDbtInd>
+        <Sts>BOOK</Sts>
+        <BookgDt><Dt>2024-01-31</Dt></BookgDt>
+        <ValDt><Dt>2024-01-30</Dt></ValDt>
+        <AcctSvcrRef>REF-1</AcctSvcrRef>
+        <NtryDtls><TxDtls>
+          <Refs><EndToEndId>INV-2024-001</EndToEndId></Refs>
+          <RltdPties><Cdtr><Nm>Coffee &amp; Co</Nm></Cdtr></RltdPties>
+          <RmtInf><Ustrd>Invoice</Ustrd><Ustrd>January</Ustrd></RmtInf>
+        </TxDtls></NtryDtls>
+      </Ntry>
+      <Ntry>
+        <Amt Ccy="EUR">1000.00</Amt>
+        <CdtDbtInd>CRDT</CdtDbtInd>
+        <RvslInd>true</RvslInd>
+        <Sts><Cd>BOOK</Cd></Sts>
+        <BookgDt><DtTm>2024-02-01T08:00:00</DtTm></BookgDt>
+        <NtryDtls><TxDtls>
+          <Refs><EndToEndId>NOTPROVIDED</EndToEndId></Refs>
+          <RltdPties><Dbtr><Pty><Nm>Employer</Nm></Pty></Dbtr></RltdPties>
+        </TxDtls></NtryDtls>
+      </Ntry>
+      <Ntry>
+        <Amt Ccy="EUR">5.00</Amt>
+        <CdtDbtInd>DBIT</CdtDbtInd>
+        <Sts>PDNG</Sts>
+        <BookgDt><Dt>2024-02-02</Dt></BookgDt>
+        <AddtlNtryInf>Card payment</AddtlNtryInf>
+      </Ntry>
+    </Stmt>
+  </BkToCstmrStmt>
+</Document>
+"#;
+
+    #[test]
+    fn reads_booked_entries_and_balances() {
+        let statement = parse_camt053(CAMT.as_bytes()).unwrap();
+        let euro = Currency::parse("EUR").unwrap();
+        assert_eq!(statement.account.as_deref(), Some("DE89370400440532013000"));
+        let balances = statement.balances.unwrap();
+        assert_eq!(balances.opening.amount().to_string(), "100.00");
+        assert_eq!(balances.closing.amount().to_string(), "1057.50");
+        assert_eq!(statement.rows.len(), 2);
+
+        let coffee = &statement.rows[0];
+        assert_eq!(coffee.line, 16);
+        assert!(!coffee.income);
+        assert_eq!(coffee.amount, Money::new("42.50".parse().unwrap(), euro));
+        assert_eq!(coffee.date, NaiveDate::from_ymd_opt(2024, 1, 31).unwrap());
+        assert_eq!(coffee.value_date, NaiveDate::from_ymd_opt(2024, 1, 30));
+        assert_eq!(coffee.description, "Coffee & Co");
+        assert_eq!(coffee.notes.as_deref(), Some("Invoice January"));
+        assert_eq!(coffee.external_id.as_deref(), Some("REF-1"));
+        assert_eq!(coffee.reference.as_deref(), Some("INV-2024-001"));
+        assert!(!coffee.reversal);
+
+        let salary = &statement.rows[1];
+        assert!(salary.income);
+        assert!(salary.reversal);
+        assert_eq!(salary.date, NaiveDate::from_ymd_opt(2024, 2, 1).unwrap());
+        assert_eq!(salary.value_date, None);
+        assert_eq!(salary.description, "Employer");
+        assert_eq!(salary.reference, None);
+    }
+
+    #[test]
+    fn reports_entries_that_are_not_booked() {
+        let statement = parse_camt053(CAMT.as_bytes()).unwrap();
+        assert_eq!(statement.errors.len(), 1);
+        assert_eq!(statement.errors[0].line, 40);
+        assert_eq!(statement.errors[0].message, "The entry is PDNG, not booked");
+    }
+
+    #[test]
+    fn refuses_files_for_several_accounts_or_that_are_not_camt() {
+        let two_accounts = CAMT.replace(
+            "    </Stmt>\n  </BkToCstmrStmt>",
+            "    </Stmt>\n    <Stmt><Acct><Id><IBAN>NL91ABNA0417164300</IBAN></Id></Acct></Stmt>\n  </BkToCstmrStmt>",
+        );
+        assert!(parse_camt053(two_accounts.as_bytes()).is_err());
+        assert!(parse_camt053(b"<Document><Other/></Document>").is_err());
+        assert!(parse_camt053(b"not xml <<").is_err());
+    }
+}
@@ -0,0 +1,387 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use diesel::dsl::count_star;
+use diesel::prelude::*;
+use diesel::PgConnection;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::models::category::{CategoryFeedbackDto, DbCategory, DbCategoryKeyword, NewCategoryKeyword};
+use crate::schema::{categories, category_keywords, merchants, transactions};
+use crate::services::merchants::{normalize_merchant_name, resolve_merchant};
+
+pub const DEFAULT_SUGGESTION_LIMIT: usize = 3;
+
+// Weight added to a keyword for the chosen category, and removed from a rejected suggestion
+const FEEDBACK_REWARD: f32 = 1.0;
+const FEEDBACK_PENALTY: f32 = 0.5;
+
+// Thai is written without spaces, so a Thai keyword of at least this many letters matches inside a
+// token; a shorter one, e.g. "ยา" or "นม", would fire inside unrelated words like "ยาง" or "ขนม"
+const MIN_THAI_SUBSTRING_LETTERS: usize = 3;
+
+// Built-in keywords per default category name; ASCII keywords and short Thai ones match whole
+// words, longer Thai ones match substrings
+const BUILTIN_KEYWORDS: &[(&str, &[&str])] = &[
+    ("Groceries", &[
+        "supermarket", "grocery", "market", "big c", "lotus", "lotuss", "tops", "makro", "villa",
+        "foodland", "gourmet", "7eleven", "familymart", "lawson", "cp all", "ซีพี ออลล์",
+        "ซูเปอร์มาร์เก็ต", "ตลาดสด", "ผักสด", "ผลไม้", "ข้าวสาร", "นมสด", "ไข่ไก่",
+    ]),
+    ("Dining", &[
+        "restaurant", "cafe", "coffee", "starbucks", "kfc", "mcdonalds", "pizza", "bistro",
+        "bakery", "sushi", "ร้านอาหาร", "กาแฟ", "ก๋วยเตี๋ยว", "ข้าวมันไก่", "ส้มตำ", "ชาบู", "หมูกระทะ",
+    ]),
+    ("Transportation", &[
+        "grab", "bolt", "taxi", "bts", "mrt", "airport rail", "ptt", "shell", "esso", "bangchak",
+        "caltex", "parking", "ปตท", "บางจาก", "น้ำมัน", "แท็กซี่", "รถไฟ", "ทางด่วน", "จอดรถ",
+    ]),
+    ("Entertainment", &[
+        "cinema", "major", "cineplex", "netflix", "spotify", "concert", "ticket", "steam",
+        "ภาพยนตร์", "โรงหนัง", "คอนเสิร์ต",
+    ]),
+    ("Utilities", &[
+        "electricity", "pea", "mea", "waterworks", "ais", "dtac", "3bb", "internet",
+        "ไฟฟ้า", "ประปา", "ค่าน้ำ", "ค่าไฟ", "อินเทอร์เน็ต", "โทรศัพท์",
+    ]),
+    ("Healthcare", &[
+        "pharmacy", "hospital", "clinic", "boots", "watsons", "dental", "drug",
+        "โรงพยาบาล", "คลินิก", "ฟาร์มาซี", "เภสัช", "ทันตกรรม", "ยา",
+    ]),
+    ("Shopping", &[
+        "central", "robinson", "uniqlo", "lazada", "shopee", "ikea", "homepro", "mall", "power buy",
+        "เสื้อ", "รองเท้า", "กระเป๋า",
+    ]),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SuggestionSource {
+    MerchantDefault,
+    History,
+    Keywords,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CategorySuggestion {
+    pub category_id: Uuid,
+    pub name: String,
+    pub score: f32,
+    pub source: SuggestionSource,
+}
+
+// Lowercased, punctuation-free text the keyword model runs over
+fn keyword_text(merchant: Option<&str>, items: &[String]) -> String {
+    merchant
+        .into_iter()
+        .chain(items.iter().map(String::as_str))
+        .map(normalize_merchant_name)
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Letters of a Thai word, leaving out the vowel and tone marks written above and below them
+fn thai_letters(word: &str) -> usize {
+    word.chars()
+        .filter(|c| !matches!(c, '\u{0E31}' | '\u{0E34}'..='\u{0E3A}' | '\u{0E47}'..='\u{0E4E}'))
+        .count()
+}
+
+fn keyword_matches(text: &str, keyword: &str) -> bool {
+    if keyword.is_ascii() || thai_letters(keyword) < MIN_THAI_SUBSTRING_LETTERS {
+        format!(" {} ", text).contains(&format!(" {} ", keyword))
+    } else {
+        text.contains(keyword)
+    }
+}
+
+// Keyword evidence per category: hits on the built-in dictionary for the categories the user
+// has by those names, plus the weights learned from feedback
+fn keyword_weights(text: &str, user_categories: &[DbCategory], learned: &[DbCategoryKeyword]) -> HashMap<Uuid, f32> {
+    let mut weights: HashMap<Uuid, f32> = HashMap::new();
+
+    for (category_name, keywords) in BUILTIN_KEYWORDS {
+        let category = user_categories
+            .iter()
+            .find(|c| c.name.eq_ignore_ascii_case(category_name));
+        if let Some(category) = category {
+            let hits = keywords
+                .iter()
+                .filter(|keyword| keyword_matches(text, &normalize_merchant_name(keyword)))
+                .count();
+            *weights.entry(category.id).or_insert(0.0) += hits as f32;
+        }
+    }
+
+    for keyword in learned {
+        if keyword_matches(text, &keyword.keyword) {
+            *weights.entry(keyword.category_id).or_insert(0.0) += keyword.weight;
+        }
+    }
+    weights
+}
+
+// Words worth learning from: every token of the merchant and item names
+fn learnable_keywords(merchant: Option<&str>, items: &[String]) -> Vec<String> {
+    let mut keywords: Vec<String> = keyword_text(merchant, items)
+        .split_whitespace()
+        .filter(|token| token.chars().count() >= 2 && !token.chars().all(|c| c.is_ascii_digit()))
+        .map(str::to_string)
+        .collect();
+    keywords.sort();
+    keywords.dedup();
+    keywords
+}
+
+// Noisy-or: independent evidence for the same category accumulates without exceeding 1.0
+fn combine(scores: &mut HashMap<Uuid, (f32, SuggestionSource)>, category_id: Uuid, score: f32, source: SuggestionSource) {
+    let entry = scores.entry(category_id).or_insert((0.0, source));
+    if score > entry.0 {
+        entry.1 = source;
+    }
+    entry.0 = 1.0 - (1.0 - entry.0) * (1.0 - score.clamp(0.0, 1.0));
+}
+
+// Suggest categories for a merchant and its items, best first
+pub fn suggest_categories(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    merchant: Option<&str>,
+    items: &[String],
+    limit: usize,
+) -> Result<Vec<CategorySuggestion>, AppError> {
+    let user_categories = categories::table
+        .filter(categories::ledger_id.eq(ledger_id))
+        .filter(categories::deleted_at.is_null())
+        .load::<DbCategory>(conn)?;
+    if user_categories.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut scores: HashMap<Uuid, (f32, SuggestionSource)> = HashMap::new();
+
+    // 1. The user's own history with this merchant
+    let resolved = match merchant {
+        Some(name) if !name.trim().is_empty() => resolve_merchant(conn, ledger_id, name, None)?,
+        _ => None,
+    };
+
+    if let Some(found) = &resolved {
+        if let Some(default_category_id) = found.merchant.default_category_id {
+            combine(&mut scores, default_category_id, 0.95, SuggestionSource::MerchantDefault);
+        }
+
+        let history: Vec<(Option<Uuid>, i64)> = transactions::table
+            .filter(transactions::ledger_id.eq(ledger_id))
+            .filter(transactions::merchant_id.eq(found.merchant.id))
+            .filter(transactions::category_id.is_not_null())
+            .filter(transactions::deleted_at.is_null())
+            .group_by(transactions::category_id)
+            .select((transactions::category_id, count_star()))
+            .load(conn)?;
+
+        let total: i64 = history.iter().map(|(_, count)| count).sum();
+        for (category_id, count) in history {
+            if let Some(category_id) = category_id {
+                let share = count as f32 / total as f32;
+                let volume = count as f32 / (count as f32 + 1.0);
+                combine(&mut scores, category_id, 0.9 * share * volume, SuggestionSource::History);
+            }
+        }
+    }
+
+    // 2. Keyword model over merchant and item names: built-in dictionary plus learned weights
+    let text = keyword_text(merchant, items);
+    if !text.is_empty() {
+        let learned = category_keywords::table
+            .filter(category_keywords::ledger_id.eq(ledger_id))
+            .load::<DbCategoryKeyword>(conn)?;
+        for (category_id, weight) in keyword_weights(&text, &user_categories, &learned) {
+            if weight > 0.0 {
+                combine(&mut scores, category_id, 0.8 * (1.0 - (-weight).exp()), SuggestionSource::Keywords);
+            }
+        }
+    }
+
+    let mut suggestions: Vec<CategorySuggestion> = scores
+        .into_iter()
+        .filter_map(|(category_id, (score, source))| {
+            user_categories
+                .iter()
+                .find(|c| c.id == category_id)
+                .map(|category| CategorySuggestion {
+                    category_id,
+                    name: category.name.clone(),
+                    score,
+                    source,
+                })
+        })
+        .collect();
+
+    suggestions.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+    suggestions.truncate(limit);
+    Ok(suggestions)
+}
+
+fn adjust_keyword_weight(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    keyword: &str,
+    category_id: Uuid,
+    delta: f32,
+) -> Result<(), AppError> {
+    let new_keyword = NewCategoryKeyword {
+        id: Uuid::new_v4(),
+        ledger_id,
+        keyword: keyword.to_string(),
+        category_id,
+        weight: delta,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+    };
+
+    diesel::insert_into(category_keywords::table)
+        .values(&new_keyword)
+        .on_conflict((category_keywords::ledger_id, category_keywords::keyword, category_keywords::category_id))
+        .do_update()
+        .set((
+            category_keywords::weight.eq(category_keywords::weight + delta),
+            category_keywords::updated_at.eq(Utc::now()),
+        ))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+// Learn from the category the user settled on: keyword weights move toward it, and the merchant remembers it
+pub fn record_feedback(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    feedback: &CategoryFeedbackDto,
+) -> Result<(), AppError> {
+    let category_exists = categories::table
+        .filter(categories::id.eq(feedback.category_id))
+        .filter(categories::ledger_id.eq(ledger_id))
+        .filter(categories::deleted_at.is_null())
+        .select(categories::id)
+        .first::<Uuid>(conn)
+        .optional()?
+        .is_some();
+    if !category_exists {
+        return Err(AppError::NotFound(format!("Category {} not found", feedback.category_id)));
+    }
+
+    let items = feedback.items.clone().unwrap_or_default();
+    let merchant = feedback.merchant.as_deref();
+    let rejected = feedback
+        .suggested_category_id
+        .filter(|suggested| *suggested != feedback.category_id);
+
+    conn.transaction(|conn| {
+        for keyword in learnable_keywords(merchant, &items) {
+            adjust_keyword_weight(conn, ledger_id, &keyword, feedback.category_id, FEEDBACK_REWARD)?;
+            if let Some(rejected) = rejected {
+                adjust_keyword_weight(conn, ledger_id, &keyword, rejected, -FEEDBACK_PENALTY)?;
+            }
+        }
+
+        if let Some(name) = merchant.filter(|m| !m.trim().is_empty()) {
+            if let Some(found) = resolve_merchant(conn, ledger_id, name, None)? {
+                if found.merchant.default_category_id.is_none() || rejected.is_some() {
+                    diesel::update(merchants::table.find(found.merchant.id))
+                        .set((
+                            merchants::default_category_id.eq(feedback.category_id),
+                            merchants::updated_at.eq(Utc::now()),
+                        ))
+                        .execute(conn)?;
+                }
+            }
+        }
+
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn category(name: &str) -> DbCategory {
+        DbCategory {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            description: None,
+            color: None,
+            icon: None,
+            ledger_id: Uuid::nil(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            kind: "expense".to_string(),
+            deleted_at: None,
+        }
+    }
+
+    #[test]
+    fn matches_short_keywords_only_as_whole_words() {
+        assert!(keyword_matches("grab food", "grab"));
+        assert!(!keyword_matches("grabbed", "grab"));
+        assert!(!keyword_matches("ยางรถยนต์", "ยา"));
+        assert!(keyword_matches("ยา แก้ปวด", "ยา"));
+        assert!(!keyword_matches("ขนมปัง", "นม"));
+        assert!(!keyword_matches("ไข่มุก", "ไข่"));
+        assert!(keyword_matches("ร้านกาแฟดี", "กาแฟ"));
+        assert!(keyword_matches("ตลาดสดบางรัก", "ตลาดสด"));
+        assert_eq!(thai_letters("ไข่"), 2);
+        assert_eq!(thai_letters("ตลาด"), 4);
+    }
+
+    #[test]
+    fn falls_back_to_keywords_for_categories_the_user_has() {
+        let dining = category("dining");
+        let healthcare = category("Healthcare");
+        let categories = [dining.clone(), healthcare.clone()];
+
+        let weights = keyword_weights(&keyword_text(Some("Starbucks Coffee สาขา 12"), &[]), &categories, &[]);
+        assert_eq!(weights.get(&dining.id), Some(&2.0));
+        assert_eq!(weights.get(&healthcare.id), Some(&0.0));
+
+        // A tyre shop and bubble tea aren't medicine or eggs
+        let weights = keyword_weights(&keyword_text(Some("ร้านยางรถยนต์"), &["ชาไข่มุก".to_string()]), &categories, &[]);
+        assert!(weights.values().all(|weight| *weight == 0.0));
+        assert!(keyword_weights("grab taxi", &categories, &[]).values().all(|weight| *weight == 0.0));
+    }
+
+    #[test]
+    fn adds_learned_weights_to_the_dictionary() {
+        let dining = category("Dining");
+        let learned = DbCategoryKeyword {
+            id: Uuid::new_v4(),
+            ledger_id: Uuid::nil(),
+            keyword: "ร้านป้าแดง".to_string(),
+            category_id: dining.id,
+            weight: 1.5,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+        let text = keyword_text(Some("ร้านป้าแดง ก๋วยเตี๋ยว"), &[]);
+        let weights = keyword_weights(&text, std::slice::from_ref(&dining), std::slice::from_ref(&learned));
+        assert_eq!(weights.get(&dining.id), Some(&2.5));
+        assert_eq!(learnable_keywords(Some("ร้านป้าแดง ก๋วยเตี๋ยว"), &["2".to_string()]), ["ก๋วยเตี๋ยว", "ร้านป้าแดง"]);
+    }
+
+    #[test]
+    fn accumulates_evidence_without_passing_one() {
+        let mut scores = HashMap::new();
+        let id = Uuid::new_v4();
+        combine(&mut scores, id, 0.5, SuggestionSource::Keywords);
+        combine(&mut scores, id, 0.9, SuggestionSource::History);
+        let (score, source) = scores[&id];
+        assert!((score - 0.95).abs() < 1e-6);
+        assert_eq!(source, SuggestionSource::History);
+        combine(&mut scores, id, 2.0, SuggestionSource::MerchantDefault);
+        assert_eq!(scores[&id].0, 1.0);
+    }
+}
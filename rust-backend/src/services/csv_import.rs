@@ -0,0 +1,321 @@
+use std::str::FromStr;
+
+use bigdecimal::{BigDecimal, Signed, Zero};
+use chrono::format::{self, Parsed, StrftimeItems};
+use chrono::NaiveDate;
+
+use crate::error::AppError;
+use crate::models::import::{ColumnRef, CsvMapping, FileEncoding, ImportRowError, ParsedRow};
+use crate::money::{Currency, Money};
+
+// Thai banks print years in the Buddhist Era, 543 years ahead of the Gregorian calendar
+const BUDDHIST_ERA_OFFSET: i32 = 543;
+const BUDDHIST_ERA_MIN_YEAR: i32 = 2400;
+
+// Refuse a mapping that can't read any file, before one is uploaded against it
+pub fn validate_mapping(mapping: &CsvMapping) -> Result<(), AppError> {
+    if !mapping.delimiter.is_ascii() {
+        return Err(AppError::BadRequest("The delimiter must be a single ASCII character".to_string()));
+    }
+    if mapping.date_format.trim().is_empty() {
+        return Err(AppError::BadRequest("Give a date format, e.g. %d/%m/%Y".to_string()));
+    }
+    let split_columns = mapping.debit_column.is_some() || mapping.credit_column.is_some();
+    match (&mapping.amount_column, split_columns) {
+        (Some(_), true) => Err(AppError::BadRequest(
+            "Map either an amount column or debit and credit columns, not both".to_string(),
+        )),
+        (None, false) => Err(AppError::BadRequest(
+            "Map an amount column or debit and credit columns".to_string(),
+        )),
+        _ => Ok(()),
+    }
+}
+
+// The file as text. Without an explicit encoding, anything that isn't valid UTF-8 is taken to be
+// Windows-874, which also covers TIS-620
+pub fn decode(bytes: &[u8], encoding: Option<FileEncoding>) -> Result<String, AppError> {
+    let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+    match encoding {
+        Some(FileEncoding::Utf8) => String::from_utf8(bytes.to_vec())
+            .map_err(|_| AppError::BadRequest("The file is not valid UTF-8; set the profile's encoding".to_string())),
+        Some(FileEncoding::Tis620) | Some(FileEncoding::Windows874) => {
+            Ok(encoding_rs::WINDOWS_874.decode_without_bom_handling(bytes).0.into_owned())
+        }
+        None => match std::str::from_utf8(bytes) {
+            Ok(text) => Ok(text.to_string()),
+            Err(_) => Ok(encoding_rs::WINDOWS_874.decode_without_bom_handling(bytes).0.into_owned()),
+        },
+    }
+}
+
+fn column_index(column: &ColumnRef, headers: &[String]) -> Result<usize, AppError> {
+    match column {
+        ColumnRef::Index(index) => Ok(*index),
+        ColumnRef::Name(name) => headers
+            .iter()
+            .position(|header| header.trim().eq_ignore_ascii_case(name.trim()))
+            .ok_or_else(|| AppError::BadRequest(format!("Column {} is not in the file", name))),
+    }
+}
+
+// Column positions resolved against the header row
+struct Columns {
+    date: usize,
+    amount: Option<usize>,
+    debit: Option<usize>,
+    credit: Option<usize>,
+    description: usize,
+    notes: Option<usize>,
+    currency: Option<usize>,
+}
+
+impl Columns {
+    fn resolve(mapping: &CsvMapping, headers: &[String]) -> Result<Self, AppError> {
+        let optional = |column: &Option<ColumnRef>| column.as_ref().map(|c| column_index(c, headers)).transpose();
+        Ok(Columns {
+            date: column_index(&mapping.date_column, headers)?,
+            amount: optional(&mapping.amount_column)?,
+            debit: optional(&mapping.debit_column)?,
+            credit: optional(&mapping.credit_column)?,
+            description: column_index(&mapping.description_column, headers)?,
+            notes: optional(&mapping.notes_column)?,
+            currency: optional(&mapping.currency_column)?,
+        })
+    }
+}
+
+// A date in the given strftime format; any time in it is ignored. Buddhist Era years are
+// converted before the date is built, since e.g. 29/02/2567 is valid but Gregorian 2567 isn't a
+// leap year
+pub fn parse_date(text: &str, format: &str) -> Option<NaiveDate> {
+    let mut parsed = Parsed::new();
+    format::parse(&mut parsed, text.trim(), StrftimeItems::new(format)).ok()?;
+    match parsed.year() {
+        Some(year) if year >= BUDDHIST_ERA_MIN_YEAR => {
+            NaiveDate::from_ymd_opt(year - BUDDHIST_ERA_OFFSET, parsed.month()?, parsed.day()?)
+        }
+        _ => parsed.to_naive_date().ok(),
+    }
+}
+
+// A statement amount with its sign: "-1,234.50", "(1,234.50)" and "1,234.50-" are all negative
+pub fn parse_signed_amount(text: &str) -> Option<BigDecimal> {
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+    let negative = text.starts_with('-')
+        || text.ends_with('-')
+        || (text.starts_with('(') && text.ends_with(')'));
+    let digits: String = text.chars().filter(|c| c.is_ascii_digit() || *c == '.').collect();
+    let amount = BigDecimal::from_str(&digits).ok()?;
+    Some(if negative { -amount } else { amount })
+}
+
+fn cell(record: &csv::StringRecord, index: usize) -> &str {
+    record.get(index).map(str::trim).unwrap_or("")
+}
+
+// Money out is negative; an empty cell in a debit/credit pair counts as zero
+fn signed_amount(record: &csv::StringRecord, columns: &Columns, mapping: &CsvMapping) -> Result<BigDecimal, String> {
+    let read = |index: usize| -> Result<BigDecimal, String> {
+        let text = cell(record, index);
+        if text.is_empty() {
+            return Ok(BigDecimal::zero());
+        }
+        parse_signed_amount(text).ok_or_else(|| format!("Can't read the amount {}", text))
+    };
+
+    let amount = match columns.amount {
+        Some(index) => read(index)?,
+        None => {
+            let debit = columns.debit.map(read).transpose()?.unwrap_or_default();
+            let credit = columns.credit.map(read).transpose()?.unwrap_or_default();
+            credit.abs() - debit.abs()
+        }
+    };
+    Ok(if mapping.negate_amounts { -amount } else { amount })
+}
+
+fn parse_record(
+    record: &csv::StringRecord,
+    columns: &Columns,
+    mapping: &CsvMapping,
+    default_currency: Currency,
+    line: usize,
+) -> Result<ParsedRow, String> {
+    let date_text = cell(record, columns.date);
+    let date = parse_date(date_text, &mapping.date_format)
+        .ok_or_else(|| format!("Can't read the date {} as {}", date_text, mapping.date_format))?;
+
+    let amount = signed_amount(record, columns, mapping)?;
+    if amount.is_zero() {
+        return Err("The amount is zero".to_string());
+    }
+
+    let currency = match columns.currency.map(|index| cell(record, index)).filter(|code| !code.is_empty()) {
+        Some(code) => Currency::parse(code).ok_or_else(|| format!("Unknown currency {}", code))?,
+        None => default_currency,
+    };
+
+    let description = cell(record, columns.description).to_string();
+    if description.is_empty() {
+        return Err("The description is empty".to_string());
+    }
+
+    Ok(ParsedRow {
+        line,
+        date,
+        amount: Money::new(amount.abs(), currency),
+        income: amount.is_positive(),
+        description,
+        notes: columns.notes.map(|index| cell(record, index).to_string()).filter(|n| !n.is_empty()),
+        external_id: None,
+        category: None,
+        value_date: None,
+        reference: None,
+        reversal: false,
+    })
+}
+
+// Every statement line the mapping can read, and why each of the others couldn't be
+pub fn parse_csv(
+    bytes: &[u8],
+    mapping: &CsvMapping,
+    default_currency: Currency,
+) -> Result<(Vec<ParsedRow>, Vec<ImportRowError>), AppError> {
+    validate_mapping(mapping)?;
+    let text = decode(bytes, mapping.encoding)?;
+    let body = text.splitn(mapping.skip_rows + 1, '\n').nth(mapping.skip_rows).unwrap_or("");
+
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(mapping.delimiter as u8)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(body.as_bytes());
+    let mut records = reader.records();
+
+    let headers: Vec<String> = if mapping.has_header {
+        match records.next() {
+            Some(header) => header
+                .map_err(|e| AppError::BadRequest(format!("Can't read the header row: {}", e)))?
+                .iter()
+                .map(str::to_string)
+                .collect(),
+            None => return Err(AppError::BadRequest("The file is empty".to_string())),
+        }
+    } else {
+        Vec::new()
+    };
+    let columns = Columns::resolve(mapping, &headers)?;
+
+    let mut rows = Vec::new();
+    let mut errors = Vec::new();
+    for record in records {
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                let line = e.position().map_or(0, |p| p.line() as usize) + mapping.skip_rows;
+                errors.push(ImportRowError { line, message: e.to_string() });
+                continue;
+            }
+        };
+        if record.iter().all(|field| field.trim().is_empty()) {
+            continue;
+        }
+        let line = record.position().map_or(0, |p| p.line() as usize) + mapping.skip_rows;
+        match parse_record(&record, &columns, mapping, default_currency, line) {
+            Ok(row) => rows.push(row),
+            Err(message) => errors.push(ImportRowError { line, message }),
+        }
+    }
+    Ok((rows, errors))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn name(column: &str) -> ColumnRef {
+        ColumnRef::Name(column.to_string())
+    }
+
+    fn mapping() -> CsvMapping {
+        CsvMapping {
+            delimiter: ',',
+            encoding: None,
+            skip_rows: 0,
+            has_header: true,
+            date_column: name("Date"),
+            date_format: "%d/%m/%Y".to_string(),
+            amount_column: None,
+            debit_column: Some(name("Withdrawal")),
+            credit_column: Some(name("Deposit")),
+            description_column: name("Description"),
+            notes_column: None,
+            currency_column: None,
+            currency: None,
+            negate_amounts: false,
+        }
+    }
+
+    #[test]
+    fn converts_buddhist_era_years() {
+        assert_eq!(parse_date("29/02/2567", "%d/%m/%Y"), NaiveDate::from_ymd_opt(2024, 2, 29));
+        assert_eq!(parse_date("15/03/2024", "%d/%m/%Y"), NaiveDate::from_ymd_opt(2024, 3, 15));
+        assert_eq!(parse_date("2024-03-15 10:30", "%Y-%m-%d %H:%M"), NaiveDate::from_ymd_opt(2024, 3, 15));
+        assert_eq!(parse_date("30/02/2567", "%d/%m/%Y"), None);
+        assert_eq!(parse_date("29/02/2023", "%d/%m/%Y"), None);
+    }
+
+    #[test]
+    fn reads_the_sign_however_the_bank_prints_it() {
+        for text in ["-1,234.50", "(1,234.50)", "1,234.50-"] {
+            assert_eq!(parse_signed_amount(text), BigDecimal::from_str("-1234.50").ok(), "{}", text);
+        }
+        assert_eq!(parse_signed_amount(" 99 "), BigDecimal::from_str("99").ok());
+        assert_eq!(parse_signed_amount(""), None);
+    }
+
+    #[test]
+    fn refuses_mappings_with_both_or_neither_amount_columns() {
+        let mut both = mapping();
+        both.amount_column = Some(name("Amount"));
+        assert!(validate_mapping(&both).is_err());
+
+        let mut neither = mapping();
+        neither.debit_column = None;
+        neither.credit_column = None;
+        assert!(validate_mapping(&neither).is_err());
+    }
+
+    #[test]
+    fn parses_debit_and_credit_columns_after_skipped_rows() {
+        let mut mapping = mapping();
+        mapping.skip_rows = 1;
+        let file = "Account 123-4-56789-0\n\
+                    Date,Description,Withdrawal,Deposit\n\
+                    29/02/2567,7-Eleven,\"1,250.00\",\n\
+                    01/03/2567,Salary,,50000\n\
+                    32/03/2567,Broken,10,\n";
+
+        let (rows, errors) = parse_csv(file.as_bytes(), &mapping, Currency::THB).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].line, 3);
+        assert_eq!(rows[0].date, NaiveDate::from_ymd_opt(2024, 2, 29).unwrap());
+        assert_eq!(rows[0].amount.amount().to_string(), "1250.00");
+        assert!(!rows[0].income);
+        assert_eq!(rows[1].description, "Salary");
+        assert!(rows[1].income);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, 5);
+    }
+
+    #[test]
+    fn decodes_windows_874_when_the_file_is_not_utf8() {
+        // "กา" in TIS-620
+        assert_eq!(decode(b"\xA1\xD2", None).unwrap(), "กา");
+        assert!(decode(b"\xA1\xD2", Some(FileEncoding::Utf8)).is_err());
+    }
+}
@@ -0,0 +1,240 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Datelike, Duration, Utc};
+use diesel::dsl::{count, count_star, sum};
+use diesel::prelude::*;
+use diesel::PgConnection;
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::money::{Currency, Money};
+use crate::models::bill::BILL_STATUS_PENDING_REVIEW;
+use crate::models::dashboard::{CategoryTotal, DashboardSummary, MerchantTotal, PeriodTotal};
+use crate::models::report::Granularity;
+use crate::models::transaction::{TransactionType, SPENDING_TYPES};
+use crate::schema::{bills, transaction_allocations, transactions};
+use crate::services::exchange_rates::{base_currency, convert_amount};
+use crate::services::reports::{
+    change_percent, local_midnights, local_today, percent_of, savings_rate, shift, spending_amount,
+    truncate,
+};
+use crate::services::transactions::{category_names, to_responses};
+
+// How many entries the top lists show
+const TOP_LIMIT: i64 = 5;
+
+const UNCATEGORIZED: &str = "Uncategorized";
+
+// SUM over no rows is NULL
+fn to_money(value: Option<BigDecimal>, currency: Currency) -> Money {
+    value.map_or_else(|| Money::zero(currency), |v| Money::new(v, currency))
+}
+
+// Totals are in the base currency; transactions with no usable rate are counted but not summed
+fn period_total(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    start_at: DateTime<Utc>,
+    end_at: DateTime<Utc>,
+    currency: Currency,
+    tz: &str,
+) -> Result<(Money, i64), AppError> {
+    let (total, count) = transactions::table
+        .filter(transactions::ledger_id.eq(ledger_id))
+        .filter(transactions::excluded.eq(false))
+        .filter(transactions::deleted_at.is_null())
+        .filter(transactions::transaction_type.eq_any(SPENDING_TYPES))
+        .filter(transactions::date.ge(start_at))
+        .filter(transactions::date.lt(end_at))
+        .select((
+            sum(convert_amount(
+                transactions::ledger_id,
+                spending_amount(transactions::transaction_type, transactions::amount),
+                transactions::currency,
+                currency.to_string(),
+                transactions::date,
+                tz.to_string(),
+            )),
+            count_star(),
+        ))
+        .first::<(Option<BigDecimal>, i64)>(conn)?;
+    Ok((to_money(total, currency), count))
+}
+
+// Month-to-date totals, comparisons and top lists in one consistent snapshot
+pub fn summary(conn: &mut PgConnection, ledger_id: Uuid, tz: &str) -> Result<DashboardSummary, AppError> {
+    conn.build_transaction().read_only().repeatable_read().run(|conn| {
+        let today = local_today(conn, tz)?;
+        let this_start = truncate(Granularity::Month, today);
+        let this_end = shift(Granularity::Month, this_start, 1);
+        let last_start = shift(Granularity::Month, this_start, -1);
+        // Same day last month, clamped so 31 March compares with 28/29 February
+        let last_to_date_end = (shift(Granularity::Month, today, -1) + Duration::days(1)).min(this_start);
+
+        let instants = local_midnights(conn, &[last_start, this_start, this_end, last_to_date_end], tz)?;
+        let (last_start_at, this_start_at, this_end_at, last_to_date_end_at) =
+            (instants[0], instants[1], instants[2], instants[3]);
+
+        let currency = base_currency(conn, ledger_id)?;
+        let (this_total, this_count) = period_total(conn, ledger_id, this_start_at, this_end_at, currency, tz)?;
+        let (last_total, last_count) = period_total(conn, ledger_id, last_start_at, this_start_at, currency, tz)?;
+        let (last_to_date_total, last_to_date_count) =
+            period_total(conn, ledger_id, last_start_at, last_to_date_end_at, currency, tz)?;
+
+        let income = transactions::table
+            .filter(transactions::ledger_id.eq(ledger_id))
+            .filter(transactions::excluded.eq(false))
+            .filter(transactions::deleted_at.is_null())
+            .filter(transactions::transaction_type.eq(TransactionType::Income.as_str()))
+            .filter(transactions::date.ge(this_start_at))
+            .filter(transactions::date.lt(this_end_at))
+            .select(sum(convert_amount(
+                transactions::ledger_id,
+                transactions::amount,
+                transactions::currency,
+                currency.to_string(),
+                transactions::date,
+                tz.to_string(),
+            )))
+            .first::<Option<BigDecimal>>(conn)?;
+        let income = to_money(income, currency);
+
+        let this_month = || {
+            transactions::table
+                .filter(transactions::ledger_id.eq(ledger_id))
+                .filter(transactions::excluded.eq(false))
+                .filter(transactions::deleted_at.is_null())
+                .filter(transactions::transaction_type.eq_any(SPENDING_TYPES))
+                .filter(transactions::date.ge(this_start_at))
+                .filter(transactions::date.lt(this_end_at))
+        };
+        let converted = || {
+            convert_amount(
+                transactions::ledger_id,
+                spending_amount(transactions::transaction_type, transactions::amount),
+                transactions::currency,
+                currency.to_string(),
+                transactions::date,
+                tz.to_string(),
+            )
+        };
+
+        let largest = this_month()
+            .order((converted().desc().nulls_last(), transactions::date.desc()))
+            .limit(TOP_LIMIT)
+            .load(conn)?;
+
+        let top_merchants = this_month()
+            .group_by(transactions::merchant)
+            .select((transactions::merchant, sum(converted()), count_star()))
+            .order(sum(converted()).desc().nulls_last())
+            .limit(TOP_LIMIT)
+            .load::<(String, Option<BigDecimal>, i64)>(conn)?
+            .into_iter()
+            .map(|(merchant, amount, count)| MerchantTotal {
+                merchant,
+                amount: to_money(amount, currency),
+                count,
+            })
+            .collect();
+
+        // Split transactions count towards the categories of their splits
+        let allocated = || {
+            convert_amount(
+                transaction_allocations::ledger_id,
+                spending_amount(transaction_allocations::transaction_type, transaction_allocations::amount),
+                transaction_allocations::currency,
+                currency.to_string(),
+                transaction_allocations::date,
+                tz.to_string(),
+            )
+        };
+        let category_rows = transaction_allocations::table
+            .filter(transaction_allocations::ledger_id.eq(ledger_id))
+            .filter(transaction_allocations::excluded.eq(false))
+            .filter(transaction_allocations::transaction_type.eq_any(SPENDING_TYPES))
+            .filter(transaction_allocations::date.ge(this_start_at))
+            .filter(transaction_allocations::date.lt(this_end_at))
+            .group_by(transaction_allocations::category_id)
+            .select((transaction_allocations::category_id, sum(allocated()), count_star()))
+            .order(sum(allocated()).desc().nulls_last())
+            .limit(TOP_LIMIT)
+            .load::<(Option<Uuid>, Option<BigDecimal>, i64)>(conn)?;
+        let category_ids: Vec<Uuid> = category_rows.iter().filter_map(|(id, _, _)| *id).collect();
+        let names = category_names(conn, &category_ids)?;
+        let top_categories = category_rows
+            .into_iter()
+            .map(|(category_id, amount, count)| {
+                let amount = to_money(amount, currency);
+                CategoryTotal {
+                    category_id,
+                    category: category_id
+                        .and_then(|id| names.get(&id).cloned())
+                        .unwrap_or_else(|| UNCATEGORIZED.to_string()),
+                    percent: percent_of(amount.amount(), this_total.amount()),
+                    amount,
+                    count,
+                }
+            })
+            .collect();
+
+        // A split transaction needs categorizing when any of its splits does
+        let uncategorized_count = transaction_allocations::table
+            .filter(transaction_allocations::ledger_id.eq(ledger_id))
+            .filter(transaction_allocations::excluded.eq(false))
+            .filter(transaction_allocations::transaction_type.ne(TransactionType::Transfer.as_str()))
+            .filter(transaction_allocations::category_id.is_null())
+            .select(count(transaction_allocations::transaction_id).aggregate_distinct())
+            .get_result::<i64>(conn)?;
+
+        let pending_review_count = bills::table
+            .filter(bills::ledger_id.eq(ledger_id))
+            .filter(bills::status.eq(BILL_STATUS_PENDING_REVIEW))
+            .filter(bills::deleted_at.is_null())
+            .count()
+            .get_result::<i64>(conn)?;
+
+        let days_elapsed = BigDecimal::from(today.day());
+        let days_in_month = BigDecimal::from((this_end - this_start).num_days());
+        let daily_average = this_total.amount() / &days_elapsed;
+        let projected_month_total = Money::new(&daily_average * &days_in_month, this_total.currency());
+        let daily_average = Money::new(daily_average, this_total.currency());
+        let change = change_percent(this_total.amount(), last_to_date_total.amount());
+        let net_cash_flow = Money::new(income.amount() - this_total.amount(), currency);
+        let savings_rate = savings_rate(income.amount(), this_total.amount());
+
+        Ok(DashboardSummary {
+            timezone: tz.to_string(),
+            today,
+            currency,
+            this_month: PeriodTotal {
+                start: this_start,
+                end: this_end - Duration::days(1),
+                total: this_total,
+                count: this_count,
+            },
+            last_month: PeriodTotal {
+                start: last_start,
+                end: this_start - Duration::days(1),
+                total: last_total,
+                count: last_count,
+            },
+            last_month_to_date: PeriodTotal {
+                start: last_start,
+                end: last_to_date_end - Duration::days(1),
+                total: last_to_date_total,
+                count: last_to_date_count,
+            },
+            change_percent: change,
+            daily_average,
+            projected_month_total,
+            income,
+            net_cash_flow,
+            savings_rate,
+            largest_transactions: to_responses(conn, largest)?,
+            top_merchants,
+            top_categories,
+            uncategorized_count,
+            pending_review_count,
+        })
+    })
+}
@@ -0,0 +1,134 @@
+use std::str::FromStr;
+
+use bigdecimal::{BigDecimal, Signed};
+use chrono::{NaiveDate, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{Numeric, Text, Timestamptz, Varchar};
+use diesel::upsert::excluded;
+use diesel::PgConnection;
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::models::exchange_rate::{CreateExchangeRateDto, DbExchangeRate, NewExchangeRate};
+use crate::money::Currency;
+use crate::schema::{exchange_rates, ledgers};
+
+diesel::define_sql_function! {
+    // Defined by the add_currencies migration; NULL when the ledger has no rate for the pair
+    fn convert_amount(
+        ledger_id: diesel::sql_types::Uuid,
+        amount: Numeric,
+        from_currency: Varchar,
+        to_currency: Varchar,
+        at: Timestamptz,
+        tz: Text
+    ) -> Nullable<Numeric>;
+}
+
+// The currency the ledger's reports and budgets are shown in
+pub fn base_currency(conn: &mut PgConnection, ledger_id: Uuid) -> Result<Currency, AppError> {
+    Ok(ledgers::table
+        .find(ledger_id)
+        .select(ledgers::base_currency)
+        .first::<String>(conn)
+        .optional()?
+        .map(|code| Currency::from_code(&code))
+        .unwrap_or_default())
+}
+
+pub fn validate_rate(rate: &CreateExchangeRateDto) -> Result<(), AppError> {
+    if rate.from_currency == rate.to_currency {
+        return Err(AppError::BadRequest("A rate needs two different currencies".to_string()));
+    }
+    if !rate.rate.is_positive() {
+        return Err(AppError::BadRequest("Rate must be greater than zero".to_string()));
+    }
+    Ok(())
+}
+
+// Insert a rate, replacing any rate already stored for the same pair and date
+pub fn upsert_rate(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    rate: &CreateExchangeRateDto,
+    source: &str,
+) -> Result<DbExchangeRate, AppError> {
+    let new_rate = NewExchangeRate {
+        id: Uuid::new_v4(),
+        ledger_id,
+        from_currency: rate.from_currency.to_string(),
+        to_currency: rate.to_currency.to_string(),
+        rate: rate.rate.clone(),
+        rate_date: rate.rate_date,
+        source: source.to_string(),
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+    };
+
+    Ok(diesel::insert_into(exchange_rates::table)
+        .values(&new_rate)
+        .on_conflict((
+            exchange_rates::ledger_id,
+            exchange_rates::from_currency,
+            exchange_rates::to_currency,
+            exchange_rates::rate_date,
+        ))
+        .do_update()
+        .set((
+            exchange_rates::rate.eq(excluded(exchange_rates::rate)),
+            exchange_rates::source.eq(excluded(exchange_rates::source)),
+            exchange_rates::updated_at.eq(excluded(exchange_rates::updated_at)),
+        ))
+        .get_result::<DbExchangeRate>(conn)?)
+}
+
+fn csv_fields(line: &str) -> Vec<String> {
+    line.split(',')
+        .map(|field| field.trim().trim_matches('"').trim().to_string())
+        .collect()
+}
+
+// Parse "date,from,to,rate" rows; the header names the columns so their order doesn't matter
+pub fn parse_rates_csv(text: &str) -> Result<Vec<CreateExchangeRateDto>, AppError> {
+    let mut lines = text
+        .trim_start_matches('\u{feff}')
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty());
+
+    let (_, header) = lines
+        .next()
+        .ok_or_else(|| AppError::BadRequest("The CSV file is empty".to_string()))?;
+    let header: Vec<String> = csv_fields(header).into_iter().map(|name| name.to_lowercase()).collect();
+    let column = |names: &[&str]| {
+        header
+            .iter()
+            .position(|name| names.contains(&name.as_str()))
+            .ok_or_else(|| AppError::BadRequest(format!("Missing CSV column: {}", names[0])))
+    };
+    let date_col = column(&["date", "rate_date"])?;
+    let from_col = column(&["from", "from_currency", "base"])?;
+    let to_col = column(&["to", "to_currency", "quote"])?;
+    let rate_col = column(&["rate"])?;
+
+    let mut rates = Vec::new();
+    for (index, line) in lines {
+        let fields = csv_fields(line);
+        let line_error = |message: &str| AppError::BadRequest(format!("Line {}: {}", index + 1, message));
+        let field = |col: usize| fields.get(col).map(String::as_str).unwrap_or("");
+
+        let rate = CreateExchangeRateDto {
+            rate_date: NaiveDate::parse_from_str(field(date_col), "%Y-%m-%d")
+                .map_err(|_| line_error("date must be YYYY-MM-DD"))?,
+            from_currency: Currency::parse(field(from_col)).ok_or_else(|| line_error("unsupported currency"))?,
+            to_currency: Currency::parse(field(to_col)).ok_or_else(|| line_error("unsupported currency"))?,
+            rate: BigDecimal::from_str(field(rate_col)).map_err(|_| line_error("invalid rate"))?,
+        };
+        validate_rate(&rate).map_err(|e| match e {
+            AppError::BadRequest(message) => line_error(&message),
+            other => other,
+        })?;
+        rates.push(rate);
+    }
+    Ok(rates)
+}
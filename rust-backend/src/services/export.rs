@@ -0,0 +1,458 @@
+use std::collections::{BTreeMap, HashMap};
+
+use bigdecimal::{BigDecimal, ToPrimitive, Zero};
+use diesel::prelude::*;
+use diesel::PgConnection;
+use rust_xlsxwriter::{Format, Workbook, Worksheet, XlsxError};
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::models::export::ExportedTransaction;
+use crate::models::split::SplitResponse;
+use crate::models::transaction::{DbTransaction, Direction, TransactionFilters, TransactionItem, TransactionResponse};
+use crate::schema::{accounts, transactions};
+use crate::services::transactions::{filtered_query, to_responses};
+
+// Transactions are read and written this many at a time, so a large export never sits in memory
+pub const EXPORT_CHUNK_SIZE: u64 = 500;
+
+// Excel only reads a CSV file as UTF-8, Thai included, when it starts with a byte order mark
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+const COLUMNS: [&str; 16] = [
+    "id",
+    "date",
+    "value_date",
+    "merchant",
+    "category",
+    "amount",
+    "currency",
+    "direction",
+    "transaction_type",
+    "account",
+    "notes",
+    "tags",
+    "items",
+    "splits",
+    "bank_reference",
+    "bill_image_url",
+];
+
+// Which of the filtered transactions to export: all of them, or the one page `page` and `limit`
+// ask for. Unlike the list endpoint, the page size isn't capped
+#[derive(Debug, Clone, Copy)]
+pub struct ExportRange {
+    offset: u64,
+    limit: Option<u64>,
+}
+
+impl ExportRange {
+    pub fn new(filters: &TransactionFilters) -> Self {
+        match filters.limit {
+            Some(limit) => {
+                let limit = limit.max(1);
+                ExportRange {
+                    offset: (filters.page.unwrap_or(1).max(1) - 1) * limit,
+                    limit: Some(limit),
+                }
+            }
+            None => ExportRange { offset: 0, limit: None },
+        }
+    }
+}
+
+// The next chunk of transactions after the `done` already exported, oldest first
+pub fn export_chunk(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    filters: &TransactionFilters,
+    range: &ExportRange,
+    done: u64,
+) -> Result<Vec<TransactionResponse>, AppError> {
+    let size = match range.limit {
+        Some(limit) => limit.saturating_sub(done).min(EXPORT_CHUNK_SIZE),
+        None => EXPORT_CHUNK_SIZE,
+    };
+    if size == 0 {
+        return Ok(Vec::new());
+    }
+    let rows = filtered_query(ledger_id, filters)
+        .order((transactions::date.asc(), transactions::created_at.asc(), transactions::id.asc()))
+        .limit(size as i64)
+        .offset((range.offset + done) as i64)
+        .load::<DbTransaction>(conn)?;
+    to_responses(conn, rows)
+}
+
+// Every transaction the filters pick, for formats that can't be written a chunk at a time
+pub fn export_all(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    filters: &TransactionFilters,
+    range: &ExportRange,
+) -> Result<Vec<TransactionResponse>, AppError> {
+    let mut exported = Vec::new();
+    loop {
+        let chunk = export_chunk(conn, ledger_id, filters, range, exported.len() as u64)?;
+        let last = (chunk.len() as u64) < EXPORT_CHUNK_SIZE;
+        exported.extend(chunk);
+        if last {
+            return Ok(exported);
+        }
+    }
+}
+
+// Account names by ID, archived accounts included
+pub fn account_names(conn: &mut PgConnection, ledger_id: Uuid) -> Result<HashMap<Uuid, String>, AppError> {
+    Ok(accounts::table
+        .filter(accounts::ledger_id.eq(ledger_id))
+        .select((accounts::id, accounts::name))
+        .load::<(Uuid, String)>(conn)?
+        .into_iter()
+        .collect())
+}
+
+// Where the bill image the transaction was created from can be downloaded
+pub fn bill_image_url(transaction: &TransactionResponse) -> Option<String> {
+    transaction
+        .bill_image
+        .as_ref()
+        .map(|_| format!("/api/transactions/{}/image", transaction.id))
+}
+
+fn account_name(transaction: &TransactionResponse, accounts: &HashMap<Uuid, String>) -> Option<String> {
+    transaction.account_id.and_then(|id| accounts.get(&id).cloned())
+}
+
+// e.g. "Rice x2 @ 40.00 THB; Water"
+fn describe_items(items: &[TransactionItem]) -> String {
+    items
+        .iter()
+        .map(|item| {
+            let mut text = item.name.clone();
+            if let Some(quantity) = item.quantity.filter(|q| *q != 1) {
+                text.push_str(&format!(" x{}", quantity));
+            }
+            if let Some(price) = &item.price {
+                text.push_str(&format!(" @ {}", price));
+            }
+            text
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+// e.g. "Groceries 120.00 THB; Household 30.00 THB"
+fn describe_splits(splits: &[SplitResponse]) -> String {
+    splits
+        .iter()
+        .map(|split| format!("{} {}", split.category, split.amount))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+fn csv_record(transaction: &TransactionResponse, accounts: &HashMap<Uuid, String>) -> Vec<String> {
+    vec![
+        transaction.id.to_string(),
+        transaction.date.date_naive().to_string(),
+        transaction.value_date.map(|d| d.to_string()).unwrap_or_default(),
+        transaction.merchant.clone(),
+        transaction.category.clone(),
+        transaction.amount.amount().to_string(),
+        transaction.amount.currency().to_string(),
+        transaction.direction.clone(),
+        transaction.transaction_type.clone(),
+        account_name(transaction, accounts).unwrap_or_default(),
+        transaction.notes.clone().unwrap_or_default(),
+        transaction.tags.join(", "),
+        transaction.items.as_deref().map(describe_items).unwrap_or_default(),
+        describe_splits(&transaction.splits),
+        transaction.bank_reference.clone().unwrap_or_default(),
+        bill_image_url(transaction).unwrap_or_default(),
+    ]
+}
+
+// One chunk of the CSV file; the first starts with the byte order mark and the header row
+pub fn csv_chunk(
+    transactions: &[TransactionResponse],
+    accounts: &HashMap<Uuid, String>,
+    first: bool,
+) -> Result<Vec<u8>, AppError> {
+    let mut buffer = Vec::new();
+    if first {
+        buffer.extend_from_slice(UTF8_BOM);
+    }
+    let csv_error = |e: csv::Error| AppError::InternalServerError(format!("Failed to write CSV: {}", e));
+    {
+        let mut writer = csv::Writer::from_writer(&mut buffer);
+        if first {
+            writer.write_record(COLUMNS).map_err(csv_error)?;
+        }
+        for transaction in transactions {
+            writer.write_record(csv_record(transaction, accounts)).map_err(csv_error)?;
+        }
+        writer.flush()?;
+    }
+    Ok(buffer)
+}
+
+// One chunk of the JSON Lines file: a transaction per line, as the API returns it
+pub fn jsonl_chunk(
+    transactions: Vec<TransactionResponse>,
+    accounts: &HashMap<Uuid, String>,
+) -> Result<Vec<u8>, AppError> {
+    let mut buffer = Vec::new();
+    for transaction in transactions {
+        let record = ExportedTransaction {
+            account: account_name(&transaction, accounts),
+            bill_image_url: bill_image_url(&transaction),
+            transaction,
+        };
+        serde_json::to_writer(&mut buffer, &record)?;
+        buffer.push(b'\n');
+    }
+    Ok(buffer)
+}
+
+fn to_number(amount: &BigDecimal) -> f64 {
+    amount.to_f64().unwrap_or_default()
+}
+
+fn write_transactions(
+    sheet: &mut Worksheet,
+    transactions: &[TransactionResponse],
+    accounts: &HashMap<Uuid, String>,
+) -> Result<(), XlsxError> {
+    let header = Format::new().set_bold();
+    let date = Format::new().set_num_format("yyyy-mm-dd");
+    let money = Format::new().set_num_format("#,##0.00");
+    let whole_money = Format::new().set_num_format("#,##0");
+
+    sheet.set_name("Transactions")?;
+    for (column, name) in COLUMNS.iter().enumerate() {
+        sheet.write_string_with_format(0, column as u16, *name, &header)?;
+    }
+    sheet.set_freeze_panes(1, 0)?;
+
+    for (index, transaction) in transactions.iter().enumerate() {
+        let row = index as u32 + 1;
+        let currency = transaction.amount.currency();
+        let amount_format = if currency.minor_units() == 0 { &whole_money } else { &money };
+        let text = csv_record(transaction, accounts);
+
+        // Dates and the amount as typed cells, everything else as text
+        sheet.write_datetime_with_format(row, 1, transaction.date.date_naive(), &date)?;
+        if let Some(value_date) = &transaction.value_date {
+            sheet.write_datetime_with_format(row, 2, value_date, &date)?;
+        }
+        sheet.write_number_with_format(row, 5, to_number(transaction.amount.amount()), amount_format)?;
+        for (column, value) in text.iter().enumerate() {
+            if ![1, 2, 5].contains(&column) && !value.is_empty() {
+                sheet.write_string(row, column as u16, value)?;
+            }
+        }
+    }
+    sheet.set_column_width(0, 38)?;
+    sheet.set_column_width(1, 12)?;
+    sheet.set_column_width(2, 12)?;
+    sheet.set_column_width(3, 30)?;
+    sheet.set_column_width(4, 20)?;
+    Ok(())
+}
+
+// Money out and in per category and currency. Split transactions count under their splits'
+// categories, as in the category reports
+fn write_summary(sheet: &mut Worksheet, transactions: &[TransactionResponse]) -> Result<(), XlsxError> {
+    let header = Format::new().set_bold();
+    let money = Format::new().set_num_format("#,##0.00");
+
+    let mut totals: BTreeMap<(String, String), (u32, BigDecimal, BigDecimal)> = BTreeMap::new();
+    for transaction in transactions {
+        let allocations: Vec<(&str, &BigDecimal)> = if transaction.splits.is_empty() {
+            vec![(transaction.category.as_str(), transaction.amount.amount())]
+        } else {
+            transaction
+                .splits
+                .iter()
+                .map(|split| (split.category.as_str(), split.amount.amount()))
+                .collect()
+        };
+        for (category, amount) in allocations {
+            let category = if category.is_empty() { "Uncategorized" } else { category };
+            let key = (category.to_string(), transaction.amount.currency().to_string());
+            let (count, money_out, money_in) = totals
+                .entry(key)
+                .or_insert_with(|| (0, BigDecimal::zero(), BigDecimal::zero()));
+            *count += 1;
+            if transaction.direction == Direction::Income.as_str() {
+                *money_in += amount;
+            } else {
+                *money_out += amount;
+            }
+        }
+    }
+
+    sheet.set_name("Categories")?;
+    let columns = ["category", "currency", "transactions", "money_out", "money_in", "net"];
+    for (column, name) in columns.iter().enumerate() {
+        sheet.write_string_with_format(0, column as u16, *name, &header)?;
+    }
+    for (index, ((category, currency), (count, money_out, money_in))) in totals.iter().enumerate() {
+        let row = index as u32 + 1;
+        sheet.write_string(row, 0, category)?;
+        sheet.write_string(row, 1, currency)?;
+        sheet.write_number(row, 2, *count)?;
+        sheet.write_number_with_format(row, 3, to_number(money_out), &money)?;
+        sheet.write_number_with_format(row, 4, to_number(money_in), &money)?;
+        sheet.write_number_with_format(row, 5, to_number(&(money_in - money_out)), &money)?;
+    }
+    sheet.set_column_width(0, 24)?;
+    Ok(())
+}
+
+// A workbook with the transactions, dates and amounts as typed cells, and a sheet of totals
+// per category
+pub fn xlsx_workbook(
+    transactions: &[TransactionResponse],
+    accounts: &HashMap<Uuid, String>,
+) -> Result<Vec<u8>, AppError> {
+    let write = || {
+        let mut workbook = Workbook::new();
+        write_transactions(workbook.add_worksheet(), transactions, accounts)?;
+        write_summary(workbook.add_worksheet(), transactions)?;
+        workbook.save_to_buffer()
+    };
+    write().map_err(|e: XlsxError| AppError::InternalServerError(format!("Failed to write the spreadsheet: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::*;
+    use crate::money::{Currency, Money};
+
+    fn thb(amount: i64) -> Money {
+        Money::new(BigDecimal::from(amount), Currency::THB)
+    }
+
+    fn transaction(merchant: &str, account_id: Option<Uuid>) -> TransactionResponse {
+        TransactionResponse {
+            id: Uuid::new_v4(),
+            amount: thb(150),
+            currency: Currency::THB,
+            date: Utc.with_ymd_and_hms(2024, 3, 5, 9, 30, 0).unwrap(),
+            merchant: merchant.to_string(),
+            merchant_id: None,
+            category: "Food & Dining".to_string(),
+            notes: None,
+            items: Some(vec![
+                TransactionItem {
+                    name: "Rice".to_string(),
+                    price: Some(thb(40)),
+                    quantity: Some(2),
+                },
+                TransactionItem {
+                    name: "Water".to_string(),
+                    price: None,
+                    quantity: Some(1),
+                },
+            ]),
+            bill_image: Some("uploads/bill.jpg".to_string()),
+            tags: vec!["lunch".to_string(), "work".to_string()],
+            excluded: false,
+            source: "ocr".to_string(),
+            recurring_id: None,
+            account_id,
+            direction: "debit".to_string(),
+            transfer_id: None,
+            transaction_type: "purchase".to_string(),
+            refund_of: None,
+            splits: Vec::new(),
+            import_batch_id: None,
+            value_date: None,
+            bank_reference: None,
+            reconciled: false,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn pages_only_when_a_limit_is_given() {
+        let range = ExportRange::new(&TransactionFilters::default());
+        assert_eq!((range.offset, range.limit), (0, None));
+
+        let filters = TransactionFilters {
+            page: Some(3),
+            limit: Some(50),
+            ..Default::default()
+        };
+        let range = ExportRange::new(&filters);
+        assert_eq!((range.offset, range.limit), (100, Some(50)));
+    }
+
+    #[test]
+    fn describes_items_and_splits() {
+        let t = transaction("Café", None);
+        assert_eq!(describe_items(t.items.as_deref().unwrap()), "Rice x2 @ 40.00 THB; Water");
+        let splits = [
+            SplitResponse {
+                id: Uuid::new_v4(),
+                category_id: None,
+                category: "Groceries".to_string(),
+                amount: thb(120),
+                items: Vec::new(),
+                notes: None,
+                tags: Vec::new(),
+            },
+            SplitResponse {
+                id: Uuid::new_v4(),
+                category_id: None,
+                category: "Household".to_string(),
+                amount: thb(30),
+                items: Vec::new(),
+                notes: None,
+                tags: Vec::new(),
+            },
+        ];
+        assert_eq!(describe_splits(&splits), "Groceries 120.00 THB; Household 30.00 THB");
+    }
+
+    #[test]
+    fn writes_the_header_and_byte_order_mark_only_once() {
+        let account_id = Uuid::new_v4();
+        let accounts = HashMap::from([(account_id, "KBank Savings".to_string())]);
+        let first = transaction("ร้านข้าวมันไก่, สาขา 2", Some(account_id));
+
+        let chunk = csv_chunk(std::slice::from_ref(&first), &accounts, true).unwrap();
+        assert!(chunk.starts_with(UTF8_BOM));
+        let text = String::from_utf8(chunk[UTF8_BOM.len()..].to_vec()).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], COLUMNS.join(","));
+        // The comma in the merchant is quoted rather than splitting the column
+        assert!(lines[1].contains(",2024-03-05,,\"ร้านข้าวมันไก่, สาขา 2\",Food & Dining,150.00,THB,debit,"));
+        assert!(lines[1].contains(",KBank Savings,,\"lunch, work\","));
+        assert!(lines[1].ends_with(&format!(",/api/transactions/{}/image", first.id)));
+
+        let next = csv_chunk(&[transaction("7-Eleven", None)], &accounts, false).unwrap();
+        assert!(!next.starts_with(UTF8_BOM));
+        assert_eq!(String::from_utf8(next).unwrap().lines().count(), 1);
+    }
+
+    #[test]
+    fn writes_a_json_object_per_line() {
+        let account_id = Uuid::new_v4();
+        let accounts = HashMap::from([(account_id, "Cash".to_string())]);
+        let chunk = jsonl_chunk(vec![transaction("A", Some(account_id)), transaction("B", None)], &accounts).unwrap();
+        let lines: Vec<serde_json::Value> = String::from_utf8(chunk)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["merchant"], "A");
+        assert_eq!(lines[0]["account"], "Cash");
+        assert!(lines[1]["account"].is_null());
+    }
+}
@@ -0,0 +1,500 @@
+use std::collections::{HashMap, HashSet};
+
+use bigdecimal::{BigDecimal, Zero};
+use chrono::{DateTime, Duration, Utc};
+use diesel::prelude::*;
+use diesel::PgConnection;
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::models::account::DbAccount;
+use crate::models::import::{
+    BalanceCheck, DbImportBatch, DbImportProfile, ImportPreview, ImportRowError, NewImportBatch, ParsedRow,
+    PreviewRow, StatementBalances,
+};
+use crate::models::rule::{Rule, RuleEffects};
+use crate::models::transaction::{DbTransaction, NewTransaction, TransactionType, SOURCE_IMPORT};
+use crate::money::Money;
+use crate::schema::{import_batches, import_profiles, transactions};
+use crate::services::categorizer;
+use crate::services::merchants::{resolve_or_create_merchant, similarity};
+use crate::services::rules::{apply_rules, load_rules, RuleInput};
+use crate::services::tags::{lower, set_transaction_tags};
+use crate::services::transactions::{day_start, resolve_category_id};
+use crate::services::trash;
+
+// Banks and receipts can disagree on the day, so a match may be a day either side
+const DUPLICATE_DAY_WINDOW: i64 = 1;
+
+pub fn find_profile(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    profile_id: Uuid,
+) -> Result<DbImportProfile, AppError> {
+    import_profiles::table
+        .filter(import_profiles::id.eq(profile_id))
+        .filter(import_profiles::ledger_id.eq(ledger_id))
+        .first::<DbImportProfile>(conn)
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("Import profile {} not found", profile_id)))
+}
+
+// Profile names are unique per user, ignoring case
+pub fn ensure_name_available(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    name: &str,
+    except: Option<Uuid>,
+) -> Result<(), AppError> {
+    let existing = import_profiles::table
+        .filter(import_profiles::ledger_id.eq(ledger_id))
+        .filter(lower(import_profiles::name).eq(name.to_lowercase()))
+        .select(import_profiles::id)
+        .first::<Uuid>(conn)
+        .optional()?;
+    match existing {
+        Some(id) if Some(id) != except => {
+            Err(AppError::BadRequest(format!("Import profile already exists: {}", name)))
+        }
+        _ => Ok(()),
+    }
+}
+
+pub fn find_batch(conn: &mut PgConnection, ledger_id: Uuid, batch_id: Uuid) -> Result<DbImportBatch, AppError> {
+    import_batches::table
+        .filter(import_batches::id.eq(batch_id))
+        .filter(import_batches::ledger_id.eq(ledger_id))
+        .first::<DbImportBatch>(conn)
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("Import {} not found", batch_id)))
+}
+
+// Lines in another currency than the account can't be recorded against it
+pub fn check_account(
+    rows: Vec<ParsedRow>,
+    account: Option<&DbAccount>,
+    errors: &mut Vec<ImportRowError>,
+) -> Vec<ParsedRow> {
+    let Some(account) = account else {
+        return rows;
+    };
+    rows.into_iter()
+        .filter(|row| {
+            let matches = row.amount.currency().to_string() == account.currency;
+            if !matches {
+                errors.push(ImportRowError {
+                    line: row.line,
+                    message: format!(
+                        "Account {} is in {}, not {}",
+                        account.name,
+                        account.currency,
+                        row.amount.currency()
+                    ),
+                });
+            }
+            matches
+        })
+        .collect()
+}
+
+// A reversed payment coming back is a refund; a reversed credit going out again is spending
+fn transaction_type(row: &ParsedRow) -> TransactionType {
+    match (row.income, row.reversal) {
+        (true, true) => TransactionType::Refund,
+        (true, false) => TransactionType::Income,
+        (false, _) => TransactionType::Expense,
+    }
+}
+
+// Add the statement's lines to its opening balance and compare with the closing one. Every line
+// read counts, including duplicates and lines in another currency than the account
+pub fn check_balances(balances: &StatementBalances, rows: &[ParsedRow]) -> BalanceCheck {
+    let currency = balances.closing.currency();
+    let total = rows
+        .iter()
+        .filter(|row| row.amount.currency() == currency)
+        .fold(BigDecimal::zero(), |total, row| {
+            if row.income {
+                total + row.amount.amount()
+            } else {
+                total - row.amount.amount()
+            }
+        });
+    let computed = balances.opening.amount() + total;
+    let difference = balances.closing.amount() - &computed;
+    BalanceCheck {
+        currency,
+        opening: balances.opening.clone(),
+        closing: balances.closing.clone(),
+        computed_closing: Money::new(computed, currency),
+        balanced: difference.is_zero(),
+        difference,
+    }
+}
+
+// An existing transaction a statement line repeats
+#[derive(Debug, Clone, Copy)]
+pub struct DuplicateMatch {
+    pub transaction_id: Uuid,
+    // Matched on the bank's own ID, so the line was certainly imported before
+    pub exact: bool,
+    // The earlier copy is in the trash, e.g. after its import was rolled back
+    pub in_trash: bool,
+}
+
+// Transactions already imported under the rows' bank IDs, by ID, and whether each is in the
+// trash; lines in the trash count too, so importing them again takes them back out of it
+fn imported_external_ids(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    account_id: Option<Uuid>,
+    rows: &[ParsedRow],
+) -> Result<HashMap<String, (Uuid, bool)>, AppError> {
+    let external_ids: Vec<&String> = rows.iter().filter_map(|r| r.external_id.as_ref()).collect();
+    if external_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let mut query = transactions::table
+        .filter(transactions::ledger_id.eq(ledger_id))
+        .filter(transactions::external_id.eq_any(external_ids))
+        .into_boxed();
+    if let Some(account_id) = account_id {
+        query = query.filter(transactions::account_id.eq(account_id));
+    }
+    Ok(query
+        .select((
+            transactions::external_id.assume_not_null(),
+            transactions::id,
+            transactions::deleted_at.is_not_null(),
+        ))
+        .load::<(String, Uuid, bool)>(conn)?
+        .into_iter()
+        .map(|(external_id, id, in_trash)| (external_id, (id, in_trash)))
+        .collect())
+}
+
+// Rows whose bank ID an earlier row in the same file already has; only the first is recorded
+fn repeated_external_ids(rows: &[ParsedRow]) -> Vec<bool> {
+    let mut seen: HashSet<&str> = HashSet::new();
+    rows.iter()
+        .map(|row| row.external_id.as_deref().is_some_and(|id| !seen.insert(id)))
+        .collect()
+}
+
+// For each row, an existing transaction it repeats. A bank ID seen before is a sure match;
+// otherwise a transaction with the same amount, currency and direction within a day, on the same
+// account or on none. Each existing transaction matches at most one row, so two identical coffees
+// on one statement are only duplicates if both were already recorded
+pub fn find_duplicates(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    account_id: Option<Uuid>,
+    rows: &[ParsedRow],
+) -> Result<Vec<Option<DuplicateMatch>>, AppError> {
+    let (Some(first), Some(last)) = (rows.iter().map(|r| r.date).min(), rows.iter().map(|r| r.date).max()) else {
+        return Ok(Vec::new());
+    };
+    let imported = imported_external_ids(conn, ledger_id, account_id, rows)?;
+
+    let mut query = transactions::table
+        .filter(transactions::ledger_id.eq(ledger_id))
+        .filter(transactions::date.ge(day_start(first - Duration::days(DUPLICATE_DAY_WINDOW))))
+        .filter(transactions::date.lt(day_start(last + Duration::days(DUPLICATE_DAY_WINDOW + 1))))
+        .filter(transactions::deleted_at.is_null())
+        .into_boxed();
+    if let Some(account_id) = account_id {
+        query = query.filter(transactions::account_id.eq(account_id).or(transactions::account_id.is_null()));
+    }
+    let candidates = query.load::<DbTransaction>(conn)?;
+
+    let mut claimed: HashSet<Uuid> = imported.values().map(|(id, _)| *id).collect();
+    Ok(rows
+        .iter()
+        .map(|row| {
+            if let Some(&(transaction_id, in_trash)) = row.external_id.as_ref().and_then(|id| imported.get(id)) {
+                return Some(DuplicateMatch {
+                    transaction_id,
+                    exact: true,
+                    in_trash,
+                });
+            }
+
+            let direction = transaction_type(row).direction();
+            let best = candidates
+                .iter()
+                .filter(|c| !claimed.contains(&c.id))
+                .filter(|c| &c.amount == row.amount.amount() && c.currency == row.amount.currency().to_string())
+                .filter(|c| c.direction == direction.as_str())
+                .filter(|c| (c.date.date_naive() - row.date).num_days().abs() <= DUPLICATE_DAY_WINDOW)
+                .min_by(|a, b| {
+                    let days = |c: &DbTransaction| (c.date.date_naive() - row.date).num_days().abs();
+                    days(a).cmp(&days(b)).then(
+                        similarity(&b.merchant.to_lowercase(), &row.description.to_lowercase())
+                            .total_cmp(&similarity(&a.merchant.to_lowercase(), &row.description.to_lowercase())),
+                    )
+                })
+                .map(|c| c.id);
+            best.map(|transaction_id| {
+                claimed.insert(transaction_id);
+                DuplicateMatch {
+                    transaction_id,
+                    exact: false,
+                    in_trash: false,
+                }
+            })
+        })
+        .collect())
+}
+
+// The account the last import of the same statement account went into
+pub fn account_for_statement(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    statement_account: &str,
+) -> Result<Option<Uuid>, AppError> {
+    Ok(import_batches::table
+        .filter(import_batches::ledger_id.eq(ledger_id))
+        .filter(import_batches::statement_account.eq(statement_account))
+        .filter(import_batches::account_id.is_not_null())
+        .order(import_batches::created_at.desc())
+        .select(import_batches::account_id)
+        .first::<Option<Uuid>>(conn)
+        .optional()?
+        .flatten())
+}
+
+// What an import would do, without saving anything
+pub fn preview(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    account_id: Option<Uuid>,
+    statement_account: Option<String>,
+    rows: Vec<ParsedRow>,
+    errors: Vec<ImportRowError>,
+    balance_check: Option<BalanceCheck>,
+) -> Result<ImportPreview, AppError> {
+    let duplicates = find_duplicates(conn, ledger_id, account_id, &rows)?;
+    let repeated = repeated_external_ids(&rows);
+    let rows: Vec<PreviewRow> = rows
+        .into_iter()
+        .zip(duplicates)
+        .zip(repeated)
+        .map(|((row, duplicate), repeated)| PreviewRow {
+            line: row.line,
+            date: row.date,
+            transaction_type: transaction_type(&row).as_str().to_string(),
+            currency: row.amount.currency(),
+            amount: row.amount,
+            description: row.description,
+            notes: row.notes,
+            duplicate_of: duplicate.filter(|d| !d.in_trash).map(|d| d.transaction_id),
+            already_imported: repeated || duplicate.is_some_and(|d| d.exact && !d.in_trash),
+        })
+        .collect();
+    Ok(ImportPreview {
+        statement_account,
+        account_id,
+        duplicate_count: rows.iter().filter(|r| r.duplicate_of.is_some() || r.already_imported).count(),
+        rows,
+        errors,
+        balance_check,
+    })
+}
+
+// Record one statement line the way a typed transaction would be: the merchant is resolved, rules
+// run, and a purchase no rule categorized gets the best suggestion
+fn insert_row(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    batch: &NewImportBatch,
+    rules: &[Rule],
+    row: &ParsedRow,
+) -> Result<(), AppError> {
+    let transaction_type = transaction_type(row);
+    let merchant = resolve_or_create_merchant(conn, ledger_id, &row.description, None)?.merchant;
+
+    let mut effects = RuleEffects {
+        category: String::new(),
+        tags: Vec::new(),
+        notes: row.notes.clone(),
+        excluded: false,
+    };
+    let input = RuleInput {
+        merchant: &merchant.name,
+        amount: row.amount.amount(),
+        currency: row.amount.currency(),
+        items: &[],
+        source: SOURCE_IMPORT,
+    };
+    apply_rules(rules, &input, &mut effects);
+
+    // A category the file assigns wins over the rules, as a typed one does
+    let mut category = row
+        .category
+        .clone()
+        .or(Some(effects.category))
+        .filter(|c| !c.is_empty());
+    if category.is_none() && transaction_type == TransactionType::Expense {
+        category = categorizer::suggest_categories(conn, ledger_id, Some(&merchant.name), &[], 1)?
+            .into_iter()
+            .next()
+            .map(|suggestion| suggestion.name);
+    }
+    let category_id = match category {
+        Some(name) => resolve_category_id(conn, ledger_id, &name, transaction_type.category_kind())?,
+        None => None,
+    };
+
+    let new_transaction = NewTransaction {
+        id: Uuid::new_v4(),
+        amount: row.amount.amount().clone(),
+        date: day_start(row.date),
+        merchant: merchant.name.clone(),
+        category_id,
+        notes: effects.notes,
+        items: None,
+        image_path: None,
+        ledger_id,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+        merchant_id: Some(merchant.id),
+        excluded: effects.excluded,
+        source: SOURCE_IMPORT.to_string(),
+        recurring_id: None,
+        currency: row.amount.currency().to_string(),
+        account_id: batch.account_id,
+        direction: transaction_type.direction().as_str().to_string(),
+        transfer_id: None,
+        transaction_type: transaction_type.as_str().to_string(),
+        refund_of: None,
+        import_batch_id: Some(batch.id),
+        external_id: row.external_id.clone(),
+        value_date: row.value_date,
+        bank_reference: row.reference.clone(),
+    };
+    let transaction_id = diesel::insert_into(transactions::table)
+        .values(&new_transaction)
+        .returning(transactions::id)
+        .get_result::<Uuid>(conn)?;
+    set_transaction_tags(conn, ledger_id, transaction_id, &effects.tags)?;
+    Ok(())
+}
+
+// Save the rows as one batch, leaving out likely duplicates unless asked to keep them; lines
+// imported before under the same bank ID are always left out, and taken back out of the trash
+// into this batch if they were there. The batch counts are filled in here; the caller sets where
+// the file came from
+pub fn import_rows(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    mut batch: NewImportBatch,
+    rows: &[ParsedRow],
+    error_count: usize,
+    include_duplicates: bool,
+) -> Result<DbImportBatch, AppError> {
+    conn.transaction(|conn| {
+        let duplicates = find_duplicates(conn, ledger_id, batch.account_id, rows)?;
+        let repeated = repeated_external_ids(rows);
+        let mut to_import: Vec<&ParsedRow> = Vec::new();
+        let mut to_restore: Vec<Uuid> = Vec::new();
+        for ((row, duplicate), repeated) in rows.iter().zip(&duplicates).zip(repeated) {
+            match duplicate {
+                _ if repeated => {}
+                None => to_import.push(row),
+                Some(duplicate) if duplicate.in_trash => to_restore.push(duplicate.transaction_id),
+                Some(duplicate) if include_duplicates && !duplicate.exact => to_import.push(row),
+                Some(_) => {}
+            }
+        }
+
+        batch.row_count = (rows.len() + error_count) as i32;
+        batch.imported_count = (to_import.len() + to_restore.len()) as i32;
+        batch.duplicate_count = (rows.len() - to_import.len() - to_restore.len()) as i32;
+        batch.error_count = error_count as i32;
+        let saved = diesel::insert_into(import_batches::table)
+            .values(&batch)
+            .get_result::<DbImportBatch>(conn)?;
+
+        diesel::update(
+            transactions::table
+                .filter(transactions::ledger_id.eq(ledger_id))
+                .filter(transactions::id.eq_any(&to_restore)),
+        )
+        .set((
+            transactions::deleted_at.eq(None::<DateTime<Utc>>),
+            transactions::import_batch_id.eq(Some(saved.id)),
+            transactions::updated_at.eq(Utc::now()),
+        ))
+        .execute(conn)?;
+
+        let rules = load_rules(conn, ledger_id)?;
+        for row in to_import {
+            insert_row(conn, ledger_id, &batch, &rules, row)?;
+        }
+        Ok(saved)
+    })
+}
+
+// Move every transaction the import created to the trash, including any edited since
+pub fn rollback_batch(conn: &mut PgConnection, ledger_id: Uuid, batch_id: Uuid) -> Result<DbImportBatch, AppError> {
+    conn.transaction(|conn| {
+        let batch = find_batch(conn, ledger_id, batch_id)?;
+        if batch.rolled_back_at.is_some() {
+            return Err(AppError::BadRequest("This import was already rolled back".to_string()));
+        }
+
+        let now = Utc::now();
+        let imported = transactions::table
+            .filter(transactions::ledger_id.eq(ledger_id))
+            .filter(transactions::import_batch_id.eq(batch.id))
+            .select(transactions::id)
+            .load::<Uuid>(conn)?;
+        trash::trash_transactions(conn, ledger_id, &imported, now)?;
+        Ok(diesel::update(import_batches::table.find(batch.id))
+            .set(import_batches::rolled_back_at.eq(Some(now)))
+            .get_result::<DbImportBatch>(conn)?)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    use crate::money::Currency;
+
+    fn row(line: usize, external_id: Option<&str>) -> ParsedRow {
+        ParsedRow {
+            line,
+            date: NaiveDate::from_ymd_opt(2024, 1, 31).unwrap(),
+            amount: Money::new(BigDecimal::from(5), Currency::THB),
+            income: false,
+            description: "Coffee".to_string(),
+            notes: None,
+            external_id: external_id.map(str::to_string),
+            category: None,
+            value_date: None,
+            reference: None,
+            reversal: false,
+        }
+    }
+
+    #[test]
+    fn keeps_only_the_first_row_with_a_bank_id() {
+        let rows = [row(1, Some("A1")), row(2, None), row(3, Some("A1")), row(4, None), row(5, Some("A2"))];
+        assert_eq!(repeated_external_ids(&rows), [false, false, true, false, false]);
+    }
+
+    #[test]
+    fn treats_reversed_payments_as_refunds() {
+        let mut reversed = row(1, None);
+        reversed.income = true;
+        reversed.reversal = true;
+        assert_eq!(transaction_type(&reversed), TransactionType::Refund);
+        reversed.reversal = false;
+        assert_eq!(transaction_type(&reversed), TransactionType::Income);
+        reversed.income = false;
+        reversed.reversal = true;
+        assert_eq!(transaction_type(&reversed), TransactionType::Expense);
+    }
+}
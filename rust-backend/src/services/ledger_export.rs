@@ -0,0 +1,662 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use bigdecimal::{BigDecimal, Zero};
+use chrono::{NaiveDate, Utc};
+use diesel::prelude::*;
+use diesel::PgConnection;
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::models::account::DbAccount;
+use crate::models::category::{CategoryKind, DbCategory};
+use crate::models::ledger::{DbLedgerAccount, LedgerAccountResponse, NewLedgerAccount, SetLedgerAccountsDto};
+use crate::models::transaction::{Direction, TransactionResponse, TransactionType};
+use crate::money::{Currency, Money};
+use crate::schema::{accounts, categories, ledger_accounts};
+use crate::services::accounts as account_service;
+
+// Every ledger account belongs under one of these
+const TOP_LEVEL_ACCOUNTS: [&str; 5] = ["Assets", "Liabilities", "Equity", "Income", "Expenses"];
+
+// For transactions recorded against no account
+const UNASSIGNED_ACCOUNT: &str = "Assets:Unassigned";
+// The other side of a transfer whose other leg isn't in the export
+const TRANSFER_ACCOUNT: &str = "Equity:Transfers";
+const UNCATEGORIZED: &str = "Uncategorized";
+
+// The plain-text accounting tool a journal is written for. ledger and hledger read the same
+// entries but write tags differently; beancount has its own syntax and stricter account names
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Dialect {
+    Ledger,
+    Hledger,
+    Beancount,
+}
+
+impl Dialect {
+    // One part of an account name made valid. Beancount parts are letters, digits and dashes and
+    // start with a capital or a digit; ledger ends an account name at two spaces or a semicolon
+    fn component(&self, text: &str) -> String {
+        match self {
+            Dialect::Beancount => {
+                let mut part = String::new();
+                for c in text.trim().chars() {
+                    if c.is_alphanumeric() {
+                        part.push(c);
+                    } else if !part.is_empty() && !part.ends_with('-') {
+                        part.push('-');
+                    }
+                }
+                let part = part.trim_end_matches('-');
+                let mut chars = part.chars();
+                match chars.next() {
+                    None => "X".to_string(),
+                    Some(first) if first.is_uppercase() || first.is_ascii_digit() => part.to_string(),
+                    Some(first) if first.is_lowercase() => first.to_uppercase().chain(chars).collect(),
+                    // Scripts without capitals, such as Thai
+                    Some(_) => format!("X-{}", part),
+                }
+            }
+            Dialect::Ledger | Dialect::Hledger => {
+                let part = text.replace([':', ';'], "-").split_whitespace().collect::<Vec<_>>().join(" ");
+                if part.is_empty() {
+                    "Unnamed".to_string()
+                } else {
+                    part
+                }
+            }
+        }
+    }
+
+    fn account(&self, name: &str) -> String {
+        name.split(':').map(|part| self.component(part)).collect::<Vec<_>>().join(":")
+    }
+
+    // A tag name without the characters each tool uses to delimit tags
+    fn tag(&self, tag: &str) -> Option<String> {
+        let tag: String = match self {
+            Dialect::Beancount => tag
+                .trim()
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() || "-_/.".contains(c) { c } else { '-' })
+                .collect(),
+            Dialect::Ledger | Dialect::Hledger => tag
+                .trim()
+                .chars()
+                .map(|c| if c.is_whitespace() || c == ':' || c == ',' { '-' } else { c })
+                .collect(),
+        };
+        Some(tag).filter(|t| t.chars().any(|c| c != '-'))
+    }
+}
+
+// Check a ledger account the user typed, e.g. "Expenses:Food:Groceries"
+pub fn validate_ledger_name(name: &str) -> Result<String, AppError> {
+    let parts: Vec<&str> = name.split(':').map(str::trim).collect();
+    if !TOP_LEVEL_ACCOUNTS.contains(&parts[0]) {
+        return Err(AppError::BadRequest(
+            "A ledger account must start with Assets, Liabilities, Equity, Income or Expenses".to_string(),
+        ));
+    }
+    if parts.len() < 2 || parts.iter().any(|part| part.is_empty()) {
+        return Err(AppError::BadRequest(format!("{} isn't a valid ledger account", name.trim())));
+    }
+    Ok(parts.join(":"))
+}
+
+fn category_kind(category: &DbCategory) -> CategoryKind {
+    if category.kind == CategoryKind::Income.as_str() {
+        CategoryKind::Income
+    } else {
+        CategoryKind::Expense
+    }
+}
+
+fn default_category_name(name: &str, kind: CategoryKind) -> String {
+    let top = match kind {
+        CategoryKind::Income => "Income",
+        CategoryKind::Expense => "Expenses",
+    };
+    format!("{}:{}", top, name.replace(':', "-"))
+}
+
+// Credit cards are money owed; everything else is money held
+fn default_account_name(account: &DbAccount) -> String {
+    let group = match account.account_type.as_str() {
+        "credit_card" => "Liabilities:CreditCard",
+        "bank" => "Assets:Bank",
+        "e_wallet" => "Assets:EWallet",
+        _ => "Assets:Cash",
+    };
+    format!("{}:{}", group, account.name.replace(':', "-"))
+}
+
+type LedgerSources = (Vec<DbCategory>, Vec<DbAccount>, Vec<DbLedgerAccount>);
+
+// The ledger's categories and accounts with the ledger accounts chosen for them
+fn load_sources(conn: &mut PgConnection, ledger_id: Uuid) -> Result<LedgerSources, AppError> {
+    let categories = categories::table
+        .filter(categories::ledger_id.eq(ledger_id))
+        .filter(categories::deleted_at.is_null())
+        .order(categories::name.asc())
+        .load::<DbCategory>(conn)?;
+    let accounts = accounts::table
+        .filter(accounts::ledger_id.eq(ledger_id))
+        .order(accounts::name.asc())
+        .load::<DbAccount>(conn)?;
+    let mappings = ledger_accounts::table
+        .filter(ledger_accounts::ledger_id.eq(ledger_id))
+        .load::<DbLedgerAccount>(conn)?;
+    Ok((categories, accounts, mappings))
+}
+
+// Every category and account with the ledger account it is exported as
+pub fn ledger_accounts(conn: &mut PgConnection, ledger_id: Uuid) -> Result<Vec<LedgerAccountResponse>, AppError> {
+    let (categories, accounts, mappings) = load_sources(conn, ledger_id)?;
+    let custom = |category_id: Option<Uuid>, account_id: Option<Uuid>| {
+        mappings
+            .iter()
+            .find(|m| {
+                (m.category_id.is_some() && m.category_id == category_id)
+                    || (m.account_id.is_some() && m.account_id == account_id)
+            })
+            .map(|m| m.name.clone())
+    };
+
+    let mut responses = Vec::new();
+    for category in &categories {
+        let name = custom(Some(category.id), None);
+        responses.push(LedgerAccountResponse {
+            category_id: Some(category.id),
+            account_id: None,
+            name: category.name.clone(),
+            custom: name.is_some(),
+            ledger_account: Dialect::Ledger.account(
+                &name.unwrap_or_else(|| default_category_name(&category.name, category_kind(category))),
+            ),
+        });
+    }
+    for account in &accounts {
+        let name = custom(None, Some(account.id));
+        responses.push(LedgerAccountResponse {
+            category_id: None,
+            account_id: Some(account.id),
+            name: account.name.clone(),
+            custom: name.is_some(),
+            ledger_account: Dialect::Ledger.account(&name.unwrap_or_else(|| default_account_name(account))),
+        });
+    }
+    Ok(responses)
+}
+
+// Set or clear the ledger accounts of categories and accounts; a mapping without a name goes
+// back to the default
+pub fn set_ledger_accounts(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    data: &SetLedgerAccountsDto,
+) -> Result<(), AppError> {
+    conn.transaction(|conn| {
+        for mapping in &data.mappings {
+            let name = mapping
+                .name
+                .as_deref()
+                .filter(|n| !n.trim().is_empty())
+                .map(validate_ledger_name)
+                .transpose()?;
+
+            match (mapping.category_id, mapping.account_id) {
+                (Some(category_id), None) => {
+                    categories::table
+                        .filter(categories::id.eq(category_id))
+                        .filter(categories::ledger_id.eq(ledger_id))
+                        .filter(categories::deleted_at.is_null())
+                        .select(categories::id)
+                        .first::<Uuid>(conn)
+                        .optional()?
+                        .ok_or_else(|| AppError::NotFound(format!("Category {} not found", category_id)))?;
+                    diesel::delete(ledger_accounts::table.filter(ledger_accounts::category_id.eq(category_id)))
+                        .execute(conn)?;
+                }
+                (None, Some(account_id)) => {
+                    account_service::find_account(conn, ledger_id, account_id)?;
+                    diesel::delete(ledger_accounts::table.filter(ledger_accounts::account_id.eq(account_id)))
+                        .execute(conn)?;
+                }
+                _ => {
+                    return Err(AppError::BadRequest(
+                        "Each mapping names either a category_id or an account_id".to_string(),
+                    ))
+                }
+            }
+
+            if let Some(name) = name {
+                diesel::insert_into(ledger_accounts::table)
+                    .values(&NewLedgerAccount {
+                        id: Uuid::new_v4(),
+                        ledger_id,
+                        category_id: mapping.category_id,
+                        account_id: mapping.account_id,
+                        name,
+                        created_at: Utc::now(),
+                        updated_at: Utc::now(),
+                    })
+                    .execute(conn)?;
+            }
+        }
+        Ok(())
+    })
+}
+
+// Ledger account names before the dialect's rules are applied. Transactions carry category
+// names, so categories are looked up by lowercased name and kind
+pub struct LedgerNames {
+    categories: HashMap<(String, &'static str), String>,
+    accounts: HashMap<Uuid, String>,
+}
+
+impl LedgerNames {
+    pub fn load(conn: &mut PgConnection, ledger_id: Uuid) -> Result<Self, AppError> {
+        let (categories, accounts, mappings) = load_sources(conn, ledger_id)?;
+        let by_category: HashMap<Uuid, &String> =
+            mappings.iter().filter_map(|m| m.category_id.map(|id| (id, &m.name))).collect();
+        let by_account: HashMap<Uuid, &String> =
+            mappings.iter().filter_map(|m| m.account_id.map(|id| (id, &m.name))).collect();
+
+        Ok(LedgerNames {
+            categories: categories
+                .iter()
+                .map(|category| {
+                    let kind = category_kind(category);
+                    let name = by_category
+                        .get(&category.id)
+                        .map(|name| name.to_string())
+                        .unwrap_or_else(|| default_category_name(&category.name, kind));
+                    ((category.name.to_lowercase(), kind.as_str()), name)
+                })
+                .collect(),
+            accounts: accounts
+                .iter()
+                .map(|account| {
+                    let name = by_account
+                        .get(&account.id)
+                        .map(|name| name.to_string())
+                        .unwrap_or_else(|| default_account_name(account));
+                    (account.id, name)
+                })
+                .collect(),
+        })
+    }
+
+    fn category(&self, name: &str, kind: CategoryKind) -> String {
+        let name = Some(name.trim()).filter(|n| !n.is_empty()).unwrap_or(UNCATEGORIZED);
+        self.categories
+            .get(&(name.to_lowercase(), kind.as_str()))
+            .cloned()
+            .unwrap_or_else(|| default_category_name(name, kind))
+    }
+
+    fn account(&self, account_id: Option<Uuid>) -> String {
+        account_id
+            .and_then(|id| self.accounts.get(&id).cloned())
+            .unwrap_or_else(|| UNASSIGNED_ACCOUNT.to_string())
+    }
+}
+
+// One line of an entry; `cost` is the total price in another currency, written `@@`
+struct Posting {
+    account: String,
+    amount: BigDecimal,
+    currency: Currency,
+    cost: Option<Money>,
+}
+
+struct Entry<'a> {
+    date: NaiveDate,
+    transaction: &'a TransactionResponse,
+    postings: Vec<Posting>,
+}
+
+fn posting(account: String, amount: BigDecimal, currency: Currency) -> Posting {
+    Posting {
+        account,
+        amount,
+        currency,
+        cost: None,
+    }
+}
+
+// The entry for an ordinary transaction: the account on one side and its category, or each of
+// its splits, on the other. Money in is positive on the account
+fn transaction_entry<'a>(transaction: &'a TransactionResponse, names: &LedgerNames) -> Entry<'a> {
+    let currency = transaction.amount.currency();
+    let amount = transaction.amount.amount();
+    let income = transaction.direction == Direction::Income.as_str();
+    let kind = TransactionType::parse(&transaction.transaction_type)
+        .map_or(CategoryKind::Expense, |t| t.category_kind());
+    let signed = |value: &BigDecimal, money_in: bool| if money_in { value.clone() } else { -value.clone() };
+
+    let mut postings = vec![posting(names.account(transaction.account_id), signed(amount, income), currency)];
+    let mut allocated = BigDecimal::zero();
+    for split in &transaction.splits {
+        allocated += split.amount.amount();
+        postings.push(posting(names.category(&split.category, kind), signed(split.amount.amount(), !income), currency));
+    }
+    let rest = amount - &allocated;
+    if !rest.is_zero() {
+        postings.push(posting(names.category(&transaction.category, kind), signed(&rest, !income), currency));
+    }
+    Entry {
+        date: transaction.date.date_naive(),
+        transaction,
+        postings,
+    }
+}
+
+// Both legs of a transfer as one entry, priced when the accounts are in different currencies.
+// A leg whose other half isn't exported is balanced against Equity:Transfers
+fn transfer_entry<'a>(legs: &[&'a TransactionResponse], names: &LedgerNames) -> Entry<'a> {
+    let from = legs.iter().find(|t| t.direction == Direction::Expense.as_str());
+    let to = legs.iter().find(|t| t.direction == Direction::Income.as_str());
+    let (transaction, postings) = match (from, to) {
+        (Some(from), Some(to)) => {
+            let (paid, received) = (&from.amount, &to.amount);
+            let mut incoming = posting(names.account(to.account_id), received.amount().clone(), received.currency());
+            if received.currency() != paid.currency() {
+                incoming.cost = Some(paid.clone());
+            }
+            let outgoing = posting(names.account(from.account_id), -paid.amount().clone(), paid.currency());
+            (*from, vec![incoming, outgoing])
+        }
+        _ => {
+            let leg = legs[0];
+            let amount = leg.amount.amount().clone();
+            let income = leg.direction == Direction::Income.as_str();
+            let (on_account, other) = if income { (amount.clone(), -amount) } else { (-amount.clone(), amount) };
+            let currency = leg.amount.currency();
+            let postings = vec![
+                posting(names.account(leg.account_id), on_account, currency),
+                posting(TRANSFER_ACCOUNT.to_string(), other, currency),
+            ];
+            (leg, postings)
+        }
+    };
+    Entry {
+        date: transaction.date.date_naive(),
+        transaction,
+        postings,
+    }
+}
+
+fn entries<'a>(transactions: &'a [TransactionResponse], names: &LedgerNames) -> Vec<Entry<'a>> {
+    let mut transfers: HashMap<Uuid, Vec<&TransactionResponse>> = HashMap::new();
+    for transaction in transactions {
+        if let Some(transfer_id) = transaction.transfer_id {
+            transfers.entry(transfer_id).or_default().push(transaction);
+        }
+    }
+
+    let mut written = HashSet::new();
+    let mut entries = Vec::new();
+    for transaction in transactions {
+        match transaction.transfer_id {
+            Some(transfer_id) if written.insert(transfer_id) => {
+                entries.push(transfer_entry(&transfers[&transfer_id], names));
+            }
+            Some(_) => {}
+            None => entries.push(transaction_entry(transaction, names)),
+        }
+    }
+    entries
+}
+
+fn quoted(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', " "))
+}
+
+fn write_posting(journal: &mut String, dialect: Dialect, posting: &Posting) {
+    let indent = if dialect == Dialect::Beancount { "  " } else { "    " };
+    let account = dialect.account(&posting.account);
+    journal.push_str(&format!("{}{:<48}  {} {}", indent, account, posting.amount, posting.currency));
+    if let Some(cost) = &posting.cost {
+        journal.push_str(&format!(" @@ {} {}", cost.amount(), cost.currency()));
+    }
+    journal.push('\n');
+}
+
+fn write_entry(journal: &mut String, dialect: Dialect, entry: &Entry) {
+    let transaction = entry.transaction;
+    let tags: Vec<String> = transaction.tags.iter().filter_map(|tag| dialect.tag(tag)).collect();
+    let payee = transaction.merchant.replace('\n', " ");
+
+    match dialect {
+        Dialect::Beancount => {
+            journal.push_str(&format!("{} * {}", entry.date, quoted(&payee)));
+            if let Some(notes) = &transaction.notes {
+                journal.push_str(&format!(" {}", quoted(notes)));
+            }
+            for tag in &tags {
+                journal.push_str(&format!(" #{}", tag));
+            }
+            journal.push('\n');
+            journal.push_str(&format!("  id: {}\n", quoted(&transaction.id.to_string())));
+            if let Some(bill) = &transaction.bill_image {
+                journal.push_str(&format!("  bill: {}\n", quoted(bill)));
+            }
+        }
+        Dialect::Ledger | Dialect::Hledger => {
+            journal.push_str(&format!("{} * {}\n", entry.date, payee));
+            journal.push_str(&format!("    ; id: {}\n", transaction.id));
+            if let Some(bill) = &transaction.bill_image {
+                journal.push_str(&format!("    ; bill: {}\n", bill));
+            }
+            if !tags.is_empty() {
+                match dialect {
+                    Dialect::Ledger => journal.push_str(&format!("    ; :{}:\n", tags.join(":"))),
+                    _ => journal.push_str(&format!("    ; {}:\n", tags.join(":, "))),
+                }
+            }
+            for line in transaction.notes.iter().flat_map(|notes| notes.lines()) {
+                journal.push_str(&format!("    ; {}\n", line));
+            }
+        }
+    }
+    for posting in &entry.postings {
+        write_posting(journal, dialect, posting);
+    }
+    journal.push('\n');
+}
+
+// hledger account types: assets, liabilities, equity, revenue and expenses
+fn hledger_type(account: &str) -> &'static str {
+    match account.split(':').next() {
+        Some("Liabilities") => "L",
+        Some("Equity") => "E",
+        Some("Income") => "R",
+        Some("Expenses") => "X",
+        _ => "A",
+    }
+}
+
+// A journal of the transactions, oldest first. Accounts are declared up front: ledger and hledger
+// with `account` directives, beancount by opening each on the day it is first used
+pub fn render_journal(
+    dialect: Dialect,
+    transactions: &[TransactionResponse],
+    names: &LedgerNames,
+    base_currency: Currency,
+) -> String {
+    let entries = entries(transactions, names);
+    let mut first_used: BTreeMap<String, NaiveDate> = BTreeMap::new();
+    for entry in &entries {
+        for posting in &entry.postings {
+            let date = first_used.entry(dialect.account(&posting.account)).or_insert(entry.date);
+            *date = (*date).min(entry.date);
+        }
+    }
+
+    let mut journal = String::new();
+    match dialect {
+        Dialect::Beancount => {
+            journal.push_str(&format!("option \"operating_currency\" \"{}\"\n\n", base_currency));
+            let mut opened: Vec<(&NaiveDate, &String)> = first_used.iter().map(|(a, d)| (d, a)).collect();
+            opened.sort();
+            for (date, account) in opened {
+                journal.push_str(&format!("{} open {}\n", date, account));
+            }
+        }
+        Dialect::Ledger => {
+            for account in first_used.keys() {
+                journal.push_str(&format!("account {}\n", account));
+            }
+        }
+        Dialect::Hledger => {
+            for account in first_used.keys() {
+                journal.push_str(&format!("account {}  ; type: {}\n", account, hledger_type(account)));
+            }
+        }
+    }
+    journal.push('\n');
+
+    for entry in &entries {
+        write_entry(&mut journal, dialect, entry);
+    }
+    journal
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::*;
+    use crate::models::split::SplitResponse;
+
+    fn money(amount: i64, currency: Currency) -> Money {
+        Money::new(BigDecimal::from(amount), currency)
+    }
+
+    fn transaction(merchant: &str, amount: Money, direction: Direction, day: u32) -> TransactionResponse {
+        let transaction_type = match direction {
+            Direction::Income => "income",
+            Direction::Expense => "purchase",
+        };
+        TransactionResponse {
+            id: Uuid::new_v4(),
+            currency: amount.currency(),
+            amount,
+            date: Utc.with_ymd_and_hms(2024, 3, day, 9, 0, 0).unwrap(),
+            merchant: merchant.to_string(),
+            merchant_id: None,
+            category: "Food".to_string(),
+            notes: None,
+            items: None,
+            bill_image: None,
+            tags: Vec::new(),
+            excluded: false,
+            source: "manual".to_string(),
+            recurring_id: None,
+            account_id: None,
+            direction: direction.as_str().to_string(),
+            transfer_id: None,
+            transaction_type: transaction_type.to_string(),
+            refund_of: None,
+            splits: Vec::new(),
+            import_batch_id: None,
+            value_date: None,
+            bank_reference: None,
+            reconciled: false,
+            created_at: Utc::now(),
+        }
+    }
+
+    fn split(category: &str, amount: Money) -> SplitResponse {
+        SplitResponse {
+            id: Uuid::new_v4(),
+            category_id: None,
+            category: category.to_string(),
+            amount,
+            items: Vec::new(),
+            notes: None,
+            tags: Vec::new(),
+        }
+    }
+
+    fn names(cash: Uuid) -> LedgerNames {
+        LedgerNames {
+            categories: HashMap::from([(("food".to_string(), "expense"), "Expenses:Food:Dining".to_string())]),
+            accounts: HashMap::from([(cash, "Assets:Cash:Wallet".to_string())]),
+        }
+    }
+
+    #[test]
+    fn makes_account_names_valid_for_each_tool() {
+        assert_eq!(Dialect::Ledger.account("Expenses:Food  & Drink;x"), "Expenses:Food & Drink-x");
+        assert_eq!(Dialect::Beancount.account("Expenses:food & drink"), "Expenses:Food-drink");
+        assert_eq!(Dialect::Beancount.account("Expenses:อาหาร"), "Expenses:X-อาหาร");
+        assert_eq!(Dialect::Beancount.account("Assets:7-Eleven card"), "Assets:7-Eleven-card");
+        assert_eq!(Dialect::Beancount.account("Assets:!!"), "Assets:X");
+    }
+
+    #[test]
+    fn cleans_tags() {
+        assert_eq!(Dialect::Ledger.tag("road trip"), Some("road-trip".to_string()));
+        assert_eq!(Dialect::Beancount.tag("work/travel 2024"), Some("work/travel-2024".to_string()));
+        assert_eq!(Dialect::Beancount.tag("ทริป"), None);
+    }
+
+    #[test]
+    fn validates_typed_ledger_names() {
+        assert_eq!(validate_ledger_name(" Expenses : Food ").unwrap(), "Expenses:Food");
+        assert!(validate_ledger_name("Food:Groceries").is_err());
+        assert!(validate_ledger_name("Expenses").is_err());
+        assert!(validate_ledger_name("Expenses::Food").is_err());
+    }
+
+    #[test]
+    fn balances_splits_against_the_account() {
+        let cash = Uuid::new_v4();
+        let mut lunch = transaction("Somtam Nua", money(150, Currency::THB), Direction::Expense, 5);
+        lunch.account_id = Some(cash);
+        lunch.splits = vec![split("Groceries", money(50, Currency::THB))];
+        lunch.tags = vec!["work lunch".to_string()];
+
+        let journal = render_journal(Dialect::Ledger, &[lunch], &names(cash), Currency::THB);
+        let postings: Vec<&str> = journal
+            .lines()
+            .filter(|line| line.starts_with("    ") && !line.contains(';'))
+            .collect();
+        assert_eq!(postings.len(), 3);
+        assert!(postings[0].starts_with("    Assets:Cash:Wallet ") && postings[0].ends_with("-150.00 THB"));
+        assert!(postings[1].starts_with("    Expenses:Groceries ") && postings[1].ends_with("50.00 THB"));
+        assert!(postings[2].starts_with("    Expenses:Food:Dining ") && postings[2].ends_with("100.00 THB"));
+        assert!(journal.contains("    ; :work-lunch:\n"));
+        assert!(journal
+            .starts_with("account Assets:Cash:Wallet\naccount Expenses:Food:Dining\naccount Expenses:Groceries\n"));
+    }
+
+    #[test]
+    fn writes_a_transfer_as_one_priced_entry() {
+        let cash = Uuid::new_v4();
+        let transfer_id = Some(Uuid::new_v4());
+        let mut out = transaction("To USD", money(3600, Currency::THB), Direction::Expense, 7);
+        out.account_id = Some(cash);
+        out.transfer_id = transfer_id;
+        let mut into = transaction("To USD", money(100, Currency::from_code("USD")), Direction::Income, 7);
+        into.transfer_id = transfer_id;
+
+        let journal = render_journal(Dialect::Beancount, &[out, into], &names(cash), Currency::THB);
+        assert_eq!(journal.matches(" * \"To USD\"").count(), 1);
+        assert!(journal.contains("100.00 USD @@ 3600.00 THB\n"));
+        assert!(journal.contains("2024-03-07 open Assets:Unassigned\n"));
+        assert!(journal.starts_with("option \"operating_currency\" \"THB\"\n"));
+    }
+
+    #[test]
+    fn balances_a_lone_transfer_leg_against_equity() {
+        let mut leg = transaction("From savings", money(500, Currency::THB), Direction::Income, 9);
+        leg.transfer_id = Some(Uuid::new_v4());
+        let journal = render_journal(Dialect::Hledger, &[leg], &names(Uuid::new_v4()), Currency::THB);
+        assert!(journal.contains("account Equity:Transfers  ; type: E\n"));
+        assert!(journal.lines().any(|line| line.starts_with("    Equity:Transfers ") && line.ends_with("-500.00 THB")));
+    }
+}
@@ -0,0 +1,527 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+use diesel::dsl::count_star;
+use diesel::prelude::*;
+use diesel::sql_types::Text;
+use diesel::PgConnection;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::models::shared_ledger::{
+    CreateInvitationDto, CreateLedgerDto, DbLedger, DbLedgerInvitation, DbLedgerMember, InvitationResponse,
+    InvitationStatus, LedgerMemberResponse, LedgerResponse, LedgerRole, UpdateLedgerDto,
+};
+use crate::money::Currency;
+use crate::schema::{bills, ledger_invitations, ledger_members, ledgers, transactions, users};
+use crate::services::exchange_rates::base_currency;
+
+diesel::define_sql_function! {
+    // Defined by the create_ledgers migration; hex SHA-256 of the token
+    fn invitation_token_hash(token: Text) -> Text;
+}
+
+const DEFAULT_INVITATION_DAYS: i64 = 7;
+const MAX_INVITATION_DAYS: i64 = 90;
+const TOKEN_LENGTH: usize = 40;
+
+// The caller's role in a ledger, or None when they aren't a member
+pub fn member_role(conn: &mut PgConnection, ledger_id: Uuid, user_id: Uuid) -> Result<Option<LedgerRole>, AppError> {
+    Ok(ledger_members::table
+        .find((ledger_id, user_id))
+        .select(ledger_members::role)
+        .first::<String>(conn)
+        .optional()?
+        .and_then(|role| LedgerRole::parse(&role)))
+}
+
+// Ledgers the caller isn't a member of are reported as missing rather than forbidden, so their
+// ids can't be probed
+pub fn require_role(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    user_id: Uuid,
+    required: LedgerRole,
+) -> Result<LedgerRole, AppError> {
+    check_role(member_role(conn, ledger_id, user_id)?, required)
+}
+
+fn check_role(role: Option<LedgerRole>, required: LedgerRole) -> Result<LedgerRole, AppError> {
+    let role = role.ok_or_else(|| AppError::NotFound("Ledger not found".to_string()))?;
+    if !role.allows(required) {
+        return Err(AppError::Forbidden(format!(
+            "This needs the {} role in the ledger; you are a {}",
+            required.as_str(),
+            role.as_str()
+        )));
+    }
+    Ok(role)
+}
+
+fn find_ledger(conn: &mut PgConnection, ledger_id: Uuid) -> Result<DbLedger, AppError> {
+    ledgers::table
+        .find(ledger_id)
+        .first::<DbLedger>(conn)
+        .optional()?
+        .ok_or_else(|| AppError::NotFound("Ledger not found".to_string()))
+}
+
+fn member_counts(conn: &mut PgConnection, ledger_ids: &[Uuid]) -> Result<HashMap<Uuid, i64>, AppError> {
+    Ok(ledger_members::table
+        .filter(ledger_members::ledger_id.eq_any(ledger_ids))
+        .group_by(ledger_members::ledger_id)
+        .select((ledger_members::ledger_id, count_star()))
+        .load::<(Uuid, i64)>(conn)?
+        .into_iter()
+        .collect())
+}
+
+fn to_response(ledger: DbLedger, role: LedgerRole, member_count: i64) -> LedgerResponse {
+    LedgerResponse {
+        id: ledger.id,
+        personal: ledger.is_personal(),
+        name: ledger.name,
+        base_currency: Currency::from_code(&ledger.base_currency),
+        role,
+        member_count,
+        created_at: ledger.created_at,
+    }
+}
+
+fn ledger_response(conn: &mut PgConnection, ledger_id: Uuid, role: LedgerRole) -> Result<LedgerResponse, AppError> {
+    let ledger = find_ledger(conn, ledger_id)?;
+    let count = member_counts(conn, &[ledger_id])?.get(&ledger_id).copied().unwrap_or(0);
+    Ok(to_response(ledger, role, count))
+}
+
+// Every ledger the caller belongs to, their personal one first
+pub fn list_ledgers(conn: &mut PgConnection, user_id: Uuid) -> Result<Vec<LedgerResponse>, AppError> {
+    let mut rows = ledgers::table
+        .inner_join(ledger_members::table)
+        .filter(ledger_members::user_id.eq(user_id))
+        .order(ledgers::created_at.asc())
+        .select((ledgers::all_columns, ledger_members::role))
+        .load::<(DbLedger, String)>(conn)?;
+    rows.sort_by_key(|(ledger, _)| ledger.id != user_id);
+
+    let ids: Vec<Uuid> = rows.iter().map(|(ledger, _)| ledger.id).collect();
+    let counts = member_counts(conn, &ids)?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|(ledger, role)| {
+            let role = LedgerRole::parse(&role)?;
+            let count = counts.get(&ledger.id).copied().unwrap_or(0);
+            Some(to_response(ledger, role, count))
+        })
+        .collect())
+}
+
+pub fn get_ledger(conn: &mut PgConnection, user_id: Uuid, ledger_id: Uuid) -> Result<LedgerResponse, AppError> {
+    let role = require_role(conn, ledger_id, user_id, LedgerRole::Viewer)?;
+    ledger_response(conn, ledger_id, role)
+}
+
+fn validate_name(name: &str) -> Result<String, AppError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(AppError::BadRequest("Ledger name must not be empty".to_string()));
+    }
+    Ok(name.to_string())
+}
+
+// A new shared ledger, with its creator as the only owner
+pub fn create_ledger(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    dto: &CreateLedgerDto,
+) -> Result<LedgerResponse, AppError> {
+    let name = validate_name(&dto.name)?;
+    let currency = match dto.base_currency {
+        Some(currency) => currency,
+        None => base_currency(conn, user_id)?,
+    };
+
+    conn.transaction(|conn| {
+        let now = Utc::now();
+        let ledger = DbLedger {
+            id: Uuid::new_v4(),
+            name,
+            base_currency: currency.to_string(),
+            created_by: user_id,
+            created_at: now,
+            updated_at: now,
+        };
+        let ledger = diesel::insert_into(ledgers::table).values(&ledger).get_result::<DbLedger>(conn)?;
+        diesel::insert_into(ledger_members::table)
+            .values(&DbLedgerMember {
+                ledger_id: ledger.id,
+                user_id,
+                role: LedgerRole::Owner.as_str().to_string(),
+                created_at: now,
+                updated_at: now,
+            })
+            .execute(conn)?;
+        Ok(to_response(ledger, LedgerRole::Owner, 1))
+    })
+}
+
+// A personal ledger's base currency is also its user's profile setting; the two are kept in step
+pub fn set_base_currency(conn: &mut PgConnection, ledger_id: Uuid, currency: Currency) -> Result<(), AppError> {
+    let code = currency.to_string();
+    let now = Utc::now();
+    diesel::update(ledgers::table.find(ledger_id).filter(ledgers::base_currency.ne(&code)))
+        .set((ledgers::base_currency.eq(&code), ledgers::updated_at.eq(now)))
+        .execute(conn)?;
+    diesel::update(users::table.find(ledger_id).filter(users::base_currency.ne(&code)))
+        .set((users::base_currency.eq(&code), users::updated_at.eq(now)))
+        .execute(conn)?;
+    Ok(())
+}
+
+pub fn update_ledger(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    ledger_id: Uuid,
+    dto: &UpdateLedgerDto,
+) -> Result<LedgerResponse, AppError> {
+    let role = require_role(conn, ledger_id, user_id, LedgerRole::Owner)?;
+    let name = dto.name.as_deref().map(validate_name).transpose()?;
+
+    conn.transaction(|conn| {
+        if let Some(name) = name {
+            diesel::update(ledgers::table.find(ledger_id))
+                .set((ledgers::name.eq(name), ledgers::updated_at.eq(Utc::now())))
+                .execute(conn)?;
+        }
+        if let Some(currency) = dto.base_currency {
+            set_base_currency(conn, ledger_id, currency)?;
+        }
+        ledger_response(conn, ledger_id, role)
+    })
+}
+
+// Delete a shared ledger with everything in it. Returns the stored images nothing points at
+// any more, for the caller to remove
+pub fn delete_ledger(conn: &mut PgConnection, user_id: Uuid, ledger_id: Uuid) -> Result<Vec<String>, AppError> {
+    require_role(conn, ledger_id, user_id, LedgerRole::Owner)?;
+    let ledger = find_ledger(conn, ledger_id)?;
+    if ledger.is_personal() {
+        return Err(AppError::BadRequest("A personal ledger can't be deleted".to_string()));
+    }
+
+    conn.transaction(|conn| {
+        let mut files = bills::table
+            .filter(bills::ledger_id.eq(ledger_id))
+            .select(bills::file_path)
+            .load::<String>(conn)?;
+        files.extend(
+            transactions::table
+                .filter(transactions::ledger_id.eq(ledger_id))
+                .filter(transactions::image_path.is_not_null())
+                .select(transactions::image_path.assume_not_null())
+                .load::<String>(conn)?,
+        );
+        files.sort();
+        files.dedup();
+
+        diesel::delete(ledgers::table.find(ledger_id)).execute(conn)?;
+        Ok(files)
+    })
+}
+
+pub fn list_members(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    ledger_id: Uuid,
+) -> Result<Vec<LedgerMemberResponse>, AppError> {
+    require_role(conn, ledger_id, user_id, LedgerRole::Viewer)?;
+    let rows = ledger_members::table
+        .inner_join(users::table)
+        .filter(ledger_members::ledger_id.eq(ledger_id))
+        .order(ledger_members::created_at.asc())
+        .select((ledger_members::user_id, users::name, users::email, ledger_members::role, ledger_members::created_at))
+        .load::<(Uuid, String, String, String, DateTime<Utc>)>(conn)?;
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|(user_id, name, email, role, joined_at)| {
+            Some(LedgerMemberResponse {
+                user_id,
+                name,
+                email,
+                role: LedgerRole::parse(&role)?,
+                joined_at,
+            })
+        })
+        .collect())
+}
+
+fn count_owners(conn: &mut PgConnection, ledger_id: Uuid) -> Result<i64, AppError> {
+    Ok(ledger_members::table
+        .filter(ledger_members::ledger_id.eq(ledger_id))
+        .filter(ledger_members::role.eq(LedgerRole::Owner.as_str()))
+        .count()
+        .get_result::<i64>(conn)?)
+}
+
+// A ledger always keeps an owner, and a personal ledger always keeps the user it belongs to as one
+fn check_owner_kept(ledger: &DbLedger, member_id: Uuid, current: LedgerRole, owners: i64) -> Result<(), AppError> {
+    if ledger.is_personal() && member_id == ledger.created_by {
+        return Err(AppError::BadRequest(
+            "The owner of a personal ledger can't be changed or removed".to_string(),
+        ));
+    }
+    if current == LedgerRole::Owner && owners <= 1 {
+        return Err(AppError::BadRequest("A ledger must keep at least one owner".to_string()));
+    }
+    Ok(())
+}
+
+pub fn set_member_role(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    ledger_id: Uuid,
+    member_id: Uuid,
+    role: LedgerRole,
+) -> Result<Vec<LedgerMemberResponse>, AppError> {
+    require_role(conn, ledger_id, user_id, LedgerRole::Owner)?;
+    let ledger = find_ledger(conn, ledger_id)?;
+    let current = member_role(conn, ledger_id, member_id)?
+        .ok_or_else(|| AppError::NotFound("Member not found".to_string()))?;
+    if current == role {
+        return list_members(conn, user_id, ledger_id);
+    }
+    check_owner_kept(&ledger, member_id, current, count_owners(conn, ledger_id)?)?;
+
+    diesel::update(ledger_members::table.find((ledger_id, member_id)))
+        .set((ledger_members::role.eq(role.as_str()), ledger_members::updated_at.eq(Utc::now())))
+        .execute(conn)?;
+    list_members(conn, user_id, ledger_id)
+}
+
+// Owners can remove anyone; any member can remove themselves to leave the ledger
+pub fn remove_member(conn: &mut PgConnection, user_id: Uuid, ledger_id: Uuid, member_id: Uuid) -> Result<(), AppError> {
+    let required = if member_id == user_id { LedgerRole::Viewer } else { LedgerRole::Owner };
+    require_role(conn, ledger_id, user_id, required)?;
+    let ledger = find_ledger(conn, ledger_id)?;
+    let current = member_role(conn, ledger_id, member_id)?
+        .ok_or_else(|| AppError::NotFound("Member not found".to_string()))?;
+    check_owner_kept(&ledger, member_id, current, count_owners(conn, ledger_id)?)?;
+
+    diesel::delete(ledger_members::table.find((ledger_id, member_id))).execute(conn)?;
+    Ok(())
+}
+
+fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+// Invite someone by email. The response carries the token, the only time it is available; it is
+// up to the inviter to pass it on
+pub fn create_invitation(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    ledger_id: Uuid,
+    dto: &CreateInvitationDto,
+    now: DateTime<Utc>,
+) -> Result<InvitationResponse, AppError> {
+    require_role(conn, ledger_id, user_id, LedgerRole::Owner)?;
+    if find_ledger(conn, ledger_id)?.is_personal() {
+        return Err(AppError::BadRequest(
+            "A personal ledger can't be shared; create a shared ledger instead".to_string(),
+        ));
+    }
+    let email = normalize_email(&dto.email);
+    if !email.contains('@') {
+        return Err(AppError::BadRequest("A valid email address is required".to_string()));
+    }
+    let days = dto.expires_in_days.unwrap_or(DEFAULT_INVITATION_DAYS);
+    if !(1..=MAX_INVITATION_DAYS).contains(&days) {
+        return Err(AppError::BadRequest(format!(
+            "Invitations expire after 1 to {} days",
+            MAX_INVITATION_DAYS
+        )));
+    }
+
+    let already_member = ledger_members::table
+        .inner_join(users::table)
+        .filter(ledger_members::ledger_id.eq(ledger_id))
+        .select(users::email)
+        .load::<String>(conn)?
+        .iter()
+        .any(|member_email| normalize_email(member_email) == email);
+    if already_member {
+        return Err(AppError::BadRequest(format!("{} is already a member of this ledger", email)));
+    }
+
+    let token: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(TOKEN_LENGTH)
+        .map(char::from)
+        .collect();
+    let role = dto.role.unwrap_or(LedgerRole::Editor);
+    let invitation = diesel::insert_into(ledger_invitations::table)
+        .values((
+            ledger_invitations::id.eq(Uuid::new_v4()),
+            ledger_invitations::ledger_id.eq(ledger_id),
+            ledger_invitations::email.eq(&email),
+            ledger_invitations::role.eq(role.as_str()),
+            ledger_invitations::token_hash.eq(invitation_token_hash(&token)),
+            ledger_invitations::invited_by.eq(Some(user_id)),
+            ledger_invitations::created_at.eq(now),
+            ledger_invitations::expires_at.eq(now + Duration::days(days)),
+        ))
+        .get_result::<DbLedgerInvitation>(conn)?;
+
+    let mut response = InvitationResponse::from_db(invitation, now);
+    response.token = Some(token);
+    Ok(response)
+}
+
+pub fn list_invitations(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    ledger_id: Uuid,
+    now: DateTime<Utc>,
+) -> Result<Vec<InvitationResponse>, AppError> {
+    require_role(conn, ledger_id, user_id, LedgerRole::Owner)?;
+    Ok(ledger_invitations::table
+        .filter(ledger_invitations::ledger_id.eq(ledger_id))
+        .order(ledger_invitations::created_at.desc())
+        .load::<DbLedgerInvitation>(conn)?
+        .into_iter()
+        .map(|invitation| InvitationResponse::from_db(invitation, now))
+        .collect())
+}
+
+pub fn revoke_invitation(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    ledger_id: Uuid,
+    invitation_id: Uuid,
+    now: DateTime<Utc>,
+) -> Result<InvitationResponse, AppError> {
+    require_role(conn, ledger_id, user_id, LedgerRole::Owner)?;
+    let invitation = ledger_invitations::table
+        .find(invitation_id)
+        .filter(ledger_invitations::ledger_id.eq(ledger_id))
+        .first::<DbLedgerInvitation>(conn)
+        .optional()?
+        .ok_or_else(|| AppError::NotFound("Invitation not found".to_string()))?;
+    if InvitationResponse::from_db(invitation, now).status != InvitationStatus::Pending {
+        return Err(AppError::BadRequest("Only a pending invitation can be revoked".to_string()));
+    }
+
+    let invitation = diesel::update(ledger_invitations::table.find(invitation_id))
+        .set(ledger_invitations::revoked_at.eq(Some(now)))
+        .get_result::<DbLedgerInvitation>(conn)?;
+    Ok(InvitationResponse::from_db(invitation, now))
+}
+
+// Join a ledger with an invitation token. It must have been sent to the caller's email address
+pub fn accept_invitation(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    token: &str,
+    now: DateTime<Utc>,
+) -> Result<LedgerResponse, AppError> {
+    let invitation = ledger_invitations::table
+        .filter(ledger_invitations::token_hash.eq(invitation_token_hash(token)))
+        .first::<DbLedgerInvitation>(conn)
+        .optional()?
+        .ok_or_else(|| AppError::NotFound("Invitation not found".to_string()))?;
+    let ledger_id = invitation.ledger_id;
+    let invitation_id = invitation.id;
+    let invited_email = invitation.email.clone();
+    let invitation = InvitationResponse::from_db(invitation, now);
+    match invitation.status {
+        InvitationStatus::Pending => {}
+        InvitationStatus::Accepted => {
+            return Err(AppError::BadRequest("This invitation has already been accepted".to_string()))
+        }
+        InvitationStatus::Revoked => return Err(AppError::BadRequest("This invitation was revoked".to_string())),
+        InvitationStatus::Expired => return Err(AppError::BadRequest("This invitation has expired".to_string())),
+    }
+
+    let email = users::table.find(user_id).select(users::email).first::<String>(conn)?;
+    if normalize_email(&email) != invited_email {
+        return Err(AppError::Forbidden("This invitation was sent to another email address".to_string()));
+    }
+    if member_role(conn, ledger_id, user_id)?.is_some() {
+        return Err(AppError::BadRequest("You are already a member of this ledger".to_string()));
+    }
+    // Invitations sent before personal ledgers stopped being shareable
+    if find_ledger(conn, ledger_id)?.is_personal() {
+        return Err(AppError::BadRequest("A personal ledger can't be joined".to_string()));
+    }
+
+    conn.transaction(|conn| {
+        // The check above can race with a second accept from the same user
+        let joined = diesel::insert_into(ledger_members::table)
+            .values(&DbLedgerMember {
+                ledger_id,
+                user_id,
+                role: invitation.role.as_str().to_string(),
+                created_at: now,
+                updated_at: now,
+            })
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+        if joined == 0 {
+            return Err(AppError::BadRequest("You are already a member of this ledger".to_string()));
+        }
+        diesel::update(ledger_invitations::table.find(invitation_id))
+            .set((
+                ledger_invitations::accepted_at.eq(Some(now)),
+                ledger_invitations::accepted_by.eq(Some(user_id)),
+            ))
+            .execute(conn)?;
+        ledger_response(conn, ledger_id, invitation.role)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ledger(id: Uuid, created_by: Uuid) -> DbLedger {
+        DbLedger {
+            id,
+            name: "Household".to_string(),
+            base_currency: "THB".to_string(),
+            created_by,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn reports_unknown_ledgers_as_missing_and_low_roles_as_forbidden() {
+        assert!(matches!(check_role(None, LedgerRole::Viewer), Err(AppError::NotFound(_))));
+        assert!(matches!(check_role(Some(LedgerRole::Viewer), LedgerRole::Editor), Err(AppError::Forbidden(_))));
+        assert!(matches!(check_role(Some(LedgerRole::Editor), LedgerRole::Owner), Err(AppError::Forbidden(_))));
+        assert_eq!(check_role(Some(LedgerRole::Editor), LedgerRole::Viewer).unwrap(), LedgerRole::Editor);
+        assert_eq!(check_role(Some(LedgerRole::Owner), LedgerRole::Owner).unwrap(), LedgerRole::Owner);
+    }
+
+    #[test]
+    fn keeps_the_personal_owner() {
+        let user = Uuid::new_v4();
+        let personal = ledger(user, user);
+        // Even with other owners around, the user a personal ledger belongs to stays
+        assert!(check_owner_kept(&personal, user, LedgerRole::Owner, 3).is_err());
+        assert!(check_owner_kept(&personal, Uuid::new_v4(), LedgerRole::Editor, 1).is_ok());
+    }
+
+    #[test]
+    fn keeps_the_last_owner_of_a_shared_ledger() {
+        let creator = Uuid::new_v4();
+        let shared = ledger(Uuid::new_v4(), creator);
+        assert!(check_owner_kept(&shared, creator, LedgerRole::Owner, 1).is_err());
+        // The creator of a shared ledger may leave once someone else owns it
+        assert!(check_owner_kept(&shared, creator, LedgerRole::Owner, 2).is_ok());
+        assert!(check_owner_kept(&shared, Uuid::new_v4(), LedgerRole::Viewer, 1).is_ok());
+    }
+}
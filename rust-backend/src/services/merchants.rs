@@ -0,0 +1,420 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel::PgConnection;
+use regex::Regex;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::models::merchant::{DbMerchant, DbMerchantAlias, NewMerchant, NewMerchantAlias};
+use crate::schema::{merchant_aliases, merchants, transactions};
+
+// Minimum bigram similarity for a fuzzy match to be accepted
+const FUZZY_MATCH_THRESHOLD: f32 = 0.8;
+
+// Thai legal-entity words, longest first so "ห้างหุ้นส่วนจำกัด" wins over "จำกัด"
+const THAI_CORPORATE_WORDS: &[&str] = &[
+    "ห้างหุ้นส่วนจำกัด", "ห้างหุ้นส่วนสามัญ", "บริษัท", "จำกัด", "มหาชน",
+    "บจก", "หจก", "บมจ", "สำนักงานใหญ่",
+];
+
+// Latin legal-entity tokens, compared after punctuation has been stripped
+const LATIN_CORPORATE_TOKENS: &[&str] = &["co", "ltd", "pcl", "plc", "inc", "corp", "limited"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchType {
+    TaxId,
+    Exact,
+    Alias,
+    Fuzzy,
+    Created,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MerchantMatch {
+    pub merchant: DbMerchant,
+    pub match_type: MatchType,
+    pub score: f32,
+}
+
+// Normalizing runs for every candidate pair when matching, so each pattern is compiled once
+fn compiled(cell: &'static OnceLock<Regex>, pattern: &str) -> &'static Regex {
+    cell.get_or_init(|| Regex::new(pattern).expect("merchant patterns are valid"))
+}
+
+// Remove branch designations such as "สาขา 01234", "Branch Silom" or "STORE#1234"
+fn strip_branch(raw: &str) -> String {
+    static BRANCH: OnceLock<Regex> = OnceLock::new();
+    static STORE_NUMBER: OnceLock<Regex> = OnceLock::new();
+    let branch = compiled(&BRANCH, r"(?i)(สาขา|\bbranch\b|\bbr\.).*$");
+    let store_number = compiled(&STORE_NUMBER, r"(?i)(\b(store|shop|no\.?))?\s*#\s*\d+|\b(store|shop)\s+\d+\b");
+
+    let result = branch.replace(raw, "");
+    store_number.replace_all(&result, " ").to_string()
+}
+
+// Canonical lookup key for a merchant string: lowercase, no branch, no legal-entity words, no punctuation
+pub fn normalize_merchant_name(raw: &str) -> String {
+    static COMPANY: OnceLock<Regex> = OnceLock::new();
+    let company = compiled(&COMPANY, r"\b(public\s+)?company\s+limited\b");
+    let mut value = company.replace_all(&strip_branch(raw).to_lowercase(), " ").to_string();
+
+    for word in THAI_CORPORATE_WORDS {
+        value = value.replace(word, " ");
+    }
+
+    // Joining punctuation is dropped ("7-eleven" -> "7eleven"), everything else separates words
+    let cleaned: String = value
+        .chars()
+        .filter(|c| !matches!(c, '-' | '.' | '\'' | '’'))
+        .map(|c| if c.is_alphanumeric() || is_thai(c) { c } else { ' ' })
+        .collect();
+
+    cleaned
+        .split_whitespace()
+        .filter(|token| !LATIN_CORPORATE_TOKENS.contains(token))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Human-readable name for a newly created merchant: the raw string without its branch suffix
+pub fn display_merchant_name(raw: &str) -> String {
+    let stripped = strip_branch(raw);
+    let display = stripped
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .trim_matches(|c: char| c == '-' || c == ',' || c == ':' || c.is_whitespace())
+        .to_string();
+
+    if display.is_empty() {
+        raw.trim().to_string()
+    } else {
+        display
+    }
+}
+
+// Thai vowels and tone marks are combining characters, so `is_alphanumeric` alone would drop them
+fn is_thai(c: char) -> bool {
+    ('\u{0E00}'..='\u{0E7F}').contains(&c)
+}
+
+// Dice coefficient over character bigrams, ignoring whitespace (Thai has no word spacing)
+pub fn similarity(a: &str, b: &str) -> f32 {
+    let a: Vec<char> = a.chars().filter(|c| !c.is_whitespace()).collect();
+    let b: Vec<char> = b.chars().filter(|c| !c.is_whitespace()).collect();
+
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    if a == b {
+        return 1.0;
+    }
+    if a.len() < 2 || b.len() < 2 {
+        return 0.0;
+    }
+
+    let mut bigrams: HashMap<(char, char), usize> = HashMap::new();
+    for pair in a.windows(2) {
+        *bigrams.entry((pair[0], pair[1])).or_insert(0) += 1;
+    }
+
+    let mut overlap = 0;
+    for pair in b.windows(2) {
+        if let Some(count) = bigrams.get_mut(&(pair[0], pair[1])) {
+            if *count > 0 {
+                *count -= 1;
+                overlap += 1;
+            }
+        }
+    }
+
+    (2 * overlap) as f32 / (a.len() + b.len() - 2) as f32
+}
+
+pub fn find_merchant(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    merchant_id: Uuid,
+) -> Result<DbMerchant, AppError> {
+    merchants::table
+        .filter(merchants::id.eq(merchant_id))
+        .filter(merchants::ledger_id.eq(ledger_id))
+        .first::<DbMerchant>(conn)
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("Merchant {} not found", merchant_id)))
+}
+
+pub fn load_aliases(
+    conn: &mut PgConnection,
+    merchant_ids: &[Uuid],
+) -> Result<Vec<DbMerchantAlias>, AppError> {
+    Ok(merchant_aliases::table
+        .filter(merchant_aliases::merchant_id.eq_any(merchant_ids))
+        .order(merchant_aliases::created_at.asc())
+        .load::<DbMerchantAlias>(conn)?)
+}
+
+// Resolve a raw merchant string to one of the user's canonical merchants: tax ID, exact, alias, then fuzzy
+pub fn resolve_merchant(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    raw_name: &str,
+    tax_id: Option<&str>,
+) -> Result<Option<MerchantMatch>, AppError> {
+    if let Some(tax_id) = tax_id.map(str::trim).filter(|t| !t.is_empty()) {
+        let by_tax_id = merchants::table
+            .filter(merchants::ledger_id.eq(ledger_id))
+            .filter(merchants::tax_id.eq(tax_id))
+            .first::<DbMerchant>(conn)
+            .optional()?;
+
+        if let Some(merchant) = by_tax_id {
+            return Ok(Some(MerchantMatch { merchant, match_type: MatchType::TaxId, score: 1.0 }));
+        }
+    }
+
+    let normalized = normalize_merchant_name(raw_name);
+    if normalized.is_empty() {
+        return Ok(None);
+    }
+
+    let exact = merchants::table
+        .filter(merchants::ledger_id.eq(ledger_id))
+        .filter(merchants::normalized_name.eq(&normalized))
+        .first::<DbMerchant>(conn)
+        .optional()?;
+
+    if let Some(merchant) = exact {
+        return Ok(Some(MerchantMatch { merchant, match_type: MatchType::Exact, score: 1.0 }));
+    }
+
+    let by_alias = merchant_aliases::table
+        .inner_join(merchants::table)
+        .filter(merchants::ledger_id.eq(ledger_id))
+        .filter(merchant_aliases::normalized_alias.eq(&normalized))
+        .select(merchants::all_columns)
+        .first::<DbMerchant>(conn)
+        .optional()?;
+
+    if let Some(merchant) = by_alias {
+        return Ok(Some(MerchantMatch { merchant, match_type: MatchType::Alias, score: 1.0 }));
+    }
+
+    // Fuzzy match against every canonical name and alias the user has
+    let candidates = merchants::table
+        .filter(merchants::ledger_id.eq(ledger_id))
+        .load::<DbMerchant>(conn)?;
+    let alias_names: Vec<(Uuid, String)> = merchant_aliases::table
+        .inner_join(merchants::table)
+        .filter(merchants::ledger_id.eq(ledger_id))
+        .select((merchant_aliases::merchant_id, merchant_aliases::normalized_alias))
+        .load(conn)?;
+
+    let mut best: Option<(Uuid, f32)> = None;
+    let names = candidates
+        .iter()
+        .map(|m| (m.id, m.normalized_name.as_str()))
+        .chain(alias_names.iter().map(|(id, alias)| (*id, alias.as_str())));
+
+    for (merchant_id, name) in names {
+        let score = similarity(&normalized, name);
+        if score >= FUZZY_MATCH_THRESHOLD && best.is_none_or(|(_, s)| score > s) {
+            best = Some((merchant_id, score));
+        }
+    }
+
+    Ok(best.and_then(|(merchant_id, score)| {
+        candidates
+            .into_iter()
+            .find(|m| m.id == merchant_id)
+            .map(|merchant| MerchantMatch { merchant, match_type: MatchType::Fuzzy, score })
+    }))
+}
+
+// Resolve a merchant string, learning fuzzy matches as aliases and creating a merchant when nothing matches
+pub fn resolve_or_create_merchant(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    raw_name: &str,
+    tax_id: Option<&str>,
+) -> Result<MerchantMatch, AppError> {
+    conn.transaction(|conn| {
+        if let Some(found) = resolve_merchant(conn, ledger_id, raw_name, tax_id)? {
+            if matches!(found.match_type, MatchType::Fuzzy | MatchType::TaxId) {
+                add_alias(conn, &found.merchant, raw_name)?;
+            }
+            return Ok(found);
+        }
+
+        let normalized = normalize_merchant_name(raw_name);
+        if normalized.is_empty() {
+            return Err(AppError::BadRequest("Merchant name is empty".to_string()));
+        }
+
+        let merchant = create_merchant(
+            conn,
+            ledger_id,
+            &display_merchant_name(raw_name),
+            tax_id.map(|t| t.trim().to_string()).filter(|t| !t.is_empty()),
+            None,
+        )?;
+        add_alias(conn, &merchant, raw_name)?;
+
+        Ok(MerchantMatch { merchant, match_type: MatchType::Created, score: 1.0 })
+    })
+}
+
+pub fn create_merchant(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    name: &str,
+    tax_id: Option<String>,
+    default_category_id: Option<Uuid>,
+) -> Result<DbMerchant, AppError> {
+    let normalized_name = normalize_merchant_name(name);
+    if normalized_name.is_empty() {
+        return Err(AppError::BadRequest("Merchant name is empty".to_string()));
+    }
+
+    let existing = merchants::table
+        .filter(merchants::ledger_id.eq(ledger_id))
+        .filter(merchants::normalized_name.eq(&normalized_name))
+        .select(merchants::id)
+        .first::<Uuid>(conn)
+        .optional()?;
+    if let Some(existing_id) = existing {
+        return Err(AppError::BadRequest(format!("Merchant already exists: {}", existing_id)));
+    }
+
+    let new_merchant = NewMerchant {
+        id: Uuid::new_v4(),
+        ledger_id,
+        name: name.trim().to_string(),
+        normalized_name,
+        tax_id,
+        default_category_id,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+    };
+
+    Ok(diesel::insert_into(merchants::table)
+        .values(&new_merchant)
+        .get_result::<DbMerchant>(conn)?)
+}
+
+// Record an alias for a merchant; aliases equal to the canonical name or already known are skipped
+pub fn add_alias(
+    conn: &mut PgConnection,
+    merchant: &DbMerchant,
+    alias: &str,
+) -> Result<Option<DbMerchantAlias>, AppError> {
+    let normalized_alias = normalize_merchant_name(alias);
+    if normalized_alias.is_empty() || normalized_alias == merchant.normalized_name {
+        return Ok(None);
+    }
+
+    let new_alias = NewMerchantAlias {
+        id: Uuid::new_v4(),
+        merchant_id: merchant.id,
+        alias: alias.trim().to_string(),
+        normalized_alias,
+        created_at: Utc::now(),
+    };
+
+    Ok(diesel::insert_into(merchant_aliases::table)
+        .values(&new_alias)
+        .on_conflict_do_nothing()
+        .get_result::<DbMerchantAlias>(conn)
+        .optional()?)
+}
+
+// Fold the source merchants into the target: aliases, transactions and missing details move over
+pub fn merge_merchants(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    target_id: Uuid,
+    source_ids: &[Uuid],
+) -> Result<DbMerchant, AppError> {
+    conn.transaction(|conn| {
+        let mut target = find_merchant(conn, ledger_id, target_id)?;
+
+        for &source_id in source_ids {
+            if source_id == target_id {
+                continue;
+            }
+            let source = find_merchant(conn, ledger_id, source_id)?;
+
+            add_alias(conn, &target, &source.name)?;
+            for alias in load_aliases(conn, &[source.id])? {
+                add_alias(conn, &target, &alias.alias)?;
+            }
+
+            diesel::update(transactions::table.filter(transactions::merchant_id.eq(source.id)))
+                .set(transactions::merchant_id.eq(target.id))
+                .execute(conn)?;
+
+            if target.tax_id.is_none() || target.default_category_id.is_none() {
+                target = diesel::update(merchants::table.find(target.id))
+                    .set((
+                        merchants::tax_id.eq(target.tax_id.clone().or(source.tax_id.clone())),
+                        merchants::default_category_id.eq(target.default_category_id.or(source.default_category_id)),
+                        merchants::updated_at.eq(Utc::now()),
+                    ))
+                    .get_result::<DbMerchant>(conn)?;
+            }
+
+            diesel::delete(merchants::table.find(source.id)).execute(conn)?;
+        }
+
+        Ok(target)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_branch_and_terminal_suffixes() {
+        assert_eq!(normalize_merchant_name("Starbucks Branch Silom"), "starbucks");
+        assert_eq!(normalize_merchant_name("STARBUCKS BR. 0123"), "starbucks");
+        assert_eq!(normalize_merchant_name("7-ELEVEN STORE#1234"), "7eleven");
+        assert_eq!(normalize_merchant_name("Tops Shop 12 Sukhumvit"), "tops sukhumvit");
+        assert_eq!(normalize_merchant_name("FamilyMart No. #77"), "familymart");
+        assert_eq!(display_merchant_name("Starbucks - Branch Silom"), "Starbucks");
+        assert_eq!(display_merchant_name("Branch 5"), "Branch 5");
+    }
+
+    #[test]
+    fn strips_legal_entity_words() {
+        assert_eq!(normalize_merchant_name("CP ALL Public Company Limited"), "cp all");
+        assert_eq!(normalize_merchant_name("Central Retail Co., Ltd."), "central retail");
+        assert_eq!(normalize_merchant_name("Grab Inc"), "grab");
+    }
+
+    #[test]
+    fn keeps_thai_text_whole() {
+        assert_eq!(normalize_merchant_name("บริษัท ซีพี ออลล์ จำกัด (มหาชน)"), "ซีพี ออลล์");
+        assert_eq!(normalize_merchant_name("ห้างหุ้นส่วนจำกัด ร้านกาแฟดี สาขา 00012"), "ร้านกาแฟดี");
+        assert_eq!(normalize_merchant_name("เซเว่น อีเลฟเว่น สาขาสีลม"), "เซเว่น อีเลฟเว่น");
+        assert_eq!(similarity("เซเว่นอีเลฟเว่น", "เซเว่น อีเลฟเว่น"), 1.0);
+    }
+
+    #[test]
+    fn scores_similarity_against_the_fuzzy_threshold() {
+        assert_eq!(similarity("starbucks", "starbucks"), 1.0);
+        assert_eq!(similarity("", "starbucks"), 0.0);
+        assert_eq!(similarity("a", "b"), 0.0);
+        assert!(similarity("starbucks", "starbuck") >= FUZZY_MATCH_THRESHOLD);
+        assert!(similarity("7eleven", "7 eleven") >= FUZZY_MATCH_THRESHOLD);
+        assert!(similarity("starbucks", "starbucks coffee") < FUZZY_MATCH_THRESHOLD);
+        assert!(similarity("tops", "lotus") < FUZZY_MATCH_THRESHOLD);
+        // Repeated bigrams only count as often as they appear in both
+        assert!((similarity("aaaa", "aa") - 0.5).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,28 @@
+pub mod accounts;
+pub mod audit;
+pub mod backup;
+pub mod bills;
+pub mod budgets;
+pub mod bulk;
+pub mod camt_import;
+pub mod categorizer;
+pub mod csv_import;
+pub mod dashboard;
+pub mod exchange_rates;
+pub mod export;
+pub mod imports;
+pub mod ledger_export;
+pub mod ledgers;
+pub mod merchants;
+pub mod mt940_import;
+pub mod ofx_import;
+pub mod qif_import;
+pub mod reconciliation;
+pub mod recurring;
+pub mod reports;
+pub mod rules;
+pub mod search;
+pub mod splits;
+pub mod tags;
+pub mod transactions;
+pub mod trash;
@@ -0,0 +1,430 @@
+use bigdecimal::{BigDecimal, Zero};
+use chrono::{Datelike, NaiveDate};
+
+use crate::error::AppError;
+use crate::models::import::{BankStatement, ImportRowError, ParsedRow, StatementBalances};
+use crate::money::{Currency, Money};
+
+// SEPA keywords German banks write into the remittance lines of :86:
+const SEPA_KEYWORDS: &[&str] = &[
+    "EREF+", "KREF+", "MREF+", "CRED+", "DEBT+", "SVWZ+", "ABWA+", "ABWE+", "IBAN+", "BIC+",
+];
+
+// Keys of the slash-separated :86: format, e.g. /CNTP/NL12BANK0123456789/BANKNL2A/Name/City/
+const SLASH_KEYS: &[&str] = &[
+    "TRTP", "CNTP", "NAME", "REMI", "EREF", "ORDP", "BENM", "MARF", "CSID", "PURP", "ULTC", "ULTD", "RTRN", "IBAN",
+    "BIC", "ISDT",
+];
+
+// MT940 is SWIFT's character set in practice, but some banks write Latin-1 names
+fn decode(bytes: &[u8]) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => text.trim_start_matches('\u{feff}').to_string(),
+        Err(_) => encoding_rs::WINDOWS_1252.decode_without_bom_handling(bytes).0.into_owned(),
+    }
+}
+
+// ":61:" starts a field; anything else continues the one before
+fn split_tag(line: &str) -> Option<(&str, &str)> {
+    let rest = line.strip_prefix(':')?;
+    let end = rest.find(':')?;
+    let tag = &rest[..end];
+    let valid = (2..=3).contains(&tag.len())
+        && tag.is_ascii()
+        && tag[..2].chars().all(|c| c.is_ascii_digit())
+        && tag[2..].chars().all(|c| c.is_ascii_uppercase());
+    valid.then(|| (tag, &rest[end + 1..]))
+}
+
+// Fields in order as (tag, value, line), skipping the SWIFT envelope around the message text
+fn fields(text: &str) -> Vec<(String, String, usize)> {
+    let mut found: Vec<(String, String, usize)> = Vec::new();
+    for (index, raw) in text.lines().enumerate() {
+        let line = raw.trim_end();
+        if line.is_empty() || line == "-" || line.starts_with("-}") || line.starts_with('{') {
+            continue;
+        }
+        match split_tag(line) {
+            Some((tag, value)) => found.push((tag.to_string(), value.to_string(), index + 1)),
+            None => {
+                if let Some((_, value, _)) = found.last_mut() {
+                    value.push('\n');
+                    value.push_str(line);
+                }
+            }
+        }
+    }
+    found
+}
+
+// Amounts use a decimal comma and may end in it, e.g. 1234,
+fn parse_mt940_amount(text: &str) -> Option<BigDecimal> {
+    let text = text.replace(',', ".");
+    let text = if text.ends_with('.') { format!("{}0", text) } else { text };
+    text.parse().ok()
+}
+
+fn parse_yymmdd(text: &str) -> Option<NaiveDate> {
+    let year: i32 = text.get(..2)?.parse().ok()?;
+    let month: u32 = text.get(2..4)?.parse().ok()?;
+    let day: u32 = text.get(4..6)?.parse().ok()?;
+    NaiveDate::from_ymd_opt(2000 + year, month, day)
+}
+
+// :60F:, :62F: and their intermediate M forms, e.g. C240131EUR1234,56
+fn parse_balance(value: &str) -> Result<Money, String> {
+    let sign = match value.get(..1) {
+        Some("C") => 1,
+        Some("D") => -1,
+        _ => return Err(format!("Can't read the balance {}", value)),
+    };
+    let code = value.get(7..10).ok_or_else(|| format!("Can't read the balance {}", value))?;
+    let currency = Currency::parse(code).ok_or_else(|| format!("Unknown currency {}", code))?;
+    let amount = value
+        .get(10..)
+        .and_then(parse_mt940_amount)
+        .ok_or_else(|| format!("Can't read the balance {}", value))?;
+    Ok(Money::new(amount * BigDecimal::from(sign), currency))
+}
+
+// What a :61: statement line says
+struct StatementLine {
+    value_date: NaiveDate,
+    booking_date: NaiveDate,
+    income: bool,
+    reversal: bool,
+    amount: BigDecimal,
+    customer_reference: Option<String>,
+    bank_reference: Option<String>,
+    details: Option<String>,
+}
+
+// Value date YYMMDD, optional booking date MMDD, mark (C, D, or RC/RD for a reversed credit or
+// debit), optional funds code, amount, transaction type, then references, e.g.
+// 2401310201DR12,34NTRFINV-2024-001//BANK123
+fn parse_statement_line(value: &str) -> Result<StatementLine, String> {
+    let (first, details) = match value.split_once('\n') {
+        Some((first, details)) => (first, Some(details.trim().to_string()).filter(|d| !d.is_empty())),
+        None => (value, None),
+    };
+    let unreadable = || format!("Can't read the statement line {}", first);
+
+    let value_date = first.get(..6).and_then(parse_yymmdd).ok_or_else(unreadable)?;
+    let mut rest = &first[6..];
+    let mut booking_date = value_date;
+    if rest.get(..4).is_some_and(|date| date.chars().all(|c| c.is_ascii_digit())) {
+        let month: u32 = rest[..2].parse().map_err(|_| unreadable())?;
+        let day: u32 = rest[2..4].parse().map_err(|_| unreadable())?;
+        // The booking date has no year; a statement line booked in December can take value in January
+        booking_date = [value_date.year(), value_date.year() - 1, value_date.year() + 1]
+            .into_iter()
+            .filter_map(|year| NaiveDate::from_ymd_opt(year, month, day))
+            .min_by_key(|date| (*date - value_date).num_days().abs())
+            .ok_or_else(unreadable)?;
+        rest = &rest[4..];
+    }
+
+    let (income, reversal, mark_length) = if rest.starts_with("RC") {
+        (false, true, 2)
+    } else if rest.starts_with("RD") {
+        (true, true, 2)
+    } else if rest.starts_with('C') {
+        (true, false, 1)
+    } else if rest.starts_with('D') {
+        (false, false, 1)
+    } else {
+        return Err(unreadable());
+    };
+    rest = &rest[mark_length..];
+    if rest.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        rest = &rest[1..];
+    }
+
+    let amount_end = rest.find(|c: char| !c.is_ascii_digit() && c != ',').unwrap_or(rest.len());
+    let amount = parse_mt940_amount(&rest[..amount_end]).ok_or_else(unreadable)?;
+    let references = rest.get(amount_end + 4..).unwrap_or("");
+    let (customer, bank) = references.split_once("//").unwrap_or((references, ""));
+    let reference = |text: &str| Some(text.trim().to_string()).filter(|r| !r.is_empty() && r != "NONREF");
+
+    Ok(StatementLine {
+        value_date,
+        booking_date,
+        income,
+        reversal,
+        amount,
+        customer_reference: reference(customer),
+        bank_reference: reference(bank),
+        details,
+    })
+}
+
+// What :86: says about the other party and the payment
+#[derive(Default)]
+struct PaymentDetails {
+    name: Option<String>,
+    remittance: Option<String>,
+    reference: Option<String>,
+}
+
+// The text after a SEPA keyword, up to the next keyword
+fn sepa_part(text: &str, keyword: &str) -> Option<String> {
+    let start = text.find(keyword)? + keyword.len();
+    let rest = &text[start..];
+    let end = SEPA_KEYWORDS.iter().filter_map(|k| rest.find(k)).min().unwrap_or(rest.len());
+    Some(rest[..end].trim().to_string()).filter(|part| !part.is_empty())
+}
+
+// :86: comes in three shapes: German ?-coded subfields (166?00GUTSCHRIFT?20...?32Name), the
+// slash-separated keys Dutch banks use, or free text
+fn parse_details(text: &str) -> PaymentDetails {
+    let joined = text.replace('\n', "");
+    if joined.get(3..4) == Some("?") {
+        let mut remittance = String::new();
+        let mut name = String::new();
+        for part in joined[3..].split('?').skip(1) {
+            let (code, value) = (part.get(..2).unwrap_or(""), part.get(2..).unwrap_or(""));
+            match code {
+                "20" | "21" | "22" | "23" | "24" | "25" | "26" | "27" | "28" | "29" | "60" | "61" | "62" | "63" => {
+                    remittance.push_str(value)
+                }
+                "32" | "33" => name.push_str(value),
+                _ => {}
+            }
+        }
+        let reference = sepa_part(&remittance, "EREF+").filter(|r| r != "NOTPROVIDED");
+        let remittance = if remittance.contains("SVWZ+") {
+            sepa_part(&remittance, "SVWZ+")
+        } else {
+            Some(remittance.trim().to_string()).filter(|r| !r.is_empty())
+        };
+        return PaymentDetails {
+            name: Some(name.trim().to_string()).filter(|n| !n.is_empty()),
+            remittance,
+            reference,
+        };
+    }
+
+    if joined.starts_with('/') && SLASH_KEYS.iter().any(|key| joined.contains(&format!("/{}/", key))) {
+        let parts: Vec<&str> = joined.split('/').collect();
+        let mut details = PaymentDetails::default();
+        // The last value ends with the closing slash
+        let value_after = |index: usize| {
+            let end = parts[index..].iter().position(|p| SLASH_KEYS.contains(p)).map_or(parts.len(), |e| index + e);
+            Some(parts[index..end].join("/").trim_end_matches('/').trim().to_string()).filter(|v| !v.is_empty())
+        };
+        for (index, part) in parts.iter().enumerate() {
+            match *part {
+                // Account, BIC, name, city
+                "CNTP" => details.name = parts.get(index + 3).map(|n| n.trim().to_string()).filter(|n| !n.is_empty()),
+                "NAME" if details.name.is_none() => details.name = value_after(index + 1),
+                "REMI" => details.remittance = value_after(index + 1),
+                "EREF" => details.reference = value_after(index + 1).filter(|r| r != "NOTPROVIDED"),
+                _ => {}
+            }
+        }
+        return details;
+    }
+
+    PaymentDetails {
+        remittance: Some(text.replace('\n', " ").trim().to_string()).filter(|r| !r.is_empty()),
+        ..PaymentDetails::default()
+    }
+}
+
+fn statement_row(
+    line: &StatementLine,
+    details: Option<&str>,
+    currency: Option<Currency>,
+    number: usize,
+) -> Result<ParsedRow, String> {
+    let currency = currency.ok_or("The statement line comes before the opening balance that gives its currency")?;
+    if line.amount.is_zero() {
+        return Err("The amount is zero".to_string());
+    }
+    let details = details.map(parse_details).unwrap_or_default();
+    let description = details
+        .name
+        .clone()
+        .or(details.remittance.clone())
+        .or(line.details.clone())
+        .or(line.customer_reference.clone())
+        .ok_or("The statement line has no counterparty or description")?;
+
+    Ok(ParsedRow {
+        line: number,
+        date: line.booking_date,
+        amount: Money::new(line.amount.clone(), currency),
+        income: line.income,
+        notes: details.remittance.filter(|r| *r != description),
+        description,
+        external_id: line.bank_reference.clone(),
+        category: None,
+        value_date: Some(line.value_date).filter(|date| *date != line.booking_date),
+        reference: details.reference.or(line.customer_reference.clone()),
+        reversal: line.reversal,
+    })
+}
+
+fn push_row(
+    statement: &mut BankStatement,
+    pending: Option<(Result<StatementLine, String>, usize)>,
+    details: Option<&str>,
+    currency: Option<Currency>,
+) {
+    if let Some((line, number)) = pending {
+        match line.and_then(|line| statement_row(&line, details, currency, number)) {
+            Ok(row) => statement.rows.push(row),
+            Err(message) => statement.errors.push(ImportRowError { line: number, message }),
+        }
+    }
+}
+
+// The lines of a SWIFT MT940 customer statement. A file may hold several statements, e.g. one per
+// day, as long as they are for the same account
+pub fn parse_mt940(bytes: &[u8]) -> Result<BankStatement, AppError> {
+    let text = decode(bytes);
+    let fields = fields(&text);
+    if !fields.iter().any(|(tag, _, _)| tag == "61" || tag.starts_with("60")) {
+        return Err(AppError::BadRequest("The file is not an MT940 statement".to_string()));
+    }
+
+    let mut statement = BankStatement {
+        account: None,
+        balances: None,
+        rows: Vec::new(),
+        errors: Vec::new(),
+    };
+    let mut opening: Option<Money> = None;
+    let mut closing: Option<Money> = None;
+    // The statement's currency, from its opening balance
+    let mut currency: Option<Currency> = None;
+    // A :61: waiting for the :86: that may follow it
+    let mut pending: Option<(Result<StatementLine, String>, usize)> = None;
+
+    for (tag, value, number) in &fields {
+        if tag == "86" && pending.is_some() {
+            push_row(&mut statement, pending.take(), Some(value), currency);
+            continue;
+        }
+        push_row(&mut statement, pending.take(), None, currency);
+
+        match tag.as_str() {
+            "25" => {
+                let account = value.trim().to_string();
+                match &statement.account {
+                    Some(existing) if *existing != account => {
+                        return Err(AppError::BadRequest(
+                            "The file holds statements for several accounts; import them one at a time".to_string(),
+                        ))
+                    }
+                    _ => statement.account = Some(account),
+                }
+            }
+            "60F" | "60M" => {
+                let balance = parse_balance(value).map_err(AppError::BadRequest)?;
+                currency = Some(balance.currency());
+                opening.get_or_insert(balance);
+            }
+            "62F" | "62M" => closing = Some(parse_balance(value).map_err(AppError::BadRequest)?),
+            "61" => pending = Some((parse_statement_line(value), *number)),
+            _ => {}
+        }
+    }
+    push_row(&mut statement, pending.take(), None, currency);
+
+    if let (Some(opening), Some(closing)) = (opening, closing) {
+        statement.balances = Some(StatementBalances { opening, closing });
+    }
+    Ok(statement)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MT940: &str = "{1:F01BANKDEFFXXXX0000000000}{2:I940BANKDEFFXXXXN}{4:\n\
+        :20:STARTUMS\n\
+        :25:10020030/1234567\n\
+        :28C:00001/001\n\
+        :60F:C231229EUR1000,00\n\
+        :61:2312290102DR12,34NTRFNONREF//BANK123\n\
+        :86:166?00SEPA-UEBERWEISUNG?20EREF+INV-2024-001?21SVWZ+Rent?22 January?32Landlord\n\
+        ?33 GmbH\n\
+        :61:240102C500,NTRFPAYROLL\n\
+        :86:/TRTP/SEPA CREDIT TRANSFER/CNTP/NL12BANK0123456789/BANKNL2A/Employer/Amsterdam/REMI/Salary/EREF/NOTPROVIDED/\n\
+        :61:240103RD5,NMSC\n\
+        :86:Card refund\n\
+        :62F:C240103EUR1482,66\n\
+        -}\n";
+
+    #[test]
+    fn reads_statement_lines_with_their_details() {
+        let statement = parse_mt940(MT940.as_bytes()).unwrap();
+        let euro = Currency::parse("EUR").unwrap();
+        assert_eq!(statement.account.as_deref(), Some("10020030/1234567"));
+        let balances = statement.balances.unwrap();
+        assert_eq!(balances.opening, Money::new("1000.00".parse().unwrap(), euro));
+        assert_eq!(balances.closing, Money::new("1482.66".parse().unwrap(), euro));
+        assert!(statement.errors.is_empty());
+        assert_eq!(statement.rows.len(), 3);
+
+        // Booked on 2 January for a value date in December
+        let rent = &statement.rows[0];
+        assert_eq!(rent.line, 6);
+        assert!(!rent.income);
+        assert!(!rent.reversal);
+        assert_eq!(rent.amount, Money::new("12.34".parse().unwrap(), euro));
+        assert_eq!(rent.date, NaiveDate::from_ymd_opt(2024, 1, 2).unwrap());
+        assert_eq!(rent.value_date, NaiveDate::from_ymd_opt(2023, 12, 29));
+        assert_eq!(rent.description, "Landlord GmbH");
+        assert_eq!(rent.notes.as_deref(), Some("Rent January"));
+        assert_eq!(rent.reference.as_deref(), Some("INV-2024-001"));
+        assert_eq!(rent.external_id.as_deref(), Some("BANK123"));
+
+        let salary = &statement.rows[1];
+        assert!(salary.income);
+        assert_eq!(salary.amount.amount().to_string(), "500.00");
+        assert_eq!(salary.date, NaiveDate::from_ymd_opt(2024, 1, 2).unwrap());
+        assert_eq!(salary.value_date, None);
+        assert_eq!(salary.description, "Employer");
+        assert_eq!(salary.notes.as_deref(), Some("Salary"));
+        assert_eq!(salary.reference.as_deref(), Some("PAYROLL"));
+
+        // A reversed debit puts money back
+        let refund = &statement.rows[2];
+        assert!(refund.income);
+        assert!(refund.reversal);
+        assert_eq!(refund.description, "Card refund");
+        assert_eq!(refund.notes, None);
+    }
+
+    #[test]
+    fn reads_yymmdd_dates_and_amounts_with_a_trailing_comma() {
+        assert_eq!(parse_yymmdd("240229"), NaiveDate::from_ymd_opt(2024, 2, 29));
+        assert_eq!(parse_yymmdd("230229"), None);
+        assert_eq!(parse_yymmdd("2402"), None);
+        assert_eq!(parse_mt940_amount("1234,"), "1234".parse().ok());
+        assert_eq!(parse_mt940_amount("0,5"), "0.5".parse().ok());
+        assert_eq!(parse_balance("D240131EUR12,").unwrap().amount().to_string(), "-12.00");
+        assert!(parse_balance("X240131EUR12,").is_err());
+    }
+
+    #[test]
+    fn reports_statement_lines_it_cannot_read() {
+        let text = ":25:ACCOUNT\n:60F:C240101EUR0,\n:61:2401X1C1,NTRF\n:61:240102C0,NTRF\n";
+        let statement = parse_mt940(text.as_bytes()).unwrap();
+        assert!(statement.rows.is_empty());
+        let errors: Vec<(usize, &str)> = statement.errors.iter().map(|e| (e.line, e.message.as_str())).collect();
+        assert_eq!(
+            errors,
+            [(3, "Can't read the statement line 2401X1C1,NTRF"), (4, "The amount is zero")]
+        );
+    }
+
+    #[test]
+    fn refuses_files_for_several_accounts_or_that_are_not_mt940() {
+        let two_accounts = format!("{}:25:OTHER\n:60F:C240103EUR1482,66\n", MT940);
+        assert!(parse_mt940(two_accounts.as_bytes()).is_err());
+        assert!(parse_mt940(b"Date,Amount\n2024-01-01,5\n").is_err());
+    }
+}
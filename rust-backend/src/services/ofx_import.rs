@@ -0,0 +1,243 @@
+use std::collections::HashMap;
+
+use bigdecimal::{BigDecimal, Signed, Zero};
+use chrono::NaiveDate;
+
+use crate::error::AppError;
+use crate::models::import::{ImportRowError, ParsedRow};
+use crate::money::{Currency, Money};
+use crate::services::csv_import::parse_signed_amount;
+
+// What an OFX file says about the statement as a whole
+pub struct OfxStatement {
+    pub account: Option<String>,
+    pub currency: Option<Currency>,
+    pub rows: Vec<ParsedRow>,
+    pub errors: Vec<ImportRowError>,
+}
+
+// OFX 1.x is SGML with a plain-text header naming its character set; OFX 2.x is XML and names
+// an encoding in its prolog. Valid UTF-8 is taken as is either way
+fn decode(bytes: &[u8]) -> String {
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return text.trim_start_matches('\u{feff}').to_string();
+    }
+    let head = String::from_utf8_lossy(&bytes[..bytes.len().min(512)]).to_uppercase();
+    let label = if head.contains("CHARSET:874") || head.contains("TIS-620") || head.contains("WINDOWS-874") {
+        "windows-874"
+    } else {
+        "windows-1252"
+    };
+    let encoding = encoding_rs::Encoding::for_label(label.as_bytes()).unwrap_or(encoding_rs::WINDOWS_1252);
+    encoding.decode_without_bom_handling(bytes).0.into_owned()
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+// Tags in document order as (tag, value, line), end tags included as "/NAME". SGML leaves out the
+// end tags of single values, so a value is whatever text follows a start tag up to the next
+// tag, which reads XML the same way
+fn elements(text: &str) -> Vec<(String, String, usize)> {
+    let body = text.find('<').map_or("", |start| &text[start..]);
+    let header_lines = text[..text.len() - body.len()].matches('\n').count();
+
+    let mut found = Vec::new();
+    let mut line = header_lines + 1;
+    let mut rest = body;
+    while let Some(start) = rest.find('<') {
+        line += rest[..start].matches('\n').count();
+        let Some(end) = rest[start..].find('>') else {
+            break;
+        };
+        let tag = rest[start + 1..start + end].trim().to_uppercase();
+        rest = &rest[start + end + 1..];
+        let value_end = rest.find('<').unwrap_or(rest.len());
+        if !tag.starts_with('?') && !tag.starts_with('!') {
+            found.push((tag, unescape(rest[..value_end].trim()), line));
+        }
+    }
+    found
+}
+
+// OFX dates are YYYYMMDD followed by an optional time and zone, e.g. 20240131120000.000[+7:ICT]
+fn parse_ofx_date(text: &str) -> Option<NaiveDate> {
+    let digits = text.get(..8)?;
+    NaiveDate::parse_from_str(digits, "%Y%m%d").ok()
+}
+
+// Some banks write a decimal comma
+fn parse_ofx_amount(text: &str) -> Option<BigDecimal> {
+    if text.contains(',') && !text.contains('.') {
+        parse_signed_amount(&text.replace(',', "."))
+    } else {
+        parse_signed_amount(text)
+    }
+}
+
+fn statement_row(
+    fields: &HashMap<String, String>,
+    currency: Currency,
+    line: usize,
+) -> Result<ParsedRow, String> {
+    let field = |name: &str| fields.get(name).map(String::as_str).filter(|v| !v.is_empty());
+
+    let date_text = field("DTPOSTED").or(field("DTUSER")).ok_or("The transaction has no date")?;
+    let date = parse_ofx_date(date_text).ok_or_else(|| format!("Can't read the date {}", date_text))?;
+    let amount_text = field("TRNAMT").ok_or("The transaction has no amount")?;
+    let amount = parse_ofx_amount(amount_text).ok_or_else(|| format!("Can't read the amount {}", amount_text))?;
+    if amount.is_zero() {
+        return Err("The amount is zero".to_string());
+    }
+    // A <CURRENCY> aggregate means the amount is in that currency; <ORIGCURRENCY> only notes
+    // what it was converted from
+    let currency = match field("CURSYM").filter(|_| fields.contains_key("CURRENCY")) {
+        Some(code) => Currency::parse(code).ok_or_else(|| format!("Unknown currency {}", code))?,
+        None => currency,
+    };
+
+    let name = field("NAME").or(field("PAYEE"));
+    let memo = field("MEMO");
+    let description = name.or(memo).ok_or("The transaction has no payee or memo")?.to_string();
+    // A memo that stands in for the payee isn't repeated as notes
+    let notes = memo.filter(|m| name.is_some_and(|name| name != *m)).map(str::to_string);
+
+    Ok(ParsedRow {
+        line,
+        date,
+        amount: Money::new(amount.abs(), currency),
+        income: amount.is_positive(),
+        description,
+        notes,
+        external_id: field("FITID").map(str::to_string),
+        category: None,
+        value_date: None,
+        reference: None,
+        reversal: false,
+    })
+}
+
+// The transactions of a bank or credit card statement in OFX or QFX, SGML or XML
+pub fn parse_ofx(bytes: &[u8], default_currency: Currency) -> Result<OfxStatement, AppError> {
+    let text = decode(bytes);
+    if !text.to_uppercase().contains("<OFX>") {
+        return Err(AppError::BadRequest("The file is not an OFX statement".to_string()));
+    }
+
+    let mut statement = OfxStatement {
+        account: None,
+        currency: None,
+        rows: Vec::new(),
+        errors: Vec::new(),
+    };
+    // The transaction being read and the line it started on
+    let mut current: Option<(HashMap<String, String>, usize)> = None;
+
+    for (tag, value, line) in elements(&text) {
+        match tag.as_str() {
+            "STMTTRN" => current = Some((HashMap::new(), line)),
+            "/STMTTRN" => {
+                if let Some((fields, start)) = current.take() {
+                    let currency = statement.currency.unwrap_or(default_currency);
+                    match statement_row(&fields, currency, start) {
+                        Ok(row) => statement.rows.push(row),
+                        Err(message) => statement.errors.push(ImportRowError { line: start, message }),
+                    }
+                }
+            }
+            "CURDEF" => {
+                statement.currency = Some(
+                    Currency::parse(&value)
+                        .ok_or_else(|| AppError::BadRequest(format!("Unknown statement currency {}", value)))?,
+                );
+            }
+            // Inside a transaction, an account is the other side of a transfer
+            "ACCTID" if current.is_none() => match &statement.account {
+                Some(account) if *account != value => {
+                    return Err(AppError::BadRequest(
+                        "The file holds statements for several accounts; import them one at a time".to_string(),
+                    ))
+                }
+                _ => statement.account = Some(value),
+            },
+            _ => {
+                if let Some((fields, _)) = current.as_mut() {
+                    if !tag.starts_with('/') {
+                        fields.insert(tag, value);
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(statement)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SGML: &str = "OFXHEADER:100\nDATA:OFXSGML\nCHARSET:1252\n\n\
+        <OFX>\n<BANKMSGSRSV1><STMTTRNRS><STMTRS>\n<CURDEF>USD\n\
+        <BANKACCTFROM><BANKID>123<ACCTID>987654321<ACCTTYPE>CHECKING</BANKACCTFROM>\n\
+        <BANKTRANLIST>\n\
+        <STMTTRN>\n<TRNTYPE>DEBIT\n<DTPOSTED>20240131120000.000[-5:EST]\n<TRNAMT>-42,50\n\
+        <FITID>A1\n<NAME>Coffee &amp; Co\n<MEMO>Card 1234\n</STMTTRN>\n\
+        <STMTTRN>\n<TRNTYPE>CREDIT\n<DTPOSTED>20240201\n<TRNAMT>1000.00\n<FITID>A2\n<MEMO>Payroll\n\
+        <CURRENCY><CURRATE>35.5<CURSYM>THB</CURRENCY>\n</STMTTRN>\n\
+        <STMTTRN>\n<TRNTYPE>DEBIT\n<TRNAMT>-5\n<FITID>A3\n<NAME>No date\n</STMTTRN>\n\
+        </BANKTRANLIST>\n</STMTRS></STMTTRNRS></BANKMSGSRSV1>\n</OFX>\n";
+
+    #[test]
+    fn reads_an_sgml_statement() {
+        let statement = parse_ofx(SGML.as_bytes(), Currency::THB).unwrap();
+        assert_eq!(statement.account.as_deref(), Some("987654321"));
+        assert_eq!(statement.currency, Currency::parse("USD"));
+        assert_eq!(statement.rows.len(), 2);
+
+        let coffee = &statement.rows[0];
+        assert_eq!(coffee.date, NaiveDate::from_ymd_opt(2024, 1, 31).unwrap());
+        assert_eq!(coffee.amount.amount().to_string(), "42.50");
+        assert_eq!(coffee.amount.currency(), Currency::parse("USD").unwrap());
+        assert!(!coffee.income);
+        assert_eq!(coffee.description, "Coffee & Co");
+        assert_eq!(coffee.notes.as_deref(), Some("Card 1234"));
+        assert_eq!(coffee.external_id.as_deref(), Some("A1"));
+
+        let payroll = &statement.rows[1];
+        assert!(payroll.income);
+        assert_eq!(payroll.description, "Payroll");
+        assert_eq!(payroll.notes, None);
+        assert_eq!(payroll.amount.currency(), Currency::THB);
+
+        assert_eq!(statement.errors.len(), 1);
+        assert_eq!(statement.errors[0].line, 26);
+    }
+
+    #[test]
+    fn reads_an_xml_statement() {
+        let xml = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<?OFX OFXHEADER=\"200\"?>\n\
+            <OFX><CREDITCARDMSGSRSV1><CCSTMTTRNRS><CCSTMTRS><CURDEF>THB</CURDEF>\
+            <CCACCTFROM><ACCTID>4111</ACCTID></CCACCTFROM><BANKTRANLIST>\
+            <STMTTRN><DTPOSTED>20240305</DTPOSTED><TRNAMT>-199.00</TRNAMT><FITID>X9</FITID>\
+            <NAME>ร้านกาแฟ</NAME></STMTTRN></BANKTRANLIST></CCSTMTRS></CCSTMTTRNRS></CREDITCARDMSGSRSV1></OFX>";
+        let statement = parse_ofx(xml.as_bytes(), Currency::THB).unwrap();
+        assert_eq!(statement.account.as_deref(), Some("4111"));
+        assert_eq!(statement.rows.len(), 1);
+        assert_eq!(statement.rows[0].description, "ร้านกาแฟ");
+        assert_eq!(statement.rows[0].external_id.as_deref(), Some("X9"));
+    }
+
+    #[test]
+    fn refuses_files_for_several_accounts_or_that_are_not_ofx() {
+        let twice = "<OFX><ACCTID>1<STMTTRN><TRNAMT>1</STMTTRN><ACCTID>2</OFX>";
+        assert!(parse_ofx(twice.as_bytes(), Currency::THB).is_err());
+        assert!(parse_ofx(b"Date,Amount\n", Currency::THB).is_err());
+    }
+}
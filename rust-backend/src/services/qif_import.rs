@@ -0,0 +1,174 @@
+use bigdecimal::{Signed, Zero};
+use chrono::{Datelike, NaiveDate};
+
+use crate::error::AppError;
+use crate::models::import::{ImportRowError, ParsedRow};
+use crate::money::{Currency, Money};
+use crate::services::csv_import::{decode, parse_date, parse_signed_amount};
+
+// QIF has no fixed date format; most exporters write US month/day/year
+pub const DEFAULT_QIF_DATE_FORMAT: &str = "%m/%d/%Y";
+
+// Account types whose lines are transactions; investment and list sections are skipped
+const TRANSACTION_SECTIONS: &[&str] = &["bank", "cash", "ccard", "oth a", "oth l"];
+
+// Quicken writes years after 1999 as 1/31'24 and sometimes pads with spaces
+fn parse_qif_date(text: &str, format: &str) -> Option<NaiveDate> {
+    let cleaned: String = text.trim().replace('\'', "/").replace(' ', "");
+    let date = parse_date(&cleaned, format)?;
+    if date.year() < 100 {
+        date.with_year(date.year() + 2000)
+    } else {
+        Some(date)
+    }
+}
+
+// One record's fields, keyed by their one-letter codes
+#[derive(Default)]
+struct Record {
+    line: usize,
+    date: Option<String>,
+    amount: Option<String>,
+    payee: Option<String>,
+    memo: Option<String>,
+    category: Option<String>,
+}
+
+fn record_row(record: &Record, date_format: &str, currency: Currency) -> Result<ParsedRow, String> {
+    let date_text = record.date.as_deref().ok_or("The record has no date")?;
+    let date = parse_qif_date(date_text, date_format)
+        .ok_or_else(|| format!("Can't read the date {} as {}", date_text, date_format))?;
+    let amount_text = record.amount.as_deref().ok_or("The record has no amount")?;
+    let amount = parse_signed_amount(amount_text).ok_or_else(|| format!("Can't read the amount {}", amount_text))?;
+    if amount.is_zero() {
+        return Err("The amount is zero".to_string());
+    }
+
+    let description = record
+        .payee
+        .clone()
+        .or_else(|| record.memo.clone())
+        .ok_or("The record has no payee or memo")?;
+    // "[Savings]" names the other account of a transfer rather than a category, and
+    // "Food:Groceries" is a subcategory, kept whole
+    let category = record.category.clone().filter(|c| !c.starts_with('['));
+
+    Ok(ParsedRow {
+        line: record.line,
+        date,
+        amount: Money::new(amount.abs(), currency),
+        income: amount.is_positive(),
+        description,
+        // A memo that stands in for the payee isn't repeated as notes
+        notes: record.memo.clone().filter(|m| record.payee.as_ref().is_some_and(|payee| payee != m)),
+        // QIF has no transaction IDs; check numbers repeat across accounts and years
+        external_id: None,
+        category,
+        value_date: None,
+        reference: None,
+        reversal: false,
+    })
+}
+
+// The transactions of a QIF file's bank, cash and credit card sections
+pub fn parse_qif(
+    bytes: &[u8],
+    date_format: &str,
+    currency: Currency,
+) -> Result<(Vec<ParsedRow>, Vec<ImportRowError>), AppError> {
+    let text = decode(bytes, None)?;
+    if !text.trim_start().starts_with('!') {
+        return Err(AppError::BadRequest("The file is not a QIF file".to_string()));
+    }
+
+    let mut rows = Vec::new();
+    let mut errors = Vec::new();
+    let mut in_transactions = false;
+    let mut record = Record::default();
+
+    for (index, raw) in text.lines().enumerate() {
+        let line = raw.trim_end();
+        let Some(code) = line.chars().next() else {
+            continue;
+        };
+        let value = line[code.len_utf8()..].trim().to_string();
+
+        if code == '!' {
+            let header = line.to_lowercase();
+            in_transactions = header
+                .strip_prefix("!type:")
+                .is_some_and(|kind| TRANSACTION_SECTIONS.contains(&kind.trim()));
+            record = Record::default();
+            continue;
+        }
+        if !in_transactions {
+            continue;
+        }
+        if record.line == 0 {
+            record.line = index + 1;
+        }
+
+        match code {
+            'D' => record.date = Some(value),
+            // U is the same amount written with more precision by newer Quicken versions
+            'T' | 'U' => record.amount = Some(value),
+            'P' => record.payee = Some(value).filter(|p| !p.is_empty()),
+            'M' => record.memo = Some(value).filter(|m| !m.is_empty()),
+            'L' => record.category = Some(value).filter(|c| !c.is_empty()),
+            '^' => {
+                match record_row(&record, date_format, currency) {
+                    Ok(row) => rows.push(row),
+                    Err(message) => errors.push(ImportRowError { line: record.line, message }),
+                }
+                record = Record::default();
+            }
+            // Split lines (S, E, $), check numbers, addresses and cleared status are left out
+            _ => {}
+        }
+    }
+
+    Ok((rows, errors))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_quicken_short_years() {
+        let date = |text| parse_qif_date(text, DEFAULT_QIF_DATE_FORMAT);
+        assert_eq!(date("1/31'24"), NaiveDate::from_ymd_opt(2024, 1, 31));
+        assert_eq!(date(" 1/ 5/2024"), NaiveDate::from_ymd_opt(2024, 1, 5));
+        assert_eq!(date("31/1/2024"), None);
+    }
+
+    #[test]
+    fn reads_transaction_sections_only() {
+        let file = "!Type:Bank\n\
+                    D1/31'24\nT-1,234.50\nPSupermarket\nMWeekly shop\nLFood:Groceries\n^\n\
+                    D2/1'24\nT500.00\nMTransfer in\nL[Savings]\n^\n\
+                    D2/2'24\nT0\nPNothing\n^\n\
+                    !Type:Invst\n\
+                    D2/3'24\nT100\nPBuy shares\n^\n";
+        let (rows, errors) = parse_qif(file.as_bytes(), DEFAULT_QIF_DATE_FORMAT, Currency::THB).unwrap();
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].line, 2);
+        assert_eq!(rows[0].amount.amount().to_string(), "1234.50");
+        assert!(!rows[0].income);
+        assert_eq!(rows[0].notes.as_deref(), Some("Weekly shop"));
+        assert_eq!(rows[0].category.as_deref(), Some("Food:Groceries"));
+        assert!(rows[1].income);
+        assert_eq!(rows[1].description, "Transfer in");
+        assert_eq!(rows[1].notes, None);
+        assert_eq!(rows[1].category, None);
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, 13);
+    }
+
+    #[test]
+    fn refuses_files_without_a_header() {
+        assert!(parse_qif(b"D1/31/2024\nT10\n^\n", DEFAULT_QIF_DATE_FORMAT, Currency::THB).is_err());
+    }
+}
@@ -0,0 +1,507 @@
+use std::collections::{HashMap, HashSet};
+
+use bigdecimal::{BigDecimal, Signed, ToPrimitive, Zero};
+use chrono::{Duration, Utc};
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel::PgConnection;
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::models::reconciliation::{
+    DbReconciliationMatch, MatchStatus, ReconcileDto, ReconcileResponse, ReconciliationMatchResponse,
+};
+use crate::models::transaction::{DbTransaction, TransactionResponse, SOURCE_OCR};
+use crate::money::Money;
+use crate::schema::{reconciliation_matches, transactions};
+use crate::services::exchange_rates::convert_amount;
+use crate::services::merchants::{normalize_merchant_name, similarity};
+use crate::services::transactions::{day_start, to_responses};
+
+// Banks book card payments a few days after the purchase
+const DEFAULT_DATE_WINDOW_DAYS: i64 = 3;
+// Card FX fees are usually 1-3% on top of the converted amount
+const DEFAULT_AMOUNT_TOLERANCE_PERCENT: u32 = 3;
+const MAX_AMOUNT_TOLERANCE_PERCENT: u32 = 50;
+const DEFAULT_MIN_SCORE: f32 = 0.6;
+
+// How much the amount, the date and the merchant each count towards a pair's score
+const AMOUNT_WEIGHT: f32 = 0.5;
+const DATE_WEIGHT: f32 = 0.2;
+const MERCHANT_WEIGHT: f32 = 0.3;
+
+// A bank line's description often wraps the merchant in card and terminal details, e.g.
+// "POS 7ELEVEN SUKHUMVIT 12 BKK"; one name found inside the other counts as nearly the same
+const CONTAINED_SIMILARITY: f32 = 0.9;
+
+fn parse_status(status: &str) -> MatchStatus {
+    MatchStatus::parse(status).unwrap_or(MatchStatus::Proposed)
+}
+
+// Unreconciled transactions of one side: receipts are the transactions created from a scanned
+// bill, bank lines the ones imported from a statement. Transfers have no receipt
+fn candidates(
+    ledger_id: Uuid,
+    receipts: bool,
+    data: &ReconcileDto,
+    window: i64,
+) -> transactions::BoxedQuery<'static, Pg> {
+    let mut query = transactions::table
+        .filter(transactions::ledger_id.eq(ledger_id))
+        .filter(transactions::reconciled.eq(false))
+        .filter(transactions::transfer_id.is_null())
+        .filter(transactions::deleted_at.is_null())
+        .into_boxed();
+    // Bank lines may be booked the day before the receipt's time or a few days after it
+    let (before, after) = if receipts { (0, 0) } else { (1, window) };
+    if let Some(start) = data.start_date {
+        query = query.filter(transactions::date.ge(day_start(start - Duration::days(before))));
+    }
+    if let Some(end) = data.end_date {
+        query = query.filter(transactions::date.lt(day_start(end + Duration::days(after + 1))));
+    }
+    if receipts {
+        query.filter(transactions::source.eq(SOURCE_OCR)).filter(transactions::import_batch_id.is_null())
+    } else {
+        query.filter(transactions::import_batch_id.is_not_null())
+    }
+}
+
+// How alike two merchant names are, from 0 to 1
+fn merchant_similarity(receipt: &DbTransaction, bank_line: &DbTransaction) -> f32 {
+    if receipt.merchant_id.is_some() && receipt.merchant_id == bank_line.merchant_id {
+        return 1.0;
+    }
+    let a = normalize_merchant_name(&receipt.merchant);
+    let b = normalize_merchant_name(&bank_line.merchant);
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    let contained = if a.contains(&b) || b.contains(&a) { CONTAINED_SIMILARITY } else { 0.0 };
+    similarity(&a, &b).max(contained)
+}
+
+struct Candidate {
+    receipt_id: Uuid,
+    bank_id: Uuid,
+    score: f32,
+    amount_difference: BigDecimal,
+    days_apart: i32,
+    merchant_similarity: f32,
+}
+
+// Score a receipt against a bank line, or None when they can't be the same spend. `amount` is
+// the receipt's amount in the bank line's currency
+fn score(
+    receipt: &DbTransaction,
+    bank_line: &DbTransaction,
+    amount: &BigDecimal,
+    tolerance_percent: &BigDecimal,
+    window: i64,
+) -> Option<Candidate> {
+    if receipt.direction != bank_line.direction {
+        return None;
+    }
+    let days = (bank_line.date.date_naive() - receipt.date.date_naive()).num_days();
+    if days < -1 || days > window {
+        return None;
+    }
+
+    let difference = &bank_line.amount - amount;
+    let amount_score = if receipt.currency == bank_line.currency {
+        if !difference.is_zero() {
+            return None;
+        }
+        1.0
+    } else {
+        let tolerance = amount * tolerance_percent / BigDecimal::from(100);
+        if tolerance.is_zero() || difference.abs() > tolerance {
+            return None;
+        }
+        // Still worth half at the edge of the tolerance
+        1.0 - 0.5 * (difference.abs() / tolerance).to_f32().unwrap_or(1.0)
+    };
+    let date_score = 1.0 - days.abs() as f32 / (window + 1) as f32;
+    let merchant_similarity = merchant_similarity(receipt, bank_line);
+
+    Some(Candidate {
+        receipt_id: receipt.id,
+        bank_id: bank_line.id,
+        score: AMOUNT_WEIGHT * amount_score + DATE_WEIGHT * date_score + MERCHANT_WEIGHT * merchant_similarity,
+        amount_difference: difference,
+        days_apart: days as i32,
+        merchant_similarity,
+    })
+}
+
+// Matches with both of their transactions, as the API returns them
+fn to_match_responses(
+    conn: &mut PgConnection,
+    matches: Vec<DbReconciliationMatch>,
+) -> Result<Vec<ReconciliationMatchResponse>, AppError> {
+    let ids: HashSet<Uuid> = matches
+        .iter()
+        .flat_map(|m| [m.receipt_transaction_id, m.bank_transaction_id])
+        .collect();
+    let rows = transactions::table
+        .filter(transactions::id.eq_any(ids))
+        .filter(transactions::deleted_at.is_null())
+        .load::<DbTransaction>(conn)?;
+    let responses: HashMap<Uuid, TransactionResponse> =
+        to_responses(conn, rows)?.into_iter().map(|t| (t.id, t)).collect();
+
+    let mut result = Vec::new();
+    for m in matches {
+        // A transaction can be in several matches, e.g. one rejected and one proposed. Matches
+        // with a transaction in the trash are left out
+        let (Some(receipt), Some(bank_line)) = (
+            responses.get(&m.receipt_transaction_id).cloned(),
+            responses.get(&m.bank_transaction_id).cloned(),
+        ) else {
+            continue;
+        };
+        result.push(ReconciliationMatchResponse {
+            id: m.id,
+            status: m.status,
+            score: m.score,
+            amount_difference: Money::new(m.amount_difference, bank_line.currency),
+            currency: bank_line.currency,
+            days_apart: m.days_apart,
+            merchant_similarity: m.merchant_similarity,
+            receipt,
+            bank_line,
+            created_at: m.created_at,
+        });
+    }
+    Ok(result)
+}
+
+// Propose pairs of receipts and bank lines for the same spend. The best-scoring pairs are
+// taken first and each transaction goes into one pair at most; pairs the user rejected are
+// never proposed again. Earlier proposals the user hasn't acted on are replaced
+pub fn reconcile(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    data: &ReconcileDto,
+) -> Result<ReconcileResponse, AppError> {
+    let window = data.date_window_days.unwrap_or(DEFAULT_DATE_WINDOW_DAYS);
+    if !(0..=31).contains(&window) {
+        return Err(AppError::BadRequest("date_window_days must be between 0 and 31".to_string()));
+    }
+    let tolerance = data
+        .amount_tolerance_percent
+        .clone()
+        .unwrap_or_else(|| BigDecimal::from(DEFAULT_AMOUNT_TOLERANCE_PERCENT));
+    let max_tolerance = BigDecimal::from(MAX_AMOUNT_TOLERANCE_PERCENT);
+    if tolerance.is_negative() || tolerance > max_tolerance {
+        return Err(AppError::BadRequest(format!(
+            "amount_tolerance_percent must be between 0 and {}",
+            MAX_AMOUNT_TOLERANCE_PERCENT
+        )));
+    }
+    let min_score = data.min_score.unwrap_or(DEFAULT_MIN_SCORE);
+
+    let receipts = candidates(ledger_id, true, data, window).load::<DbTransaction>(conn)?;
+    let bank_lines = candidates(ledger_id, false, data, window).load::<DbTransaction>(conn)?;
+    let rejected: HashSet<(Uuid, Uuid)> = reconciliation_matches::table
+        .filter(reconciliation_matches::ledger_id.eq(ledger_id))
+        .filter(reconciliation_matches::status.eq(MatchStatus::Rejected.as_str()))
+        .select((reconciliation_matches::receipt_transaction_id, reconciliation_matches::bank_transaction_id))
+        .load::<(Uuid, Uuid)>(conn)?
+        .into_iter()
+        .collect();
+
+    // Receipts in another currency are converted at the rate for their day; without a rate
+    // they can't be compared
+    let mut converted: HashMap<(Uuid, String), Option<BigDecimal>> = HashMap::new();
+    let mut pairs = Vec::new();
+    for receipt in &receipts {
+        for bank_line in &bank_lines {
+            if rejected.contains(&(receipt.id, bank_line.id)) {
+                continue;
+            }
+            let amount = if receipt.currency == bank_line.currency {
+                Some(receipt.amount.clone())
+            } else {
+                let key = (receipt.id, bank_line.currency.clone());
+                if !converted.contains_key(&key) {
+                    let amount = diesel::select(convert_amount(
+                        ledger_id,
+                        receipt.amount.clone(),
+                        receipt.currency.clone(),
+                        bank_line.currency.clone(),
+                        receipt.date,
+                        "UTC",
+                    ))
+                    .get_result::<Option<BigDecimal>>(conn)?;
+                    converted.insert(key.clone(), amount);
+                }
+                converted[&key].clone()
+            };
+            if let Some(candidate) = amount.and_then(|amount| score(receipt, bank_line, &amount, &tolerance, window)) {
+                if candidate.score >= min_score {
+                    pairs.push(candidate);
+                }
+            }
+        }
+    }
+
+    pairs.sort_by(|a, b| b.score.total_cmp(&a.score));
+    let mut paired = HashSet::new();
+    let mut proposals = Vec::new();
+    let now = Utc::now();
+    for pair in pairs {
+        if paired.contains(&pair.receipt_id) || paired.contains(&pair.bank_id) {
+            continue;
+        }
+        paired.insert(pair.receipt_id);
+        paired.insert(pair.bank_id);
+        proposals.push(DbReconciliationMatch {
+            id: Uuid::new_v4(),
+            ledger_id,
+            receipt_transaction_id: pair.receipt_id,
+            bank_transaction_id: pair.bank_id,
+            score: pair.score,
+            amount_difference: pair.amount_difference,
+            days_apart: pair.days_apart,
+            merchant_similarity: pair.merchant_similarity,
+            status: MatchStatus::Proposed.as_str().to_string(),
+            created_at: now,
+            updated_at: now,
+        });
+    }
+
+    conn.transaction(|conn| {
+        diesel::delete(
+            reconciliation_matches::table
+                .filter(reconciliation_matches::ledger_id.eq(ledger_id))
+                .filter(reconciliation_matches::status.eq(MatchStatus::Proposed.as_str())),
+        )
+        .execute(conn)?;
+        if !proposals.is_empty() {
+            diesel::insert_into(reconciliation_matches::table)
+                .values(&proposals)
+                .execute(conn)?;
+        }
+        Ok::<_, AppError>(())
+    })?;
+
+    let unmatched_receipts = receipts.iter().filter(|t| !paired.contains(&t.id)).count();
+    let unmatched_bank_lines = bank_lines.iter().filter(|t| !paired.contains(&t.id)).count();
+    Ok(ReconcileResponse {
+        proposed: to_match_responses(conn, proposals)?,
+        unmatched_receipts,
+        unmatched_bank_lines,
+    })
+}
+
+pub fn list_matches(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    status: Option<MatchStatus>,
+) -> Result<Vec<ReconciliationMatchResponse>, AppError> {
+    let mut query = reconciliation_matches::table
+        .filter(reconciliation_matches::ledger_id.eq(ledger_id))
+        .into_boxed();
+    if let Some(status) = status {
+        query = query.filter(reconciliation_matches::status.eq(status.as_str()));
+    }
+    let matches = query
+        .order((reconciliation_matches::score.desc(), reconciliation_matches::created_at.desc()))
+        .load::<DbReconciliationMatch>(conn)?;
+    to_match_responses(conn, matches)
+}
+
+fn find_match(conn: &mut PgConnection, ledger_id: Uuid, match_id: Uuid) -> Result<DbReconciliationMatch, AppError> {
+    reconciliation_matches::table
+        .filter(reconciliation_matches::id.eq(match_id))
+        .filter(reconciliation_matches::ledger_id.eq(ledger_id))
+        .first::<DbReconciliationMatch>(conn)
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("Reconciliation match {} not found", match_id)))
+}
+
+fn set_reconciled(conn: &mut PgConnection, m: &DbReconciliationMatch, reconciled: bool) -> Result<(), AppError> {
+    diesel::update(
+        transactions::table.filter(transactions::id.eq_any([m.receipt_transaction_id, m.bank_transaction_id])),
+    )
+    .set((transactions::reconciled.eq(reconciled), transactions::updated_at.eq(Utc::now())))
+    .execute(conn)?;
+    Ok(())
+}
+
+fn set_status(conn: &mut PgConnection, match_id: Uuid, status: MatchStatus) -> Result<(), AppError> {
+    diesel::update(reconciliation_matches::table.find(match_id))
+        .set((
+            reconciliation_matches::status.eq(status.as_str()),
+            reconciliation_matches::updated_at.eq(Utc::now()),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
+// Confirm a proposed pair: both transactions are marked reconciled, and other proposals for
+// either of them are dropped
+pub fn confirm_match(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    match_id: Uuid,
+) -> Result<ReconciliationMatchResponse, AppError> {
+    conn.transaction(|conn| {
+        let m = find_match(conn, ledger_id, match_id)?;
+        if parse_status(&m.status) != MatchStatus::Proposed {
+            return Err(AppError::BadRequest(format!("The match is already {}", m.status)));
+        }
+        let already = transactions::table
+            .filter(transactions::id.eq_any([m.receipt_transaction_id, m.bank_transaction_id]))
+            .filter(transactions::reconciled.eq(true))
+            .count()
+            .get_result::<i64>(conn)?;
+        if already > 0 {
+            return Err(AppError::BadRequest(
+                "One of the transactions has already been reconciled with another".to_string(),
+            ));
+        }
+
+        set_status(conn, m.id, MatchStatus::Confirmed)?;
+        set_reconciled(conn, &m, true)?;
+        diesel::delete(
+            reconciliation_matches::table
+                .filter(reconciliation_matches::status.eq(MatchStatus::Proposed.as_str()))
+                .filter(
+                    reconciliation_matches::receipt_transaction_id
+                        .eq(m.receipt_transaction_id)
+                        .or(reconciliation_matches::bank_transaction_id.eq(m.bank_transaction_id)),
+                ),
+        )
+        .execute(conn)?;
+
+        let m = find_match(conn, ledger_id, match_id)?;
+        to_match_responses(conn, vec![m])?
+            .pop()
+            .ok_or_else(|| AppError::NotFound(format!("Reconciliation match {} not found", match_id)))
+    })
+}
+
+// Reject a pair. Rejecting a confirmed pair undoes it, marking both transactions unreconciled
+pub fn reject_match(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    match_id: Uuid,
+) -> Result<ReconciliationMatchResponse, AppError> {
+    conn.transaction(|conn| {
+        let m = find_match(conn, ledger_id, match_id)?;
+        match parse_status(&m.status) {
+            MatchStatus::Rejected => {
+                return Err(AppError::BadRequest("The match is already rejected".to_string()));
+            }
+            MatchStatus::Confirmed => set_reconciled(conn, &m, false)?,
+            MatchStatus::Proposed => {}
+        }
+        set_status(conn, m.id, MatchStatus::Rejected)?;
+
+        let m = find_match(conn, ledger_id, match_id)?;
+        to_match_responses(conn, vec![m])?
+            .pop()
+            .ok_or_else(|| AppError::NotFound(format!("Reconciliation match {} not found", match_id)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn transaction(merchant: &str, amount: &str, currency: &str, day: u32) -> DbTransaction {
+        let date = Utc.with_ymd_and_hms(2024, 5, day, 12, 0, 0).unwrap();
+        DbTransaction {
+            id: Uuid::new_v4(),
+            amount: amount.parse().unwrap(),
+            date,
+            merchant: merchant.to_string(),
+            category_id: None,
+            notes: None,
+            items: None,
+            image_path: None,
+            ledger_id: Uuid::nil(),
+            created_at: date,
+            updated_at: date,
+            merchant_id: None,
+            excluded: false,
+            source: SOURCE_OCR.to_string(),
+            recurring_id: None,
+            currency: currency.to_string(),
+            account_id: None,
+            direction: "expense".to_string(),
+            transfer_id: None,
+            transaction_type: "purchase".to_string(),
+            refund_of: None,
+            import_batch_id: None,
+            external_id: None,
+            value_date: None,
+            bank_reference: None,
+            reconciled: false,
+            deleted_at: None,
+        }
+    }
+
+    fn percent(value: u32) -> BigDecimal {
+        BigDecimal::from(value)
+    }
+
+    #[test]
+    fn rates_merchant_names() {
+        let receipt = transaction("7-Eleven", "85", "THB", 10);
+        let bank_line = transaction("POS 7-ELEVEN SUKHUMVIT 12 BKK", "85", "THB", 11);
+        assert!(merchant_similarity(&receipt, &bank_line) >= CONTAINED_SIMILARITY);
+
+        let mut other = transaction("Shell Rama 4", "85", "THB", 11);
+        assert!(merchant_similarity(&receipt, &other) < 0.5);
+        // The same merchant record wins over differently written names
+        other.merchant_id = Some(Uuid::new_v4());
+        let mut known = receipt.clone();
+        known.merchant_id = other.merchant_id;
+        assert_eq!(merchant_similarity(&known, &other), 1.0);
+
+        assert_eq!(merchant_similarity(&transaction("", "85", "THB", 10), &bank_line), 0.0);
+    }
+
+    #[test]
+    fn needs_the_exact_amount_in_the_same_currency() {
+        let receipt = transaction("Tops Market", "412.50", "THB", 10);
+        let amount = receipt.amount.clone();
+        let exact = score(&receipt, &transaction("TOPS MARKET", "412.50", "THB", 10), &amount, &percent(3), 3).unwrap();
+        assert!(exact.score > 0.99);
+        assert_eq!(exact.days_apart, 0);
+        assert!(score(&receipt, &transaction("TOPS MARKET", "412.00", "THB", 10), &amount, &percent(3), 3).is_none());
+    }
+
+    #[test]
+    fn allows_fx_fees_within_the_tolerance() {
+        // A 100 USD receipt converted to 3600 THB, charged with a 2.5% fee
+        let receipt = transaction("Hotel", "100", "USD", 10);
+        let converted = BigDecimal::from(3600);
+        let charged = score(&receipt, &transaction("HOTEL", "3690", "THB", 12), &converted, &percent(3), 3).unwrap();
+        assert_eq!(charged.amount_difference, BigDecimal::from(90));
+        assert_eq!(charged.days_apart, 2);
+        assert!(charged.score < 0.9);
+        assert!(score(&receipt, &transaction("HOTEL", "3720", "THB", 12), &converted, &percent(3), 3).is_none());
+        assert!(score(&receipt, &transaction("HOTEL", "3601", "THB", 12), &converted, &percent(0), 3).is_none());
+    }
+
+    #[test]
+    fn keeps_to_the_date_window_and_direction() {
+        let receipt = transaction("Grab", "120", "THB", 10);
+        let amount = receipt.amount.clone();
+        // Booked the day before the receipt's time, or up to the window after it
+        assert!(score(&receipt, &transaction("GRAB", "120", "THB", 9), &amount, &percent(3), 3).is_some());
+        assert!(score(&receipt, &transaction("GRAB", "120", "THB", 13), &amount, &percent(3), 3).is_some());
+        assert!(score(&receipt, &transaction("GRAB", "120", "THB", 8), &amount, &percent(3), 3).is_none());
+        assert!(score(&receipt, &transaction("GRAB", "120", "THB", 14), &amount, &percent(3), 3).is_none());
+
+        let mut refund = transaction("GRAB", "120", "THB", 10);
+        refund.direction = "income".to_string();
+        assert!(score(&receipt, &refund, &amount, &percent(3), 3).is_none());
+    }
+}
@@ -0,0 +1,626 @@
+use std::collections::{HashMap, HashSet};
+
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+use diesel::prelude::*;
+use diesel::PgConnection;
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::money::{Currency, Money};
+use crate::models::recurring::{
+    DbRecurringTransaction, Frequency, RecurringTransactionChanges, Schedule, SubscriptionCandidate,
+    UpcomingBill,
+};
+use crate::models::transaction::{Direction, NewTransaction, TransactionType, SOURCE_RECURRING};
+use crate::schema::{recurring_transactions, transactions};
+use crate::services::merchants::normalize_merchant_name;
+use crate::services::tags::set_transaction_tags;
+use crate::services::transactions::{category_names, day_start};
+
+// Upper bound on occurrences produced in one pass, so a bad schedule can't loop forever
+const MAX_OCCURRENCES_PER_RUN: usize = 1000;
+
+// How far back the subscription detector looks
+const DETECTION_LOOKBACK_DAYS: i64 = 400;
+
+// Share of gaps that must fit the cadence before a pattern counts as recurring
+const MIN_REGULARITY: f64 = 0.75;
+
+const MIN_DETECTION_CONFIDENCE: f64 = 0.5;
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    let (next_year, next_month) = if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+    let first_of_next = NaiveDate::from_ymd_opt(next_year, next_month, 1).expect("first of month is a valid date");
+    (first_of_next - Duration::days(1)).day()
+}
+
+// The given day of a month, pulled back to the month's last day ("31st" in February is the 28th/29th)
+fn clamped_date(year: i32, month: u32, day: u32) -> NaiveDate {
+    let day = day.clamp(1, days_in_month(year, month));
+    NaiveDate::from_ymd_opt(year, month, day).expect("clamped day is a valid date")
+}
+
+fn add_months(date: NaiveDate, months: i32, day: u32) -> NaiveDate {
+    let total = date.year() * 12 + date.month0() as i32 + months;
+    clamped_date(total.div_euclid(12), total.rem_euclid(12) as u32 + 1, day)
+}
+
+// The first date on or after the start date that the schedule fires
+pub fn first_occurrence(schedule: &Schedule) -> NaiveDate {
+    let start = schedule.start_date;
+    match (schedule.frequency, schedule.day_of_month) {
+        (Frequency::Monthly, Some(day)) => {
+            let candidate = clamped_date(start.year(), start.month(), day);
+            if candidate >= start {
+                candidate
+            } else {
+                add_months(start, 1, day)
+            }
+        }
+        _ => start,
+    }
+}
+
+pub fn next_occurrence(schedule: &Schedule, current: NaiveDate) -> NaiveDate {
+    let interval = schedule.interval.max(1);
+    match schedule.frequency {
+        Frequency::Daily => current + Duration::days(interval as i64),
+        Frequency::Weekly => current + Duration::weeks(interval as i64),
+        Frequency::Monthly => add_months(
+            current,
+            interval,
+            schedule.day_of_month.unwrap_or_else(|| schedule.start_date.day()),
+        ),
+        Frequency::Yearly => add_months(current, 12 * interval, schedule.start_date.day()),
+    }
+}
+
+// The first occurrence on or after `date`
+pub fn occurrence_on_or_after(schedule: &Schedule, date: NaiveDate) -> NaiveDate {
+    let mut occurrence = first_occurrence(schedule);
+    for _ in 0..MAX_OCCURRENCES_PER_RUN {
+        if occurrence >= date {
+            break;
+        }
+        occurrence = next_occurrence(schedule, occurrence);
+    }
+    occurrence
+}
+
+// Occurrences from `from` up to and including `until`, stopping at the template's end date
+fn occurrences_until(
+    schedule: &Schedule,
+    from: NaiveDate,
+    until: NaiveDate,
+    end_date: Option<NaiveDate>,
+) -> Vec<NaiveDate> {
+    let mut dates = Vec::new();
+    let mut date = from;
+    while date <= until && end_date.is_none_or(|end| date <= end) && dates.len() < MAX_OCCURRENCES_PER_RUN {
+        dates.push(date);
+        date = next_occurrence(schedule, date);
+    }
+    dates
+}
+
+pub fn find_recurring(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    recurring_id: Uuid,
+) -> Result<DbRecurringTransaction, AppError> {
+    recurring_transactions::table
+        .filter(recurring_transactions::id.eq(recurring_id))
+        .filter(recurring_transactions::ledger_id.eq(ledger_id))
+        .first::<DbRecurringTransaction>(conn)
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("Recurring transaction {} not found", recurring_id)))
+}
+
+// Create the transactions a template owes up to `today` and move its schedule forward.
+// Returns how many transactions were inserted; occurrences that already exist are skipped.
+pub fn materialize_template(
+    conn: &mut PgConnection,
+    template: &DbRecurringTransaction,
+    today: NaiveDate,
+) -> Result<usize, AppError> {
+    let schedule = template.schedule();
+    let due = occurrences_until(&schedule, template.next_run, today, template.end_date);
+    let Some(&last_due) = due.last() else {
+        return Ok(0);
+    };
+
+    let mut created = 0;
+    for date in &due {
+        let new_transaction = NewTransaction {
+            id: Uuid::new_v4(),
+            amount: template.amount.clone(),
+            date: day_start(*date),
+            merchant: template.merchant.clone(),
+            category_id: template.category_id,
+            notes: template.notes.clone(),
+            items: None,
+            image_path: None,
+            ledger_id: template.ledger_id,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            merchant_id: template.merchant_id,
+            excluded: false,
+            source: SOURCE_RECURRING.to_string(),
+            recurring_id: Some(template.id),
+            currency: template.currency.clone(),
+            account_id: None,
+            direction: Direction::Expense.as_str().to_string(),
+            transfer_id: None,
+            transaction_type: TransactionType::Expense.as_str().to_string(),
+            refund_of: None,
+            import_batch_id: None,
+            external_id: None,
+            value_date: None,
+            bank_reference: None,
+        };
+
+        let inserted = diesel::insert_into(transactions::table)
+            .values(&new_transaction)
+            .on_conflict_do_nothing()
+            .returning(transactions::id)
+            .get_result::<Uuid>(conn)
+            .optional()?;
+        if let Some(transaction_id) = inserted {
+            set_transaction_tags(conn, template.ledger_id, transaction_id, &template.tags)?;
+            created += 1;
+        }
+    }
+
+    let next_run = next_occurrence(&schedule, last_due);
+    let finished = template.end_date.is_some_and(|end| next_run > end);
+    let changes = RecurringTransactionChanges {
+        next_run: Some(next_run),
+        last_run: Some(last_due),
+        active: Some(!finished),
+        updated_at: Some(Utc::now()),
+        ..Default::default()
+    };
+    diesel::update(recurring_transactions::table.find(template.id))
+        .set(&changes)
+        .execute(conn)?;
+
+    Ok(created)
+}
+
+// Materialize every user's due templates; rows locked by another worker are left to it
+pub fn materialize_due(conn: &mut PgConnection, today: NaiveDate) -> Result<usize, AppError> {
+    conn.transaction(|conn| {
+        let due = recurring_transactions::table
+            .filter(recurring_transactions::active.eq(true))
+            .filter(recurring_transactions::next_run.le(today))
+            .for_update()
+            .skip_locked()
+            .load::<DbRecurringTransaction>(conn)?;
+
+        let mut created = 0;
+        for template in &due {
+            created += materialize_template(conn, template, today)?;
+        }
+        Ok(created)
+    })
+}
+
+// A transaction as loaded for detection: date, merchant, merchant id, amount, currency, category
+type LoadedHistoryRow = (DateTime<Utc>, String, Option<Uuid>, BigDecimal, String, Option<Uuid>);
+
+struct HistoryRow {
+    date: NaiveDate,
+    merchant: String,
+    amount: BigDecimal,
+    currency: String,
+    category_id: Option<Uuid>,
+}
+
+fn median(mut values: Vec<f64>) -> f64 {
+    values.sort_by(|a, b| a.total_cmp(b));
+    let middle = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[middle - 1] + values[middle]) / 2.0
+    } else {
+        values[middle]
+    }
+}
+
+fn median_amount(mut amounts: Vec<BigDecimal>) -> BigDecimal {
+    amounts.sort();
+    let middle = amounts.len() / 2;
+    if amounts.len().is_multiple_of(2) {
+        (&amounts[middle - 1] + &amounts[middle]) / BigDecimal::from(2)
+    } else {
+        amounts[middle].clone()
+    }
+}
+
+// Map a typical gap in days onto a cadence: (frequency, interval, expected gap, tolerance in days)
+fn classify_gap(gap: f64) -> Option<(Frequency, i32, f64, f64)> {
+    match gap.round() as i64 {
+        5..=9 => Some((Frequency::Weekly, 1, 7.0, 2.0)),
+        12..=16 => Some((Frequency::Weekly, 2, 14.0, 3.0)),
+        26..=35 => Some((Frequency::Monthly, 1, 30.4, 4.0)),
+        56..=66 => Some((Frequency::Monthly, 2, 60.9, 6.0)),
+        85..=97 => Some((Frequency::Monthly, 3, 91.3, 8.0)),
+        350..=380 => Some((Frequency::Yearly, 1, 365.25, 15.0)),
+        _ => None,
+    }
+}
+
+// Look for a steady cadence and a steady amount in one merchant's history (oldest first)
+fn detect_pattern(
+    merchant_id: Option<Uuid>,
+    rows: &[HistoryRow],
+    today: NaiveDate,
+) -> Option<(SubscriptionCandidate, Option<Uuid>)> {
+    let mut dates: Vec<NaiveDate> = rows.iter().map(|r| r.date).collect();
+    dates.dedup();
+    if dates.len() < 2 {
+        return None;
+    }
+
+    let gaps: Vec<f64> = dates.windows(2).map(|w| (w[1] - w[0]).num_days() as f64).collect();
+    let (frequency, interval, expected_gap, tolerance) = classify_gap(median(gaps.clone()))?;
+    let min_occurrences = if frequency == Frequency::Yearly { 2 } else { 3 };
+    if dates.len() < min_occurrences {
+        return None;
+    }
+
+    let regularity = gaps.iter().filter(|gap| (**gap - expected_gap).abs() <= tolerance).count() as f64
+        / gaps.len() as f64;
+    if regularity < MIN_REGULARITY {
+        return None;
+    }
+
+    // Bills like utilities drift a little; within 10% still counts as the same charge
+    let amounts: Vec<BigDecimal> = rows.iter().map(|r| r.amount.clone()).collect();
+    let typical_amount = median_amount(amounts.clone());
+    let amount_tolerance = (typical_amount.abs() / BigDecimal::from(10)).max(BigDecimal::from(1));
+    let amount_consistency = amounts
+        .iter()
+        .filter(|amount| (*amount - &typical_amount).abs() <= amount_tolerance)
+        .count() as f64
+        / amounts.len() as f64;
+
+    // A pattern that stopped more than two cycles ago has most likely been cancelled
+    let first_date = dates[0];
+    let last_date = *dates.last()?;
+    if (today - last_date).num_days() as f64 > expected_gap * 2.0 + tolerance {
+        return None;
+    }
+
+    let confidence = regularity * (0.5 + 0.5 * amount_consistency) * (gaps.len() as f64 / 3.0).min(1.0);
+    if confidence < MIN_DETECTION_CONFIDENCE {
+        return None;
+    }
+
+    let day_of_month = (frequency == Frequency::Monthly)
+        .then(|| median(dates.iter().map(|d| d.day() as f64).collect()).round() as u32);
+    let schedule = Schedule {
+        frequency,
+        interval,
+        day_of_month,
+        start_date: first_date,
+    };
+    let mut next_date = next_occurrence(&schedule, last_date);
+    for _ in 0..MAX_OCCURRENCES_PER_RUN {
+        if next_date >= today {
+            break;
+        }
+        next_date = next_occurrence(&schedule, next_date);
+    }
+
+    let mut category_counts: HashMap<Uuid, usize> = HashMap::new();
+    for category_id in rows.iter().filter_map(|r| r.category_id) {
+        *category_counts.entry(category_id).or_default() += 1;
+    }
+    let category_id = category_counts.into_iter().max_by_key(|(_, count)| *count).map(|(id, _)| id);
+
+    let currency = Currency::from_code(&rows.last()?.currency);
+    let candidate = SubscriptionCandidate {
+        merchant: rows.last()?.merchant.clone(),
+        merchant_id,
+        category: String::new(),
+        amount: Money::new(typical_amount, currency),
+        currency,
+        frequency,
+        interval,
+        day_of_month: day_of_month.map(|d| d as i32),
+        occurrences: dates.len(),
+        first_date,
+        last_date,
+        next_date,
+        confidence,
+    };
+    Some((candidate, category_id))
+}
+
+// Scan transaction history for repeating merchant/amount patterns that have no template yet
+pub fn detect_subscriptions(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    today: NaiveDate,
+) -> Result<Vec<SubscriptionCandidate>, AppError> {
+    let rows: Vec<LoadedHistoryRow> = transactions::table
+        .filter(transactions::ledger_id.eq(ledger_id))
+        .filter(transactions::excluded.eq(false))
+        .filter(transactions::transaction_type.eq(TransactionType::Expense.as_str()))
+        .filter(transactions::recurring_id.is_null())
+        .filter(transactions::deleted_at.is_null())
+        .filter(transactions::date.ge(day_start(today - Duration::days(DETECTION_LOOKBACK_DAYS))))
+        .order(transactions::date.asc())
+        .select((
+            transactions::date,
+            transactions::merchant,
+            transactions::merchant_id,
+            transactions::amount,
+            transactions::currency,
+            transactions::category_id,
+        ))
+        .load(conn)?;
+
+    let templates: Vec<(Option<Uuid>, String)> = recurring_transactions::table
+        .filter(recurring_transactions::ledger_id.eq(ledger_id))
+        .filter(recurring_transactions::active.eq(true))
+        .select((recurring_transactions::merchant_id, recurring_transactions::merchant))
+        .load(conn)?;
+    let covered_ids: HashSet<Uuid> = templates.iter().filter_map(|(id, _)| *id).collect();
+    let covered_names: HashSet<String> = templates.iter().map(|(_, name)| normalize_merchant_name(name)).collect();
+
+    // Group by canonical merchant when known, otherwise by normalized name; a merchant billed in two
+    // currencies is two subscriptions
+    let mut groups: HashMap<String, (Option<Uuid>, Vec<HistoryRow>)> = HashMap::new();
+    for (date, merchant, merchant_id, amount, currency, category_id) in rows {
+        let normalized = normalize_merchant_name(&merchant);
+        if merchant_id.is_some_and(|id| covered_ids.contains(&id)) || covered_names.contains(&normalized) {
+            continue;
+        }
+        let key = format!("{}:{}", currency, merchant_id.map(|id| id.to_string()).unwrap_or(normalized));
+        groups.entry(key).or_insert_with(|| (merchant_id, Vec::new())).1.push(HistoryRow {
+            date: date.date_naive(),
+            merchant,
+            amount,
+            currency,
+            category_id,
+        });
+    }
+
+    let detected: Vec<(SubscriptionCandidate, Option<Uuid>)> = groups
+        .values()
+        .filter_map(|(merchant_id, rows)| detect_pattern(*merchant_id, rows, today))
+        .collect();
+
+    let category_ids: Vec<Uuid> = detected.iter().filter_map(|(_, id)| *id).collect();
+    let names = category_names(conn, &category_ids)?;
+
+    let mut candidates: Vec<SubscriptionCandidate> = detected
+        .into_iter()
+        .map(|(mut candidate, category_id)| {
+            candidate.category = category_id.and_then(|id| names.get(&id).cloned()).unwrap_or_default();
+            candidate
+        })
+        .collect();
+    candidates.sort_by(|a, b| b.confidence.total_cmp(&a.confidence).then_with(|| a.merchant.cmp(&b.merchant)));
+    Ok(candidates)
+}
+
+// Bills expected from today through `days` ahead, from templates and detected subscriptions
+pub fn upcoming_bills(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    today: NaiveDate,
+    days: i64,
+) -> Result<Vec<UpcomingBill>, AppError> {
+    let until = today + Duration::days(days);
+
+    let templates = recurring_transactions::table
+        .filter(recurring_transactions::ledger_id.eq(ledger_id))
+        .filter(recurring_transactions::active.eq(true))
+        .load::<DbRecurringTransaction>(conn)?;
+    let category_ids: Vec<Uuid> = templates.iter().filter_map(|t| t.category_id).collect();
+    let names = category_names(conn, &category_ids)?;
+
+    let mut bills = Vec::new();
+    for template in &templates {
+        let category = template.category_id.and_then(|id| names.get(&id).cloned()).unwrap_or_default();
+        let amount = Money::new(template.amount.clone(), Currency::from_code(&template.currency));
+        for date in occurrences_until(&template.schedule(), template.next_run, until, template.end_date) {
+            if date < today {
+                continue;
+            }
+            bills.push(UpcomingBill {
+                date,
+                name: template.name.clone(),
+                merchant: template.merchant.clone(),
+                amount: amount.clone(),
+                currency: amount.currency(),
+                category: category.clone(),
+                recurring_id: Some(template.id),
+                detected: false,
+            });
+        }
+    }
+
+    for candidate in detect_subscriptions(conn, ledger_id, today)? {
+        let schedule = Schedule {
+            frequency: candidate.frequency,
+            interval: candidate.interval,
+            day_of_month: candidate.day_of_month.map(|d| d as u32),
+            start_date: candidate.first_date,
+        };
+        for date in occurrences_until(&schedule, candidate.next_date, until, None) {
+            bills.push(UpcomingBill {
+                date,
+                name: candidate.merchant.clone(),
+                merchant: candidate.merchant.clone(),
+                amount: candidate.amount.clone(),
+                currency: candidate.currency,
+                category: candidate.category.clone(),
+                recurring_id: None,
+                detected: true,
+            });
+        }
+    }
+
+    bills.sort_by(|a, b| a.date.cmp(&b.date).then_with(|| a.name.cmp(&b.name)));
+    Ok(bills)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn monthly(day_of_month: Option<u32>, start_date: NaiveDate) -> Schedule {
+        Schedule {
+            frequency: Frequency::Monthly,
+            interval: 1,
+            day_of_month,
+            start_date,
+        }
+    }
+
+    fn history(dates: &[NaiveDate], amounts: &[i64]) -> Vec<HistoryRow> {
+        dates
+            .iter()
+            .zip(amounts)
+            .map(|(date, amount)| HistoryRow {
+                date: *date,
+                merchant: "Netflix".to_string(),
+                amount: BigDecimal::from(*amount),
+                currency: "THB".to_string(),
+                category_id: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn clamps_the_31st_to_the_end_of_shorter_months() {
+        let schedule = monthly(Some(31), date(2023, 1, 31));
+        assert_eq!(next_occurrence(&schedule, date(2023, 1, 31)), date(2023, 2, 28));
+        // The 31st comes back after a short month rather than sticking to the 28th
+        assert_eq!(next_occurrence(&schedule, date(2023, 2, 28)), date(2023, 3, 31));
+        assert_eq!(next_occurrence(&schedule, date(2023, 3, 31)), date(2023, 4, 30));
+        assert_eq!(next_occurrence(&schedule, date(2023, 12, 31)), date(2024, 1, 31));
+
+        // Without a day of month the start date's day is kept
+        let schedule = monthly(None, date(2023, 1, 30));
+        assert_eq!(next_occurrence(&schedule, date(2023, 2, 28)), date(2023, 3, 30));
+    }
+
+    #[test]
+    fn follows_leap_years() {
+        let schedule = monthly(Some(29), date(2024, 1, 29));
+        assert_eq!(next_occurrence(&schedule, date(2024, 1, 29)), date(2024, 2, 29));
+        assert_eq!(next_occurrence(&schedule, date(2025, 1, 29)), date(2025, 2, 28));
+
+        let yearly = Schedule {
+            frequency: Frequency::Yearly,
+            interval: 1,
+            day_of_month: None,
+            start_date: date(2024, 2, 29),
+        };
+        assert_eq!(next_occurrence(&yearly, date(2024, 2, 29)), date(2025, 2, 28));
+        assert_eq!(next_occurrence(&yearly, date(2027, 2, 28)), date(2028, 2, 29));
+        assert_eq!(days_in_month(2100, 2), 28);
+        assert_eq!(days_in_month(2000, 2), 29);
+    }
+
+    #[test]
+    fn finds_the_first_occurrence_from_the_start_date() {
+        assert_eq!(
+            first_occurrence(&monthly(Some(15), date(2024, 1, 10))),
+            date(2024, 1, 15)
+        );
+        assert_eq!(first_occurrence(&monthly(Some(5), date(2024, 1, 10))), date(2024, 2, 5));
+        assert_eq!(
+            first_occurrence(&monthly(Some(31), date(2024, 2, 10))),
+            date(2024, 2, 29)
+        );
+        let schedule = monthly(Some(31), date(2024, 1, 31));
+        assert_eq!(occurrence_on_or_after(&schedule, date(2024, 3, 1)), date(2024, 3, 31));
+
+        let biweekly = Schedule {
+            frequency: Frequency::Weekly,
+            interval: 2,
+            day_of_month: None,
+            start_date: date(2024, 1, 1),
+        };
+        assert_eq!(
+            occurrences_until(&biweekly, date(2024, 1, 1), date(2024, 2, 1), Some(date(2024, 1, 20))),
+            [date(2024, 1, 1), date(2024, 1, 15)]
+        );
+    }
+
+    #[test]
+    fn detects_a_monthly_charge_across_short_months() {
+        let dates = [
+            date(2024, 1, 31),
+            date(2024, 2, 29),
+            date(2024, 3, 31),
+            date(2024, 4, 30),
+        ];
+        let (candidate, _) = detect_pattern(None, &history(&dates, &[419, 419, 419, 449]), date(2024, 5, 10)).unwrap();
+        assert_eq!(candidate.frequency, Frequency::Monthly);
+        assert_eq!(candidate.interval, 1);
+        // The median of 31, 29, 31 and 30 rounds up to the month end
+        assert_eq!(candidate.day_of_month, Some(31));
+        assert_eq!(candidate.amount.amount(), &BigDecimal::from(419));
+        assert_eq!(candidate.next_date, date(2024, 5, 31));
+        assert_eq!(candidate.occurrences, 4);
+    }
+
+    #[test]
+    fn classifies_gaps_into_cadences() {
+        assert!(matches!(classify_gap(7.0), Some((Frequency::Weekly, 1, _, _))));
+        assert!(matches!(classify_gap(14.0), Some((Frequency::Weekly, 2, _, _))));
+        assert!(matches!(classify_gap(91.0), Some((Frequency::Monthly, 3, _, _))));
+        assert!(matches!(classify_gap(365.0), Some((Frequency::Yearly, 1, _, _))));
+        assert!(classify_gap(20.0).is_none());
+        assert!(classify_gap(180.0).is_none());
+    }
+
+    #[test]
+    fn ignores_irregular_or_stopped_patterns() {
+        let today = date(2024, 6, 1);
+        // Gaps of 5, 40, 12 and 70 days fit no cadence
+        let irregular = [
+            date(2024, 1, 1),
+            date(2024, 1, 6),
+            date(2024, 2, 15),
+            date(2024, 2, 27),
+            date(2024, 5, 7),
+        ];
+        assert!(detect_pattern(None, &history(&irregular, &[100; 5]), today).is_none());
+
+        // Mostly monthly, but two gaps in five are off by weeks
+        let mostly = [
+            date(2024, 1, 5),
+            date(2024, 2, 5),
+            date(2024, 3, 5),
+            date(2024, 3, 25),
+            date(2024, 4, 5),
+            date(2024, 5, 6),
+        ];
+        assert!(detect_pattern(None, &history(&mostly, &[100; 6]), today).is_none());
+
+        // Two payments are too few for anything but a yearly charge
+        let two = [date(2024, 4, 1), date(2024, 5, 1)];
+        assert!(detect_pattern(None, &history(&two, &[100; 2]), today).is_none());
+
+        // Monthly until January, then nothing: cancelled
+        let stopped = [
+            date(2023, 10, 1),
+            date(2023, 11, 1),
+            date(2023, 12, 1),
+            date(2024, 1, 1),
+        ];
+        assert!(detect_pattern(None, &history(&stopped, &[100; 4]), today).is_none());
+    }
+}
@@ -0,0 +1,637 @@
+use std::collections::HashMap;
+
+use bigdecimal::{BigDecimal, Signed, ToPrimitive, Zero};
+use chrono::{DateTime, Datelike, Duration, Months, NaiveDate, Utc};
+use diesel::prelude::*;
+use diesel::sql_query;
+use diesel::sql_types::{Array, BigInt, Date, Nullable, Numeric, Text, Timestamptz, Varchar};
+use diesel::PgConnection;
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::money::{Currency, Money};
+use crate::models::report::{
+    CashFlowPoint, CashFlowReport, CategoryReport, CategorySpending, Granularity, ReportPeriod, SeriesPoint,
+    SeriesReport, TagReport, TagSpending,
+};
+use crate::services::exchange_rates::base_currency;
+
+pub const DEFAULT_TIMEZONE: &str = "UTC";
+
+// Label used for transactions without a category
+const UNCATEGORIZED: &str = "Uncategorized";
+
+// Range bounds are local dates, turned into instants in the report's time zone.
+// Spending is purchases minus refunds, converted to the base currency ($6) at the rate for the
+// transaction's local date; transactions with no usable rate are counted but left out of the totals.
+const SERIES_SQL: &str = "
+    SELECT bucket,
+           COALESCE(SUM(converted), 0) AS total,
+           COUNT(*) AS count,
+           COUNT(*) - COUNT(converted) AS unconverted
+    FROM (
+        SELECT CAST(date_trunc($5, t.date AT TIME ZONE $4) AS date) AS bucket,
+               convert_amount(t.ledger_id, spending_amount(t.transaction_type, t.amount), t.currency, $6, t.date, $4)
+                   AS converted
+        FROM transactions t
+        WHERE t.ledger_id = $1
+          AND NOT t.excluded
+          AND t.deleted_at IS NULL
+          AND t.transaction_type IN ('expense', 'refund')
+          AND t.date >= (CAST($2 AS timestamp) AT TIME ZONE $4)
+          AND t.date < (CAST($3 AS timestamp) AT TIME ZONE $4)
+    ) AS converted_transactions
+    GROUP BY bucket";
+
+// Split transactions count towards the categories of their splits rather than their own; spending
+// in a category that's in the trash counts as uncategorized
+const CATEGORY_SQL: &str = "
+    SELECT c.id AS category_id,
+           COALESCE(c.name, $5) AS category,
+           c.color,
+           COALESCE(SUM(t.converted), 0) AS total,
+           COUNT(*) AS count,
+           COUNT(*) - COUNT(t.converted) AS unconverted
+    FROM (
+        SELECT category_id,
+               convert_amount(ledger_id, spending_amount(transaction_type, amount), currency, $6, date, $4)
+                   AS converted
+        FROM transaction_allocations
+        WHERE ledger_id = $1
+          AND NOT excluded
+          AND transaction_type IN ('expense', 'refund')
+          AND date >= (CAST($2 AS timestamp) AT TIME ZONE $4)
+          AND date < (CAST($3 AS timestamp) AT TIME ZONE $4)
+    ) AS t
+    LEFT JOIN categories c ON c.id = t.category_id AND c.deleted_at IS NULL
+    GROUP BY c.id, c.name, c.color";
+
+// A transaction with several tags counts towards each; a split counts towards its own tags and the
+// transaction's. Untagged spending comes back with a NULL tag
+const TAG_SQL: &str = "
+    SELECT g.id AS tag_id,
+           g.name AS tag,
+           g.color,
+           COALESCE(SUM(t.converted), 0) AS total,
+           COUNT(DISTINCT t.transaction_id) AS count,
+           COUNT(DISTINCT t.transaction_id) FILTER (WHERE t.converted IS NULL) AS unconverted
+    FROM (
+        SELECT id,
+               transaction_id,
+               convert_amount(ledger_id, spending_amount(transaction_type, amount), currency, $5, date, $4)
+                   AS converted
+        FROM transaction_allocations
+        WHERE ledger_id = $1
+          AND NOT excluded
+          AND transaction_type IN ('expense', 'refund')
+          AND date >= (CAST($2 AS timestamp) AT TIME ZONE $4)
+          AND date < (CAST($3 AS timestamp) AT TIME ZONE $4)
+    ) AS t
+    LEFT JOIN LATERAL (
+        SELECT tag_id FROM transaction_tags WHERE transaction_id = t.transaction_id
+        UNION
+        SELECT tag_id FROM split_tags WHERE split_id = t.id
+    ) AS l ON TRUE
+    LEFT JOIN tags g ON g.id = l.tag_id
+    GROUP BY g.id, g.name, g.color";
+
+diesel::define_sql_function! {
+    // Defined by the add_transaction_types migration: purchases count as is, refunds negatively, the rest as zero
+    fn spending_amount(transaction_type: Varchar, amount: Numeric) -> Numeric;
+}
+
+const CASH_FLOW_SQL: &str = "
+    SELECT bucket,
+           COALESCE(SUM(converted) FILTER (WHERE transaction_type = 'income'), 0) AS income,
+           COALESCE(SUM(converted) FILTER (WHERE transaction_type = 'expense'), 0)
+               - COALESCE(SUM(converted) FILTER (WHERE transaction_type = 'refund'), 0) AS expenses,
+           COUNT(*) - COUNT(converted) AS unconverted
+    FROM (
+        SELECT CAST(date_trunc($5, t.date AT TIME ZONE $4) AS date) AS bucket,
+               t.transaction_type,
+               convert_amount(t.ledger_id, t.amount, t.currency, $6, t.date, $4) AS converted
+        FROM transactions t
+        WHERE t.ledger_id = $1
+          AND NOT t.excluded
+          AND t.deleted_at IS NULL
+          AND t.transaction_type <> 'transfer'
+          AND t.date >= (CAST($2 AS timestamp) AT TIME ZONE $4)
+          AND t.date < (CAST($3 AS timestamp) AT TIME ZONE $4)
+    ) AS converted_transactions
+    GROUP BY bucket";
+
+#[derive(QueryableByName)]
+struct LocalToday {
+    #[diesel(sql_type = Date)]
+    today: NaiveDate,
+}
+
+#[derive(QueryableByName)]
+struct LocalMidnight {
+    #[diesel(sql_type = Timestamptz)]
+    instant: DateTime<Utc>,
+}
+
+#[derive(QueryableByName)]
+struct BucketRow {
+    #[diesel(sql_type = Date)]
+    bucket: NaiveDate,
+    #[diesel(sql_type = Numeric)]
+    total: BigDecimal,
+    #[diesel(sql_type = BigInt)]
+    count: i64,
+    #[diesel(sql_type = BigInt)]
+    unconverted: i64,
+}
+
+#[derive(QueryableByName)]
+struct CashFlowRow {
+    #[diesel(sql_type = Date)]
+    bucket: NaiveDate,
+    #[diesel(sql_type = Numeric)]
+    income: BigDecimal,
+    #[diesel(sql_type = Numeric)]
+    expenses: BigDecimal,
+    #[diesel(sql_type = BigInt)]
+    unconverted: i64,
+}
+
+#[derive(QueryableByName)]
+struct CategoryRow {
+    #[diesel(sql_type = Nullable<diesel::sql_types::Uuid>)]
+    category_id: Option<Uuid>,
+    #[diesel(sql_type = Text)]
+    category: String,
+    #[diesel(sql_type = Nullable<Text>)]
+    color: Option<String>,
+    #[diesel(sql_type = Numeric)]
+    total: BigDecimal,
+    #[diesel(sql_type = BigInt)]
+    count: i64,
+    #[diesel(sql_type = BigInt)]
+    unconverted: i64,
+}
+
+#[derive(QueryableByName)]
+struct TagRow {
+    #[diesel(sql_type = Nullable<diesel::sql_types::Uuid>)]
+    tag_id: Option<Uuid>,
+    #[diesel(sql_type = Nullable<Text>)]
+    tag: Option<String>,
+    #[diesel(sql_type = Nullable<Text>)]
+    color: Option<String>,
+    #[diesel(sql_type = Numeric)]
+    total: BigDecimal,
+    #[diesel(sql_type = BigInt)]
+    count: i64,
+}
+
+pub fn timezone_or_default(tz: Option<&str>) -> String {
+    tz.map(str::trim)
+        .filter(|tz| !tz.is_empty())
+        .unwrap_or(DEFAULT_TIMEZONE)
+        .to_string()
+}
+
+// Today's date in the given IANA time zone; unknown zones are rejected
+pub fn local_today(conn: &mut PgConnection, tz: &str) -> Result<NaiveDate, AppError> {
+    sql_query(
+        "SELECT CAST(NOW() AT TIME ZONE name AS date) AS today \
+         FROM pg_timezone_names WHERE name = $1 LIMIT 1",
+    )
+    .bind::<Text, _>(tz)
+    .get_result::<LocalToday>(conn)
+    .optional()?
+    .map(|row| row.today)
+    .ok_or_else(|| AppError::BadRequest(format!("Unknown time zone: {}", tz)))
+}
+
+// The instant each local date starts in the given time zone, in the same order as `dates`
+pub fn local_midnights(
+    conn: &mut PgConnection,
+    dates: &[NaiveDate],
+    tz: &str,
+) -> Result<Vec<DateTime<Utc>>, AppError> {
+    Ok(sql_query(
+        "SELECT CAST(d AS timestamp) AT TIME ZONE $2 AS instant \
+         FROM unnest($1) WITH ORDINALITY AS u(d, i) ORDER BY i",
+    )
+    .bind::<Array<Date>, _>(dates.to_vec())
+    .bind::<Text, _>(tz)
+    .load::<LocalMidnight>(conn)?
+    .into_iter()
+    .map(|row| row.instant)
+    .collect())
+}
+
+// Start of the bucket containing `date`; weeks start on Monday like Postgres' date_trunc
+pub fn truncate(granularity: Granularity, date: NaiveDate) -> NaiveDate {
+    let first_of = |month: u32| NaiveDate::from_ymd_opt(date.year(), month, 1).expect("first of month is a valid date");
+    match granularity {
+        Granularity::Day => date,
+        Granularity::Week => date - Duration::days(date.weekday().num_days_from_monday() as i64),
+        Granularity::Month => first_of(date.month()),
+        Granularity::Quarter => first_of((date.month0() / 3) * 3 + 1),
+        Granularity::Year => first_of(1),
+    }
+}
+
+// Move a date by `buckets` buckets, clamping to the end of shorter months
+pub fn shift(granularity: Granularity, date: NaiveDate, buckets: i64) -> NaiveDate {
+    let months = match granularity {
+        Granularity::Day => return date + Duration::days(buckets),
+        Granularity::Week => return date + Duration::weeks(buckets),
+        Granularity::Month => buckets,
+        Granularity::Quarter => buckets * 3,
+        Granularity::Year => buckets * 12,
+    };
+    let shifted = if months >= 0 {
+        date.checked_add_months(Months::new(months as u32))
+    } else {
+        date.checked_sub_months(Months::new(months.unsigned_abs() as u32))
+    };
+    shifted.unwrap_or(date)
+}
+
+fn label(granularity: Granularity, date: NaiveDate) -> String {
+    match granularity {
+        Granularity::Day => date.format("%Y-%m-%d").to_string(),
+        Granularity::Week => {
+            let week = date.iso_week();
+            format!("{}-W{:02}", week.year(), week.week())
+        }
+        Granularity::Month => date.format("%Y-%m").to_string(),
+        Granularity::Quarter => format!("{}-Q{}", date.year(), date.month0() / 3 + 1),
+        Granularity::Year => date.year().to_string(),
+    }
+}
+
+fn report_period(start: NaiveDate, end_exclusive: NaiveDate, tz: &str) -> ReportPeriod {
+    ReportPeriod {
+        start,
+        end: end_exclusive - Duration::days(1),
+        timezone: tz.to_string(),
+    }
+}
+
+// Ratios are computed on the exact amounts; only the resulting percentage is a float
+pub fn change_percent(current: &BigDecimal, previous: &BigDecimal) -> Option<f64> {
+    if previous.is_zero() {
+        None
+    } else {
+        ((current - previous) / previous.abs() * BigDecimal::from(100)).to_f64()
+    }
+}
+
+// Share of income not spent; negative when spending exceeds income
+pub fn savings_rate(income: &BigDecimal, expenses: &BigDecimal) -> Option<f64> {
+    if income.is_positive() {
+        ((income - expenses) / income * BigDecimal::from(100)).to_f64()
+    } else {
+        None
+    }
+}
+
+pub fn percent_of(part: &BigDecimal, whole: &BigDecimal) -> f64 {
+    if whole.is_positive() {
+        (part / whole * BigDecimal::from(100)).to_f64().unwrap_or(0.0)
+    } else {
+        0.0
+    }
+}
+
+fn bucket_totals(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    granularity: Granularity,
+    start: NaiveDate,
+    end_exclusive: NaiveDate,
+    tz: &str,
+    currency: Currency,
+) -> Result<HashMap<NaiveDate, BucketRow>, AppError> {
+    Ok(sql_query(SERIES_SQL)
+        .bind::<diesel::sql_types::Uuid, _>(ledger_id)
+        .bind::<Date, _>(start)
+        .bind::<Date, _>(end_exclusive)
+        .bind::<Text, _>(tz)
+        .bind::<Text, _>(granularity.as_str())
+        .bind::<Text, _>(currency.to_string())
+        .load::<BucketRow>(conn)?
+        .into_iter()
+        .map(|row| (row.bucket, row))
+        .collect())
+}
+
+// Spending per bucket over [start, end_exclusive), compared with the same number of buckets before it
+pub fn series_report(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    granularity: Granularity,
+    start: NaiveDate,
+    end_exclusive: NaiveDate,
+    tz: &str,
+) -> Result<SeriesReport, AppError> {
+    let mut buckets = Vec::new();
+    let mut bucket = truncate(granularity, start);
+    while bucket < end_exclusive {
+        buckets.push(bucket);
+        bucket = shift(granularity, bucket, 1);
+    }
+    let bucket_count = buckets.len() as i64;
+
+    let previous_start = shift(granularity, start, -bucket_count);
+    let previous_end = shift(granularity, end_exclusive, -bucket_count);
+    let currency = base_currency(conn, ledger_id)?;
+    let current = bucket_totals(conn, ledger_id, granularity, start, end_exclusive, tz, currency)?;
+    let previous = bucket_totals(conn, ledger_id, granularity, previous_start, previous_end, tz, currency)?;
+
+    let zero = BigDecimal::zero();
+    let points: Vec<SeriesPoint> = buckets
+        .iter()
+        .map(|bucket| {
+            let (amount, count) = current.get(bucket).map_or((&zero, 0), |row| (&row.total, row.count));
+            let previous_bucket = truncate(granularity, shift(granularity, *bucket, -bucket_count));
+            let previous_amount = previous.get(&previous_bucket).map_or(&zero, |row| &row.total);
+            SeriesPoint {
+                period_start: *bucket,
+                label: label(granularity, *bucket),
+                amount: Money::new(amount.clone(), currency),
+                count,
+                previous_amount: Money::new(previous_amount.clone(), currency),
+                change_percent: change_percent(amount, previous_amount),
+            }
+        })
+        .collect();
+
+    let total: BigDecimal = current.values().map(|row| &row.total).sum();
+    let previous_total: BigDecimal = previous.values().map(|row| &row.total).sum();
+    let unconverted_count = current.values().map(|row| row.unconverted).sum();
+    let average = if points.is_empty() {
+        BigDecimal::zero()
+    } else {
+        &total / BigDecimal::from(points.len() as u64)
+    };
+
+    Ok(SeriesReport {
+        granularity,
+        period: report_period(start, end_exclusive, tz),
+        previous_period: report_period(previous_start, previous_end, tz),
+        currency,
+        change_percent: change_percent(&total, &previous_total),
+        total: Money::new(total, currency),
+        previous_total: Money::new(previous_total, currency),
+        average: Money::new(average, currency),
+        points,
+        unconverted_count,
+    })
+}
+
+// Income, spending and what's left per bucket over [start, end_exclusive)
+pub fn cash_flow_report(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    granularity: Granularity,
+    start: NaiveDate,
+    end_exclusive: NaiveDate,
+    tz: &str,
+) -> Result<CashFlowReport, AppError> {
+    let currency = base_currency(conn, ledger_id)?;
+    let rows: HashMap<NaiveDate, CashFlowRow> = sql_query(CASH_FLOW_SQL)
+        .bind::<diesel::sql_types::Uuid, _>(ledger_id)
+        .bind::<Date, _>(start)
+        .bind::<Date, _>(end_exclusive)
+        .bind::<Text, _>(tz)
+        .bind::<Text, _>(granularity.as_str())
+        .bind::<Text, _>(currency.to_string())
+        .load::<CashFlowRow>(conn)?
+        .into_iter()
+        .map(|row| (row.bucket, row))
+        .collect();
+
+    let mut points = Vec::new();
+    let mut bucket = truncate(granularity, start);
+    while bucket < end_exclusive {
+        let (income, expenses) = match rows.get(&bucket) {
+            Some(row) => (row.income.clone(), row.expenses.clone()),
+            None => (BigDecimal::zero(), BigDecimal::zero()),
+        };
+        points.push(CashFlowPoint {
+            period_start: bucket,
+            label: label(granularity, bucket),
+            savings_rate: savings_rate(&income, &expenses),
+            net: Money::new(&income - &expenses, currency),
+            income: Money::new(income, currency),
+            expenses: Money::new(expenses, currency),
+        });
+        bucket = shift(granularity, bucket, 1);
+    }
+
+    let income: BigDecimal = rows.values().map(|row| &row.income).sum();
+    let expenses: BigDecimal = rows.values().map(|row| &row.expenses).sum();
+    Ok(CashFlowReport {
+        granularity,
+        period: report_period(start, end_exclusive, tz),
+        currency,
+        savings_rate: savings_rate(&income, &expenses),
+        net: Money::new(&income - &expenses, currency),
+        income: Money::new(income, currency),
+        expenses: Money::new(expenses, currency),
+        points,
+        unconverted_count: rows.values().map(|row| row.unconverted).sum(),
+    })
+}
+
+// Spending per tag over [start, end_exclusive); tags overlap, so their amounts can add up to more
+// than the total
+pub fn spending_by_tag(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    start: NaiveDate,
+    end_exclusive: NaiveDate,
+    tz: &str,
+) -> Result<TagReport, AppError> {
+    let currency = base_currency(conn, ledger_id)?;
+    // Year buckets just to get the overall total; every transaction in range falls in one of them
+    let buckets = bucket_totals(conn, ledger_id, Granularity::Year, start, end_exclusive, tz, currency)?;
+    let total: BigDecimal = buckets.values().map(|row| &row.total).sum();
+    let unconverted_count = buckets.values().map(|row| row.unconverted).sum();
+
+    let rows = sql_query(TAG_SQL)
+        .bind::<diesel::sql_types::Uuid, _>(ledger_id)
+        .bind::<Date, _>(start)
+        .bind::<Date, _>(end_exclusive)
+        .bind::<Text, _>(tz)
+        .bind::<Text, _>(currency.to_string())
+        .load::<TagRow>(conn)?;
+
+    let mut untagged = (BigDecimal::zero(), 0);
+    let mut tags = Vec::new();
+    for row in rows {
+        match (row.tag_id, row.tag) {
+            (Some(tag_id), Some(tag)) => tags.push(TagSpending {
+                tag_id,
+                tag,
+                color: row.color,
+                count: row.count,
+                percent: percent_of(&row.total, &total),
+                amount: Money::new(row.total, currency),
+            }),
+            _ => untagged = (row.total, row.count),
+        }
+    }
+    tags.sort_by(|a, b| b.amount.amount().cmp(a.amount.amount()).then_with(|| a.tag.cmp(&b.tag)));
+
+    Ok(TagReport {
+        period: report_period(start, end_exclusive, tz),
+        currency,
+        total: Money::new(total, currency),
+        untagged: Money::new(untagged.0, currency),
+        untagged_count: untagged.1,
+        tags,
+        unconverted_count,
+    })
+}
+
+fn category_totals(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    start: NaiveDate,
+    end_exclusive: NaiveDate,
+    tz: &str,
+    currency: Currency,
+) -> Result<Vec<CategoryRow>, AppError> {
+    Ok(sql_query(CATEGORY_SQL)
+        .bind::<diesel::sql_types::Uuid, _>(ledger_id)
+        .bind::<Date, _>(start)
+        .bind::<Date, _>(end_exclusive)
+        .bind::<Text, _>(tz)
+        .bind::<Text, _>(UNCATEGORIZED)
+        .bind::<Text, _>(currency.to_string())
+        .load::<CategoryRow>(conn)?)
+}
+
+// Spending per category over [start, end_exclusive), compared with the equally long period before it
+pub fn spending_by_category(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    start: NaiveDate,
+    end_exclusive: NaiveDate,
+    tz: &str,
+) -> Result<CategoryReport, AppError> {
+    let length = end_exclusive - start;
+    let previous_start = start - length;
+
+    let currency = base_currency(conn, ledger_id)?;
+    let current = category_totals(conn, ledger_id, start, end_exclusive, tz, currency)?;
+    let previous = category_totals(conn, ledger_id, previous_start, start, tz, currency)?;
+
+    let total: BigDecimal = current.iter().map(|row| &row.total).sum();
+    let unconverted_count = current.iter().map(|row| row.unconverted).sum();
+    let previous_total: BigDecimal = previous.iter().map(|row| &row.total).sum();
+    let mut previous_by_category: HashMap<Option<Uuid>, CategoryRow> =
+        previous.into_iter().map(|row| (row.category_id, row)).collect();
+
+    let mut categories: Vec<CategorySpending> = current
+        .into_iter()
+        .map(|row| {
+            let previous_amount = previous_by_category
+                .remove(&row.category_id)
+                .map_or_else(BigDecimal::zero, |previous| previous.total);
+            CategorySpending {
+                category_id: row.category_id,
+                category: row.category,
+                color: row.color,
+                count: row.count,
+                percent: percent_of(&row.total, &total),
+                change_percent: change_percent(&row.total, &previous_amount),
+                amount: Money::new(row.total, currency),
+                previous_amount: Money::new(previous_amount, currency),
+            }
+        })
+        .collect();
+
+    // Categories with spending only in the previous period still show up, at zero
+    categories.extend(previous_by_category.into_values().map(|row| CategorySpending {
+        category_id: row.category_id,
+        category: row.category,
+        color: row.color,
+        amount: Money::zero(currency),
+        count: 0,
+        percent: 0.0,
+        change_percent: change_percent(&BigDecimal::zero(), &row.total),
+        previous_amount: Money::new(row.total, currency),
+    }));
+    categories.sort_by(|a, b| {
+        b.amount
+            .amount()
+            .cmp(a.amount.amount())
+            .then_with(|| b.previous_amount.amount().cmp(a.previous_amount.amount()))
+            .then_with(|| a.category.cmp(&b.category))
+    });
+
+    Ok(CategoryReport {
+        period: report_period(start, end_exclusive, tz),
+        previous_period: report_period(previous_start, start, tz),
+        currency,
+        change_percent: change_percent(&total, &previous_total),
+        total: Money::new(total, currency),
+        previous_total: Money::new(previous_total, currency),
+        categories,
+        unconverted_count,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn amount(value: &str) -> BigDecimal {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn truncates_to_the_start_of_the_bucket() {
+        let d = date(2024, 8, 15);
+        assert_eq!(truncate(Granularity::Day, d), d);
+        // 2024-08-15 is a Thursday
+        assert_eq!(truncate(Granularity::Week, d), date(2024, 8, 12));
+        assert_eq!(truncate(Granularity::Month, d), date(2024, 8, 1));
+        assert_eq!(truncate(Granularity::Quarter, d), date(2024, 7, 1));
+        assert_eq!(truncate(Granularity::Quarter, date(2024, 3, 31)), date(2024, 1, 1));
+        assert_eq!(truncate(Granularity::Year, d), date(2024, 1, 1));
+        // A week can start in the previous year
+        assert_eq!(truncate(Granularity::Week, date(2025, 1, 1)), date(2024, 12, 30));
+    }
+
+    #[test]
+    fn shifts_by_buckets_and_clamps_month_ends() {
+        assert_eq!(shift(Granularity::Day, date(2024, 2, 28), 2), date(2024, 3, 1));
+        assert_eq!(shift(Granularity::Week, date(2024, 1, 1), -1), date(2023, 12, 25));
+        assert_eq!(shift(Granularity::Month, date(2024, 1, 31), 1), date(2024, 2, 29));
+        assert_eq!(shift(Granularity::Month, date(2024, 3, 31), -1), date(2024, 2, 29));
+        assert_eq!(shift(Granularity::Quarter, date(2024, 11, 30), 1), date(2025, 2, 28));
+        assert_eq!(shift(Granularity::Year, date(2024, 2, 29), -1), date(2023, 2, 28));
+    }
+
+    #[test]
+    fn compares_with_the_previous_period() {
+        assert_eq!(change_percent(&amount("150"), &amount("100")), Some(50.0));
+        assert_eq!(change_percent(&amount("75"), &amount("100")), Some(-25.0));
+        // Measured against the size of a negative previous value, so an improvement stays positive
+        assert_eq!(change_percent(&amount("-50"), &amount("-100")), Some(50.0));
+        assert_eq!(change_percent(&amount("10"), &amount("0")), None);
+    }
+
+    #[test]
+    fn computes_the_savings_rate() {
+        assert_eq!(savings_rate(&amount("40000"), &amount("30000")), Some(25.0));
+        assert_eq!(savings_rate(&amount("1000"), &amount("1500")), Some(-50.0));
+        assert_eq!(savings_rate(&amount("0"), &amount("500")), None);
+        assert_eq!(percent_of(&amount("250"), &amount("1000")), 25.0);
+        assert_eq!(percent_of(&amount("250"), &amount("0")), 0.0);
+    }
+}
@@ -0,0 +1,454 @@
+use std::str::FromStr;
+
+use bigdecimal::BigDecimal;
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel::PgConnection;
+use regex::RegexBuilder;
+use serde_json::Value as JsonValue;
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::money::{Currency, Money};
+use crate::models::rule::{
+    DbRule, MatchMode, Rule, RuleAction, RuleChangePreview, RuleCondition, RuleDryRunResponse,
+    RuleEffects, RuleField, RuleOperator,
+};
+use crate::models::transaction::{DbTransaction, TransactionChanges, TransactionItem, TransactionType};
+use crate::schema::{rules, transactions};
+use crate::services::exchange_rates::base_currency;
+use crate::services::tags::{set_transaction_tags, tags_by_transaction};
+use crate::services::transactions::{category_names, resolve_category_id};
+
+// What a rule's conditions are evaluated against
+pub struct RuleInput<'a> {
+    pub merchant: &'a str,
+    pub amount: &'a BigDecimal,
+    pub currency: Currency,
+    pub items: &'a [TransactionItem],
+    pub source: &'a str,
+}
+
+fn value_as_text(value: &JsonValue) -> String {
+    match value {
+        JsonValue::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+// Compared as decimals so a rule for "amount = 19.99" matches exactly
+fn value_as_decimal(value: &JsonValue) -> Option<BigDecimal> {
+    match value {
+        JsonValue::Number(n) => BigDecimal::from_str(&n.to_string()).ok(),
+        JsonValue::String(s) => BigDecimal::from_str(s.trim()).ok(),
+        _ => None,
+    }
+}
+
+fn text_matches(text: &str, operator: RuleOperator, value: &JsonValue) -> bool {
+    let expected = value_as_text(value);
+    let text_lower = text.to_lowercase();
+    let expected_lower = expected.to_lowercase();
+
+    match operator {
+        RuleOperator::Contains => text_lower.contains(&expected_lower),
+        RuleOperator::NotContains => !text_lower.contains(&expected_lower),
+        RuleOperator::Equals => text_lower.trim() == expected_lower.trim(),
+        RuleOperator::StartsWith => text_lower.starts_with(&expected_lower),
+        RuleOperator::EndsWith => text_lower.ends_with(&expected_lower),
+        RuleOperator::Regex => RegexBuilder::new(&expected)
+            .case_insensitive(true)
+            .build()
+            .map(|regex| regex.is_match(text))
+            .unwrap_or(false),
+        RuleOperator::Lt | RuleOperator::Lte | RuleOperator::Gt | RuleOperator::Gte => false,
+    }
+}
+
+fn amount_matches(amount: &BigDecimal, operator: RuleOperator, value: &JsonValue) -> bool {
+    let expected = match value_as_decimal(value) {
+        Some(expected) => expected,
+        None => return false,
+    };
+
+    match operator {
+        RuleOperator::Equals => *amount == expected,
+        RuleOperator::Lt => *amount < expected,
+        RuleOperator::Lte => *amount <= expected,
+        RuleOperator::Gt => *amount > expected,
+        RuleOperator::Gte => *amount >= expected,
+        _ => false,
+    }
+}
+
+pub fn condition_matches(condition: &RuleCondition, input: &RuleInput, effects: &RuleEffects) -> bool {
+    match condition.field {
+        RuleField::Merchant => text_matches(input.merchant, condition.operator, &condition.value),
+        RuleField::Amount => {
+            condition.currency.is_none_or(|currency| currency == input.currency)
+                && amount_matches(input.amount, condition.operator, &condition.value)
+        }
+        RuleField::Notes => text_matches(
+            effects.notes.as_deref().unwrap_or(""),
+            condition.operator,
+            &condition.value,
+        ),
+        RuleField::Source => text_matches(input.source, condition.operator, &condition.value),
+        // "not contains" must hold for every item, every other operator for at least one
+        RuleField::Items => {
+            let mut names = input.items.iter().map(|item| item.name.as_str());
+            if condition.operator == RuleOperator::NotContains {
+                names.all(|name| text_matches(name, condition.operator, &condition.value))
+            } else {
+                names.any(|name| text_matches(name, condition.operator, &condition.value))
+            }
+        }
+    }
+}
+
+// A rule without conditions never matches, so a half-written rule can't rewrite every transaction
+pub fn rule_matches(rule: &Rule, input: &RuleInput, effects: &RuleEffects) -> bool {
+    if rule.conditions.is_empty() {
+        return false;
+    }
+
+    match rule.match_mode {
+        MatchMode::All => rule.conditions.iter().all(|c| condition_matches(c, input, effects)),
+        MatchMode::Any => rule.conditions.iter().any(|c| condition_matches(c, input, effects)),
+    }
+}
+
+pub fn apply_actions(rule: &Rule, effects: &mut RuleEffects) {
+    for action in &rule.actions {
+        match action {
+            RuleAction::SetCategory { category } => effects.category = category.clone(),
+            RuleAction::AddTag { tag } => {
+                let tag = tag.trim().to_string();
+                if !tag.is_empty() && !effects.tags.contains(&tag) {
+                    effects.tags.push(tag);
+                }
+            }
+            RuleAction::SetNote { note } => effects.notes = Some(note.clone()),
+            RuleAction::MarkExcluded => effects.excluded = true,
+        }
+    }
+}
+
+// Run enabled rules in order; returns the IDs of the rules that fired
+pub fn apply_rules(rules: &[Rule], input: &RuleInput, effects: &mut RuleEffects) -> Vec<Uuid> {
+    let mut applied = Vec::new();
+
+    for rule in rules.iter().filter(|r| r.enabled) {
+        if rule_matches(rule, input, effects) {
+            apply_actions(rule, effects);
+            applied.push(rule.id);
+            if rule.stop_processing {
+                break;
+            }
+        }
+    }
+
+    applied
+}
+
+// Give amount conditions saved without a currency the ledger's base currency, so a rule for
+// "> 1000" doesn't fire on 1000.01 of every currency alike
+pub fn set_condition_currencies(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    conditions: &mut [RuleCondition],
+) -> Result<(), AppError> {
+    if conditions.iter().any(|c| c.field == RuleField::Amount && c.currency.is_none()) {
+        let base = base_currency(conn, ledger_id)?;
+        for condition in conditions.iter_mut().filter(|c| c.field == RuleField::Amount) {
+            condition.currency.get_or_insert(base);
+        }
+    }
+    Ok(())
+}
+
+pub fn load_rules(conn: &mut PgConnection, ledger_id: Uuid) -> Result<Vec<Rule>, AppError> {
+    let mut all = rules::table
+        .filter(rules::ledger_id.eq(ledger_id))
+        .order((rules::position.asc(), rules::created_at.asc()))
+        .load::<DbRule>(conn)?
+        .into_iter()
+        .map(Rule::try_from)
+        .collect::<Result<Vec<Rule>, AppError>>()?;
+    for rule in &mut all {
+        set_condition_currencies(conn, ledger_id, &mut rule.conditions)?;
+    }
+    Ok(all)
+}
+
+pub fn find_rule(conn: &mut PgConnection, ledger_id: Uuid, rule_id: Uuid) -> Result<Rule, AppError> {
+    let mut rule = rules::table
+        .filter(rules::id.eq(rule_id))
+        .filter(rules::ledger_id.eq(ledger_id))
+        .first::<DbRule>(conn)
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("Rule {} not found", rule_id)))
+        .and_then(Rule::try_from)?;
+    set_condition_currencies(conn, ledger_id, &mut rule.conditions)?;
+    Ok(rule)
+}
+
+// Evaluate a single rule against every existing transaction and report what it would change
+fn evaluate_existing(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    rule: &Rule,
+) -> Result<(usize, Vec<(DbTransaction, RuleChangePreview)>), AppError> {
+    let rows = transactions::table
+        .filter(transactions::ledger_id.eq(ledger_id))
+        .filter(transactions::deleted_at.is_null())
+        .order(transactions::date.desc())
+        .load::<DbTransaction>(conn)?;
+
+    let category_ids: Vec<Uuid> = rows.iter().filter_map(|t| t.category_id).collect();
+    let names = category_names(conn, &category_ids)?;
+    let transaction_ids: Vec<Uuid> = rows.iter().map(|t| t.id).collect();
+    let mut tags = tags_by_transaction(conn, &transaction_ids)?;
+
+    let mut matched = 0;
+    let mut changes = Vec::new();
+
+    for row in rows {
+        let items = row.item_list();
+        let input = RuleInput {
+            merchant: &row.merchant,
+            amount: &row.amount,
+            currency: Currency::from_code(&row.currency),
+            items: &items,
+            source: &row.source,
+        };
+        let before = RuleEffects {
+            category: row.category_id.and_then(|id| names.get(&id).cloned()).unwrap_or_default(),
+            tags: tags.remove(&row.id).unwrap_or_default(),
+            notes: row.notes.clone(),
+            excluded: row.excluded,
+        };
+
+        if !rule_matches(rule, &input, &before) {
+            continue;
+        }
+        matched += 1;
+
+        let mut after = before.clone();
+        apply_actions(rule, &mut after);
+        if after != before {
+            let currency = Currency::from_code(&row.currency);
+            let preview = RuleChangePreview {
+                transaction_id: row.id,
+                date: row.date,
+                merchant: row.merchant.clone(),
+                amount: Money::new(row.amount.clone(), currency),
+                currency,
+                before,
+                after,
+            };
+            changes.push((row, preview));
+        }
+    }
+
+    Ok((matched, changes))
+}
+
+pub fn dry_run(conn: &mut PgConnection, ledger_id: Uuid, rule: &Rule) -> Result<RuleDryRunResponse, AppError> {
+    let (matched, changes) = evaluate_existing(conn, ledger_id, rule)?;
+
+    Ok(RuleDryRunResponse {
+        matched,
+        changed: changes.len(),
+        changes: changes.into_iter().map(|(_, preview)| preview).collect(),
+    })
+}
+
+// Apply a rule to existing transactions and persist the changes
+pub fn apply_to_existing(conn: &mut PgConnection, ledger_id: Uuid, rule: &Rule) -> Result<RuleDryRunResponse, AppError> {
+    conn.transaction(|conn| {
+        let (matched, changes) = evaluate_existing(conn, ledger_id, rule)?;
+
+        for (row, preview) in &changes {
+            let category_id = if preview.after.category != preview.before.category {
+                let kind = TransactionType::parse(&row.transaction_type)
+                    .unwrap_or(TransactionType::Expense)
+                    .category_kind();
+                resolve_category_id(conn, ledger_id, &preview.after.category, kind)?
+            } else {
+                None
+            };
+
+            let changeset = TransactionChanges {
+                category_id,
+                notes: preview.after.notes.clone().filter(|_| preview.after.notes != preview.before.notes),
+                excluded: Some(preview.after.excluded),
+                updated_at: Some(Utc::now()),
+                ..Default::default()
+            };
+
+            diesel::update(transactions::table.find(row.id))
+                .set(&changeset)
+                .execute(conn)?;
+            if preview.after.tags != preview.before.tags {
+                set_transaction_tags(conn, ledger_id, row.id, &preview.after.tags)?;
+            }
+        }
+
+        Ok(RuleDryRunResponse {
+            matched,
+            changed: changes.len(),
+            changes: changes.into_iter().map(|(_, preview)| preview).collect(),
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn condition(field: RuleField, operator: RuleOperator, value: JsonValue) -> RuleCondition {
+        RuleCondition {
+            field,
+            operator,
+            value,
+            currency: None,
+        }
+    }
+
+    fn rule(match_mode: MatchMode, conditions: Vec<RuleCondition>, actions: Vec<RuleAction>) -> Rule {
+        Rule {
+            id: Uuid::new_v4(),
+            name: "Rule".to_string(),
+            position: 0,
+            enabled: true,
+            match_mode,
+            stop_processing: false,
+            conditions,
+            actions,
+            created_at: Utc::now(),
+        }
+    }
+
+    fn effects() -> RuleEffects {
+        RuleEffects {
+            category: String::new(),
+            tags: Vec::new(),
+            notes: Some("Monthly top-up".to_string()),
+            excluded: false,
+        }
+    }
+
+    fn matches(condition: RuleCondition, input: &RuleInput) -> bool {
+        condition_matches(&condition, input, &effects())
+    }
+
+    fn with_input(test: impl FnOnce(&RuleInput)) {
+        let amount = BigDecimal::from_str("1000.01").unwrap();
+        let items = [TransactionItem {
+            name: "Latte".to_string(),
+            price: None,
+            quantity: None,
+        }];
+        test(&RuleInput {
+            merchant: "Grab Taxi",
+            amount: &amount,
+            currency: Currency::THB,
+            items: &items,
+            source: "manual",
+        });
+    }
+
+    #[test]
+    fn matches_text_conditions_ignoring_case() {
+        with_input(|input| {
+            let merchant = |operator, value: &str| matches(condition(RuleField::Merchant, operator, json!(value)), input);
+            assert!(merchant(RuleOperator::Contains, "TAXI"));
+            assert!(merchant(RuleOperator::NotContains, "bolt"));
+            assert!(merchant(RuleOperator::Equals, " grab taxi "));
+            assert!(merchant(RuleOperator::StartsWith, "grab"));
+            assert!(merchant(RuleOperator::EndsWith, "taxi"));
+            assert!(merchant(RuleOperator::Regex, "^gr.b\\s"));
+            assert!(!merchant(RuleOperator::Regex, "(unclosed"));
+            assert!(!merchant(RuleOperator::Gt, "a"));
+            assert!(matches(condition(RuleField::Notes, RuleOperator::Contains, json!("top-up")), input));
+            assert!(matches(condition(RuleField::Source, RuleOperator::Equals, json!("manual")), input));
+        });
+    }
+
+    #[test]
+    fn matches_items_by_any_but_not_contains_by_all() {
+        with_input(|input| {
+            assert!(matches(condition(RuleField::Items, RuleOperator::Contains, json!("latte")), input));
+            assert!(!matches(condition(RuleField::Items, RuleOperator::NotContains, json!("latte")), input));
+            assert!(matches(condition(RuleField::Items, RuleOperator::NotContains, json!("beer")), input));
+        });
+    }
+
+    #[test]
+    fn compares_amounts_as_decimals_in_the_condition_currency() {
+        with_input(|input| {
+            let amount = |operator, value: JsonValue| matches(condition(RuleField::Amount, operator, value), input);
+            assert!(amount(RuleOperator::Gt, json!(1000)));
+            assert!(amount(RuleOperator::Gte, json!("1000.01")));
+            assert!(amount(RuleOperator::Equals, json!(1000.01)));
+            assert!(amount(RuleOperator::Lt, json!("1000.02")));
+            assert!(!amount(RuleOperator::Lte, json!(1000)));
+            assert!(!amount(RuleOperator::Contains, json!(1000)));
+            assert!(!amount(RuleOperator::Gt, json!("a lot")));
+
+            let mut in_dollars = condition(RuleField::Amount, RuleOperator::Gt, json!(1000));
+            in_dollars.currency = Currency::parse("USD");
+            assert!(!matches(in_dollars.clone(), input));
+            in_dollars.currency = Some(Currency::THB);
+            assert!(matches(in_dollars, input));
+        });
+    }
+
+    #[test]
+    fn combines_conditions_by_match_mode() {
+        with_input(|input| {
+            let taxi = condition(RuleField::Merchant, RuleOperator::Contains, json!("taxi"));
+            let bolt = condition(RuleField::Merchant, RuleOperator::Contains, json!("bolt"));
+            let all = rule(MatchMode::All, vec![taxi.clone(), bolt.clone()], Vec::new());
+            let any = rule(MatchMode::Any, vec![taxi, bolt], Vec::new());
+            assert!(!rule_matches(&all, input, &effects()));
+            assert!(rule_matches(&any, input, &effects()));
+            assert!(!rule_matches(&rule(MatchMode::Any, Vec::new(), Vec::new()), input, &effects()));
+        });
+    }
+
+    #[test]
+    fn runs_enabled_rules_in_order_until_one_stops() {
+        with_input(|input| {
+            let taxi = || vec![condition(RuleField::Merchant, RuleOperator::Contains, json!("taxi"))];
+            let set = |category: &str| RuleAction::SetCategory {
+                category: category.to_string(),
+            };
+            let tag = |tag: &str| RuleAction::AddTag { tag: tag.to_string() };
+
+            let first = rule(MatchMode::All, taxi(), vec![set("Transport"), tag("work")]);
+            let mut disabled = rule(MatchMode::All, taxi(), vec![set("Ignored")]);
+            disabled.enabled = false;
+            // A later rule overrides an earlier one's category and reads the notes it left
+            let mut second = rule(
+                MatchMode::All,
+                vec![condition(RuleField::Notes, RuleOperator::Equals, json!("commute"))],
+                vec![set("Commute"), tag("work"), RuleAction::MarkExcluded],
+            );
+            let note = rule(MatchMode::All, taxi(), vec![RuleAction::SetNote { note: "commute".to_string() }]);
+            second.stop_processing = true;
+            let after_stop = rule(MatchMode::All, taxi(), vec![set("Never")]);
+
+            let rules = [first.clone(), disabled, note.clone(), second.clone(), after_stop];
+            let mut result = effects();
+            let applied = apply_rules(&rules, input, &mut result);
+            assert_eq!(applied, [first.id, note.id, second.id]);
+            assert_eq!(result.category, "Commute");
+            assert_eq!(result.tags, ["work"]);
+            assert_eq!(result.notes.as_deref(), Some("commute"));
+            assert!(result.excluded);
+        });
+    }
+}
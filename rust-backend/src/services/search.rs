@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+
+use diesel::dsl::sql;
+use diesel::expression::BoxableExpression;
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel::sql_types::{Array, Bool, Float, Text};
+use diesel::PgConnection;
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::models::search::{SearchMatch, FIELD_ITEMS, FIELD_MERCHANT, FIELD_NOTES, FIELD_RECEIPT};
+use crate::models::transaction::DbTransaction;
+use crate::schema::{bills, transactions};
+
+// Characters of context kept on either side of the first hit in a snippet
+const SNIPPET_CONTEXT: usize = 40;
+
+type TransactionCondition = Box<dyn BoxableExpression<transactions::table, Pg, SqlType = Bool>>;
+type TransactionScore = Box<dyn BoxableExpression<transactions::table, Pg, SqlType = Float>>;
+
+fn words(term: &str) -> Vec<String> {
+    term.split_whitespace().map(str::to_lowercase).collect()
+}
+
+// Makes % and _ in user input match themselves under (I)LIKE, whose default escape is a backslash
+pub fn escape_like(word: &str) -> String {
+    word.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+// A transaction matches when the words match as full text, when every word appears somewhere in
+// its text (Thai has no spaces, so its words are only found as substrings), or when the term is
+// close to one of its words, which forgives typos
+pub fn search_condition(term: &str) -> TransactionCondition {
+    let patterns: Vec<String> = words(term)
+        .iter()
+        .map(|word| format!("%{}%", escape_like(word)))
+        .collect();
+    Box::new(
+        sql::<Bool>("(transactions.search_vector @@ websearch_to_tsquery('simple', ")
+            .bind::<Text, _>(term.to_string())
+            .sql(") OR transactions.search_text ILIKE ALL(")
+            .bind::<Array<Text>, _>(patterns)
+            .sql(") OR ")
+            .bind::<Text, _>(term.to_string())
+            .sql(" <% transactions.search_text)"),
+    )
+}
+
+// Full-text rank, where merchant hits outweigh items, notes and receipt text, plus trigram closeness
+pub fn search_rank(term: &str) -> TransactionScore {
+    Box::new(
+        sql::<Float>("(ts_rank_cd(transactions.search_vector, websearch_to_tsquery('simple', ")
+            .bind::<Text, _>(term.to_string())
+            .sql(")) + word_similarity(")
+            .bind::<Text, _>(term.to_string())
+            .sql(", transactions.search_text))"),
+    )
+}
+
+fn escape_html(c: char, out: &mut String) {
+    match c {
+        '&' => out.push_str("&amp;"),
+        '<' => out.push_str("&lt;"),
+        '>' => out.push_str("&gt;"),
+        '"' => out.push_str("&quot;"),
+        _ => out.push(c),
+    }
+}
+
+// The text around the first hit with every hit marked, or None when no word occurs in it
+pub fn highlight(text: &str, words: &[String]) -> Option<String> {
+    let chars: Vec<char> = text.chars().collect();
+    // Lower-case one char at a time so positions line up with `chars`
+    let lower: Vec<char> = chars
+        .iter()
+        .map(|c| c.to_lowercase().next().unwrap_or(*c))
+        .collect();
+
+    let mut marked = vec![false; chars.len()];
+    for word in words {
+        let word: Vec<char> = word.chars().collect();
+        if word.is_empty() || word.len() > lower.len() {
+            continue;
+        }
+        for start in 0..=lower.len() - word.len() {
+            if lower[start..start + word.len()] == word[..] {
+                marked[start..start + word.len()].iter_mut().for_each(|m| *m = true);
+            }
+        }
+    }
+
+    let first = marked.iter().position(|&m| m)?;
+    let start = first.saturating_sub(SNIPPET_CONTEXT);
+    let last = marked.iter().rposition(|&m| m).unwrap_or(first);
+    let end = (last + 1 + SNIPPET_CONTEXT).min(first + 1 + SNIPPET_CONTEXT * 3).min(chars.len());
+
+    let mut snippet = String::new();
+    if start > 0 {
+        snippet.push('…');
+    }
+    for i in start..end {
+        if marked[i] && (i == start || !marked[i - 1]) {
+            snippet.push_str("<mark>");
+        }
+        escape_html(chars[i], &mut snippet);
+        if marked[i] && (i + 1 == end || !marked[i + 1]) {
+            snippet.push_str("</mark>");
+        }
+    }
+    if end < chars.len() {
+        snippet.push('…');
+    }
+    Some(snippet.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+// Snippets for each field of each transaction that contains a search word
+pub fn matches_by_transaction(
+    conn: &mut PgConnection,
+    rows: &[DbTransaction],
+    term: &str,
+) -> Result<HashMap<Uuid, Vec<SearchMatch>>, AppError> {
+    let words = words(term);
+    let transaction_ids: Vec<Uuid> = rows.iter().map(|t| t.id).collect();
+    let mut receipts: HashMap<Uuid, Vec<String>> = HashMap::new();
+    for (transaction_id, text) in bills::table
+        .filter(bills::transaction_id.eq_any(&transaction_ids))
+        .filter(bills::deleted_at.is_null())
+        .select((bills::transaction_id, bills::ocr_text))
+        .load::<(Option<Uuid>, Option<String>)>(conn)?
+    {
+        if let (Some(transaction_id), Some(text)) = (transaction_id, text) {
+            receipts.entry(transaction_id).or_default().push(text);
+        }
+    }
+
+    Ok(rows
+        .iter()
+        .map(|row| {
+            let item_names: Vec<String> = row.item_list().into_iter().map(|item| item.name).collect();
+            let receipt = receipts.remove(&row.id).unwrap_or_default().join("\n");
+            let fields = [
+                (FIELD_MERCHANT, row.merchant.clone()),
+                (FIELD_NOTES, row.notes.clone().unwrap_or_default()),
+                (FIELD_ITEMS, item_names.join(", ")),
+                (FIELD_RECEIPT, receipt),
+            ];
+            let matches = fields
+                .into_iter()
+                .filter_map(|(field, text)| {
+                    highlight(&text, &words).map(|snippet| SearchMatch {
+                        field: field.to_string(),
+                        snippet,
+                    })
+                })
+                .collect();
+            (row.id, matches)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_like_wildcards() {
+        assert_eq!(escape_like("7-Eleven"), "7-Eleven");
+        assert_eq!(escape_like("100%_off"), "100\\%\\_off");
+        // The backslash is escaped first so the added ones are not doubled
+        assert_eq!(escape_like("a\\%"), "a\\\\\\%");
+    }
+}
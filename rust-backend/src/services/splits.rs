@@ -0,0 +1,166 @@
+use std::collections::{HashMap, HashSet};
+
+use bigdecimal::{BigDecimal, Zero};
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel::PgConnection;
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::models::split::{DbSplit, NewSplit, SplitDto, SplitResponse};
+use crate::models::transaction::{DbTransaction, TransactionItem, TransactionType};
+use crate::money::{Currency, Money};
+use crate::schema::transaction_splits;
+use crate::services::tags::{add_split_tags, tags_by_split};
+use crate::services::transactions::{category_names, resolve_category_id};
+
+pub fn find_splits(conn: &mut PgConnection, transaction_id: Uuid) -> Result<Vec<DbSplit>, AppError> {
+    Ok(transaction_splits::table
+        .filter(transaction_splits::transaction_id.eq(transaction_id))
+        .order(transaction_splits::position.asc())
+        .load::<DbSplit>(conn)?)
+}
+
+// Splits of each of the transactions, in the order they were entered
+pub fn splits_by_transaction(
+    conn: &mut PgConnection,
+    transactions: &[DbTransaction],
+) -> Result<HashMap<Uuid, Vec<SplitResponse>>, AppError> {
+    let transaction_ids: Vec<Uuid> = transactions.iter().map(|t| t.id).collect();
+    let rows = transaction_splits::table
+        .filter(transaction_splits::transaction_id.eq_any(&transaction_ids))
+        .order((transaction_splits::transaction_id, transaction_splits::position.asc()))
+        .load::<DbSplit>(conn)?;
+    if rows.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let currencies: HashMap<Uuid, Currency> =
+        transactions.iter().map(|t| (t.id, Currency::from_code(&t.currency))).collect();
+    let category_ids: Vec<Uuid> = rows.iter().filter_map(|s| s.category_id).collect();
+    let names = category_names(conn, &category_ids)?;
+    let split_ids: Vec<Uuid> = rows.iter().map(|s| s.id).collect();
+    let mut tags = tags_by_split(conn, &split_ids)?;
+
+    let mut splits: HashMap<Uuid, Vec<SplitResponse>> = HashMap::new();
+    for split in rows {
+        let category = split.category_id.and_then(|id| names.get(&id).cloned()).unwrap_or_default();
+        let split_tags = tags.remove(&split.id).unwrap_or_default();
+        let currency = currencies.get(&split.transaction_id).copied().unwrap_or_default();
+        splits
+            .entry(split.transaction_id)
+            .or_default()
+            .push(SplitResponse::new(split, category, split_tags, currency));
+    }
+    Ok(splits)
+}
+
+// Unit price times quantity; an item without a price can't be allocated on its own
+fn item_total(item: &TransactionItem, index: usize) -> Result<BigDecimal, AppError> {
+    let price = item
+        .price
+        .as_ref()
+        .ok_or_else(|| AppError::BadRequest(format!("Item {} ({}) has no price", index, item.name)))?;
+    Ok(price.amount() * BigDecimal::from(item.quantity.unwrap_or(1)))
+}
+
+// Work out each allocation's amount: given outright, from its items, or the remainder
+fn split_amounts(transaction: &DbTransaction, splits: &[SplitDto]) -> Result<Vec<BigDecimal>, AppError> {
+    let currency = Currency::from_code(&transaction.currency);
+    let items = transaction.item_list();
+    let mut used_items = HashSet::new();
+
+    let mut amounts = Vec::with_capacity(splits.len());
+    for split in splits {
+        let mut items_total = BigDecimal::zero();
+        for &index in split.items.iter().flatten() {
+            let item = items
+                .get(index)
+                .ok_or_else(|| AppError::BadRequest(format!("Transaction has no item {}", index)))?;
+            if !used_items.insert(index) {
+                return Err(AppError::BadRequest(format!("Item {} is in more than one split", index)));
+            }
+            items_total += item_total(item, index)?;
+        }
+
+        let amount = match &split.amount {
+            Some(amount) => Some(currency.round(amount)),
+            None if split.items.as_ref().is_some_and(|items| !items.is_empty()) => Some(currency.round(&items_total)),
+            None => None,
+        };
+        amounts.push(amount);
+    }
+
+    if amounts.iter().filter(|amount| amount.is_none()).count() > 1 {
+        return Err(AppError::BadRequest("Only one split can take the remaining amount".to_string()));
+    }
+    let allocated: BigDecimal = amounts.iter().flatten().sum();
+    let remainder = &transaction.amount - allocated;
+    let amounts: Vec<BigDecimal> = amounts
+        .into_iter()
+        .map(|amount| amount.unwrap_or_else(|| remainder.clone()))
+        .collect();
+
+    if amounts.iter().any(|amount| amount <= &BigDecimal::zero()) {
+        return Err(AppError::BadRequest("Every split needs an amount greater than zero".to_string()));
+    }
+    let total: BigDecimal = amounts.iter().sum();
+    if total != transaction.amount {
+        return Err(AppError::BadRequest(format!(
+            "Splits add up to {} but the transaction is {}",
+            Money::new(total, currency),
+            Money::new(transaction.amount.clone(), currency)
+        )));
+    }
+    Ok(amounts)
+}
+
+// Replace the transaction's splits; an empty list makes it a single-category transaction again
+pub fn replace_splits(
+    conn: &mut PgConnection,
+    transaction: &DbTransaction,
+    splits: &[SplitDto],
+) -> Result<(), AppError> {
+    if transaction.transfer_id.is_some() {
+        return Err(AppError::BadRequest("Transfers can't be split".to_string()));
+    }
+    if splits.len() == 1 {
+        return Err(AppError::BadRequest(
+            "A split needs at least two parts; set the transaction's category instead".to_string(),
+        ));
+    }
+
+    diesel::delete(transaction_splits::table.filter(transaction_splits::transaction_id.eq(transaction.id)))
+        .execute(conn)?;
+    if splits.is_empty() {
+        return Ok(());
+    }
+
+    let amounts = split_amounts(transaction, splits)?;
+    let kind = TransactionType::parse(&transaction.transaction_type)
+        .unwrap_or(TransactionType::Expense)
+        .category_kind();
+    let mut new_splits = Vec::with_capacity(splits.len());
+    for (position, (split, amount)) in splits.iter().zip(amounts).enumerate() {
+        new_splits.push(NewSplit {
+            id: Uuid::new_v4(),
+            transaction_id: transaction.id,
+            ledger_id: transaction.ledger_id,
+            category_id: resolve_category_id(conn, transaction.ledger_id, &split.category, kind)?,
+            amount,
+            item_indexes: split.items.iter().flatten().map(|&index| index as i32).collect(),
+            notes: split.notes.clone().filter(|n| !n.trim().is_empty()),
+            position: position as i32,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        });
+    }
+
+    diesel::insert_into(transaction_splits::table)
+        .values(&new_splits)
+        .execute(conn)?;
+    for (split, new_split) in splits.iter().zip(&new_splits) {
+        add_split_tags(conn, transaction.ledger_id, new_split.id, split.tags.as_deref().unwrap_or_default())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,318 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use diesel::sql_query;
+use diesel::sql_types::{BigInt, Text};
+use diesel::PgConnection;
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::models::tag::{DbTag, NewSplitTag, NewTag, NewTransactionTag, TagResponse};
+use crate::schema::{split_tags, tags, transaction_tags};
+
+// Postgres limits a statement to 65535 bind parameters; each link takes two
+const LINK_BATCH_SIZE: usize = 10_000;
+
+diesel::define_sql_function! {
+    fn lower(value: Text) -> Text;
+}
+
+// A transaction counts once per tag, whether the tag is on the transaction or on its splits
+const TAG_COUNTS_SQL: &str = "
+    SELECT l.tag_id, COUNT(DISTINCT l.transaction_id) AS count
+    FROM (
+        SELECT tag_id, transaction_id FROM transaction_tags
+        UNION ALL
+        SELECT st.tag_id, s.transaction_id
+        FROM split_tags st
+        JOIN transaction_splits s ON s.id = st.split_id
+    ) AS l
+    JOIN tags g ON g.id = l.tag_id
+    WHERE g.ledger_id = $1
+    GROUP BY l.tag_id";
+
+#[derive(QueryableByName)]
+struct TagCount {
+    #[diesel(sql_type = diesel::sql_types::Uuid)]
+    tag_id: Uuid,
+    #[diesel(sql_type = BigInt)]
+    count: i64,
+}
+
+// Trimmed, non-empty names with repeats that differ only in case dropped
+pub fn normalize_names(names: &[String]) -> Vec<String> {
+    let mut seen = HashSet::new();
+    names
+        .iter()
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty() && seen.insert(name.to_lowercase()))
+        .collect()
+}
+
+// Lower-cased names from a comma-separated filter, for matching regardless of case
+pub fn filter_keys(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|name| name.trim().to_lowercase())
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+pub fn find_tag(conn: &mut PgConnection, ledger_id: Uuid, tag_id: Uuid) -> Result<DbTag, AppError> {
+    tags::table
+        .filter(tags::id.eq(tag_id))
+        .filter(tags::ledger_id.eq(ledger_id))
+        .first::<DbTag>(conn)
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("Tag {} not found", tag_id)))
+}
+
+// Tag names are unique per user, ignoring case
+pub fn ensure_name_available(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    name: &str,
+    except: Option<Uuid>,
+) -> Result<(), AppError> {
+    let existing = tags::table
+        .filter(tags::ledger_id.eq(ledger_id))
+        .filter(lower(tags::name).eq(name.to_lowercase()))
+        .select(tags::id)
+        .first::<Uuid>(conn)
+        .optional()?;
+    match existing {
+        Some(id) if Some(id) != except => Err(AppError::BadRequest(format!(
+            "Tag already exists: {}; merge the tags instead",
+            name
+        ))),
+        _ => Ok(()),
+    }
+}
+
+// Another request can take the name between the check above and the write; the unique index
+// then turns it away with the same error
+pub fn name_error(e: DieselError, name: &str) -> AppError {
+    match e {
+        DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => {
+            AppError::BadRequest(format!("Tag already exists: {}; merge the tags instead", name))
+        }
+        e => AppError::DbError(e),
+    }
+}
+
+// The user's tags with the given names, creating any that don't exist yet
+pub fn resolve_tags(conn: &mut PgConnection, ledger_id: Uuid, names: &[String]) -> Result<Vec<DbTag>, AppError> {
+    let names = normalize_names(names);
+    if names.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let new_tags: Vec<NewTag> = names
+        .iter()
+        .map(|name| NewTag {
+            id: Uuid::new_v4(),
+            ledger_id,
+            name: name.clone(),
+            color: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        })
+        .collect();
+    diesel::insert_into(tags::table)
+        .values(&new_tags)
+        .on_conflict_do_nothing()
+        .execute(conn)?;
+
+    let keys: Vec<String> = names.iter().map(|name| name.to_lowercase()).collect();
+    Ok(tags::table
+        .filter(tags::ledger_id.eq(ledger_id))
+        .filter(lower(tags::name).eq_any(keys))
+        .load::<DbTag>(conn)?)
+}
+
+// IDs of the user's existing tags with the given names; unknown names are ignored
+pub fn existing_tag_ids(conn: &mut PgConnection, ledger_id: Uuid, names: &[String]) -> Result<Vec<Uuid>, AppError> {
+    let keys: Vec<String> = normalize_names(names).iter().map(|name| name.to_lowercase()).collect();
+    Ok(tags::table
+        .filter(tags::ledger_id.eq(ledger_id))
+        .filter(lower(tags::name).eq_any(keys))
+        .select(tags::id)
+        .load::<Uuid>(conn)?)
+}
+
+// Put every tag on every transaction; returns how many of those links are new
+pub fn add_tags(conn: &mut PgConnection, transaction_ids: &[Uuid], tags: &[DbTag]) -> Result<usize, AppError> {
+    let links: Vec<NewTransactionTag> = transaction_ids
+        .iter()
+        .flat_map(|&transaction_id| {
+            tags.iter().map(move |tag| NewTransactionTag {
+                transaction_id,
+                tag_id: tag.id,
+            })
+        })
+        .collect();
+
+    let mut added = 0;
+    for batch in links.chunks(LINK_BATCH_SIZE) {
+        added += diesel::insert_into(transaction_tags::table)
+            .values(batch)
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+    }
+    Ok(added)
+}
+
+// Take the tags off the transactions; returns how many links were removed
+pub fn remove_tags(conn: &mut PgConnection, transaction_ids: &[Uuid], tag_ids: &[Uuid]) -> Result<usize, AppError> {
+    if tag_ids.is_empty() {
+        return Ok(0);
+    }
+    Ok(diesel::delete(
+        transaction_tags::table
+            .filter(transaction_tags::transaction_id.eq_any(transaction_ids))
+            .filter(transaction_tags::tag_id.eq_any(tag_ids)),
+    )
+    .execute(conn)?)
+}
+
+// Replace a transaction's tags with the named ones
+pub fn set_transaction_tags(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    transaction_id: Uuid,
+    names: &[String],
+) -> Result<(), AppError> {
+    let tags = resolve_tags(conn, ledger_id, names)?;
+    diesel::delete(transaction_tags::table.filter(transaction_tags::transaction_id.eq(transaction_id)))
+        .execute(conn)?;
+    add_tags(conn, &[transaction_id], &tags)?;
+    Ok(())
+}
+
+// Put the named tags on a split, creating any that don't exist yet
+pub fn add_split_tags(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    split_id: Uuid,
+    names: &[String],
+) -> Result<(), AppError> {
+    let links: Vec<NewSplitTag> = resolve_tags(conn, ledger_id, names)?
+        .iter()
+        .map(|tag| NewSplitTag { split_id, tag_id: tag.id })
+        .collect();
+    diesel::insert_into(split_tags::table)
+        .values(&links)
+        .on_conflict_do_nothing()
+        .execute(conn)?;
+    Ok(())
+}
+
+// Tag names of each split, alphabetical
+pub fn tags_by_split(conn: &mut PgConnection, split_ids: &[Uuid]) -> Result<HashMap<Uuid, Vec<String>>, AppError> {
+    let rows = split_tags::table
+        .inner_join(tags::table)
+        .filter(split_tags::split_id.eq_any(split_ids))
+        .order(tags::name.asc())
+        .select((split_tags::split_id, tags::name))
+        .load::<(Uuid, String)>(conn)?;
+
+    let mut by_split: HashMap<Uuid, Vec<String>> = HashMap::new();
+    for (split_id, name) in rows {
+        by_split.entry(split_id).or_default().push(name);
+    }
+    Ok(by_split)
+}
+
+// Tag names of each transaction, alphabetical
+pub fn tags_by_transaction(
+    conn: &mut PgConnection,
+    transaction_ids: &[Uuid],
+) -> Result<HashMap<Uuid, Vec<String>>, AppError> {
+    let rows = transaction_tags::table
+        .inner_join(tags::table)
+        .filter(transaction_tags::transaction_id.eq_any(transaction_ids))
+        .order(tags::name.asc())
+        .select((transaction_tags::transaction_id, tags::name))
+        .load::<(Uuid, String)>(conn)?;
+
+    let mut by_transaction: HashMap<Uuid, Vec<String>> = HashMap::new();
+    for (transaction_id, name) in rows {
+        by_transaction.entry(transaction_id).or_default().push(name);
+    }
+    Ok(by_transaction)
+}
+
+// All of the user's tags with how many transactions carry each, directly or on a split
+pub fn list_tags(conn: &mut PgConnection, ledger_id: Uuid) -> Result<Vec<TagResponse>, AppError> {
+    let all = tags::table
+        .filter(tags::ledger_id.eq(ledger_id))
+        .order(tags::name.asc())
+        .load::<DbTag>(conn)?;
+    let counts = tag_counts(conn, ledger_id)?;
+
+    Ok(all
+        .into_iter()
+        .map(|tag| {
+            let count = counts.get(&tag.id).copied().unwrap_or(0);
+            TagResponse::new(tag, count)
+        })
+        .collect())
+}
+
+pub fn tag_counts(conn: &mut PgConnection, ledger_id: Uuid) -> Result<HashMap<Uuid, i64>, AppError> {
+    Ok(sql_query(TAG_COUNTS_SQL)
+        .bind::<diesel::sql_types::Uuid, _>(ledger_id)
+        .load::<TagCount>(conn)?
+        .into_iter()
+        .map(|row| (row.tag_id, row.count))
+        .collect())
+}
+
+// Fold the source tags into the target: their transactions and splits get the target tag and the
+// sources go away
+pub fn merge_tags(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    target_id: Uuid,
+    source_ids: &[Uuid],
+) -> Result<DbTag, AppError> {
+    conn.transaction(|conn| {
+        let target = find_tag(conn, ledger_id, target_id)?;
+
+        for &source_id in source_ids {
+            if source_id == target_id {
+                continue;
+            }
+            let source = find_tag(conn, ledger_id, source_id)?;
+
+            sql_query(
+                "INSERT INTO transaction_tags (transaction_id, tag_id)
+                 SELECT transaction_id, $1 FROM transaction_tags WHERE tag_id = $2
+                 ON CONFLICT DO NOTHING",
+            )
+            .bind::<diesel::sql_types::Uuid, _>(target.id)
+            .bind::<diesel::sql_types::Uuid, _>(source.id)
+            .execute(conn)?;
+            sql_query(
+                "INSERT INTO split_tags (split_id, tag_id)
+                 SELECT split_id, $1 FROM split_tags WHERE tag_id = $2
+                 ON CONFLICT DO NOTHING",
+            )
+            .bind::<diesel::sql_types::Uuid, _>(target.id)
+            .bind::<diesel::sql_types::Uuid, _>(source.id)
+            .execute(conn)?;
+
+            diesel::delete(tags::table.find(source.id)).execute(conn)?;
+        }
+
+        Ok(target)
+    })
+}
+
+// Delete a tag; it comes off every transaction and split that carried it
+pub fn delete_tag(conn: &mut PgConnection, tag: &DbTag) -> Result<(), AppError> {
+    diesel::delete(tags::table.find(tag.id)).execute(conn)?;
+    Ok(())
+}
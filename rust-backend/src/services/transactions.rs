@@ -0,0 +1,260 @@
+use std::collections::HashMap;
+
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+use diesel::dsl::sum;
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel::PgConnection;
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::models::category::{CategoryKind, DbCategory, NewCategory};
+use crate::models::transaction::{DbTransaction, TransactionFilters, TransactionResponse, TransactionType};
+use crate::schema::{categories, split_tags, tags, transaction_splits, transaction_tags, transactions};
+use crate::services::search::{escape_like, search_condition};
+use crate::services::splits::splits_by_transaction;
+use crate::services::tags::{filter_keys, lower, tags_by_transaction};
+
+pub const DEFAULT_PAGE_SIZE: u64 = 20;
+pub const MAX_PAGE_SIZE: u64 = 100;
+
+// Midnight UTC at the start of a calendar day
+pub fn day_start(date: NaiveDate) -> DateTime<Utc> {
+    Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0).expect("midnight is a valid time"))
+}
+
+// The search text, when one was given
+pub fn search_term(filters: &TransactionFilters) -> Option<&str> {
+    filters.search.as_deref().map(str::trim).filter(|term| !term.is_empty())
+}
+
+// All of the user's transactions matching the filters; pagination is left to the caller
+pub fn filtered_query(ledger_id: Uuid, filters: &TransactionFilters) -> transactions::BoxedQuery<'static, Pg> {
+    let mut query = transactions::table
+        .filter(transactions::ledger_id.eq(ledger_id))
+        .filter(transactions::deleted_at.is_null())
+        .into_boxed();
+
+    if let Some(start_date) = filters.start_date {
+        query = query.filter(transactions::date.ge(start_date));
+    }
+    if let Some(end_date) = filters.end_date {
+        query = query.filter(transactions::date.le(end_date));
+    }
+    if let Some(min_amount) = filters.min_amount.clone() {
+        query = query.filter(transactions::amount.ge(min_amount));
+    }
+    if let Some(max_amount) = filters.max_amount.clone() {
+        query = query.filter(transactions::amount.le(max_amount));
+    }
+    if let Some(merchant) = filters.merchant.as_ref().filter(|m| !m.trim().is_empty()) {
+        query = query.filter(transactions::merchant.ilike(format!("%{}%", escape_like(merchant.trim()))));
+    }
+    if let Some(term) = search_term(filters) {
+        query = query.filter(search_condition(term));
+    }
+    if let Some(account_id) = filters.account_id {
+        query = query.filter(transactions::account_id.eq(account_id));
+    }
+    if let Some(direction) = filters.direction {
+        query = query.filter(transactions::direction.eq(direction.as_str()));
+    }
+    if let Some(transaction_type) = filters.transaction_type {
+        query = query.filter(transactions::transaction_type.eq(transaction_type.as_str()));
+    }
+    if let Some(import_batch_id) = filters.import_batch_id {
+        query = query.filter(transactions::import_batch_id.eq(import_batch_id));
+    }
+    if let Some(reconciled) = filters.reconciled {
+        query = query.filter(transactions::reconciled.eq(reconciled));
+    }
+    // Tag names match regardless of case; a split transaction carries its splits' tags too
+    let tagged = |keys: Vec<String>| {
+        transaction_tags::table
+            .inner_join(tags::table)
+            .filter(tags::ledger_id.eq(ledger_id))
+            .filter(lower(tags::name).eq_any(keys))
+            .select(transaction_tags::transaction_id)
+    };
+    let split_tagged = |keys: Vec<String>| {
+        split_tags::table
+            .inner_join(tags::table)
+            .inner_join(transaction_splits::table)
+            .filter(tags::ledger_id.eq(ledger_id))
+            .filter(lower(tags::name).eq_any(keys))
+            .select(transaction_splits::transaction_id)
+    };
+    if let Some(keys) = filters.tags_any.as_deref().map(filter_keys).filter(|keys| !keys.is_empty()) {
+        query = query.filter(
+            transactions::id
+                .eq_any(tagged(keys.clone()))
+                .or(transactions::id.eq_any(split_tagged(keys))),
+        );
+    }
+    for key in filters.tags_all.as_deref().map(filter_keys).unwrap_or_default() {
+        query = query.filter(
+            transactions::id
+                .eq_any(tagged(vec![key.clone()]))
+                .or(transactions::id.eq_any(split_tagged(vec![key]))),
+        );
+    }
+    if let Some(keys) = filters.tags_none.as_deref().map(filter_keys).filter(|keys| !keys.is_empty()) {
+        query = query
+            .filter(transactions::id.ne_all(tagged(keys.clone())))
+            .filter(transactions::id.ne_all(split_tagged(keys)));
+    }
+
+    // A split transaction matches the category of any of its splits
+    if let Some(category) = filters.category.as_ref().filter(|c| !c.trim().is_empty()) {
+        let category_ids = || {
+            categories::table
+                .filter(categories::ledger_id.eq(ledger_id))
+                .filter(categories::deleted_at.is_null())
+                .filter(categories::name.ilike(escape_like(category.trim())))
+                .select(categories::id.nullable())
+        };
+        let split_transaction_ids = transaction_splits::table
+            .filter(transaction_splits::category_id.eq_any(category_ids()))
+            .select(transaction_splits::transaction_id);
+        query = query.filter(
+            transactions::category_id
+                .eq_any(category_ids())
+                .or(transactions::id.eq_any(split_transaction_ids)),
+        );
+    }
+
+    query
+}
+
+pub fn find_transaction(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    transaction_id: Uuid,
+) -> Result<DbTransaction, AppError> {
+    transactions::table
+        .filter(transactions::id.eq(transaction_id))
+        .filter(transactions::ledger_id.eq(ledger_id))
+        .filter(transactions::deleted_at.is_null())
+        .first::<DbTransaction>(conn)
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("Transaction {} not found", transaction_id)))
+}
+
+// The purchase a refund points at; refunds can't add up to more than was paid for it
+pub fn refundable_purchase(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    purchase_id: Uuid,
+    amount: &BigDecimal,
+    currency: &str,
+    except_refund: Option<Uuid>,
+) -> Result<DbTransaction, AppError> {
+    let purchase = find_transaction(conn, ledger_id, purchase_id)?;
+    if purchase.transaction_type != TransactionType::Expense.as_str() {
+        return Err(AppError::BadRequest("Only a purchase can be refunded".to_string()));
+    }
+    if purchase.currency != currency {
+        return Err(AppError::BadRequest(format!(
+            "A refund must be in {}, the currency of the purchase",
+            purchase.currency
+        )));
+    }
+
+    let mut query = transactions::table
+        .filter(transactions::refund_of.eq(purchase.id))
+        .filter(transactions::deleted_at.is_null())
+        .into_boxed();
+    if let Some(refund_id) = except_refund {
+        query = query.filter(transactions::id.ne(refund_id));
+    }
+    let refunded = query
+        .select(sum(transactions::amount))
+        .first::<Option<BigDecimal>>(conn)?
+        .unwrap_or_default()
+        + amount;
+    if refunded > purchase.amount {
+        return Err(AppError::BadRequest(format!(
+            "Refunds would total {} but the purchase was {}",
+            refunded, purchase.amount
+        )));
+    }
+    Ok(purchase)
+}
+
+// Categories in the trash have no name, so what was filed under them shows as uncategorized
+pub fn category_names(
+    conn: &mut PgConnection,
+    category_ids: &[Uuid],
+) -> Result<HashMap<Uuid, String>, AppError> {
+    Ok(categories::table
+        .filter(categories::id.eq_any(category_ids))
+        .filter(categories::deleted_at.is_null())
+        .select((categories::id, categories::name))
+        .load::<(Uuid, String)>(conn)?
+        .into_iter()
+        .collect())
+}
+
+pub fn to_responses(
+    conn: &mut PgConnection,
+    rows: Vec<DbTransaction>,
+) -> Result<Vec<TransactionResponse>, AppError> {
+    let category_ids: Vec<Uuid> = rows.iter().filter_map(|t| t.category_id).collect();
+    let names = category_names(conn, &category_ids)?;
+    let transaction_ids: Vec<Uuid> = rows.iter().map(|t| t.id).collect();
+    let mut tags = tags_by_transaction(conn, &transaction_ids)?;
+    let mut splits = splits_by_transaction(conn, &rows)?;
+
+    Ok(rows
+        .into_iter()
+        .map(|t| {
+            let category = t.category_id.and_then(|id| names.get(&id).cloned()).unwrap_or_default();
+            let tags = tags.remove(&t.id).unwrap_or_default();
+            let splits = splits.remove(&t.id).unwrap_or_default();
+            TransactionResponse::new(t, category, tags, splits)
+        })
+        .collect())
+}
+
+// Map a category name onto the user's category, creating one of `kind` when it doesn't exist yet.
+// A category in the trash doesn't count
+pub fn resolve_category_id(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    name: &str,
+    kind: CategoryKind,
+) -> Result<Option<Uuid>, AppError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Ok(None);
+    }
+
+    let existing = categories::table
+        .filter(categories::ledger_id.eq(ledger_id))
+        .filter(categories::deleted_at.is_null())
+        .load::<DbCategory>(conn)?
+        .into_iter()
+        .find(|c| c.name.eq_ignore_ascii_case(name) || c.name == name);
+    if let Some(category) = existing {
+        return Ok(Some(category.id));
+    }
+
+    let new_category = NewCategory {
+        id: Uuid::new_v4(),
+        name: name.to_string(),
+        description: None,
+        color: None,
+        icon: None,
+        ledger_id,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+        kind: kind.as_str().to_string(),
+    };
+
+    let id = diesel::insert_into(categories::table)
+        .values(&new_category)
+        .returning(categories::id)
+        .get_result::<Uuid>(conn)?;
+    Ok(Some(id))
+}
@@ -0,0 +1,239 @@
+use std::collections::HashSet;
+use std::env;
+
+use chrono::{DateTime, Duration, Utc};
+use diesel::prelude::*;
+use diesel::PgConnection;
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::models::bill::{BillResponse, DbBill};
+use crate::models::category::{Category, DbCategory};
+use crate::models::transaction::{DbTransaction, TransactionResponse};
+use crate::models::trash::{PurgeReport, TrashEntry, TrashResponse};
+use crate::schema::{bills, categories, transactions};
+use crate::services::transactions::to_responses;
+
+// How long deleted rows stay in the trash, overridable with TRASH_RETENTION_DAYS
+const DEFAULT_RETENTION_DAYS: i64 = 30;
+
+pub fn retention_days() -> i64 {
+    env::var("TRASH_RETENTION_DAYS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .filter(|days| *days > 0)
+        .unwrap_or(DEFAULT_RETENTION_DAYS)
+}
+
+fn entry<T>(item: T, deleted_at: Option<DateTime<Utc>>) -> TrashEntry<T> {
+    let deleted_at = deleted_at.unwrap_or_else(Utc::now);
+    TrashEntry {
+        item,
+        deleted_at,
+        purge_at: deleted_at + Duration::days(retention_days()),
+    }
+}
+
+// Everything the user has deleted and can still restore, most recently deleted first
+pub fn list_trash(conn: &mut PgConnection, ledger_id: Uuid) -> Result<TrashResponse, AppError> {
+    let rows = transactions::table
+        .filter(transactions::ledger_id.eq(ledger_id))
+        .filter(transactions::deleted_at.is_not_null())
+        .order(transactions::deleted_at.desc())
+        .load::<DbTransaction>(conn)?;
+    let deleted: Vec<Option<DateTime<Utc>>> = rows.iter().map(|t| t.deleted_at).collect();
+    let transactions = to_responses(conn, rows)?
+        .into_iter()
+        .zip(deleted)
+        .map(|(t, deleted_at)| entry(t, deleted_at))
+        .collect();
+
+    let bills = bills::table
+        .filter(bills::ledger_id.eq(ledger_id))
+        .filter(bills::deleted_at.is_not_null())
+        .order(bills::deleted_at.desc())
+        .load::<DbBill>(conn)?
+        .into_iter()
+        .map(|bill| {
+            let deleted_at = bill.deleted_at;
+            entry(BillResponse::from(bill), deleted_at)
+        })
+        .collect();
+
+    let categories = categories::table
+        .filter(categories::ledger_id.eq(ledger_id))
+        .filter(categories::deleted_at.is_not_null())
+        .order(categories::deleted_at.desc())
+        .load::<DbCategory>(conn)?
+        .into_iter()
+        .map(|category| {
+            let deleted_at = category.deleted_at;
+            entry(Category::from(category), deleted_at)
+        })
+        .collect();
+
+    Ok(TrashResponse {
+        retention_days: retention_days(),
+        transactions,
+        bills,
+        categories,
+    })
+}
+
+fn not_in_trash(what: &str, id: Uuid) -> AppError {
+    AppError::NotFound(format!("No {} {} in the trash", what, id))
+}
+
+// Move transactions to the trash, leaving any already there alone; returns how many were moved
+pub fn trash_transactions(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    transaction_ids: &[Uuid],
+    now: DateTime<Utc>,
+) -> Result<usize, AppError> {
+    Ok(diesel::update(
+        transactions::table
+            .filter(transactions::ledger_id.eq(ledger_id))
+            .filter(transactions::id.eq_any(transaction_ids))
+            .filter(transactions::deleted_at.is_null()),
+    )
+    .set((transactions::deleted_at.eq(Some(now)), transactions::updated_at.eq(now)))
+    .execute(conn)?)
+}
+
+// Take a transaction out of the trash; both legs of a transfer come back together
+pub fn restore_transaction(
+    conn: &mut PgConnection,
+    ledger_id: Uuid,
+    transaction_id: Uuid,
+) -> Result<Vec<TransactionResponse>, AppError> {
+    conn.transaction(|conn| {
+        let trashed = transactions::table
+            .filter(transactions::id.eq(transaction_id))
+            .filter(transactions::ledger_id.eq(ledger_id))
+            .filter(transactions::deleted_at.is_not_null())
+            .first::<DbTransaction>(conn)
+            .optional()?
+            .ok_or_else(|| not_in_trash("transaction", transaction_id))?;
+
+        let mut query = transactions::table
+            .filter(transactions::ledger_id.eq(ledger_id))
+            .filter(transactions::deleted_at.is_not_null())
+            .into_boxed();
+        query = match trashed.transfer_id {
+            Some(transfer_id) => query.filter(transactions::transfer_id.eq(transfer_id)),
+            None => query.filter(transactions::id.eq(trashed.id)),
+        };
+        let ids = query.select(transactions::id).load::<Uuid>(conn)?;
+
+        let rows = diesel::update(transactions::table.filter(transactions::id.eq_any(ids)))
+            .set((
+                transactions::deleted_at.eq(None::<DateTime<Utc>>),
+                transactions::updated_at.eq(Utc::now()),
+            ))
+            .get_results::<DbTransaction>(conn)?;
+        to_responses(conn, rows)
+    })
+}
+
+pub fn restore_bill(conn: &mut PgConnection, ledger_id: Uuid, bill_id: Uuid) -> Result<BillResponse, AppError> {
+    let bill = diesel::update(
+        bills::table
+            .filter(bills::id.eq(bill_id))
+            .filter(bills::ledger_id.eq(ledger_id))
+            .filter(bills::deleted_at.is_not_null()),
+    )
+    .set((bills::deleted_at.eq(None::<DateTime<Utc>>), bills::updated_at.eq(Utc::now())))
+    .get_result::<DbBill>(conn)
+    .optional()?
+    .ok_or_else(|| not_in_trash("bill", bill_id))?;
+    Ok(BillResponse::from(bill))
+}
+
+// A category comes back with its budgets, keywords and merchant defaults, unless one of the same
+// name and kind has been made since
+pub fn restore_category(conn: &mut PgConnection, ledger_id: Uuid, category_id: Uuid) -> Result<Category, AppError> {
+    conn.transaction(|conn| {
+        let trashed = categories::table
+            .filter(categories::id.eq(category_id))
+            .filter(categories::ledger_id.eq(ledger_id))
+            .filter(categories::deleted_at.is_not_null())
+            .first::<DbCategory>(conn)
+            .optional()?
+            .ok_or_else(|| not_in_trash("category", category_id))?;
+
+        let clash = categories::table
+            .filter(categories::ledger_id.eq(ledger_id))
+            .filter(categories::deleted_at.is_null())
+            .filter(categories::kind.eq(&trashed.kind))
+            .select(categories::name)
+            .load::<String>(conn)?
+            .into_iter()
+            .any(|name| name.to_lowercase() == trashed.name.to_lowercase());
+        if clash {
+            return Err(AppError::BadRequest(format!(
+                "There is already a category named {}; rename it before restoring this one",
+                trashed.name
+            )));
+        }
+
+        let category = diesel::update(categories::table.find(trashed.id))
+            .set((
+                categories::deleted_at.eq(None::<DateTime<Utc>>),
+                categories::updated_at.eq(Utc::now()),
+            ))
+            .get_result::<DbCategory>(conn)?;
+        Ok(Category::from(category))
+    })
+}
+
+// Delete for good, for every user, whatever has been in the trash longer than the retention
+// period. Splits, tags and matches of purged transactions go with them, and so do their images
+pub fn purge_expired(conn: &mut PgConnection, now: DateTime<Utc>) -> Result<PurgeReport, AppError> {
+    let cutoff = now - Duration::days(retention_days());
+    conn.transaction(|conn| {
+        let purged_images = diesel::delete(transactions::table.filter(transactions::deleted_at.lt(cutoff)))
+            .returning(transactions::image_path)
+            .get_results::<Option<String>>(conn)?;
+        let transactions = purged_images.len();
+        let purged_files = diesel::delete(bills::table.filter(bills::deleted_at.lt(cutoff)))
+            .returning(bills::file_path)
+            .get_results::<String>(conn)?;
+        let bills = purged_files.len();
+
+        // A transaction created from a bill shares its image, so an image goes only once neither a
+        // bill nor a transaction left, in the trash or not, points at it
+        let mut files: Vec<String> = purged_images.into_iter().flatten().chain(purged_files).collect();
+        files.sort();
+        files.dedup();
+        let kept: HashSet<String> = bills::table
+            .filter(bills::file_path.eq_any(&files))
+            .select(bills::file_path)
+            .load::<String>(conn)?
+            .into_iter()
+            .chain(
+                transactions::table
+                    .filter(transactions::image_path.eq_any(&files))
+                    .select(transactions::image_path.assume_not_null())
+                    .load::<String>(conn)?,
+            )
+            .collect();
+        files.retain(|file| !kept.contains(file));
+
+        let expired = categories::table
+            .filter(categories::deleted_at.lt(cutoff))
+            .select(categories::id)
+            .load::<Uuid>(conn)?;
+        diesel::update(transactions::table.filter(transactions::category_id.eq_any(&expired)))
+            .set(transactions::category_id.eq(None::<Uuid>))
+            .execute(conn)?;
+        let categories = diesel::delete(categories::table.filter(categories::id.eq_any(&expired))).execute(conn)?;
+
+        Ok(PurgeReport {
+            transactions,
+            bills,
+            categories,
+            files,
+        })
+    })
+}